@@ -0,0 +1,55 @@
+// Copyright (c) 2024-present, fjall-rs
+// This source code is licensed under both the Apache 2.0 and MIT License
+// (found in the LICENSE-* files in the repository)
+
+use base64::{engine::general_purpose::STANDARD as BASE64, Engine};
+use std::fmt::Write;
+
+/// Encoding of keys and values on the command line
+#[derive(Copy, Clone, Debug, clap::ValueEnum)]
+pub enum Encoding {
+    /// UTF-8 text, invalid bytes are printed as U+FFFD
+    Utf8,
+
+    /// Lowercase hexadecimal
+    Hex,
+
+    /// Standard base64 with padding
+    Base64,
+}
+
+impl Encoding {
+    pub fn encode(self, bytes: &[u8]) -> String {
+        match self {
+            Self::Utf8 => String::from_utf8_lossy(bytes).into_owned(),
+            Self::Hex => bytes.iter().fold(String::new(), |mut s, byte| {
+                let _ = write!(s, "{byte:02x}");
+                s
+            }),
+            Self::Base64 => BASE64.encode(bytes),
+        }
+    }
+
+    pub fn decode(self, s: &str) -> Result<Vec<u8>, String> {
+        match self {
+            Self::Utf8 => Ok(s.as_bytes().to_vec()),
+            Self::Hex => {
+                if s.len() % 2 == 1 {
+                    return Err(format!("invalid hex string {s:?}: odd length"));
+                }
+
+                (0..s.len())
+                    .step_by(2)
+                    .map(|idx| {
+                        s.get(idx..idx + 2)
+                            .and_then(|byte| u8::from_str_radix(byte, 16).ok())
+                            .ok_or_else(|| format!("invalid hex string {s:?}"))
+                    })
+                    .collect()
+            }
+            Self::Base64 => BASE64
+                .decode(s)
+                .map_err(|e| format!("invalid base64 string {s:?}: {e}")),
+        }
+    }
+}
@@ -0,0 +1,566 @@
+// Copyright (c) 2024-present, fjall-rs
+// This source code is licensed under both the Apache 2.0 and MIT License
+// (found in the LICENSE-* files in the repository)
+
+mod encoding;
+
+use clap::{Parser, Subcommand};
+use encoding::Encoding;
+use fjall::{
+    compaction, AbstractTree, AnyTree, CloseOptions, Config, GarbageCollection, JournalDump,
+    JournalStopReason, Keyspace, KvPair, PartitionCreateOptions, PartitionHandle, RepairOptions,
+    StdFs, VerifyOptions,
+};
+use std::{
+    path::{Path, PathBuf},
+    process::ExitCode,
+    sync::Arc,
+};
+
+type Result<T> = std::result::Result<T, Box<dyn std::error::Error>>;
+
+/// Inspect and administer fjall keyspaces
+///
+/// Commands that modify the keyspace fail if it is opened by another process.
+#[derive(Parser)]
+#[command(name = "fjall-cli", version)]
+struct Cli {
+    /// Path of the keyspace folder
+    path: PathBuf,
+
+    /// Encoding of keys and values that are passed as arguments
+    #[arg(long, short, value_enum, default_value_t = Encoding::Utf8, global = true)]
+    input: Encoding,
+
+    /// Encoding of keys and values that are printed
+    #[arg(long, short, value_enum, default_value_t = Encoding::Utf8, global = true)]
+    output: Encoding,
+
+    #[command(subcommand)]
+    command: Command,
+}
+
+#[derive(Subcommand)]
+enum Command {
+    /// Lists partitions with their options
+    Partitions,
+
+    /// Prints statistics of the keyspace and its partitions
+    Stats,
+
+    /// Prints the value of a key
+    Get { partition: String, key: String },
+
+    /// Prints the items of a key range
+    Scan {
+        partition: String,
+
+        /// Lower bound (inclusive)
+        #[arg(long)]
+        from: Option<String>,
+
+        /// Upper bound (exclusive)
+        #[arg(long)]
+        to: Option<String>,
+
+        #[command(flatten)]
+        print: PrintOptions,
+    },
+
+    /// Prints the items with a key prefix
+    Prefix {
+        partition: String,
+        prefix: String,
+
+        #[command(flatten)]
+        print: PrintOptions,
+    },
+
+    /// Inserts a key-value pair
+    Put {
+        partition: String,
+        key: String,
+        value: String,
+
+        /// Create the partition if it does not exist
+        #[arg(long)]
+        create: bool,
+    },
+
+    /// Removes a key
+    Delete { partition: String, key: String },
+
+    /// Lists active and sealed journals
+    Journals,
+
+    /// Decodes journal files without opening the keyspace
+    ///
+    /// Prints every batch and item, and where journal recovery would stop.
+    DumpJournal {
+        /// Journal file in the keyspace's journals folder (e.g. `0.sealed`), dumps all journals if not set
+        journal: Option<String>,
+    },
+
+    /// Repairs a damaged keyspace that fails to open, moving broken files into `lost+found`
+    Repair {
+        /// Only print what would be repaired
+        #[arg(long)]
+        dry_run: bool,
+    },
+
+    /// Runs a major compaction
+    Compact {
+        /// Partition to compact, compacts all partitions if not set
+        partition: Option<String>,
+    },
+
+    /// Rewrites blobs of a KV-separated partition to reclaim space
+    Gc {
+        partition: String,
+
+        /// Rewrite blobs until the space amplification is below this factor
+        #[arg(long, default_value_t = 1.5, conflicts_with = "staleness")]
+        space_amp: f32,
+
+        /// Rewrite blob files that have at least this ratio of stale blobs
+        #[arg(long)]
+        staleness: Option<f32>,
+    },
+
+    /// Verifies the integrity of the keyspace, including all checksums
+    Verify,
+}
+
+#[derive(clap::Args)]
+struct PrintOptions {
+    /// Maximum amount of items to print
+    #[arg(long)]
+    limit: Option<usize>,
+
+    /// Iterate in descending key order
+    #[arg(long)]
+    reverse: bool,
+
+    /// Only print keys
+    #[arg(long)]
+    keys_only: bool,
+}
+
+impl Command {
+    /// Returns `true` if the command only inspects the keyspace.
+    fn is_read_only(&self) -> bool {
+        matches!(
+            self,
+            Self::Partitions
+                | Self::Stats
+                | Self::Get { .. }
+                | Self::Scan { .. }
+                | Self::Prefix { .. }
+                | Self::Journals
+                | Self::Verify
+        )
+    }
+}
+
+fn open_keyspace(cli: &Cli) -> Result<Keyspace> {
+    // NOTE: Opening would create a new keyspace otherwise
+    if !cli.path.join("version").try_exists()? {
+        return Err(format!("no keyspace found at {}", cli.path.display()).into());
+    }
+
+    let read_only = cli.command.is_read_only();
+
+    // NOTE: Read-only opens don't take the lock, but the files may change while reading
+    if read_only {
+        if let Err(fjall::Error::Locked { pid }) = Keyspace::check_lock(&cli.path) {
+            let holder =
+                pid.map_or_else(|| "another process".into(), |pid| format!("process {pid}"));
+            eprintln!("warning: keyspace is opened by {holder}, output may be inconsistent");
+        }
+    }
+
+    // NOTE: Inspecting does not truncate journals or start background threads
+    Ok(Config::new(&cli.path).read_only(read_only).open()?)
+}
+
+fn get_partition(keyspace: &Keyspace, name: &str) -> Result<PartitionHandle> {
+    if !keyspace.partition_exists(name) {
+        return Err(format!("partition {name:?} does not exist").into());
+    }
+
+    Ok(keyspace.open_partition(name, PartitionCreateOptions::default())?)
+}
+
+fn print_items<I: DoubleEndedIterator<Item = fjall::Result<KvPair>>>(
+    iter: I,
+    print: &PrintOptions,
+    output: Encoding,
+) -> Result<()> {
+    let iter: Box<dyn Iterator<Item = _>> = if print.reverse {
+        Box::new(iter.rev())
+    } else {
+        Box::new(iter)
+    };
+
+    for kv in iter.take(print.limit.unwrap_or(usize::MAX)) {
+        let (key, value) = kv?;
+
+        if print.keys_only {
+            println!("{}", output.encode(&key));
+        } else {
+            println!("{}\t{}", output.encode(&key), output.encode(&value));
+        }
+    }
+
+    Ok(())
+}
+
+fn partitions(keyspace: &Keyspace) -> Result<()> {
+    for (idx, name) in keyspace.list_partitions().into_iter().enumerate() {
+        let partition = get_partition(keyspace, &name)?;
+        let config = &partition.config;
+
+        let compaction = match config.compaction_strategy {
+            compaction::Strategy::Leveled(_) => "leveled",
+            compaction::Strategy::SizeTiered(_) => "size-tiered",
+            compaction::Strategy::Fifo(_) => "fifo",
+        };
+
+        if idx > 0 {
+            println!();
+        }
+
+        println!("[{name}]");
+        println!("max memtable size:    {} bytes", config.max_memtable_size);
+        println!("data block size:      {} bytes", config.data_block_size);
+        println!("index block size:     {} bytes", config.index_block_size);
+        println!("level count:          {}", config.level_count);
+        println!("bloom bits per key:   {}", config.bloom_bits_per_key);
+        println!("compression:          {}", config.compression);
+        println!("compaction:           {compaction}");
+        println!("manual persist:       {}", config.manual_journal_persist);
+        println!("kv-separated:         {}", config.kv_separation.is_some());
+
+        if let Some(kv) = &config.kv_separation {
+            println!("blob compression:     {}", kv.compression);
+            println!("separation threshold: {} bytes", kv.separation_threshold);
+            println!("blob file size:       {} bytes", kv.file_target_size);
+        }
+    }
+
+    Ok(())
+}
+
+fn stats(keyspace: &Keyspace, cli: &Cli) -> Result<()> {
+    println!("path:         {}", cli.path.display());
+    println!("disk space:   {} bytes", keyspace.disk_space());
+    println!("journals:     {}", keyspace.journal_count());
+    println!("write buffer: {} bytes", keyspace.write_buffer_size());
+    println!("partitions:   {}", keyspace.partition_count());
+
+    for name in keyspace.list_partitions() {
+        let partition = get_partition(keyspace, &name)?;
+
+        println!();
+        println!("[{name}]");
+        println!("approximate items: {}", partition.approximate_len());
+        println!("disk space:        {} bytes", partition.disk_space());
+        println!("segments:          {}", partition.tree.segment_count());
+        println!(
+            "L0 segments:       {}",
+            partition.tree.first_level_segment_count()
+        );
+        println!(
+            "kv-separated:      {}",
+            matches!(partition.tree, AnyTree::Blob(_))
+        );
+    }
+
+    Ok(())
+}
+
+fn journals(keyspace: &Keyspace) -> Result<()> {
+    println!("path\tstate\tsize\tbatches\tseqnos");
+
+    for journal in keyspace.journals()? {
+        println!(
+            "{}\t{}\t{}\t{}\t{}",
+            journal.path.display(),
+            if journal.sealed { "sealed" } else { "active" },
+            journal.size,
+            journal.batch_count,
+            journal
+                .seqnos
+                .map_or_else(|| String::from("-"), |(lo, hi)| format!("{lo}..={hi}")),
+        );
+    }
+
+    Ok(())
+}
+
+/// Returns the journal files in ID order.
+fn journal_paths(folder: &Path) -> Result<Vec<PathBuf>> {
+    let mut paths = vec![];
+
+    for dirent in std::fs::read_dir(folder)? {
+        let path = dirent?.path();
+
+        let id = path
+            .file_name()
+            .and_then(|name| name.to_str())
+            .and_then(|name| name.trim_end_matches(".sealed").parse::<u64>().ok());
+
+        if let Some(id) = id {
+            paths.push((id, path));
+        }
+    }
+
+    paths.sort();
+
+    Ok(paths.into_iter().map(|(_, path)| path).collect())
+}
+
+fn dump_journal(path: &Path, output: Encoding) -> Result<bool> {
+    let dump = JournalDump::from_file(path, Arc::new(StdFs))?;
+
+    println!("journal {} ({} bytes)", path.display(), dump.size);
+
+    for batch in &dump.batches {
+        let checksum = match batch.checksum_ok {
+            Some(true) => "ok",
+            Some(false) => "MISMATCH",
+            None => "missing end marker",
+        };
+
+        println!(
+            "@{}\tbatch seqno={} items={}/{} checksum={checksum}",
+            batch.offset,
+            batch.seqno,
+            batch.items.len(),
+            batch.item_count,
+        );
+
+        for item in &batch.items {
+            println!(
+                "@{}\t  {:?} partition={} key={} value_size={}",
+                item.offset,
+                item.value_type,
+                item.partition,
+                output.encode(&item.key),
+                item.value_size,
+            );
+        }
+    }
+
+    let Some(stop) = dump.stop else {
+        println!("recovery reads the whole journal");
+        return Ok(true);
+    };
+
+    match stop.reason {
+        JournalStopReason::Broken(e) => {
+            println!(
+                "recovery fails at batch @{}: {e:?}, the keyspace cannot be opened",
+                stop.offset
+            );
+        }
+        reason => {
+            println!(
+                "recovery stops at @{}: {reason:?}, the rest of the journal is discarded",
+                stop.offset
+            );
+        }
+    }
+
+    Ok(false)
+}
+
+fn gc(partition: &PartitionHandle, space_amp: f32, staleness: Option<f32>) -> Result<()> {
+    if !matches!(partition.tree, AnyTree::Blob(_)) {
+        return Err(format!("partition {:?} is not KV-separated", partition.name).into());
+    }
+
+    let report = partition.gc_scan()?;
+    println!("before: {report}");
+
+    let bytes_freed = match staleness {
+        Some(threshold) => partition.gc_with_staleness_threshold(threshold)?,
+        None => partition.gc_with_space_amp_target(space_amp)?,
+    };
+    println!("freed {bytes_freed} bytes");
+
+    let report = partition.gc_scan()?;
+    println!("after: {report}");
+
+    Ok(())
+}
+
+fn verify(keyspace: &Keyspace) -> Result<bool> {
+    let report = keyspace.verify(VerifyOptions::default())?;
+
+    for issue in &report.issues {
+        println!("{issue:?}");
+    }
+
+    println!(
+        "checked {} partitions and {} journals, found {} issues",
+        report.partitions_checked,
+        report.journals_checked,
+        report.issues.len(),
+    );
+
+    Ok(report.is_ok())
+}
+
+fn run(cli: &Cli) -> Result<bool> {
+    // NOTE: Opening the keyspace would recover (and truncate) the journals
+    if let Command::DumpJournal { journal } = &cli.command {
+        let folder = cli.path.join("journals");
+
+        let paths = match journal {
+            Some(name) => vec![folder.join(name)],
+            None => journal_paths(&folder)?,
+        };
+
+        let mut ok = true;
+
+        for path in paths {
+            ok &= dump_journal(&path, cli.output)?;
+            println!();
+        }
+
+        return Ok(ok);
+    }
+
+    if let Command::Repair { dry_run } = &cli.command {
+        let report = Keyspace::repair(&cli.path, RepairOptions { dry_run: *dry_run })?;
+
+        for action in &report.actions {
+            println!("{action:?}");
+        }
+
+        if let Some(folder) = report.lost_and_found {
+            println!("moved files and wrote repair log to {}", folder.display());
+        } else if report.is_clean() {
+            println!("nothing to repair");
+        }
+
+        return Ok(true);
+    }
+
+    let keyspace = open_keyspace(cli)?;
+
+    match &cli.command {
+        Command::Partitions => partitions(&keyspace)?,
+        Command::Stats => stats(&keyspace, cli)?,
+        Command::Get { partition, key } => {
+            let partition = get_partition(&keyspace, partition)?;
+
+            match partition.get(cli.input.decode(key)?)? {
+                Some(value) => println!("{}", cli.output.encode(&value)),
+                None => return Ok(false),
+            }
+        }
+        Command::Scan {
+            partition,
+            from,
+            to,
+            print,
+        } => {
+            let partition = get_partition(&keyspace, partition)?;
+
+            let lo = match from {
+                Some(key) => std::ops::Bound::Included(cli.input.decode(key)?),
+                None => std::ops::Bound::Unbounded,
+            };
+            let hi = match to {
+                Some(key) => std::ops::Bound::Excluded(cli.input.decode(key)?),
+                None => std::ops::Bound::Unbounded,
+            };
+
+            print_items(partition.range((lo, hi)), print, cli.output)?;
+        }
+        Command::Prefix {
+            partition,
+            prefix,
+            print,
+        } => {
+            let partition = get_partition(&keyspace, partition)?;
+            print_items(
+                partition.prefix(cli.input.decode(prefix)?),
+                print,
+                cli.output,
+            )?;
+        }
+        Command::Put {
+            partition,
+            key,
+            value,
+            create,
+        } => {
+            let partition = if *create {
+                keyspace.open_partition(partition, PartitionCreateOptions::default())?
+            } else {
+                get_partition(&keyspace, partition)?
+            };
+
+            partition.insert(cli.input.decode(key)?, cli.input.decode(value)?)?;
+        }
+        Command::Delete { partition, key } => {
+            let partition = get_partition(&keyspace, partition)?;
+            partition.remove(cli.input.decode(key)?)?;
+        }
+        Command::Journals => journals(&keyspace)?,
+        Command::DumpJournal { .. } | Command::Repair { .. } => {
+            unreachable!("command runs without opening the keyspace")
+        }
+        Command::Compact { partition } => {
+            let names = match partition {
+                Some(name) => vec![name.as_str().into()],
+                None => keyspace.list_partitions(),
+            };
+
+            for name in names {
+                println!("compacting {name}");
+                get_partition(&keyspace, &name)?.major_compact()?;
+            }
+        }
+        Command::Gc {
+            partition,
+            space_amp,
+            staleness,
+        } => gc(
+            &get_partition(&keyspace, partition)?,
+            *space_amp,
+            *staleness,
+        )?,
+        Command::Verify => {
+            if !verify(&keyspace)? {
+                return Ok(false);
+            }
+        }
+    }
+
+    // NOTE: Don't create a segment for every write done using the CLI
+    keyspace.close(CloseOptions {
+        flush_memtables: false,
+        ..Default::default()
+    })?;
+
+    Ok(true)
+}
+
+fn main() -> ExitCode {
+    env_logger::init();
+
+    let cli = Cli::parse();
+
+    match run(&cli) {
+        Ok(true) => ExitCode::SUCCESS,
+        Ok(false) => ExitCode::FAILURE,
+        Err(e) => {
+            eprintln!("error: {e}");
+            ExitCode::from(2)
+        }
+    }
+}
@@ -0,0 +1,141 @@
+use fjall::{Config, KvSeparationOptions, PartitionCreateOptions, PersistMode};
+use std::{path::Path, process::Command};
+
+/// Runs the CLI, returning its exit code and stdout.
+fn run(path: &Path, args: &[&str]) -> (Option<i32>, String) {
+    let output = Command::new(env!("CARGO_BIN_EXE_fjall-cli"))
+        .arg(path)
+        .args(args)
+        .output()
+        .expect("should run CLI");
+
+    (
+        output.status.code(),
+        String::from_utf8(output.stdout).expect("should be utf-8"),
+    )
+}
+
+fn create_keyspace(path: &Path) -> fjall::Result<()> {
+    let keyspace = Config::new(path).open()?;
+
+    let items = keyspace.open_partition("items", PartitionCreateOptions::default())?;
+    items.insert("a", "abc")?;
+    items.insert("b", "def")?;
+
+    let blobs = keyspace.open_partition(
+        "blobs",
+        PartitionCreateOptions::default().with_kv_separation(KvSeparationOptions::default()),
+    )?;
+    blobs.insert("c", "ghi")?;
+
+    keyspace.persist(PersistMode::SyncAll)?;
+
+    Ok(())
+}
+
+#[test]
+fn cli_stats() -> fjall::Result<()> {
+    let folder = tempfile::tempdir()?;
+    create_keyspace(folder.path())?;
+
+    let (code, stdout) = run(folder.path(), &["stats"]);
+    assert_eq!(Some(0), code);
+    assert!(stdout.contains("partitions:   2"));
+    assert!(stdout.contains("[items]"));
+    assert!(stdout.contains("[blobs]"));
+
+    Ok(())
+}
+
+#[test]
+fn cli_partitions() -> fjall::Result<()> {
+    let folder = tempfile::tempdir()?;
+    create_keyspace(folder.path())?;
+
+    let (code, stdout) = run(folder.path(), &["partitions"]);
+    assert_eq!(Some(0), code);
+    assert!(stdout.contains("[items]"));
+    assert!(stdout.contains("data block size:      4096 bytes"));
+    assert!(stdout.contains("kv-separated:         false"));
+    assert!(stdout.contains("kv-separated:         true"));
+    assert!(stdout.contains("separation threshold: 1024 bytes"));
+
+    Ok(())
+}
+
+#[test]
+fn cli_get_scan() -> fjall::Result<()> {
+    let folder = tempfile::tempdir()?;
+    create_keyspace(folder.path())?;
+
+    let (code, stdout) = run(folder.path(), &["get", "items", "a"]);
+    assert_eq!(Some(0), code);
+    assert_eq!("abc\n", stdout);
+
+    let (code, stdout) = run(folder.path(), &["get", "items", "x"]);
+    assert_eq!(Some(1), code);
+    assert_eq!("", stdout);
+
+    let (code, stdout) = run(folder.path(), &["scan", "items", "--output", "hex"]);
+    assert_eq!(Some(0), code);
+    assert_eq!("61\t616263\n62\t646566\n", stdout);
+
+    Ok(())
+}
+
+#[test]
+fn cli_put_delete() -> fjall::Result<()> {
+    let folder = tempfile::tempdir()?;
+    create_keyspace(folder.path())?;
+
+    let (code, _) = run(folder.path(), &["put", "items", "x", "xyz"]);
+    assert_eq!(Some(0), code);
+
+    let (code, _) = run(folder.path(), &["delete", "items", "a"]);
+    assert_eq!(Some(0), code);
+
+    let (code, stdout) = run(folder.path(), &["prefix", "items", "", "--keys-only"]);
+    assert_eq!(Some(0), code);
+    assert_eq!("b\nx\n", stdout);
+
+    Ok(())
+}
+
+#[test]
+fn cli_dump_journal() -> fjall::Result<()> {
+    let folder = tempfile::tempdir()?;
+    create_keyspace(folder.path())?;
+
+    let (code, stdout) = run(folder.path(), &["dump-journal"]);
+    assert_eq!(Some(0), code);
+    assert!(stdout.contains("partition=items key=a value_size=3"));
+    assert!(stdout.contains("partition=blobs key=c value_size=3"));
+    assert!(stdout.contains("recovery reads the whole journal"));
+
+    Ok(())
+}
+
+#[test]
+fn cli_verify() -> fjall::Result<()> {
+    let folder = tempfile::tempdir()?;
+    create_keyspace(folder.path())?;
+
+    let (code, stdout) = run(folder.path(), &["verify"]);
+    assert_eq!(Some(0), code);
+    assert!(stdout.contains("checked 2 partitions and 1 journals, found 0 issues"));
+
+    Ok(())
+}
+
+#[test]
+fn cli_missing_keyspace() -> fjall::Result<()> {
+    let folder = tempfile::tempdir()?;
+
+    let (code, _) = run(folder.path(), &["stats"]);
+    assert_eq!(Some(2), code);
+
+    // NOTE: The CLI never creates a keyspace
+    assert!(!folder.path().join("version").try_exists()?);
+
+    Ok(())
+}
@@ -1,24 +1,15 @@
-use fjall::{Batch, BlockCache, Config, PartitionHandle};
-use format_bytes::format_bytes;
+use fjall::{Config, PartitionHandle};
 use nanoid::nanoid;
 use std::path::Path;
 
-fn create_item(
-    batch: &mut Batch,
-    table: &PartitionHandle,
-    index: &PartitionHandle,
-    name: &str,
-    year: u64,
-) -> fjall::Result<()> {
+/// Item value = year (u64, big endian) followed by the name
+fn create_item(table: &PartitionHandle, name: &str, year: u64) -> fjall::Result<String> {
     let id = nanoid!();
-    batch.insert(table, &id, format!("{name} [{year}]"));
 
-    let ts_bytes = year.to_be_bytes();
-    let key = format_bytes!(b"{}#{}", ts_bytes, id.as_bytes());
+    let value = [&year.to_be_bytes()[..], name.as_bytes()].concat();
+    table.insert(&id, value)?;
 
-    batch.insert(index, &key, "");
-
-    Ok(())
+    Ok(id)
 }
 
 fn main() -> fjall::Result<()> {
@@ -26,25 +17,33 @@ fn main() -> fjall::Result<()> {
 
     let keyspace = Config::new(path).temporary(true).open()?;
     let items = keyspace.open_partition("items", Default::default())?;
-    let sec = keyspace.open_partition("sec_idx", Default::default())?;
-
-    let mut batch = keyspace.batch();
-    create_item(&mut batch, &items, &sec, "Remain in Light", 1_980)?;
-    create_item(&mut batch, &items, &sec, "Power, Corruption & Lies", 1_983)?;
-    create_item(&mut batch, &items, &sec, "Hounds of Love", 1_985)?;
-    create_item(&mut batch, &items, &sec, "Black Celebration", 1_986)?;
-    create_item(&mut batch, &items, &sec, "Disintegration", 1_989)?;
-    create_item(&mut batch, &items, &sec, "Violator", 1_990)?;
-    create_item(&mut batch, &items, &sec, "Wish", 1_991)?;
-    create_item(&mut batch, &items, &sec, "Loveless", 1_991)?;
-    create_item(&mut batch, &items, &sec, "Dummy", 1_994)?;
-    create_item(&mut batch, &items, &sec, "When The Pawn...", 1_999)?;
-    create_item(&mut batch, &items, &sec, "Kid A", 2_000)?;
-    create_item(&mut batch, &items, &sec, "Have You In My Wilderness", 2_015)?;
-
-    batch.commit()?;
+
+    // Index items by year, the index entries are written in the same batch as the item
+    let by_year = keyspace.create_index("items", "items_by_year", |_key, value| {
+        value
+            .get(0..8)
+            .map(|year| vec![year.into()])
+            .unwrap_or_default()
+    })?;
+
+    create_item(&items, "Remain in Light", 1_980)?;
+    create_item(&items, "Power, Corruption & Lies", 1_983)?;
+    create_item(&items, "Hounds of Love", 1_985)?;
+    create_item(&items, "Black Celebration", 1_986)?;
+    create_item(&items, "Disintegration", 1_989)?;
+    create_item(&items, "Violator", 1_990)?;
+    create_item(&items, "Wish", 1_991)?;
+    create_item(&items, "Loveless", 1_991)?;
+    create_item(&items, "Dummy", 1_994)?;
+    let pawn = create_item(&items, "When The Pawn...", 1_998)?;
+    create_item(&items, "Kid A", 2_000)?;
+    create_item(&items, "Have You In My Wilderness", 2_015)?;
+
     keyspace.persist(fjall::PersistMode::SyncAll)?;
 
+    // Fix the release year, the index is updated automatically
+    items.insert(&pawn, [&1_999_u64.to_be_bytes()[..], b"When The Pawn..."].concat())?;
+
     // Get items from 1990 to 2000 (exclusive)
     let lo = 1_990_u64;
     let hi = 1_999_u64;
@@ -53,16 +52,11 @@ fn main() -> fjall::Result<()> {
 
     let mut found_count = 0;
 
-    for kv in sec.range(lo.to_be_bytes()..(hi + 1).to_be_bytes()) {
-        let (k, _) = kv?;
-
-        // Get ID
-        let primary_key = k.split(|&c| c == b'#').nth(1).unwrap();
-
-        // Get from primary index
-        let item = items.get(primary_key)?.unwrap();
+    for kv in by_year.lookup(lo.to_be_bytes()..=hi.to_be_bytes()) {
+        let (_, item) = kv?;
 
-        println!("found: {}", std::str::from_utf8(&item).unwrap());
+        let name = item.get(8..).unwrap();
+        println!("found: {}", std::str::from_utf8(name).unwrap());
 
         found_count += 1;
     }
@@ -1,28 +1,30 @@
-use tokio::task::spawn_blocking;
+use fjall::{AsyncKeyspace, Config};
+use futures::TryStreamExt;
 
 #[tokio::main]
 async fn main() -> fjall::Result<()> {
-    let keyspace = fjall::Config::default().open()?;
-    let items = keyspace.open_partition("items", Default::default())?;
-
-    {
-        let items = items.clone();
-        spawn_blocking(move || items.insert("hello", "world"))
-            .await
-            .expect("join failed")?;
-    }
+    let keyspace = AsyncKeyspace::open(Config::default()).await?;
+    let items = keyspace.open_partition("items", Default::default()).await?;
 
-    let item = {
-        let items = items.clone();
-        spawn_blocking(move || items.get("hello"))
-            .await
-            .expect("join failed")?
-    };
+    items.insert("hello", "world").await?;
 
+    let item = items.get("hello").await?;
     let item = item.expect("should exist");
 
     assert_eq!(b"world", &*item);
 
+    for idx in 0..10 {
+        items.insert(format!("item:{idx}"), idx.to_string()).await?;
+    }
+
+    let values = items
+        .prefix("item:")
+        .map_ok(|(_, v)| v)
+        .try_collect::<Vec<_>>()
+        .await?;
+
+    assert_eq!(10, values.len());
+
     println!("OK");
 
     Ok(())
@@ -22,22 +22,28 @@ fn main() -> fjall::Result<()> {
                 let mut rng = rand::thread_rng();
 
                 loop {
-                    let mut write_tx = keyspace.write_tx().unwrap();
+                    let next = keyspace
+                        .transaction(|write_tx| {
+                            let item = write_tx.get(&counters, "c1")?.unwrap();
 
-                    let item = write_tx.get(&counters, "c1")?.unwrap();
+                            let mut bytes = [0; 8];
+                            bytes.copy_from_slice(&item);
+                            let prev = u64::from_be_bytes(bytes);
 
-                    let mut bytes = [0; 8];
-                    bytes.copy_from_slice(&item);
-                    let prev = u64::from_be_bytes(bytes);
+                            if prev >= LIMIT {
+                                return Ok(None);
+                            }
 
-                    if prev >= LIMIT {
-                        return Ok::<_, fjall::Error>(());
-                    }
+                            let next = prev + 1;
+                            write_tx.insert(&counters, "c1", next.to_be_bytes());
 
-                    let next = prev + 1;
+                            Ok::<_, fjall::Error>(Some(next))
+                        })
+                        .unwrap();
 
-                    write_tx.insert(&counters, "c1", next.to_be_bytes());
-                    write_tx.commit()?.ok();
+                    let Some(next) = next else {
+                        return Ok::<_, fjall::Error>(());
+                    };
 
                     println!("worker {idx} incremented to {next}");
 
@@ -58,18 +58,24 @@ fn main() -> fjall::Result<()> {
                 let mut rng = rand::thread_rng();
 
                 loop {
-                    let mut tx = keyspace.write_tx().unwrap();
-
                     // TODO: NOTE:
                     // Tombstones will add up over time, making first KV slower
                     // Something like SingleDelete https://github.com/facebook/rocksdb/wiki/Single-Delete
                     // would be good for this type of workload
-                    if let Some((key, _)) = tx.first_key_value(&tasks)? {
-                        tx.remove(&tasks, &key);
+                    let task = keyspace
+                        .transaction(|tx| {
+                            let Some((key, _)) = tx.first_key_value(&tasks)? else {
+                                return Ok(None);
+                            };
+
+                            tx.remove(&tasks, &key);
+
+                            Ok::<_, fjall::Error>(Some(key))
+                        })
+                        .unwrap();
 
-                        if tx.commit()?.is_ok() {
-                            counter.fetch_add(1, Relaxed);
-                        }
+                    if let Some(key) = task {
+                        counter.fetch_add(1, Relaxed);
 
                         let task_id = std::str::from_utf8(&key).unwrap();
                         println!("consumer {idx} completed task {task_id}");
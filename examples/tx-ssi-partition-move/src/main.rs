@@ -26,26 +26,32 @@ fn main() -> fjall::Result<()> {
                 let mut rng = rand::thread_rng();
 
                 loop {
-                    let mut tx = keyspace.write_tx().unwrap();
-
                     // TODO: NOTE:
                     // Tombstones will add up over time, making first KV slower
                     // Something like SingleDelete https://github.com/facebook/rocksdb/wiki/Single-Delete
                     // would be good for this type of workload
-                    if let Some((key, value)) = tx.first_key_value(&src)? {
-                        tx.remove(&src, &key);
-                        tx.insert(&dst, &key, &value);
+                    let moved = keyspace
+                        .transaction(|tx| {
+                            let Some((key, value)) = tx.first_key_value(&src)? else {
+                                return Ok(None);
+                            };
 
-                        tx.commit()?.ok();
+                            tx.remove(&src, &key);
+                            tx.insert(&dst, &key, &value);
 
-                        let task_id = std::str::from_utf8(&key).unwrap();
-                        println!("consumer {idx} moved {task_id}");
+                            Ok::<_, fjall::Error>(Some(key))
+                        })
+                        .unwrap();
 
-                        let ms = rng.gen_range(10..100);
-                        std::thread::sleep(std::time::Duration::from_millis(ms));
-                    } else {
+                    let Some(key) = moved else {
                         return Ok::<_, fjall::Error>(());
-                    }
+                    };
+
+                    let task_id = std::str::from_utf8(&key).unwrap();
+                    println!("consumer {idx} moved {task_id}");
+
+                    let ms = rng.gen_range(10..100);
+                    std::thread::sleep(std::time::Duration::from_millis(ms));
                 }
             })
         })
@@ -0,0 +1,218 @@
+// Copyright (c) 2024-present, fjall-rs
+// This source code is licensed under both the Apache 2.0 and MIT License
+// (found in the LICENSE-* files in the repository)
+
+use super::{partition::AsyncPartition, BlockingPool, Task, DEFAULT_POOL_SIZE};
+use crate::{
+    batch::PartitionKey, Batch, CloseOptions, Config, Keyspace, PartitionCreateOptions,
+    PartitionHandle, PersistMode, VerifyOptions, VerifyReport,
+};
+use std::sync::Arc;
+
+/// Async wrapper around a [`Keyspace`]
+///
+/// Blocking operations (I/O, write stalls, write halts) run on a dedicated
+/// thread pool, so they never block the async runtime. The wrapper does not
+/// depend on a specific runtime.
+///
+/// # Examples
+///
+/// ```
+/// # use fjall::{AsyncKeyspace, Config, PartitionCreateOptions};
+/// # futures::executor::block_on(async {
+/// # let folder = tempfile::tempdir()?;
+/// let keyspace = AsyncKeyspace::open(Config::new(folder)).await?;
+/// let items = keyspace
+///     .open_partition("items", PartitionCreateOptions::default())
+///     .await?;
+///
+/// items.insert("a", "hello").await?;
+/// assert_eq!(Some("hello".as_bytes().into()), items.get("a").await?);
+/// #
+/// # Ok::<(), fjall::Error>(())
+/// # })?;
+/// # Ok::<(), fjall::Error>(())
+/// ```
+#[derive(Clone)]
+#[allow(clippy::module_name_repetitions)]
+pub struct AsyncKeyspace {
+    inner: Keyspace,
+    pool: Arc<BlockingPool>,
+}
+
+impl AsyncKeyspace {
+    /// Wraps a keyspace, using a blocking pool with 4 threads.
+    ///
+    /// # Errors
+    ///
+    /// Will return `Err` if the pool's threads could not be spawned.
+    pub fn new(keyspace: Keyspace) -> crate::Result<Self> {
+        Self::with_pool_size(keyspace, DEFAULT_POOL_SIZE)
+    }
+
+    /// Wraps a keyspace, using a blocking pool with the given amount of threads.
+    ///
+    /// Long write stalls occupy a pool thread each, so the pool should be
+    /// sized for the expected amount of concurrent operations.
+    ///
+    /// # Errors
+    ///
+    /// Will return `Err` if the pool's threads could not be spawned.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `threads` is 0.
+    pub fn with_pool_size(keyspace: Keyspace, threads: usize) -> crate::Result<Self> {
+        Ok(Self {
+            inner: keyspace,
+            pool: BlockingPool::new(threads)?,
+        })
+    }
+
+    /// Opens a keyspace using the config, without blocking the async runtime.
+    ///
+    /// # Errors
+    ///
+    /// Will return `Err` if an IO error occurs.
+    pub async fn open(config: Config) -> crate::Result<Self> {
+        let pool = BlockingPool::new(DEFAULT_POOL_SIZE)?;
+        let inner = pool.spawn(move || config.open()).await?;
+        Ok(Self { inner, pool })
+    }
+
+    /// Returns the underlying (blocking) keyspace.
+    #[must_use]
+    pub fn inner(&self) -> &Keyspace {
+        &self.inner
+    }
+
+    /// Runs a blocking function on the keyspace's pool.
+    ///
+    /// Use this for operations that have no async counterpart,
+    /// or to run multiple operations in one go.
+    pub fn spawn_blocking<T: Send + 'static, F: FnOnce() -> T + Send + 'static>(
+        &self,
+        f: F,
+    ) -> Task<T> {
+        self.pool.spawn(f)
+    }
+
+    /// Wraps a partition handle of this keyspace.
+    #[must_use]
+    pub fn wrap_partition(&self, partition: PartitionHandle) -> AsyncPartition {
+        AsyncPartition::new(partition, self.pool.clone())
+    }
+
+    /// Creates or opens a keyspace partition.
+    ///
+    /// See [`Keyspace::open_partition`].
+    ///
+    /// # Errors
+    ///
+    /// Returns error, if an IO error occurred.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the partition name is invalid.
+    pub fn open_partition(
+        &self,
+        name: &str,
+        create_options: PartitionCreateOptions,
+    ) -> Task<crate::Result<AsyncPartition>> {
+        let keyspace = self.inner.clone();
+        let pool = self.pool.clone();
+        let name = name.to_owned();
+
+        self.pool.spawn(move || {
+            keyspace
+                .open_partition(&name, create_options)
+                .map(|partition| AsyncPartition::new(partition, pool))
+        })
+    }
+
+    /// Destroys the partition, removing all data associated with it.
+    ///
+    /// See [`Keyspace::delete_partition`].
+    ///
+    /// # Errors
+    ///
+    /// Will return `Err` if an IO error occurs.
+    pub fn delete_partition(&self, handle: AsyncPartition) -> Task<crate::Result<()>> {
+        let keyspace = self.inner.clone();
+        self.pool
+            .spawn(move || keyspace.delete_partition(handle.into_inner()))
+    }
+
+    /// Initializes a new atomic write batch, see [`AsyncKeyspace::commit`].
+    #[must_use]
+    pub fn batch(&self) -> Batch {
+        self.inner.batch()
+    }
+
+    /// Commits a write batch.
+    ///
+    /// # Errors
+    ///
+    /// Will return `Err` if an IO error occurs.
+    pub fn commit(&self, batch: Batch) -> Task<crate::Result<()>> {
+        self.pool.spawn(move || batch.commit())
+    }
+
+    /// Flushes the active journal, see [`Keyspace::persist`].
+    ///
+    /// # Errors
+    ///
+    /// Returns error, if an IO error occurred.
+    pub fn persist(&self, mode: PersistMode) -> Task<crate::Result<()>> {
+        let keyspace = self.inner.clone();
+        self.pool.spawn(move || keyspace.persist(mode))
+    }
+
+    /// Verifies the integrity of the keyspace, see [`Keyspace::verify`].
+    ///
+    /// # Errors
+    ///
+    /// Will return `Err` if an IO error occurs.
+    pub fn verify(&self, options: VerifyOptions) -> Task<crate::Result<VerifyReport>> {
+        let keyspace = self.inner.clone();
+        self.pool.spawn(move || keyspace.verify(options))
+    }
+
+    /// Brings a secondary keyspace up to date with its primary, see [`Keyspace::catch_up`].
+    ///
+    /// # Errors
+    ///
+    /// Will return `Err` if an IO error occurs.
+    pub fn catch_up(&self) -> Task<crate::Result<()>> {
+        let keyspace = self.inner.clone();
+        self.pool.spawn(move || keyspace.catch_up())
+    }
+
+    /// Closes the keyspace, see [`Keyspace::close`].
+    ///
+    /// # Errors
+    ///
+    /// Will return `Err` if an IO error occurs, or the timeout is exceeded.
+    pub fn close(self, options: CloseOptions) -> Task<crate::Result<()>> {
+        let keyspace = self.inner;
+        self.pool.spawn(move || keyspace.close(options))
+    }
+
+    /// Returns the amount of partitions
+    #[must_use]
+    pub fn partition_count(&self) -> usize {
+        self.inner.partition_count()
+    }
+
+    /// Gets a list of all partition names in the keyspace
+    #[must_use]
+    pub fn list_partitions(&self) -> Vec<PartitionKey> {
+        self.inner.list_partitions()
+    }
+
+    /// Returns `true` if the partition with the given name exists.
+    #[must_use]
+    pub fn partition_exists(&self, name: &str) -> bool {
+        self.inner.partition_exists(name)
+    }
+}
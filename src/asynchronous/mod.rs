@@ -0,0 +1,98 @@
+// Copyright (c) 2024-present, fjall-rs
+// This source code is licensed under both the Apache 2.0 and MIT License
+// (found in the LICENSE-* files in the repository)
+
+pub mod keyspace;
+pub mod partition;
+pub mod stream;
+
+use futures_channel::oneshot;
+use std::{
+    future::Future,
+    panic::AssertUnwindSafe,
+    pin::Pin,
+    sync::{mpsc, Arc, Mutex},
+    task::{Context, Poll},
+};
+
+type Job = Box<dyn FnOnce() + Send + 'static>;
+
+/// Default amount of threads of the blocking pool
+pub const DEFAULT_POOL_SIZE: usize = 4;
+
+/// Dedicated thread pool that runs blocking keyspace operations
+///
+/// Threads exit once the pool (and all async handles holding it) is dropped.
+pub struct BlockingPool {
+    sender: Mutex<mpsc::Sender<Job>>,
+}
+
+impl BlockingPool {
+    pub fn new(threads: usize) -> crate::Result<Arc<Self>> {
+        assert!(threads > 0, "blocking pool needs at least one thread");
+
+        let (sender, receiver) = mpsc::channel::<Job>();
+        let receiver = Arc::new(Mutex::new(receiver));
+
+        for idx in 0..threads {
+            let receiver = receiver.clone();
+
+            std::thread::Builder::new()
+                .name(format!("fjall:async#{idx}"))
+                .spawn(move || loop {
+                    let job = receiver.lock().expect("lock is poisoned").recv();
+
+                    let Ok(job) = job else {
+                        log::trace!("async: blocking pool shut down");
+                        return;
+                    };
+
+                    job();
+                })?;
+        }
+
+        Ok(Arc::new(Self {
+            sender: Mutex::new(sender),
+        }))
+    }
+
+    /// Runs the function on the pool, returning a future of its result.
+    pub fn spawn<T: Send + 'static, F: FnOnce() -> T + Send + 'static>(&self, f: F) -> Task<T> {
+        let (tx, rx) = oneshot::channel();
+
+        let job: Job = Box::new(move || {
+            // NOTE: If the task was dropped, nobody is interested in the result anymore
+            let _ = tx.send(std::panic::catch_unwind(AssertUnwindSafe(f)));
+        });
+
+        self.sender
+            .lock()
+            .expect("lock is poisoned")
+            .send(job)
+            .expect("blocking pool should be running");
+
+        Task(rx)
+    }
+}
+
+/// Future of an operation that runs on the keyspace's blocking pool
+///
+/// The operation is started immediately, even if the task is never awaited.
+/// If the operation panics, the panic is resumed when awaiting the task.
+#[must_use = "tasks do nothing useful unless awaited"]
+pub struct Task<T>(oneshot::Receiver<std::thread::Result<T>>);
+
+impl<T> Future for Task<T> {
+    type Output = T;
+
+    fn poll(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        match Pin::new(&mut self.0).poll(cx) {
+            Poll::Ready(Ok(Ok(value))) => Poll::Ready(value),
+            Poll::Ready(Ok(Err(panic))) => std::panic::resume_unwind(panic),
+            Poll::Ready(Err(oneshot::Canceled)) => {
+                panic!("blocking pool dropped task without result")
+            }
+            Poll::Pending => Poll::Pending,
+        }
+    }
+}
@@ -0,0 +1,190 @@
+// Copyright (c) 2024-present, fjall-rs
+// This source code is licensed under both the Apache 2.0 and MIT License
+// (found in the LICENSE-* files in the repository)
+
+use super::{stream::KvStream, BlockingPool, Task};
+use crate::{PartitionHandle, Snapshot};
+use lsm_tree::{KvPair, UserKey, UserValue};
+use std::{
+    ops::{Bound, RangeBounds},
+    sync::Arc,
+};
+
+/// Async wrapper around a [`PartitionHandle`]
+///
+/// Reads and writes run on the keyspace's blocking pool, so write stalls
+/// and I/O never block the async runtime. Iterators are exposed as [`KvStream`]s.
+#[derive(Clone)]
+#[allow(clippy::module_name_repetitions)]
+pub struct AsyncPartition {
+    inner: PartitionHandle,
+    pool: Arc<BlockingPool>,
+}
+
+impl AsyncPartition {
+    pub(super) fn new(inner: PartitionHandle, pool: Arc<BlockingPool>) -> Self {
+        Self { inner, pool }
+    }
+
+    /// Returns the underlying (blocking) partition handle.
+    #[must_use]
+    pub fn inner(&self) -> &PartitionHandle {
+        &self.inner
+    }
+
+    /// Returns the underlying (blocking) partition handle.
+    #[must_use]
+    pub fn into_inner(self) -> PartitionHandle {
+        self.inner
+    }
+
+    /// Returns the partition name.
+    #[must_use]
+    pub fn name(&self) -> &str {
+        &self.inner.name
+    }
+
+    fn spawn<T: Send + 'static, F: FnOnce(PartitionHandle) -> T + Send + 'static>(
+        &self,
+        f: F,
+    ) -> Task<T> {
+        let partition = self.inner.clone();
+        self.pool.spawn(move || f(partition))
+    }
+
+    /// Retrieves an item from the partition.
+    ///
+    /// # Errors
+    ///
+    /// Will return `Err` if an IO error occurs.
+    pub fn get<K: AsRef<[u8]>>(&self, key: K) -> Task<crate::Result<Option<UserValue>>> {
+        let key: UserKey = key.as_ref().into();
+        self.spawn(move |partition| partition.get(key))
+    }
+
+    /// Returns `true` if the partition contains the specified key.
+    ///
+    /// # Errors
+    ///
+    /// Will return `Err` if an IO error occurs.
+    pub fn contains_key<K: AsRef<[u8]>>(&self, key: K) -> Task<crate::Result<bool>> {
+        let key: UserKey = key.as_ref().into();
+        self.spawn(move |partition| partition.contains_key(key))
+    }
+
+    /// Inserts a key-value pair into the partition.
+    ///
+    /// If writes are stalled or halted, the task waits on the blocking pool.
+    ///
+    /// # Errors
+    ///
+    /// Will return `Err` if an IO error occurs.
+    pub fn insert<K: AsRef<[u8]>, V: AsRef<[u8]>>(
+        &self,
+        key: K,
+        value: V,
+    ) -> Task<crate::Result<()>> {
+        let key: UserKey = key.as_ref().into();
+        let value: UserValue = value.as_ref().into();
+        self.spawn(move |partition| partition.insert(key, value))
+    }
+
+    /// Removes an item from the partition.
+    ///
+    /// If writes are stalled or halted, the task waits on the blocking pool.
+    ///
+    /// # Errors
+    ///
+    /// Will return `Err` if an IO error occurs.
+    pub fn remove<K: AsRef<[u8]>>(&self, key: K) -> Task<crate::Result<()>> {
+        let key: UserKey = key.as_ref().into();
+        self.spawn(move |partition| partition.remove(key))
+    }
+
+    /// Scans the entire partition, returning the amount of items.
+    ///
+    /// # Errors
+    ///
+    /// Will return `Err` if an IO error occurs.
+    pub fn len(&self) -> Task<crate::Result<usize>> {
+        self.spawn(|partition| partition.len())
+    }
+
+    /// Returns `true` if the partition is empty.
+    ///
+    /// # Errors
+    ///
+    /// Will return `Err` if an IO error occurs.
+    pub fn is_empty(&self) -> Task<crate::Result<bool>> {
+        self.spawn(|partition| partition.is_empty())
+    }
+
+    /// Approximates the amount of items in the partition, see [`PartitionHandle::approximate_len`].
+    #[must_use]
+    pub fn approximate_len(&self) -> usize {
+        self.inner.approximate_len()
+    }
+
+    /// Returns the first key-value pair in the partition.
+    ///
+    /// # Errors
+    ///
+    /// Will return `Err` if an IO error occurs.
+    pub fn first_key_value(&self) -> Task<crate::Result<Option<KvPair>>> {
+        self.spawn(|partition| partition.first_key_value())
+    }
+
+    /// Returns the last key-value pair in the partition.
+    ///
+    /// # Errors
+    ///
+    /// Will return `Err` if an IO error occurs.
+    pub fn last_key_value(&self) -> Task<crate::Result<Option<KvPair>>> {
+        self.spawn(|partition| partition.last_key_value())
+    }
+
+    /// Opens a snapshot of this partition.
+    #[must_use]
+    pub fn snapshot(&self) -> Snapshot {
+        self.inner.snapshot()
+    }
+
+    /// Returns a stream that scans through the entire partition.
+    ///
+    /// The stream reads from a snapshot taken when calling this function.
+    #[must_use]
+    #[allow(clippy::iter_not_returning_iterator)]
+    pub fn iter(&self) -> KvStream {
+        self.range::<&[u8], _>(..)
+    }
+
+    /// Returns a stream over a range of items.
+    ///
+    /// The stream reads from a snapshot taken when calling this function.
+    #[must_use]
+    pub fn range<K: AsRef<[u8]>, R: RangeBounds<K>>(&self, range: R) -> KvStream {
+        // TODO: Bound::map 1.77
+        let lo: Bound<UserKey> = match range.start_bound() {
+            Bound::Included(key) => Bound::Included(key.as_ref().into()),
+            Bound::Excluded(key) => Bound::Excluded(key.as_ref().into()),
+            Bound::Unbounded => Bound::Unbounded,
+        };
+
+        let hi: Bound<UserKey> = match range.end_bound() {
+            Bound::Included(key) => Bound::Included(key.as_ref().into()),
+            Bound::Excluded(key) => Bound::Excluded(key.as_ref().into()),
+            Bound::Unbounded => Bound::Unbounded,
+        };
+
+        KvStream::new(self.pool.clone(), self.inner.snapshot(), (lo, hi))
+    }
+
+    /// Returns a stream over a prefixed set of items.
+    ///
+    /// The stream reads from a snapshot taken when calling this function.
+    #[must_use]
+    pub fn prefix<K: AsRef<[u8]>>(&self, prefix: K) -> KvStream {
+        let bounds = lsm_tree::range::prefix_to_range(prefix.as_ref());
+        KvStream::new(self.pool.clone(), self.inner.snapshot(), bounds)
+    }
+}
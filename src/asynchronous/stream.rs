@@ -0,0 +1,164 @@
+// Copyright (c) 2024-present, fjall-rs
+// This source code is licensed under both the Apache 2.0 and MIT License
+// (found in the LICENSE-* files in the repository)
+
+use super::{BlockingPool, Task};
+use crate::Snapshot;
+use futures_core::Stream;
+use lsm_tree::{KvPair, UserKey};
+use std::{
+    collections::VecDeque,
+    future::Future,
+    ops::Bound,
+    pin::Pin,
+    sync::Arc,
+    task::{Context, Poll},
+};
+
+/// Amount of items that are read from the partition in one go
+const CHUNK_SIZE: usize = 128;
+
+type Bounds = (Bound<UserKey>, Bound<UserKey>);
+
+/// Stream of key-value pairs of an [`AsyncPartition`](crate::AsyncPartition)
+///
+/// Items are read in chunks on the blocking pool. Because partition iterators
+/// cannot be moved between threads, every chunk opens a new iterator that
+/// continues after the last read key. All chunks read from the same snapshot,
+/// so the stream is consistent.
+#[allow(clippy::module_name_repetitions)]
+pub struct KvStream {
+    pool: Arc<BlockingPool>,
+    snapshot: Arc<Snapshot>,
+    bounds: Bounds,
+    reverse: bool,
+    buffer: VecDeque<KvPair>,
+    pending: Option<Task<crate::Result<Vec<KvPair>>>>,
+    done: bool,
+}
+
+impl KvStream {
+    pub(super) fn new(pool: Arc<BlockingPool>, snapshot: Snapshot, bounds: Bounds) -> Self {
+        Self {
+            pool,
+            snapshot: Arc::new(snapshot),
+            bounds,
+            reverse: false,
+            buffer: VecDeque::new(),
+            pending: None,
+            done: false,
+        }
+    }
+
+    /// Reverses the stream, so items are returned in descending key order.
+    ///
+    /// Like [`DoubleEndedIterator::rev`], if the stream was already polled,
+    /// it continues with the not yet returned items, starting at the other end of the range.
+    #[must_use]
+    pub fn rev(mut self) -> Self {
+        // NOTE: Buffered items have not been returned yet, so they need to be read again
+        if let Some((first_key, _)) = self.buffer.front() {
+            if self.reverse {
+                self.bounds.1 = Bound::Included(first_key.clone());
+            } else {
+                self.bounds.0 = Bound::Included(first_key.clone());
+            }
+
+            self.done = false;
+        }
+
+        self.buffer.clear();
+        self.pending = None;
+        self.reverse = !self.reverse;
+        self
+    }
+
+    fn read_chunk(&self) -> Task<crate::Result<Vec<KvPair>>> {
+        let snapshot = self.snapshot.clone();
+        let bounds = self.bounds.clone();
+        let reverse = self.reverse;
+
+        self.pool.spawn(move || {
+            let iter = snapshot.range(bounds);
+
+            if reverse {
+                iter.rev()
+                    .take(CHUNK_SIZE)
+                    .map(|kv| kv.map_err(Into::into))
+                    .collect()
+            } else {
+                iter.take(CHUNK_SIZE)
+                    .map(|kv| kv.map_err(Into::into))
+                    .collect()
+            }
+        })
+    }
+
+    /// Narrows the bounds to the items that have not been read yet.
+    fn advance(&mut self, last_key: &UserKey) {
+        if self.reverse {
+            self.bounds.1 = Bound::Excluded(last_key.clone());
+        } else {
+            self.bounds.0 = Bound::Excluded(last_key.clone());
+        }
+
+        // NOTE: Stop if the remaining range is empty, instead of scanning an inverted range
+        if let (
+            Bound::Included(lo) | Bound::Excluded(lo),
+            Bound::Included(hi) | Bound::Excluded(hi),
+        ) = &self.bounds
+        {
+            if lo >= hi {
+                self.done = true;
+            }
+        }
+    }
+}
+
+impl Stream for KvStream {
+    type Item = crate::Result<KvPair>;
+
+    fn poll_next(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        let this = self.get_mut();
+
+        loop {
+            if let Some(item) = this.buffer.pop_front() {
+                return Poll::Ready(Some(Ok(item)));
+            }
+
+            if this.done {
+                return Poll::Ready(None);
+            }
+
+            let pending = match &mut this.pending {
+                Some(pending) => pending,
+                None => this.pending.insert(this.read_chunk()),
+            };
+
+            let chunk = match Pin::new(pending).poll(cx) {
+                Poll::Ready(chunk) => chunk,
+                Poll::Pending => return Poll::Pending,
+            };
+            this.pending = None;
+
+            match chunk {
+                Ok(items) => {
+                    if items.len() < CHUNK_SIZE {
+                        this.done = true;
+                    }
+
+                    if let Some((last_key, _)) = items.last() {
+                        let last_key = last_key.clone();
+                        this.advance(&last_key);
+                    }
+
+                    this.buffer.extend(items);
+                }
+                Err(e) => {
+                    this.done = true;
+                    return Poll::Ready(Some(Err(e)));
+                }
+            }
+        }
+    }
+}
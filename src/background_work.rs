@@ -0,0 +1,145 @@
+// Copyright (c) 2024-present, fjall-rs
+// This source code is licensed under both the Apache 2.0 and MIT License
+// (found in the LICENSE-* files in the repository)
+
+use lsm_tree::stop_signal::StopSignal;
+use std::{
+    sync::{Arc, Condvar, Mutex, MutexGuard, PoisonError},
+    time::Instant,
+};
+
+#[derive(Default)]
+struct State {
+    paused: bool,
+    active_jobs: usize,
+}
+
+#[derive(Default)]
+pub struct BackgroundWorkInner {
+    state: Mutex<State>,
+    signal: Condvar,
+}
+
+/// Gate that flush and compaction workers pass before starting a job
+///
+/// While paused, workers block before starting new jobs.
+#[derive(Clone, Default)]
+pub struct BackgroundWork(Arc<BackgroundWorkInner>);
+
+impl std::ops::Deref for BackgroundWork {
+    type Target = BackgroundWorkInner;
+
+    fn deref(&self) -> &Self::Target {
+        &self.0
+    }
+}
+
+impl BackgroundWork {
+    fn state(&self) -> MutexGuard<'_, State> {
+        // NOTE: The state only holds a flag and a counter, which stay valid even if a thread panicked
+        self.state.lock().unwrap_or_else(PoisonError::into_inner)
+    }
+
+    /// Stops new jobs from starting, and waits for running jobs to finish.
+    pub fn pause(&self) {
+        self.pause_until(None);
+    }
+
+    /// Stops new jobs from starting, and waits for running jobs to finish,
+    /// but not longer than the deadline.
+    ///
+    /// Returns `false` if jobs are still running at the deadline.
+    pub fn pause_until(&self, deadline: Option<Instant>) -> bool {
+        let mut state = self.state();
+        state.paused = true;
+
+        while state.active_jobs > 0 {
+            let Some(deadline) = deadline else {
+                state = self
+                    .signal
+                    .wait(state)
+                    .unwrap_or_else(PoisonError::into_inner);
+                continue;
+            };
+
+            let timeout = deadline.saturating_duration_since(Instant::now());
+
+            if timeout.is_zero() {
+                return false;
+            }
+
+            state = self
+                .signal
+                .wait_timeout(state, timeout)
+                .unwrap_or_else(PoisonError::into_inner)
+                .0;
+        }
+
+        drop(state);
+
+        true
+    }
+
+    /// Allows jobs to start again.
+    pub fn resume(&self) {
+        self.state().paused = false;
+        self.signal.notify_all();
+    }
+
+    /// Wakes up paused workers, so they notice that the stop signal was sent.
+    ///
+    /// Background work stays paused, so the workers exit without starting a job.
+    pub fn wake_for_stop(&self) {
+        // IMPORTANT: Notify while holding the lock, otherwise a worker that has just
+        // checked the stop signal may miss the notification
+        let _state = self.state();
+        self.signal.notify_all();
+    }
+
+    /// Returns the amount of running jobs.
+    pub fn active_jobs(&self) -> usize {
+        self.state().active_jobs
+    }
+
+    pub fn is_paused(&self) -> bool {
+        self.state().paused
+    }
+
+    /// Waits until background work is not paused, and registers a running job.
+    ///
+    /// The job is finished when the returned guard is dropped.
+    ///
+    /// Returns `None` if the stop signal was sent, in which case the worker should exit.
+    pub fn start_job(&self, stop_signal: &StopSignal) -> Option<JobGuard> {
+        let mut state = self.state();
+
+        while state.paused && !stop_signal.is_stopped() {
+            state = self
+                .signal
+                .wait(state)
+                .unwrap_or_else(PoisonError::into_inner);
+        }
+
+        if stop_signal.is_stopped() {
+            return None;
+        }
+
+        state.active_jobs += 1;
+        drop(state);
+
+        Some(JobGuard(self.clone()))
+    }
+}
+
+/// Marks a running background job
+pub struct JobGuard(BackgroundWork);
+
+impl Drop for JobGuard {
+    fn drop(&mut self) {
+        let mut state = self.0.state();
+        state.active_jobs -= 1;
+        drop(state);
+
+        self.0.signal.notify_all();
+    }
+}
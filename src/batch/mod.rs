@@ -3,10 +3,12 @@
 // (found in the LICENSE-* files in the repository)
 
 pub mod item;
+pub mod precondition;
 
-use crate::{Keyspace, PartitionHandle, PersistMode};
+use crate::{write_stall::WriteAdmission, Keyspace, PartitionHandle, PersistMode};
 use item::Item;
-use lsm_tree::{AbstractTree, ValueType};
+use lsm_tree::{AbstractTree, SeqNo, ValueType};
+use precondition::{Check, Precondition};
 use std::{
     collections::{HashMap, HashSet},
     sync::Arc,
@@ -21,6 +23,7 @@ pub type PartitionKey = Arc<str>;
 #[doc(alias = "WriteBatch")]
 pub struct Batch {
     pub(crate) data: Vec<Item>,
+    checks: Vec<Check>,
     keyspace: Keyspace,
     durability: Option<PersistMode>,
 }
@@ -32,6 +35,7 @@ impl Batch {
     pub(crate) fn new(keyspace: Keyspace) -> Self {
         Self {
             data: Vec::new(),
+            checks: Vec::new(),
             keyspace,
             durability: None,
         }
@@ -69,12 +73,98 @@ impl Batch {
         ));
     }
 
+    /// Requires the key to not exist when the batch is committed
+    pub fn require_absent<K: AsRef<[u8]>>(&mut self, p: &PartitionHandle, key: K) {
+        self.require(p, key, Precondition::Absent);
+    }
+
+    /// Requires the key to hold exactly the given value when the batch is committed
+    pub fn require_value<K: AsRef<[u8]>, V: AsRef<[u8]>>(
+        &mut self,
+        p: &PartitionHandle,
+        key: K,
+        value: V,
+    ) {
+        self.require(p, key, Precondition::Value(value.as_ref().into()));
+    }
+
+    /// Requires the key to not have been written (inserted or removed)
+    /// after the given sequence number when the batch is committed
+    ///
+    /// Keys that were never written pass this check.
+    ///
+    /// Note that [`Keyspace::instant`] returns the sequence number of the *next* write,
+    /// so to check that a key was not changed since some instant, pass `instant - 1`.
+    pub fn require_seqno_at_most<K: AsRef<[u8]>>(
+        &mut self,
+        p: &PartitionHandle,
+        key: K,
+        seqno: SeqNo,
+    ) {
+        self.require(p, key, Precondition::SeqnoAtMost(seqno));
+    }
+
+    fn require<K: AsRef<[u8]>>(&mut self, p: &PartitionHandle, key: K, precondition: Precondition) {
+        self.checks.push(Check {
+            partition: p.name.clone(),
+            key: key.as_ref().into(),
+            precondition,
+        });
+    }
+
     /// Commits the batch to the [`Keyspace`] atomically
     ///
+    /// All preconditions are checked atomically with the write,
+    /// if any of them fails, nothing is written.
+    ///
     /// # Errors
     ///
     /// Will return `Err` if an IO error occurs.
-    pub fn commit(mut self) -> crate::Result<()> {
+    ///
+    /// Will return [`Error::PreconditionFailed`](crate::Error::PreconditionFailed)
+    /// listing every failed precondition, if any.
+    ///
+    /// Will return [`Error::WriteStalled`](crate::Error::WriteStalled) if writes to
+    /// any of the partitions are stalled, and the keyspace uses [`WriteAdmission::FailFast`].
+    ///
+    /// Will return [`Error::ReadOnly`](crate::Error::ReadOnly) if the keyspace is opened in read-only mode.
+    pub fn commit(self) -> crate::Result<()> {
+        self.commit_with_seqno(None).map(|_| ())
+    }
+
+    /// Rejects the batch if writes to any of its partitions are stalled
+    /// and the keyspace fails fast.
+    fn check_write_admission(&self) -> crate::Result<()> {
+        if self.keyspace.config.write_admission != WriteAdmission::FailFast {
+            return Ok(());
+        }
+
+        let partitions = self.keyspace.partitions.read().expect("lock is poisoned");
+
+        let mut checked = HashSet::new();
+
+        for item in &self.data {
+            if !checked.insert(&item.partition) {
+                continue;
+            }
+
+            if let Some(partition) = partitions.get(&item.partition) {
+                partition.check_write_admission()?;
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Commits the batch, returning the sequence number it was written with.
+    ///
+    /// A replicated batch keeps the `seqno` it was written with on the primary.
+    #[allow(clippy::too_many_lines)]
+    pub(crate) fn commit_with_seqno(mut self, seqno: Option<SeqNo>) -> crate::Result<SeqNo> {
+        if self.keyspace.config.read_only {
+            return Err(crate::Error::ReadOnly);
+        }
+
         if self
             .keyspace
             .is_poisoned
@@ -83,6 +173,9 @@ impl Batch {
             return Err(crate::Error::Poisoned);
         }
 
+        // NOTE: Check admission before writing anything, so a rejected batch has no effect
+        self.check_write_admission()?;
+
         log::trace!("batch: Acquiring journal writer");
         let mut journal_writer = self.keyspace.journal.get_writer();
 
@@ -90,18 +183,50 @@ impl Batch {
         log::trace!("batch: Acquiring partitions lock");
         let partitions = self.keyspace.partitions.write().expect("lock is poisoned");
 
+        // NOTE: Read the latest versions of all checked keys before locking the memtables,
+        // because reading through the tree would need to read lock the active memtable
+        //
+        // Every write inserts into the memtable before releasing the journal writer,
+        // so no write with a lower seqno is missing. Holding the journal writer also
+        // prevents memtable rotation, so any newer version can only be in the active memtable,
+        // which is checked again below
+        let mut prechecked = Vec::with_capacity(self.checks.len());
+
+        for check in &self.checks {
+            let Some(partition) = partitions.get(&check.partition) else {
+                return Err(crate::Error::PartitionDeleted);
+            };
+
+            prechecked.push(precondition::read_latest(&partition.tree, &check.key)?);
+        }
+
+        // NOTE: Writes to indexed partitions always go through batches,
+        // so, while holding the journal writer, their latest versions cannot change
+        //
+        // A replicated batch already contains the index changes of the primary
+        if seqno.is_none() {
+            let index_changes = crate::index::collect_changes(&self.data, &partitions)?;
+            self.data.extend(index_changes);
+        }
+
         // IMPORTANT: Need to WRITE lock all affected partition's memtables
         // Otherwise, there may be read skew
         log::trace!("batch: Acquiring memtable locks");
         let locked_memtables = {
             let mut lock_map = HashMap::new();
 
-            for item in &self.data {
-                if lock_map.contains_key(&item.partition) {
+            let affected_partitions = self
+                .data
+                .iter()
+                .map(|item| &item.partition)
+                .chain(self.checks.iter().map(|check| &check.partition));
+
+            for partition_name in affected_partitions {
+                if lock_map.contains_key(partition_name) {
                     continue;
                 }
 
-                let Some(partition) = partitions.get(&item.partition) else {
+                let Some(partition) = partitions.get(partition_name) else {
                     continue;
                 };
 
@@ -113,7 +238,7 @@ impl Batch {
                 }
 
                 lock_map.insert(
-                    item.partition.clone(),
+                    partition_name.clone(),
                     partition.tree.lock_active_memtable(),
                 );
             }
@@ -121,7 +246,16 @@ impl Batch {
             lock_map
         };
 
-        let batch_seqno = self.keyspace.seqno.next();
+        precondition::check_all(&self.checks, prechecked, &partitions, &locked_memtables)?;
+
+        let batch_seqno = if let Some(seqno) = seqno {
+            self.keyspace
+                .seqno
+                .fetch_max(seqno + 1, std::sync::atomic::Ordering::AcqRel);
+            seqno
+        } else {
+            self.keyspace.seqno.next()
+        };
 
         let items = self.data.iter().collect::<Vec<_>>();
         let _ = journal_writer.write_batch(&items, batch_seqno)?;
@@ -182,16 +316,13 @@ impl Batch {
         for partition in partitions_with_possible_stall {
             let memtable_size = partition.tree.active_memtable_size();
 
+            // NOTE: Also applies the write stall policy, which checks the write buffer as well
+            // Otherwise batch writes are never stalled/halted
             if let Err(e) = partition.check_memtable_overflow(memtable_size) {
                 log::error!("Failed memtable rotate check: {e:?}");
             };
-
-            // IMPORTANT: Check write buffer as well
-            // Otherwise batch writes are never stalled/halted
-            let write_buffer_size = self.keyspace.write_buffer_manager.get();
-            partition.check_write_buffer_size(write_buffer_size);
         }
 
-        Ok(())
+        Ok(batch_seqno)
     }
 }
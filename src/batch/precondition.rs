@@ -0,0 +1,170 @@
+// Copyright (c) 2024-present, fjall-rs
+// This source code is licensed under both the Apache 2.0 and MIT License
+// (found in the LICENSE-* files in the repository)
+
+use super::PartitionKey;
+use crate::keyspace::Partitions;
+use lsm_tree::{
+    blob_tree::value::MaybeInlineValue, coding::Decode, AnyTree, InternalValue, Memtable, SeqNo,
+    UserKey, UserValue,
+};
+use std::{collections::HashMap, io::Cursor, sync::RwLockWriteGuard};
+
+/// A condition that needs to hold for a [`Batch`](super::Batch) to be committed
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub enum Precondition {
+    /// The key must not exist
+    Absent,
+
+    /// The key must exist and hold exactly the given value
+    Value(UserValue),
+
+    /// The latest write (insert or remove) of the key must have a
+    /// sequence number less than or equal to the given one
+    ///
+    /// Keys that were never written pass this check.
+    SeqnoAtMost(SeqNo),
+}
+
+/// A precondition that did not hold when its batch was committed
+#[derive(Clone, Debug, Eq, PartialEq)]
+#[allow(clippy::module_name_repetitions)]
+pub struct FailedPrecondition {
+    /// Partition the key belongs to
+    pub partition: PartitionKey,
+
+    /// Key the precondition was placed on
+    pub key: UserKey,
+
+    /// The precondition that failed
+    pub precondition: Precondition,
+
+    /// Sequence number of the latest write of the key, if any
+    pub actual_seqno: Option<SeqNo>,
+}
+
+/// A precondition placed on some key
+#[derive(Clone, Debug)]
+pub struct Check {
+    pub partition: PartitionKey,
+    pub key: UserKey,
+    pub precondition: Precondition,
+}
+
+/// Latest version of a key, including tombstones
+pub struct Latest {
+    pub seqno: SeqNo,
+
+    /// `None` if the key is deleted
+    pub value: Option<UserValue>,
+}
+
+impl Check {
+    /// Evaluates the precondition against the latest version of the key.
+    pub fn evaluate(&self, latest: Option<&Latest>) -> Result<(), FailedPrecondition> {
+        let holds = match &self.precondition {
+            Precondition::Absent => latest.map_or(true, |x| x.value.is_none()),
+            Precondition::Value(expected) => latest
+                .and_then(|x| x.value.as_ref())
+                .is_some_and(|value| value == expected),
+            Precondition::SeqnoAtMost(seqno) => latest.map_or(true, |x| x.seqno <= *seqno),
+        };
+
+        if holds {
+            Ok(())
+        } else {
+            Err(FailedPrecondition {
+                partition: self.partition.clone(),
+                key: self.key.clone(),
+                precondition: self.precondition.clone(),
+                actual_seqno: latest.map(|x| x.seqno),
+            })
+        }
+    }
+}
+
+/// Reads the latest version of a key, including tombstones.
+///
+/// This takes a read lock on the active memtable, so it
+/// must not be called while the memtable is write-locked.
+pub fn read_latest(tree: &AnyTree, key: &[u8]) -> crate::Result<Option<Latest>> {
+    let entry = match tree {
+        AnyTree::Standard(tree) => tree.get_internal_entry(key, false, None)?,
+        AnyTree::Blob(tree) => tree.index.get_internal_entry(key, false, None)?,
+    };
+
+    entry.map(|entry| resolve(tree, entry)).transpose()
+}
+
+/// Turns an entry of a tree into its latest version, resolving blob indirections.
+pub fn resolve(tree: &AnyTree, entry: InternalValue) -> crate::Result<Latest> {
+    let seqno = entry.key.seqno;
+
+    if entry.is_tombstone() {
+        return Ok(Latest { seqno, value: None });
+    }
+
+    let value = match tree {
+        AnyTree::Standard(_) => entry.value,
+        AnyTree::Blob(tree) => {
+            let mut cursor = Cursor::new(&*entry.value);
+
+            match MaybeInlineValue::decode_from(&mut cursor)? {
+                MaybeInlineValue::Inline(bytes) => bytes,
+                MaybeInlineValue::Indirect { vhandle, .. } => tree
+                    .blobs
+                    .get(&vhandle)
+                    .map_err(lsm_tree::Error::from)?
+                    .ok_or(crate::Error::Storage(lsm_tree::Error::Unrecoverable))?,
+            }
+        }
+    };
+
+    Ok(Latest {
+        seqno,
+        value: Some(value),
+    })
+}
+
+/// Checks all preconditions while the active memtables are write-locked.
+///
+/// `prechecked` contains the latest versions read by [`read_latest`] before
+/// the memtables were locked; any newer version can only be in the active memtable.
+pub fn check_all(
+    checks: &[Check],
+    prechecked: Vec<Option<Latest>>,
+    partitions: &Partitions,
+    locked_memtables: &HashMap<PartitionKey, RwLockWriteGuard<'_, Memtable>>,
+) -> crate::Result<()> {
+    if checks.is_empty() {
+        return Ok(());
+    }
+
+    log::trace!("batch: Checking {} preconditions", checks.len());
+
+    let mut failed = Vec::new();
+
+    for (check, latest) in checks.iter().zip(prechecked) {
+        let (Some(partition), Some(active_memtable)) = (
+            partitions.get(&check.partition),
+            locked_memtables.get(&check.partition),
+        ) else {
+            return Err(crate::Error::PartitionDeleted);
+        };
+
+        let latest = match active_memtable.get(&check.key, None) {
+            Some(entry) => Some(resolve(&partition.tree, entry)?),
+            None => latest,
+        };
+
+        if let Err(e) = check.evaluate(latest.as_ref()) {
+            failed.push(e);
+        }
+    }
+
+    if failed.is_empty() {
+        Ok(())
+    } else {
+        Err(crate::Error::PreconditionFailed(failed))
+    }
+}
@@ -0,0 +1,36 @@
+// Copyright (c) 2024-present, fjall-rs
+// This source code is licensed under both the Apache 2.0 and MIT License
+// (found in the LICENSE-* files in the repository)
+
+use std::time::Duration;
+
+/// Options for [`Keyspace::close`](crate::Keyspace::close)
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+pub struct CloseOptions {
+    /// Flush all memtables to disk segments, so journals can be evicted
+    /// and the next recovery does not need to replay them.
+    ///
+    /// Default = true
+    pub flush_memtables: bool,
+
+    /// Wait until all queued compactions have finished.
+    ///
+    /// Default = false
+    pub wait_for_compactions: bool,
+
+    /// Maximum time to spend flushing and waiting for compactions,
+    /// after which [`Error::Timeout`](crate::Error::Timeout) is returned.
+    ///
+    /// Default = None (wait indefinitely)
+    pub timeout: Option<Duration>,
+}
+
+impl Default for CloseOptions {
+    fn default() -> Self {
+        Self {
+            flush_memtables: true,
+            wait_for_compactions: false,
+            timeout: None,
+        }
+    }
+}
@@ -0,0 +1,272 @@
+// Copyright (c) 2024-present, fjall-rs
+// This source code is licensed under both the Apache 2.0 and MIT License
+// (found in the LICENSE-* files in the repository)
+
+//! Codecs that turn typed keys and values into bytes, see [`TypedPartition`](crate::TypedPartition).
+
+use crate::escape;
+use lsm_tree::Slice;
+
+/// Error returned by a [`Codec`] that could not decode an item
+#[derive(Debug)]
+#[allow(clippy::module_name_repetitions)]
+pub struct CodecError(String);
+
+impl CodecError {
+    /// Creates a new codec error.
+    pub fn new<M: Into<String>>(message: M) -> Self {
+        Self(message.into())
+    }
+
+    /// Returns the error message.
+    #[must_use]
+    pub fn message(&self) -> &str {
+        &self.0
+    }
+}
+
+impl std::fmt::Display for CodecError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "CodecError: {}", self.0)
+    }
+}
+
+impl std::error::Error for CodecError {}
+
+/// Encodes and decodes items of type `T`
+///
+/// Codecs are zero-sized marker types; one codec type can implement
+/// `Codec` for many item types.
+///
+/// When used for keys, the encoding should preserve the order of `T`,
+/// otherwise ranges and iteration order will not match the typed order.
+pub trait Codec<T> {
+    /// Appends the encoded item to the buffer.
+    fn encode_into(item: &T, buf: &mut Vec<u8>);
+
+    /// Encodes the item.
+    fn encode(item: &T) -> Vec<u8> {
+        let mut buf = vec![];
+        Self::encode_into(item, &mut buf);
+        buf
+    }
+
+    /// Decodes an item.
+    ///
+    /// # Errors
+    ///
+    /// Returns error if the bytes are not a valid encoding of `T`.
+    fn decode(bytes: &[u8]) -> Result<T, CodecError>;
+}
+
+/// A type that has an order-preserving, self-delimiting byte encoding
+///
+/// Encoded values sort (bytewise) in the same order as the values themselves,
+/// and can be concatenated into tuples, which sort lexicographically.
+///
+/// - Integers are encoded as fixed-size big-endian numbers (signed integers with the sign bit flipped)
+/// - Strings and byte strings are escaped (0x00 -> 0x00 0xFF) and terminated by 0x00 0x01
+/// - Tuples are the concatenation of their components
+pub trait OrderedKey: Sized {
+    /// Appends the encoded value to the buffer.
+    fn write_ordered(&self, buf: &mut Vec<u8>);
+
+    /// Reads a value from the start of the input, advancing it.
+    ///
+    /// # Errors
+    ///
+    /// Returns error if the input does not start with a valid encoding.
+    fn read_ordered(input: &mut &[u8]) -> Result<Self, CodecError>;
+}
+
+fn take<'a>(input: &mut &'a [u8], len: usize) -> Result<&'a [u8], CodecError> {
+    if input.len() < len {
+        return Err(CodecError::new(format!(
+            "unexpected end of input, expected {len} more bytes"
+        )));
+    }
+
+    let (head, tail) = input.split_at(len);
+    *input = tail;
+    Ok(head)
+}
+
+macro_rules! impl_ordered_unsigned {
+    ($($t:ty),*) => {$(
+        impl OrderedKey for $t {
+            fn write_ordered(&self, buf: &mut Vec<u8>) {
+                buf.extend_from_slice(&self.to_be_bytes());
+            }
+
+            fn read_ordered(input: &mut &[u8]) -> Result<Self, CodecError> {
+                let bytes = take(input, std::mem::size_of::<$t>())?;
+                let mut arr = [0; std::mem::size_of::<$t>()];
+                arr.copy_from_slice(bytes);
+                Ok(<$t>::from_be_bytes(arr))
+            }
+        }
+    )*};
+}
+
+macro_rules! impl_ordered_signed {
+    ($($t:ty => $u:ty),*) => {$(
+        impl OrderedKey for $t {
+            fn write_ordered(&self, buf: &mut Vec<u8>) {
+                // NOTE: Flipping the sign bit makes negative numbers sort before positive ones
+                #[allow(clippy::cast_sign_loss)]
+                let flipped = (*self as $u) ^ (1 << (<$u>::BITS - 1));
+                flipped.write_ordered(buf);
+            }
+
+            fn read_ordered(input: &mut &[u8]) -> Result<Self, CodecError> {
+                let flipped = <$u>::read_ordered(input)?;
+
+                #[allow(clippy::cast_possible_wrap)]
+                Ok((flipped ^ (1 << (<$u>::BITS - 1))) as $t)
+            }
+        }
+    )*};
+}
+
+impl_ordered_unsigned!(u8, u16, u32, u64, u128);
+impl_ordered_signed!(i8 => u8, i16 => u16, i32 => u32, i64 => u64, i128 => u128);
+
+impl OrderedKey for bool {
+    fn write_ordered(&self, buf: &mut Vec<u8>) {
+        buf.push(u8::from(*self));
+    }
+
+    fn read_ordered(input: &mut &[u8]) -> Result<Self, CodecError> {
+        match u8::read_ordered(input)? {
+            0 => Ok(false),
+            1 => Ok(true),
+            byte => Err(CodecError::new(format!("invalid bool: {byte}"))),
+        }
+    }
+}
+
+/// Reads an escaped byte string from the start of the input, advancing it.
+fn read_escaped(input: &mut &[u8]) -> Result<Vec<u8>, CodecError> {
+    let mut iter = input.iter();
+    let out = escape::read_escaped(iter.by_ref().copied())?;
+    *input = iter.as_slice();
+    Ok(out)
+}
+
+impl OrderedKey for Vec<u8> {
+    fn write_ordered(&self, buf: &mut Vec<u8>) {
+        escape::write_escaped(buf, self);
+    }
+
+    fn read_ordered(input: &mut &[u8]) -> Result<Self, CodecError> {
+        read_escaped(input)
+    }
+}
+
+impl OrderedKey for Slice {
+    fn write_ordered(&self, buf: &mut Vec<u8>) {
+        escape::write_escaped(buf, self);
+    }
+
+    fn read_ordered(input: &mut &[u8]) -> Result<Self, CodecError> {
+        read_escaped(input).map(Into::into)
+    }
+}
+
+impl OrderedKey for String {
+    fn write_ordered(&self, buf: &mut Vec<u8>) {
+        escape::write_escaped(buf, self.as_bytes());
+    }
+
+    fn read_ordered(input: &mut &[u8]) -> Result<Self, CodecError> {
+        Self::from_utf8(read_escaped(input)?)
+            .map_err(|e| CodecError::new(format!("invalid UTF-8: {e}")))
+    }
+}
+
+macro_rules! impl_ordered_tuple {
+    ($($name:ident),+) => {
+        impl<$($name: OrderedKey),+> OrderedKey for ($($name,)+) {
+            #[allow(non_snake_case)]
+            fn write_ordered(&self, buf: &mut Vec<u8>) {
+                let ($($name,)+) = self;
+                $($name.write_ordered(buf);)+
+            }
+
+            fn read_ordered(input: &mut &[u8]) -> Result<Self, CodecError> {
+                Ok(($($name::read_ordered(input)?,)+))
+            }
+        }
+    };
+}
+
+impl_ordered_tuple!(A);
+impl_ordered_tuple!(A, B);
+impl_ordered_tuple!(A, B, C);
+impl_ordered_tuple!(A, B, C, D);
+impl_ordered_tuple!(A, B, C, D, E);
+impl_ordered_tuple!(A, B, C, D, E, F);
+
+/// Order-preserving codec for all [`OrderedKey`] types (integers, strings, byte strings & tuples)
+///
+/// Because every encoding is self-delimiting, a tuple's first component(s)
+/// can be used as a prefix, see [`TypedPartition::prefix`](crate::TypedPartition::prefix).
+#[derive(Copy, Clone, Debug, Default)]
+pub struct OrderedCodec;
+
+impl<T: OrderedKey> Codec<T> for OrderedCodec {
+    fn encode_into(item: &T, buf: &mut Vec<u8>) {
+        item.write_ordered(buf);
+    }
+
+    fn decode(mut bytes: &[u8]) -> Result<T, CodecError> {
+        let item = T::read_ordered(&mut bytes)?;
+
+        if !bytes.is_empty() {
+            return Err(CodecError::new(format!(
+                "{} trailing bytes after item",
+                bytes.len()
+            )));
+        }
+
+        Ok(item)
+    }
+}
+
+/// Codec that stores strings and byte strings as-is
+///
+/// Keys keep their bytewise order, and string prefixes can be used
+/// as prefixes, but the encoding cannot be used inside tuples.
+#[derive(Copy, Clone, Debug, Default)]
+pub struct RawCodec;
+
+impl Codec<Vec<u8>> for RawCodec {
+    fn encode_into(item: &Vec<u8>, buf: &mut Vec<u8>) {
+        buf.extend_from_slice(item);
+    }
+
+    fn decode(bytes: &[u8]) -> Result<Vec<u8>, CodecError> {
+        Ok(bytes.to_vec())
+    }
+}
+
+impl Codec<Slice> for RawCodec {
+    fn encode_into(item: &Slice, buf: &mut Vec<u8>) {
+        buf.extend_from_slice(item);
+    }
+
+    fn decode(bytes: &[u8]) -> Result<Slice, CodecError> {
+        Ok(bytes.into())
+    }
+}
+
+impl Codec<String> for RawCodec {
+    fn encode_into(item: &String, buf: &mut Vec<u8>) {
+        buf.extend_from_slice(item.as_bytes());
+    }
+
+    fn decode(bytes: &[u8]) -> Result<String, CodecError> {
+        String::from_utf8(bytes.to_vec())
+            .map_err(|e| CodecError::new(format!("invalid UTF-8: {e}")))
+    }
+}
@@ -2,15 +2,48 @@
 // This source code is licensed under both the Apache 2.0 and MIT License
 // (found in the LICENSE-* files in the repository)
 
-use crate::PartitionHandle;
+use crate::{batch::PartitionKey, HashMap, PartitionHandle};
+use lsm_tree::AbstractTree;
 use std::{
-    collections::VecDeque,
     sync::{Arc, Mutex},
+    time::Instant,
 };
 use std_semaphore::Semaphore;
 
+struct CompactionQueue {
+    /// Partitions that are waiting for compaction, each partition is queued at most once
+    partitions: Vec<PartitionHandle>,
+
+    /// Time of the last compaction per partition
+    last_compaction: HashMap<PartitionKey, Instant>,
+
+    /// Time the queue was created, used for partitions that have not been compacted yet
+    created_at: Instant,
+}
+
+impl CompactionQueue {
+    /// Scores a queued partition, the partition with the highest score is compacted first.
+    ///
+    /// The score grows with the amount of L0 segments, the partition's priority
+    /// and the time since the partition was last compacted, so low priority partitions
+    /// cannot be starved forever.
+    #[allow(clippy::cast_precision_loss)]
+    fn score(&self, partition: &PartitionHandle, now: Instant) -> f64 {
+        let l0_segments = partition.tree.first_level_segment_count() as f64;
+        let priority = f64::from(partition.compaction_priority());
+
+        let last_compaction = self
+            .last_compaction
+            .get(&partition.name)
+            .unwrap_or(&self.created_at);
+        let waited_secs = now.duration_since(*last_compaction).as_secs_f64();
+
+        priority * (1.0 + l0_segments) * (1.0 + waited_secs)
+    }
+}
+
 pub struct CompactionManagerInner {
-    partitions: Mutex<VecDeque<PartitionHandle>>,
+    queue: Mutex<CompactionQueue>,
     semaphore: Semaphore,
 }
 
@@ -23,17 +56,23 @@ impl Drop for CompactionManagerInner {
 impl Default for CompactionManagerInner {
     fn default() -> Self {
         Self {
-            partitions: Mutex::new(VecDeque::with_capacity(10)),
+            queue: Mutex::new(CompactionQueue {
+                partitions: Vec::with_capacity(10),
+                last_compaction: HashMap::default(),
+                created_at: Instant::now(),
+            }),
             semaphore: Semaphore::new(0),
         }
     }
 }
 
 /// The compaction manager keeps track of which partitions
-/// have recently been flushed in a FIFO queue.
+/// have recently been flushed and need to be checked for compaction.
 ///
 /// Its semaphore notifies compaction threads which will wake
-/// up and consume the queue items.
+/// up and consume the queue items. A partition is queued at most once,
+/// and the queued partition with the highest score is compacted first,
+/// see [`PartitionHandle::set_compaction_priority`].
 ///
 /// The semaphore is incremented by the flush worker and optionally
 /// by the individual partitions in case of write halting.
@@ -51,12 +90,24 @@ impl std::ops::Deref for CompactionManager {
 
 impl CompactionManager {
     pub fn clear(&self) {
-        self.partitions.lock().expect("lock is poisoned").clear();
+        let mut lock = self.queue.lock().expect("lock is poisoned");
+        lock.partitions.clear();
+        lock.last_compaction.clear();
     }
 
     pub fn remove_partition(&self, name: &str) {
-        let mut lock = self.partitions.lock().expect("lock is poisoned");
-        lock.retain(|x| &*x.name != name);
+        let mut lock = self.queue.lock().expect("lock is poisoned");
+        lock.partitions.retain(|x| &*x.name != name);
+        lock.last_compaction.remove(name);
+    }
+
+    /// Returns the amount of partitions that are waiting for compaction.
+    pub fn len(&self) -> usize {
+        self.queue
+            .lock()
+            .expect("lock is poisoned")
+            .partitions
+            .len()
     }
 
     pub fn wait_for(&self) {
@@ -64,8 +115,17 @@ impl CompactionManager {
     }
 
     pub fn notify(&self, partition: PartitionHandle) {
-        let mut lock = self.partitions.lock().expect("lock is poisoned");
-        lock.push_back(partition);
+        let mut lock = self.queue.lock().expect("lock is poisoned");
+
+        // NOTE: The partition will be checked for compaction anyway,
+        // so don't wake up another compaction worker
+        if lock.partitions.contains(&partition) {
+            return;
+        }
+
+        lock.partitions.push(partition);
+        drop(lock);
+
         self.semaphore.release();
     }
 
@@ -73,8 +133,21 @@ impl CompactionManager {
         self.semaphore.release();
     }
 
+    /// Takes the partition with the highest score out of the queue.
     pub fn pop(&self) -> Option<PartitionHandle> {
-        let mut lock = self.partitions.lock().expect("lock is poisoned");
-        lock.pop_front()
+        let mut lock = self.queue.lock().expect("lock is poisoned");
+        let now = Instant::now();
+
+        let (idx, _) = lock
+            .partitions
+            .iter()
+            .map(|partition| lock.score(partition, now))
+            .enumerate()
+            .max_by(|(_, a), (_, b)| a.total_cmp(b))?;
+
+        let partition = lock.partitions.swap_remove(idx);
+        lock.last_compaction.insert(partition.name.clone(), now);
+
+        Some(partition)
     }
 }
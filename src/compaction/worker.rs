@@ -3,21 +3,75 @@
 // (found in the LICENSE-* files in the repository)
 
 use super::manager::CompactionManager;
-use crate::snapshot_tracker::SnapshotTracker;
-use lsm_tree::AbstractTree;
+use crate::{rate_limiter::IoActivity, snapshot_tracker::SnapshotTracker, PartitionHandle};
+use lsm_tree::{
+    compaction::{Choice, CompactionStrategy},
+    stop_signal::StopSignal,
+    AbstractTree, AnyTree,
+};
 
-/// Runs a single run of compaction.
-pub fn run(compaction_manager: &CompactionManager, snapshot_tracker: &SnapshotTracker) {
+/// Returns how many bytes the next compaction of the tree will rewrite,
+/// which is the size of the segments the strategy chooses to merge.
+fn planned_compaction_bytes(tree: &AnyTree, strategy: &dyn CompactionStrategy) -> u64 {
+    let tree = match tree {
+        AnyTree::Standard(tree) => tree,
+        AnyTree::Blob(tree) => &tree.index,
+    };
+
+    // NOTE: Compaction fails anyway if the levels are poisoned
+    let Ok(levels) = tree.levels.read() else {
+        return 0;
+    };
+
+    let Choice::Merge(input) = strategy.choose(&levels, &tree.config) else {
+        return 0;
+    };
+
+    levels
+        .iter()
+        .filter(|segment| input.segment_ids.contains(&segment.metadata.id))
+        .map(|segment| segment.metadata.file_size)
+        .sum()
+}
+
+/// Runs a single run of compaction on the partition that is next in line.
+pub fn run(
+    compaction_manager: &CompactionManager,
+    snapshot_tracker: &SnapshotTracker,
+    stop_signal: &StopSignal,
+) {
     let Some(item) = compaction_manager.pop() else {
         return;
     };
 
+    compact(&item, snapshot_tracker, stop_signal);
+}
+
+/// Compacts a single partition.
+pub fn compact(
+    item: &PartitionHandle,
+    snapshot_tracker: &SnapshotTracker,
+    stop_signal: &StopSignal,
+) {
     log::trace!(
         "compactor: calling compaction strategy for partition {:?}",
         item.0.name
     );
 
     let strategy = item.config.compaction_strategy.clone();
+    let rate_limiter = &item.keyspace_config.rate_limiter;
+
+    // NOTE: lsm-tree writes a compaction in one go, so the bytes it will rewrite
+    // are paid off before it starts, instead of after it has written them
+    if rate_limiter.limit(IoActivity::Compaction).is_some() {
+        let bytes = planned_compaction_bytes(&item.tree, &*strategy.inner());
+        rate_limiter.acquire(IoActivity::Compaction, bytes, Some(stop_signal));
+
+        if stop_signal.is_stopped() {
+            log::debug!("compactor: stopping before compaction because of stop signal");
+            return;
+        }
+    }
 
     // TODO: loop if there's more work to do
 
@@ -2,13 +2,25 @@
 // This source code is licensed under both the Apache 2.0 and MIT License
 // (found in the LICENSE-* files in the repository)
 
-use crate::{journal::error::RecoveryMode, path::absolute_path, Keyspace};
+use crate::{
+    encryption::Keys,
+    fs::{Fs, StdFs},
+    journal::error::RecoveryMode,
+    path::absolute_path,
+    rate_limiter::{IoActivity, RateLimit, RateLimiter},
+    write_stall::{DefaultWriteStallPolicy, WriteAdmission, WriteStallPolicy},
+    Keyspace,
+};
+
 use lsm_tree::{descriptor_table::FileDescriptorTable, BlobCache, BlockCache};
 use std::{
     path::{Path, PathBuf},
     sync::Arc,
 };
 
+#[cfg(feature = "encryption")]
+use crate::encryption::KeyProvider;
+
 /// Global keyspace configuration
 #[derive(Clone)]
 pub struct Config {
@@ -50,6 +62,35 @@ pub struct Config {
     pub(crate) fsync_ms: Option<u16>,
 
     pub(crate) journal_recovery_mode: RecoveryMode,
+
+    /// How writes are admitted when writes are stalled or halted
+    pub(crate) write_admission: WriteAdmission,
+
+    /// Decides when writes are stalled or halted
+    pub(crate) write_stall_policy: Arc<dyn WriteStallPolicy>,
+
+    /// Limits background I/O
+    pub(crate) rate_limiter: RateLimiter,
+
+    /// If `true`, the keyspace is never written to
+    pub(crate) read_only: bool,
+
+    /// If set, the keyspace is a secondary of the keyspace at `path`,
+    /// mirroring its partitions into this folder
+    pub(crate) secondary_path: Option<PathBuf>,
+
+    /// Catch up with the primary every N ms
+    pub(crate) catch_up_ms: Option<u16>,
+
+    /// File system that journals and keyspace metadata are written through
+    pub(crate) fs: Arc<dyn Fs>,
+
+    /// Supplies the keys that journals are encrypted with
+    pub(crate) encryption: Keys,
+
+    /// Max time a pessimistic transaction waits for a lock
+    #[cfg(feature = "pessimistic_tx")]
+    pub(crate) tx_lock_timeout: std::time::Duration,
 }
 
 const DEFAULT_CPU_CORES: usize = 4;
@@ -87,6 +128,17 @@ impl Default for Config {
             compaction_workers_count: cpus.min(4),
             journal_recovery_mode: RecoveryMode::default(),
             manual_journal_persist: false,
+            write_admission: WriteAdmission::default(),
+            write_stall_policy: Arc::new(DefaultWriteStallPolicy),
+            rate_limiter: RateLimiter::default(),
+            read_only: false,
+            secondary_path: None,
+            catch_up_ms: None,
+            fs: Arc::new(StdFs),
+            encryption: None,
+
+            #[cfg(feature = "pessimistic_tx")]
+            tx_lock_timeout: std::time::Duration::from_secs(5),
         }
     }
 }
@@ -111,6 +163,215 @@ impl Config {
         self
     }
 
+    /// Sets how writes are admitted when flushing or compaction cannot keep up.
+    ///
+    /// With [`WriteAdmission::FailFast`], writes (and batches) that would be
+    /// stalled or halted return [`Error::WriteStalled`](crate::Error::WriteStalled)
+    /// instead of sleeping, and are not written.
+    ///
+    /// Default = [`WriteAdmission::Wait`]
+    #[must_use]
+    pub fn write_admission(mut self, admission: WriteAdmission) -> Self {
+        self.write_admission = admission;
+        self
+    }
+
+    /// Sets the policy that decides when writes are stalled or halted,
+    /// so flushing and compaction can keep up.
+    ///
+    /// Partitions can override the policy using
+    /// [`PartitionHandle::set_write_stall_policy`](crate::PartitionHandle::set_write_stall_policy).
+    ///
+    /// Default = [`DefaultWriteStallPolicy`]
+    #[must_use]
+    pub fn write_stall_policy<P: WriteStallPolicy + 'static>(mut self, policy: P) -> Self {
+        self.write_stall_policy = Arc::new(policy);
+        self
+    }
+
+    /// Limits the write rate of a background activity (flush, compaction or GC).
+    ///
+    /// Limits can be changed at runtime using [`Keyspace::rate_limiter`].
+    ///
+    /// Default = unlimited
+    #[must_use]
+    pub fn rate_limit(self, activity: IoActivity, limit: RateLimit) -> Self {
+        self.rate_limiter.set_limit(activity, Some(limit));
+        self
+    }
+
+    /// Sets the maximum time a pessimistic transaction waits to acquire a lock.
+    ///
+    /// Default = 5 seconds
+    #[cfg(feature = "pessimistic_tx")]
+    #[must_use]
+    pub fn tx_lock_timeout(mut self, timeout: std::time::Duration) -> Self {
+        self.tx_lock_timeout = timeout;
+        self
+    }
+
+    /// Opens the keyspace in read-only mode.
+    ///
+    /// Recovery replays the journals into memtables, but does not truncate,
+    /// rotate or create journal files, and does not clean up deleted partitions.
+    /// No flush, compaction or fsync threads are started.
+    ///
+    /// Every write (including creating or deleting partitions, compacting, garbage collection
+    /// and persisting) returns [`Error::ReadOnly`](crate::Error::ReadOnly).
+    /// Opening a folder that does not contain a keyspace also returns [`Error::ReadOnly`](crate::Error::ReadOnly).
+    ///
+    /// This allows inspecting a copy of a keyspace, without any risk of modifying it.
+    /// If a partition contains segment files left unfinished by a crashed flush or compaction,
+    /// opening fails with [`Error::ReadOnly`](crate::Error::ReadOnly), because recovering
+    /// the partition would delete them. Open the keyspace for writing once to clean them up.
+    ///
+    /// Default = false
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use fjall::{Config, PartitionCreateOptions};
+    /// # let folder = tempfile::tempdir()?;
+    /// # {
+    /// #     let keyspace = Config::new(&folder).open()?;
+    /// #     let items = keyspace.open_partition("items", PartitionCreateOptions::default())?;
+    /// #     items.insert("a", "hello")?;
+    /// # }
+    /// let keyspace = Config::new(&folder).read_only(true).open()?;
+    /// let items = keyspace.open_partition("items", PartitionCreateOptions::default())?;
+    ///
+    /// assert_eq!(Some("hello".as_bytes().into()), items.get("a")?);
+    /// assert!(matches!(items.insert("b", "world"), Err(fjall::Error::ReadOnly)));
+    /// #
+    /// # Ok::<(), fjall::Error>(())
+    /// ```
+    #[must_use]
+    pub fn read_only(mut self, flag: bool) -> Self {
+        self.read_only = flag;
+        self
+    }
+
+    /// Opens the keyspace as a secondary of the (primary) keyspace at the configured path.
+    ///
+    /// A secondary is a [read-only](Config::read_only) keyspace, that follows the writes of
+    /// a primary keyspace, which is opened by another process on the same host.
+    /// It does not need to be the only secondary, and does not lock the primary's folder.
+    ///
+    /// Partitions are opened from a mirror in the given folder,
+    /// which hard links the primary's segment files (or copies them, if hard links
+    /// are not supported), so the primary is free to delete files the secondary still reads.
+    /// The mirror folder is locked, so it can only be used by one secondary at a time.
+    ///
+    /// The secondary is brought up to date with [`Keyspace::catch_up`],
+    /// see also [`Config::catch_up_ms`].
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use fjall::{Config, PartitionCreateOptions};
+    /// # let folder = tempfile::tempdir()?;
+    /// # let secondary_folder = tempfile::tempdir()?;
+    /// let primary = Config::new(&folder).open()?;
+    /// let items = primary.open_partition("items", PartitionCreateOptions::default())?;
+    /// items.insert("a", "hello")?;
+    ///
+    /// let secondary = Config::new(&folder).secondary(&secondary_folder).open()?;
+    /// let secondary_items = secondary.open_partition("items", PartitionCreateOptions::default())?;
+    /// assert_eq!(Some("hello".as_bytes().into()), secondary_items.get("a")?);
+    ///
+    /// items.insert("b", "world")?;
+    /// assert!(!secondary_items.contains_key("b")?);
+    ///
+    /// secondary.catch_up()?;
+    /// assert_eq!(Some("world".as_bytes().into()), secondary_items.get("b")?);
+    /// #
+    /// # Ok::<(), fjall::Error>(())
+    /// ```
+    #[must_use]
+    pub fn secondary<P: AsRef<Path>>(mut self, path: P) -> Self {
+        self.secondary_path = Some(absolute_path(path));
+        self.read_only = true;
+        self
+    }
+
+    /// If Some, starts a thread that calls [`Keyspace::catch_up`] every N ms,
+    /// if the keyspace is opened as a [secondary](Config::secondary).
+    ///
+    /// Default = off
+    ///
+    /// # Panics
+    ///
+    /// Panics if ms is 0.
+    #[must_use]
+    pub fn catch_up_ms(mut self, ms: Option<u16>) -> Self {
+        if let Some(ms) = ms {
+            assert!(ms > 0);
+        }
+
+        self.catch_up_ms = ms;
+        self
+    }
+
+    /// Sets the file system that journals and keyspace metadata are written and recovered through.
+    ///
+    /// This is mostly useful to inject faults in tests, see [`FaultyFs`](crate::FaultyFs).
+    ///
+    /// Segments and blob files are written and read by `lsm-tree`,
+    /// using the OS file system directly.
+    ///
+    /// Default = [`StdFs`]
+    #[must_use]
+    pub fn fs<F: Fs + 'static>(mut self, fs: F) -> Self {
+        self.fs = Arc::new(fs);
+        self
+    }
+
+    /// Encrypts journals with keys of the given key provider.
+    ///
+    /// Every batch is encrypted using `XChaCha20-Poly1305`, with a random nonce.
+    /// Every journal stores the ID of its key in its header, so keys can be rotated
+    /// by changing the [current key](crate::KeyProvider::current_key_id), as long as
+    /// the key provider knows the keys of all journals that have not been evicted yet.
+    ///
+    /// Opening the keyspace fails with [`Error::Encryption`](crate::Error::Encryption)
+    /// if a key is unknown or wrong.
+    ///
+    /// Journals that were written before encryption was enabled are still read,
+    /// only new journals are encrypted.
+    ///
+    /// Segments and blob files are written by `lsm-tree`, and are **not** encrypted.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use fjall::{Config, EncryptionKey, KeyId, KeyProvider};
+    ///
+    /// struct StaticKey(EncryptionKey);
+    ///
+    /// impl KeyProvider for StaticKey {
+    ///     fn current_key_id(&self) -> KeyId {
+    ///         0
+    ///     }
+    ///
+    ///     fn key(&self, id: KeyId) -> Option<EncryptionKey> {
+    ///         (id == 0).then_some(self.0)
+    ///     }
+    /// }
+    ///
+    /// # let folder = tempfile::tempdir()?;
+    /// let keyspace = Config::new(folder)
+    ///     .encryption(StaticKey([7; 32]))
+    ///     .open()?;
+    /// #
+    /// # Ok::<(), fjall::Error>(())
+    /// ```
+    #[cfg(feature = "encryption")]
+    #[must_use]
+    pub fn encryption<K: KeyProvider + 'static>(mut self, keys: K) -> Self {
+        self.encryption = Some(Arc::new(keys));
+        self
+    }
+
     /// Sets the amount of flush workers
     ///
     /// Default = # CPU cores
@@ -233,7 +494,11 @@ impl Config {
     /// # Errors
     ///
     /// Will return `Err` if an IO error occurs.
-    #[cfg(any(feature = "single_writer_tx", feature = "ssi_tx"))]
+    #[cfg(any(
+        feature = "single_writer_tx",
+        feature = "ssi_tx",
+        feature = "pessimistic_tx"
+    ))]
     pub fn open_transactional(self) -> crate::Result<crate::TxKeyspace> {
         crate::TxKeyspace::open(self)
     }
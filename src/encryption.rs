@@ -0,0 +1,296 @@
+// Copyright (c) 2024-present, fjall-rs
+// This source code is licensed under both the Apache 2.0 and MIT License
+// (found in the LICENSE-* files in the repository)
+
+use crate::EncryptionError;
+use byteorder::{BigEndian, ReadBytesExt};
+use std::{
+    io::{Read, Seek, SeekFrom, Write},
+    sync::Arc,
+};
+
+#[cfg(feature = "encryption")]
+use byteorder::WriteBytesExt;
+
+#[cfg(feature = "encryption")]
+use chacha20poly1305::{
+    aead::{rand_core::RngCore, Aead, AeadCore, KeyInit, OsRng, Payload},
+    Key, XChaCha20Poly1305, XNonce,
+};
+
+/// ID of an encryption key, which is stored in the header of every encrypted journal
+pub type KeyId = u32;
+
+/// 256-bit encryption key
+pub type EncryptionKey = [u8; 32];
+
+/// Supplies the keys that journals are encrypted with, see [`Config::encryption`](crate::Config::encryption)
+///
+/// Every journal stores the ID of its key, so keys can be rotated:
+/// new journals are encrypted with the current key, while older journals
+/// are still decrypted with the key they were written with.
+#[cfg_attr(not(feature = "encryption"), allow(dead_code))]
+pub trait KeyProvider: Send + Sync {
+    /// Returns the ID of the key that new journals are encrypted with.
+    fn current_key_id(&self) -> KeyId;
+
+    /// Returns the key with the given ID, `None` if the key is unknown.
+    fn key(&self, id: KeyId) -> Option<EncryptionKey>;
+}
+
+/// Key provider of the keyspace, `None` if journals are not encrypted
+pub type Keys = Option<Arc<dyn KeyProvider>>;
+
+/// Marks an encrypted journal
+///
+/// The first byte is never a valid marker tag, so plaintext journals are not mistaken for encrypted ones.
+const MAGIC: &[u8; 4] = b"FJE\x01";
+
+const SALT_LEN: usize = 16;
+const NONCE_LEN: usize = 24;
+const TAG_LEN: usize = 16;
+
+/// Magic bytes, key ID and random salt, which every frame is bound to
+const HEADER_AAD_LEN: usize = MAGIC.len() + std::mem::size_of::<KeyId>() + SALT_LEN;
+
+/// Header, followed by a nonce and the tag of an empty message, which checks the key
+pub const HEADER_LEN: u64 = (HEADER_AAD_LEN + NONCE_LEN + TAG_LEN) as u64;
+
+/// Length prefix, nonce and tag that a frame adds to the markers of a batch
+#[cfg(feature = "encryption")]
+const FRAME_OVERHEAD: usize = std::mem::size_of::<u32>() + NONCE_LEN + TAG_LEN;
+
+fn read_key_id(aad: &[u8; HEADER_AAD_LEN]) -> std::io::Result<KeyId> {
+    let mut reader = std::io::Cursor::new(aad);
+    reader.set_position(MAGIC.len() as u64);
+    reader.read_u32::<BigEndian>()
+}
+
+/// Encrypts the batches of a single journal file
+///
+/// An encrypted journal starts with a header (see [`HEADER_LEN`]) and contains one frame per batch:
+/// the ciphertext length (u32), a random nonce and the `XChaCha20-Poly1305` ciphertext of the batch's markers.
+/// The nonce is random for every frame, so rewriting a frame after truncating
+/// a torn write never reuses a nonce.
+pub struct FileCipher {
+    #[cfg_attr(not(feature = "encryption"), allow(dead_code))]
+    aad: [u8; HEADER_AAD_LEN],
+
+    #[cfg(feature = "encryption")]
+    cipher: XChaCha20Poly1305,
+}
+
+impl FileCipher {
+    /// Creates the cipher of a new journal, using the current key, and writes its header.
+    #[cfg(feature = "encryption")]
+    pub fn create<W: Write>(keys: &dyn KeyProvider, writer: &mut W) -> crate::Result<Self> {
+        let key_id = keys.current_key_id();
+        let key = keys
+            .key(key_id)
+            .ok_or(EncryptionError::UnknownKey(key_id))?;
+
+        let mut aad = [0; HEADER_AAD_LEN];
+        {
+            let mut aad = aad.as_mut_slice();
+            aad.write_all(MAGIC)?;
+            aad.write_u32::<BigEndian>(key_id)?;
+
+            let mut salt = [0; SALT_LEN];
+            OsRng.fill_bytes(&mut salt);
+            aad.write_all(&salt)?;
+        }
+
+        let this = Self {
+            aad,
+            cipher: XChaCha20Poly1305::new(Key::from_slice(&key)),
+        };
+
+        writer.write_all(&this.aad)?;
+        this.write_sealed(&[], writer)?;
+
+        Ok(this)
+    }
+
+    /// Creates the cipher of a new journal, using the current key, and writes its header.
+    #[cfg(not(feature = "encryption"))]
+    pub fn create<W: Write>(_keys: &dyn KeyProvider, _writer: &mut W) -> crate::Result<Self> {
+        Err(EncryptionError::NoKeyProvider.into())
+    }
+
+    /// Reads the header of a journal, and checks that the key matches.
+    ///
+    /// Returns `None` if the journal is not encrypted, leaving the reader at the start of the file.
+    pub fn read_header<R: Read + Seek>(reader: &mut R, keys: &Keys) -> crate::Result<Option<Self>> {
+        let mut aad = [0; HEADER_AAD_LEN];
+
+        // NOTE: Plaintext journals may be shorter than the header, or empty
+        let is_encrypted = match reader.read_exact(&mut aad) {
+            Ok(()) => aad.starts_with(MAGIC),
+            Err(e) if e.kind() == std::io::ErrorKind::UnexpectedEof => false,
+            Err(e) => return Err(e.into()),
+        };
+
+        if !is_encrypted {
+            reader.seek(SeekFrom::Start(0))?;
+            return Ok(None);
+        }
+
+        let Some(keys) = keys else {
+            return Err(EncryptionError::NoKeyProvider.into());
+        };
+
+        let key_id = read_key_id(&aad)?;
+
+        let Some(key) = keys.key(key_id) else {
+            return Err(EncryptionError::UnknownKey(key_id).into());
+        };
+
+        Self::with_key(aad, &key, reader)
+    }
+
+    #[cfg(feature = "encryption")]
+    fn with_key<R: Read>(
+        aad: [u8; HEADER_AAD_LEN],
+        key: &EncryptionKey,
+        reader: &mut R,
+    ) -> crate::Result<Option<Self>> {
+        let key_id = read_key_id(&aad)?;
+
+        let this = Self {
+            aad,
+            cipher: XChaCha20Poly1305::new(Key::from_slice(key)),
+        };
+
+        let mut check = [0; NONCE_LEN + TAG_LEN];
+        reader.read_exact(&mut check)?;
+
+        let (nonce, tag) = check.split_at(NONCE_LEN);
+
+        if this.open(nonce, tag).is_none() {
+            return Err(EncryptionError::WrongKey(key_id).into());
+        }
+
+        Ok(Some(this))
+    }
+
+    #[cfg(not(feature = "encryption"))]
+    fn with_key<R: Read>(
+        _aad: [u8; HEADER_AAD_LEN],
+        _key: &EncryptionKey,
+        _reader: &mut R,
+    ) -> crate::Result<Option<Self>> {
+        Err(EncryptionError::NoKeyProvider.into())
+    }
+
+    /// Encrypts the markers of a batch, and writes them as a frame.
+    ///
+    /// Returns the amount of bytes written.
+    #[cfg(feature = "encryption")]
+    pub fn write_frame<W: Write>(&self, plaintext: &[u8], writer: &mut W) -> crate::Result<usize> {
+        let ciphertext_len = plaintext.len() + TAG_LEN;
+
+        // NOTE: Batches are limited by the item count (u32) and value size (u32),
+        // but a single frame needs to fit its length prefix
+        let Ok(len) = u32::try_from(ciphertext_len) else {
+            return Err(crate::Error::Io(std::io::Error::new(
+                std::io::ErrorKind::InvalidInput,
+                "batch is too large to be encrypted",
+            )));
+        };
+
+        writer.write_u32::<BigEndian>(len)?;
+        self.write_sealed(plaintext, writer)?;
+
+        Ok(plaintext.len() + FRAME_OVERHEAD)
+    }
+
+    #[cfg(not(feature = "encryption"))]
+    #[allow(clippy::unused_self)]
+    pub fn write_frame<W: Write>(
+        &self,
+        _plaintext: &[u8],
+        _writer: &mut W,
+    ) -> crate::Result<usize> {
+        Err(EncryptionError::NoKeyProvider.into())
+    }
+
+    /// Reads and decrypts the next frame.
+    ///
+    /// Returns `None` if there is no complete, authentic frame,
+    /// for example because it was only partially written (or the rest of the file is zeroed).
+    pub fn read_frame<R: Read>(&self, reader: &mut R) -> std::io::Result<Option<Vec<u8>>> {
+        let len = match reader.read_u32::<BigEndian>() {
+            Ok(0) => return Ok(None),
+            Ok(len) => len as usize,
+            Err(e) if e.kind() == std::io::ErrorKind::UnexpectedEof => return Ok(None),
+            Err(e) => return Err(e),
+        };
+
+        if len < TAG_LEN {
+            return Ok(None);
+        }
+
+        let mut nonce = [0; NONCE_LEN];
+        let mut ciphertext = vec![];
+
+        // NOTE: Do not allocate a length that was read from a torn frame up front
+        let read = reader.read_exact(&mut nonce).and_then(|()| {
+            reader
+                .take(len as u64)
+                .read_to_end(&mut ciphertext)
+                .map(|_| ())
+        });
+
+        match read {
+            Ok(()) if ciphertext.len() == len => Ok(self.open(&nonce, &ciphertext)),
+            Ok(()) => Ok(None),
+            Err(e) if e.kind() == std::io::ErrorKind::UnexpectedEof => Ok(None),
+            Err(e) => Err(e),
+        }
+    }
+
+    #[cfg(feature = "encryption")]
+    fn write_sealed<W: Write>(&self, plaintext: &[u8], writer: &mut W) -> crate::Result<()> {
+        let nonce = XChaCha20Poly1305::generate_nonce(&mut OsRng);
+
+        let ciphertext = self
+            .cipher
+            .encrypt(
+                &nonce,
+                Payload {
+                    msg: plaintext,
+                    aad: &self.aad,
+                },
+            )
+            .map_err(|_| {
+                crate::Error::Io(std::io::Error::new(
+                    std::io::ErrorKind::InvalidInput,
+                    "encryption failed",
+                ))
+            })?;
+
+        writer.write_all(&nonce)?;
+        writer.write_all(&ciphertext)?;
+
+        Ok(())
+    }
+
+    #[cfg(feature = "encryption")]
+    fn open(&self, nonce: &[u8], ciphertext: &[u8]) -> Option<Vec<u8>> {
+        self.cipher
+            .decrypt(
+                XNonce::from_slice(nonce),
+                Payload {
+                    msg: ciphertext,
+                    aad: &self.aad,
+                },
+            )
+            .ok()
+    }
+
+    #[cfg(not(feature = "encryption"))]
+    #[allow(clippy::unused_self)]
+    fn open(&self, _nonce: &[u8], _ciphertext: &[u8]) -> Option<Vec<u8>> {
+        None
+    }
+}
@@ -2,8 +2,64 @@
 // This source code is licensed under both the Apache 2.0 and MIT License
 // (found in the LICENSE-* files in the repository)
 
-use crate::{journal::error::RecoveryError as JournalRecoveryError, version::Version};
+use crate::{
+    batch::{precondition::FailedPrecondition, PartitionKey},
+    journal::error::RecoveryError as JournalRecoveryError,
+    replication::ReplicationError,
+    typed::TypedDecodeError,
+    version::Version,
+    write_stall::WriteStallReason,
+};
 use lsm_tree::{DecodeError, EncodeError};
+use std::fmt;
+
+/// Reason why a pessimistic transaction could not acquire a lock, see [`Error::Lock`]
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub enum LockError {
+    /// Waiting for the lock would have caused a deadlock
+    ///
+    /// The transaction should be rolled back and retried.
+    Deadlock,
+
+    /// The lock could not be acquired in time
+    Timeout,
+}
+
+impl std::error::Error for LockError {}
+
+impl fmt::Display for LockError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Deadlock => "transaction deadlock".fmt(f),
+            Self::Timeout => "transaction lock timeout".fmt(f),
+        }
+    }
+}
+
+/// Reason why an encrypted journal could not be read, see [`Error::Encryption`]
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub enum EncryptionError {
+    /// The journal is encrypted, but the keyspace has no key provider
+    NoKeyProvider,
+
+    /// The key provider does not know the key with this ID
+    UnknownKey(u32),
+
+    /// The key with this ID is not the key the journal was encrypted with
+    WrongKey(u32),
+}
+
+impl std::error::Error for EncryptionError {}
+
+impl fmt::Display for EncryptionError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::NoKeyProvider => "journal is encrypted, but no key provider is configured".fmt(f),
+            Self::UnknownKey(id) => write!(f, "unknown encryption key {id}"),
+            Self::WrongKey(id) => write!(f, "wrong encryption key {id}"),
+        }
+    }
+}
 
 /// Errors that may occur in the storage engine
 #[derive(Debug)]
@@ -37,6 +93,69 @@ pub enum Error {
 
     /// Partition is deleted
     PartitionDeleted,
+
+    /// Some preconditions of a batch did not hold, so it was not committed
+    PreconditionFailed(Vec<FailedPrecondition>),
+
+    /// A typed partition could not decode a stored key or value
+    TypedDecode(TypedDecodeError),
+
+    /// The write was rejected because writes are stalled,
+    /// see [`WriteAdmission::FailFast`](crate::WriteAdmission::FailFast)
+    ///
+    /// Nothing was written.
+    WriteStalled {
+        /// Why writes are stalled
+        reason: WriteStallReason,
+
+        /// Suggested time to wait before retrying
+        retry_after: std::time::Duration,
+    },
+
+    /// An operation did not finish in time, see [`CloseOptions::timeout`](crate::CloseOptions::timeout)
+    Timeout,
+
+    /// The keyspace folder is locked, because the keyspace is already opened,
+    /// either by another process or in this process
+    ///
+    /// Only one keyspace can be opened for writing at a time, see [`Keyspace::check_lock`](crate::Keyspace::check_lock).
+    Locked {
+        /// PID of the process holding the lock, if known
+        pid: Option<u32>,
+    },
+
+    /// The keyspace is opened in read-only mode, see [`Config::read_only`](crate::Config::read_only)
+    ///
+    /// Nothing was written.
+    ///
+    /// Also returned when opening a keyspace read-only, if recovering a partition
+    /// would need to delete unfinished or unreferenced segment files.
+    ReadOnly,
+
+    /// A replicated message could not be applied, see [`ReplicaApplier`](crate::ReplicaApplier)
+    Replication(ReplicationError),
+
+    /// A partition could not be opened, because it has a secondary index that was not
+    /// registered since the keyspace was opened, see [`Keyspace::create_index`](crate::Keyspace::create_index)
+    IndexNotRegistered(PartitionKey),
+
+    /// A write transaction was rolled back to a savepoint that was released,
+    /// or that belongs to another transaction
+    ///
+    /// Nothing was rolled back.
+    InvalidSavepoint,
+
+    /// A pessimistic transaction could not lock a key or range,
+    /// either when locking explicitly (`lock`, `lock_range`, `lock_prefix`, `get_for_update`)
+    /// or when writing, see `Config::tx_lock_timeout`
+    ///
+    /// Only returned with the `pessimistic_tx` feature.
+    Lock(LockError),
+
+    /// A journal could not be decrypted, see `Config::encryption`
+    ///
+    /// Only returned with the `encryption` feature, or when opening an encrypted keyspace without it.
+    Encryption(EncryptionError),
 }
 
 impl std::fmt::Display for Error {
@@ -69,6 +188,24 @@ impl From<lsm_tree::Error> for Error {
     }
 }
 
+impl From<ReplicationError> for Error {
+    fn from(value: ReplicationError) -> Self {
+        Self::Replication(value)
+    }
+}
+
+impl From<LockError> for Error {
+    fn from(value: LockError) -> Self {
+        Self::Lock(value)
+    }
+}
+
+impl From<EncryptionError> for Error {
+    fn from(value: EncryptionError) -> Self {
+        Self::Encryption(value)
+    }
+}
+
 impl std::error::Error for Error {}
 
 /// Result helper type
@@ -0,0 +1,62 @@
+// Copyright (c) 2024-present, fjall-rs
+// This source code is licensed under both the Apache 2.0 and MIT License
+// (found in the LICENSE-* files in the repository)
+
+//! Byte stuffing of variable-length byte strings inside order-preserving keys
+//!
+//! Byte strings are escaped (0x00 -> 0x00 0xFF) and terminated (0x00 0x01),
+//! so an escaped byte string sorts like the raw one, and is never a prefix
+//! of another escaped byte string. This allows appending more data
+//! (e.g. further tuple elements) without changing the order.
+//!
+//! The terminator needs two bytes, otherwise a terminated string would be
+//! a prefix of a longer string that continues with an escaped 0x00.
+
+use crate::codec::CodecError;
+
+pub const ESCAPE: u8 = 0x00;
+pub const ESCAPED_ZERO: u8 = 0xFF;
+pub const TERMINATOR: u8 = 0x01;
+
+/// Appends the escaped bytes, without terminating them.
+pub fn write_escaped_unterminated(buf: &mut Vec<u8>, bytes: &[u8]) {
+    for &byte in bytes {
+        buf.push(byte);
+
+        if byte == ESCAPE {
+            buf.push(ESCAPED_ZERO);
+        }
+    }
+}
+
+/// Appends the escaped and terminated bytes.
+pub fn write_escaped(buf: &mut Vec<u8>, bytes: &[u8]) {
+    write_escaped_unterminated(buf, bytes);
+    buf.push(ESCAPE);
+    buf.push(TERMINATOR);
+}
+
+/// Reads an escaped byte string, consuming bytes up to and including its terminator.
+pub fn read_escaped(mut input: impl Iterator<Item = u8>) -> Result<Vec<u8>, CodecError> {
+    let mut out = vec![];
+
+    while let Some(byte) = input.next() {
+        if byte != ESCAPE {
+            out.push(byte);
+            continue;
+        }
+
+        match input.next() {
+            Some(ESCAPED_ZERO) => out.push(ESCAPE),
+            Some(TERMINATOR) => return Ok(out),
+            Some(byte) => {
+                return Err(CodecError::new(format!(
+                    "invalid escape sequence: 0x00 0x{byte:02x}"
+                )))
+            }
+            None => break,
+        }
+    }
+
+    Err(CodecError::new("unterminated byte string"))
+}
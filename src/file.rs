@@ -10,11 +10,15 @@ pub const JOURNALS_FOLDER: &str = "journals";
 pub const PARTITIONS_FOLDER: &str = "partitions";
 
 pub const FJALL_MARKER: &str = "version";
+pub const LOCK_FILE: &str = "lock";
 pub const PARTITION_DELETED_MARKER: &str = ".deleted";
 pub const PARTITION_CONFIG_FILE: &str = "config";
 
 pub const LSM_MANIFEST_FILE: &str = "manifest";
 
+pub const LOST_AND_FOUND_FOLDER: &str = "lost+found";
+pub const REPAIR_LOG_FILE: &str = "repair.log";
+
 #[cfg(not(target_os = "windows"))]
 pub fn fsync_directory<P: AsRef<Path>>(path: P) -> std::io::Result<()> {
     let file = std::fs::File::open(path)?;
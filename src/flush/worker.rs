@@ -5,17 +5,29 @@
 use super::manager::{FlushManager, Task};
 use crate::{
     batch::PartitionKey, compaction::manager::CompactionManager, journal::manager::JournalManager,
-    snapshot_tracker::SnapshotTracker, write_buffer_manager::WriteBufferManager, HashMap,
-    PartitionHandle,
+    rate_limiter::IoActivity, snapshot_tracker::SnapshotTracker,
+    write_buffer_manager::WriteBufferManager, HashMap, PartitionHandle,
 };
-use lsm_tree::{AbstractTree, Segment, SeqNo};
+use lsm_tree::{stop_signal::StopSignal, AbstractTree, Segment, SeqNo};
 use std::sync::{Arc, RwLock};
 
 /// Flushes a single segment.
 fn run_flush_worker(
     task: &Arc<Task>,
     eviction_threshold: SeqNo,
+    stop_signal: &StopSignal,
 ) -> crate::Result<Option<Arc<Segment>>> {
+    // NOTE: Flushes are accounted by memtable size, which also covers
+    // blobs that are written to the value log of KV-separated partitions
+    //
+    // IMPORTANT: Throttle before writing the segment, never between writing
+    // and registering it, otherwise freeing the memtable is delayed
+    task.partition.keyspace_config.rate_limiter.acquire(
+        IoActivity::Flush,
+        u64::from(task.sealed_memtable.size()),
+        Some(stop_signal),
+    );
+
     #[rustfmt::skip]
     let segment = task.partition.tree.flush_memtable(
         // IMPORTANT: Segment has to get the task ID
@@ -44,6 +56,7 @@ type MultiFlushResults = Vec<crate::Result<MultiFlushResultItem>>;
 fn run_multi_flush(
     partitioned_tasks: &HashMap<PartitionKey, Vec<Arc<Task>>>,
     eviction_threshold: SeqNo,
+    stop_signal: &StopSignal,
 ) -> MultiFlushResults {
     log::debug!("spawning {} worker threads", partitioned_tasks.len());
 
@@ -54,6 +67,7 @@ fn run_multi_flush(
         .map(|(partition_name, tasks)| {
             let partition_name = partition_name.clone();
             let tasks = tasks.clone();
+            let stop_signal = stop_signal.clone();
 
             std::thread::spawn(move || {
                 log::trace!(
@@ -77,7 +91,11 @@ fn run_multi_flush(
                 let flush_workers = tasks
                     .into_iter()
                     .map(|task| {
-                        std::thread::spawn(move || run_flush_worker(&task, eviction_threshold))
+                        let stop_signal = stop_signal.clone();
+
+                        std::thread::spawn(move || {
+                            run_flush_worker(&task, eviction_threshold, &stop_signal)
+                        })
                     })
                     .collect::<Vec<_>>();
 
@@ -102,6 +120,8 @@ fn run_multi_flush(
 }
 
 /// Runs flush logic.
+///
+/// Errors are logged, the first error is returned.
 #[allow(clippy::too_many_lines)]
 pub fn run(
     flush_manager: &Arc<RwLock<FlushManager>>,
@@ -109,8 +129,9 @@ pub fn run(
     compaction_manager: &CompactionManager,
     write_buffer_manager: &WriteBufferManager,
     snapshot_tracker: &SnapshotTracker,
+    stop_signal: &StopSignal,
     parallelism: usize,
-) {
+) -> crate::Result<()> {
     log::debug!("write locking flush manager");
     let mut fm = flush_manager.write().expect("lock is poisoned");
     let partitioned_tasks = fm.collect_tasks(parallelism);
@@ -120,10 +141,16 @@ pub fn run(
 
     if task_count == 0 {
         log::debug!("No tasks collected");
-        return;
+        return Ok(());
     }
 
-    for result in run_multi_flush(&partitioned_tasks, snapshot_tracker.get_seqno_safe_to_gc()) {
+    let mut first_error = None;
+
+    for result in run_multi_flush(
+        &partitioned_tasks,
+        snapshot_tracker.get_seqno_safe_to_gc(),
+        stop_signal,
+    ) {
         match result {
             Ok(MultiFlushResultItem {
                 partition,
@@ -134,6 +161,7 @@ pub fn run(
                 // otherwise we could cover up an unwritten journal, which will result in data loss
                 if let Err(e) = partition.tree.register_segments(&created_segments) {
                     log::error!("Failed to register segments: {e:?}");
+                    first_error.get_or_insert_with(|| e.into());
                 } else {
                     log::debug!("write locking flush manager to submit results");
                     let mut flush_manager = flush_manager.write().expect("lock is poisoned");
@@ -151,6 +179,7 @@ pub fn run(
             }
             Err(e) => {
                 log::error!("Flush error: {e:?}");
+                first_error.get_or_insert(e);
             }
         }
     }
@@ -162,7 +191,10 @@ pub fn run(
         .maintenance()
     {
         log::error!("journal GC failed: {e:?}");
+        first_error.get_or_insert(e);
     }
 
     log::debug!("fully done");
+
+    first_error.map_or(Ok(()), Err)
 }
@@ -0,0 +1,568 @@
+// Copyright (c) 2024-present, fjall-rs
+// This source code is licensed under both the Apache 2.0 and MIT License
+// (found in the LICENSE-* files in the repository)
+
+use crate::{file::fsync_directory, HashMap};
+use std::{
+    fs::{File, OpenOptions},
+    io::{Read, Seek, SeekFrom, Write},
+    path::{Path, PathBuf},
+    sync::{Arc, Mutex, MutexGuard, PoisonError},
+};
+
+/// How a file is opened, see [`Fs::open`]
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+pub enum OpenMode {
+    /// Opens an existing file for reading
+    Read,
+
+    /// Creates a file for writing, truncating it if it exists
+    Create,
+
+    /// Creates a file for writing, failing if it exists
+    CreateNew,
+
+    /// Opens an existing file for appending
+    Append,
+
+    /// Opens an existing file for writing, starting at its beginning
+    Write,
+}
+
+/// An entry of a folder, see [`Fs::read_dir`]
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct FsDirEntry {
+    /// Path of the entry
+    pub path: PathBuf,
+
+    /// Whether the entry is a folder
+    pub is_dir: bool,
+}
+
+/// A file opened through a [`Fs`]
+pub trait FsFile: Read + Write + Seek + Send {
+    /// Flushes data and metadata to disk, like `fsync`.
+    ///
+    /// # Errors
+    ///
+    /// Will return `Err` if an IO error occurs.
+    fn sync_all(&mut self) -> std::io::Result<()>;
+
+    /// Flushes data to disk, like `fdatasync`.
+    ///
+    /// # Errors
+    ///
+    /// Will return `Err` if an IO error occurs.
+    fn sync_data(&mut self) -> std::io::Result<()>;
+
+    /// Truncates or extends the file.
+    ///
+    /// # Errors
+    ///
+    /// Will return `Err` if an IO error occurs.
+    fn set_len(&mut self, len: u64) -> std::io::Result<()>;
+
+    /// Returns the size of the file in bytes.
+    ///
+    /// # Errors
+    ///
+    /// Will return `Err` if an IO error occurs.
+    fn size(&self) -> std::io::Result<u64>;
+}
+
+/// File system that journals and keyspace metadata are written and recovered through, see [`Config::fs`](crate::Config::fs)
+///
+/// Segments and blob files are written and read by `lsm-tree`,
+/// using the OS file system directly.
+pub trait Fs: Send + Sync {
+    /// Opens a file.
+    ///
+    /// # Errors
+    ///
+    /// Will return `Err` if an IO error occurs.
+    fn open(&self, path: &Path, mode: OpenMode) -> std::io::Result<Box<dyn FsFile>>;
+
+    /// Lists the entries of a folder, in no particular order.
+    ///
+    /// # Errors
+    ///
+    /// Will return `Err` if an IO error occurs.
+    fn read_dir(&self, path: &Path) -> std::io::Result<Vec<FsDirEntry>>;
+
+    /// Returns `true` if the path points to an existing file or folder.
+    ///
+    /// # Errors
+    ///
+    /// Will return `Err` if an IO error occurs.
+    fn exists(&self, path: &Path) -> std::io::Result<bool>;
+
+    /// Creates a folder and all its missing parents.
+    ///
+    /// # Errors
+    ///
+    /// Will return `Err` if an IO error occurs.
+    fn create_dir_all(&self, path: &Path) -> std::io::Result<()>;
+
+    /// Renames a file, replacing the target if it exists.
+    ///
+    /// # Errors
+    ///
+    /// Will return `Err` if an IO error occurs.
+    fn rename(&self, from: &Path, to: &Path) -> std::io::Result<()>;
+
+    /// Removes a file.
+    ///
+    /// # Errors
+    ///
+    /// Will return `Err` if an IO error occurs.
+    fn remove_file(&self, path: &Path) -> std::io::Result<()>;
+
+    /// Removes a folder and all its contents.
+    ///
+    /// # Errors
+    ///
+    /// Will return `Err` if an IO error occurs.
+    fn remove_dir_all(&self, path: &Path) -> std::io::Result<()>;
+
+    /// Flushes the entries of a folder to disk, so created, renamed
+    /// and removed files survive a crash.
+    ///
+    /// # Errors
+    ///
+    /// Will return `Err` if an IO error occurs.
+    fn sync_directory(&self, path: &Path) -> std::io::Result<()>;
+}
+
+/// The OS file system, using [`std::fs`]
+#[derive(Copy, Clone, Debug, Default)]
+pub struct StdFs;
+
+impl FsFile for File {
+    fn sync_all(&mut self) -> std::io::Result<()> {
+        Self::sync_all(self)
+    }
+
+    fn sync_data(&mut self) -> std::io::Result<()> {
+        Self::sync_data(self)
+    }
+
+    fn set_len(&mut self, len: u64) -> std::io::Result<()> {
+        Self::set_len(self, len)
+    }
+
+    fn size(&self) -> std::io::Result<u64> {
+        Ok(self.metadata()?.len())
+    }
+}
+
+fn read_dir_std(path: &Path) -> std::io::Result<Vec<FsDirEntry>> {
+    std::fs::read_dir(path)?
+        .map(|dirent| {
+            let dirent = dirent?;
+
+            Ok(FsDirEntry {
+                path: dirent.path(),
+                is_dir: dirent.file_type()?.is_dir(),
+            })
+        })
+        .collect()
+}
+
+fn open_std(path: &Path, mode: OpenMode) -> std::io::Result<File> {
+    match mode {
+        OpenMode::Read => File::open(path),
+        OpenMode::Create => File::create(path),
+        OpenMode::CreateNew => OpenOptions::new().create_new(true).write(true).open(path),
+        OpenMode::Append => OpenOptions::new().append(true).open(path),
+        OpenMode::Write => OpenOptions::new().write(true).open(path),
+    }
+}
+
+impl Fs for StdFs {
+    fn open(&self, path: &Path, mode: OpenMode) -> std::io::Result<Box<dyn FsFile>> {
+        Ok(Box::new(open_std(path, mode)?))
+    }
+
+    fn read_dir(&self, path: &Path) -> std::io::Result<Vec<FsDirEntry>> {
+        read_dir_std(path)
+    }
+
+    fn exists(&self, path: &Path) -> std::io::Result<bool> {
+        path.try_exists()
+    }
+
+    fn create_dir_all(&self, path: &Path) -> std::io::Result<()> {
+        std::fs::create_dir_all(path)
+    }
+
+    fn rename(&self, from: &Path, to: &Path) -> std::io::Result<()> {
+        std::fs::rename(from, to)
+    }
+
+    fn remove_file(&self, path: &Path) -> std::io::Result<()> {
+        std::fs::remove_file(path)
+    }
+
+    fn remove_dir_all(&self, path: &Path) -> std::io::Result<()> {
+        std::fs::remove_dir_all(path)
+    }
+
+    fn sync_directory(&self, path: &Path) -> std::io::Result<()> {
+        fsync_directory(path)
+    }
+}
+
+#[cfg(not(target_os = "windows"))]
+const ENOSPC: i32 = 28;
+
+// NOTE: ERROR_DISK_FULL
+#[cfg(target_os = "windows")]
+const ENOSPC: i32 = 112;
+
+/// Old contents of a file range, to undo a write that was not synced
+struct Undo {
+    offset: u64,
+    bytes: Vec<u8>,
+    len: u64,
+}
+
+#[derive(Default)]
+struct Faults {
+    fail_sync: bool,
+    fail_reads: bool,
+    space_left: Option<u64>,
+    tear_next_write: bool,
+
+    /// Writes since the last sync, per file
+    unsynced: HashMap<PathBuf, Vec<Undo>>,
+}
+
+/// Reads the bytes of the file that a write or truncation is about to replace.
+fn undo_for(path: &Path, offset: u64, len: u64) -> std::io::Result<Undo> {
+    let mut file = File::open(path)?;
+    let file_len = file.metadata()?.len();
+
+    let mut bytes = vec![];
+    if offset < file_len {
+        file.seek(SeekFrom::Start(offset))?;
+        file.take(len).read_to_end(&mut bytes)?;
+    }
+
+    Ok(Undo {
+        offset,
+        bytes,
+        len: file_len,
+    })
+}
+
+/// A file system that injects faults, for testing crash consistency
+///
+/// Files are written to the OS file system, but every write that is not synced yet
+/// is recorded, so [`FaultyFs::crash`] can drop it, like a power loss would.
+/// Created, renamed and removed files are not undone.
+///
+/// Clones share their faults, so a clone can be passed to [`Config::fs`](crate::Config::fs)
+/// and the faults can be toggled while the keyspace is open.
+///
+/// # Examples
+///
+/// ```
+/// # use fjall::{Config, FaultyFs, PartitionCreateOptions, PersistMode};
+/// # let folder = tempfile::tempdir()?;
+/// let fs = FaultyFs::default();
+///
+/// {
+///     let keyspace = Config::new(&folder).fs(fs.clone()).open()?;
+///     let items = keyspace.open_partition("default", PartitionCreateOptions::default())?;
+///
+///     items.insert("a", "abc")?;
+///     keyspace.persist(PersistMode::SyncAll)?;
+///
+///     items.insert("b", "def")?;
+///
+///     // NOTE: The keyspace cannot sync on drop anymore
+///     fs.fail_sync(true);
+/// }
+///
+/// fs.crash()?;
+///
+/// let keyspace = Config::new(&folder).open()?;
+/// let items = keyspace.open_partition("default", PartitionCreateOptions::default())?;
+/// assert!(items.contains_key("a")?);
+/// assert!(!items.contains_key("b")?);
+/// #
+/// # Ok::<(), fjall::Error>(())
+/// ```
+#[derive(Clone, Default)]
+pub struct FaultyFs {
+    faults: Arc<Mutex<Faults>>,
+}
+
+impl FaultyFs {
+    fn faults(&self) -> MutexGuard<'_, Faults> {
+        // NOTE: Faults are only flags and undo records, which stay usable even if a thread panicked
+        self.faults.lock().unwrap_or_else(PoisonError::into_inner)
+    }
+
+    /// If `true`, every sync fails, including syncing folders.
+    pub fn fail_sync(&self, fail: bool) {
+        self.faults().fail_sync = fail;
+    }
+
+    /// If `true`, every read fails, including opening files for reading and listing folders.
+    pub fn fail_reads(&self, fail: bool) {
+        self.faults().fail_reads = fail;
+    }
+
+    /// Limits the amount of bytes that can still be written,
+    /// after which writes fail with `ENOSPC`.
+    ///
+    /// `None` removes the limit.
+    pub fn set_space_left(&self, bytes: Option<u64>) {
+        self.faults().space_left = bytes;
+    }
+
+    /// Tears the next write: only half of it is written, then it fails.
+    pub fn tear_next_write(&self) {
+        self.faults().tear_next_write = true;
+    }
+
+    /// Drops all writes that were not synced, like a power loss, and clears all faults.
+    ///
+    /// Files that are still open should not be written to afterwards,
+    /// so the keyspace should be dropped before.
+    ///
+    /// # Errors
+    ///
+    /// Will return `Err` if an IO error occurs.
+    pub fn crash(&self) -> std::io::Result<()> {
+        let unsynced = std::mem::take(&mut *self.faults()).unsynced;
+
+        for (path, undos) in unsynced {
+            if !path.try_exists()? {
+                continue;
+            }
+
+            let mut file = OpenOptions::new().write(true).open(&path)?;
+
+            for undo in undos.into_iter().rev() {
+                file.seek(SeekFrom::Start(undo.offset))?;
+                file.write_all(&undo.bytes)?;
+                file.set_len(undo.len)?;
+            }
+
+            file.sync_all()?;
+        }
+
+        Ok(())
+    }
+
+    fn check_sync(&self) -> std::io::Result<()> {
+        if self.faults().fail_sync {
+            return Err(std::io::Error::other("injected sync failure"));
+        }
+        Ok(())
+    }
+
+    fn check_read(&self) -> std::io::Result<()> {
+        if self.faults().fail_reads {
+            return Err(std::io::Error::other("injected read failure"));
+        }
+        Ok(())
+    }
+}
+
+impl Fs for FaultyFs {
+    fn open(&self, path: &Path, mode: OpenMode) -> std::io::Result<Box<dyn FsFile>> {
+        if mode == OpenMode::Read {
+            self.check_read()?;
+        }
+
+        let existed = path.try_exists()?;
+        let file = open_std(path, mode)?;
+
+        // NOTE: Truncating an existing file cannot be undone
+        if mode == OpenMode::Create && existed {
+            self.faults().unsynced.remove(path);
+        }
+
+        Ok(Box::new(FaultyFile {
+            path: path.into(),
+            file,
+            pos: 0,
+            append: mode == OpenMode::Append,
+            fs: self.clone(),
+        }))
+    }
+
+    fn read_dir(&self, path: &Path) -> std::io::Result<Vec<FsDirEntry>> {
+        self.check_read()?;
+        read_dir_std(path)
+    }
+
+    fn exists(&self, path: &Path) -> std::io::Result<bool> {
+        path.try_exists()
+    }
+
+    fn create_dir_all(&self, path: &Path) -> std::io::Result<()> {
+        std::fs::create_dir_all(path)
+    }
+
+    fn rename(&self, from: &Path, to: &Path) -> std::io::Result<()> {
+        std::fs::rename(from, to)?;
+
+        let mut faults = self.faults();
+        if let Some(undos) = faults.unsynced.remove(from) {
+            faults.unsynced.insert(to.into(), undos);
+        }
+        drop(faults);
+
+        Ok(())
+    }
+
+    fn remove_file(&self, path: &Path) -> std::io::Result<()> {
+        std::fs::remove_file(path)?;
+
+        self.faults().unsynced.remove(path);
+
+        Ok(())
+    }
+
+    fn remove_dir_all(&self, path: &Path) -> std::io::Result<()> {
+        std::fs::remove_dir_all(path)?;
+
+        self.faults()
+            .unsynced
+            .retain(|file, _| !file.starts_with(path));
+
+        Ok(())
+    }
+
+    fn sync_directory(&self, path: &Path) -> std::io::Result<()> {
+        self.check_sync()?;
+        fsync_directory(path)
+    }
+}
+
+struct FaultyFile {
+    path: PathBuf,
+    file: File,
+
+    /// Offset of the next read or write, if not appending
+    pos: u64,
+    append: bool,
+
+    fs: FaultyFs,
+}
+
+impl FaultyFile {
+    fn sync(&self, sync: fn(&File) -> std::io::Result<()>) -> std::io::Result<()> {
+        self.fs.check_sync()?;
+        sync(&self.file)?;
+
+        self.fs.faults().unsynced.remove(&self.path);
+
+        Ok(())
+    }
+}
+
+impl Write for FaultyFile {
+    fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+        let mut faults = self.fs.faults();
+
+        let mut len = buf.len() as u64;
+
+        if let Some(space_left) = faults.space_left {
+            if space_left == 0 && len > 0 {
+                return Err(std::io::Error::from_raw_os_error(ENOSPC));
+            }
+            len = len.min(space_left);
+        }
+
+        let is_torn = std::mem::take(&mut faults.tear_next_write);
+        if is_torn {
+            len /= 2;
+        }
+
+        let offset = if self.append {
+            self.file.metadata()?.len()
+        } else {
+            self.pos
+        };
+
+        let undo = undo_for(&self.path, offset, len)?;
+        faults
+            .unsynced
+            .entry(self.path.clone())
+            .or_default()
+            .push(undo);
+
+        if let Some(space_left) = &mut faults.space_left {
+            *space_left -= len;
+        }
+
+        drop(faults);
+
+        // NOTE: len is at most buf.len()
+        #[allow(clippy::cast_possible_truncation)]
+        let bytes = buf.get(..len as usize).unwrap_or(buf);
+        self.file.write_all(bytes)?;
+
+        self.pos = offset + len;
+
+        if is_torn {
+            return Err(std::io::Error::other("injected torn write"));
+        }
+
+        Ok(bytes.len())
+    }
+
+    fn flush(&mut self) -> std::io::Result<()> {
+        self.file.flush()
+    }
+}
+
+impl Read for FaultyFile {
+    fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+        self.fs.check_read()?;
+
+        let n = self.file.read(buf)?;
+        self.pos += n as u64;
+
+        Ok(n)
+    }
+}
+
+impl Seek for FaultyFile {
+    fn seek(&mut self, pos: SeekFrom) -> std::io::Result<u64> {
+        self.pos = self.file.seek(pos)?;
+        Ok(self.pos)
+    }
+}
+
+impl FsFile for FaultyFile {
+    fn sync_all(&mut self) -> std::io::Result<()> {
+        self.sync(File::sync_all)
+    }
+
+    fn sync_data(&mut self) -> std::io::Result<()> {
+        self.sync(File::sync_data)
+    }
+
+    fn set_len(&mut self, len: u64) -> std::io::Result<()> {
+        let undo = undo_for(&self.path, len, u64::MAX)?;
+
+        self.fs
+            .faults()
+            .unsynced
+            .entry(self.path.clone())
+            .or_default()
+            .push(undo);
+
+        self.file.set_len(len)
+    }
+
+    fn size(&self) -> std::io::Result<u64> {
+        Ok(self.file.metadata()?.len())
+    }
+}
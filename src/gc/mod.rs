@@ -2,8 +2,12 @@
 // This source code is licensed under both the Apache 2.0 and MIT License
 // (found in the LICENSE-* files in the repository)
 
-use crate::PartitionHandle;
-use lsm_tree::{gc::Report as GcReport, AnyTree};
+use crate::{rate_limiter::IoActivity, HashMap, PartitionHandle};
+use lsm_tree::{
+    gc::{Report as GcReport, Strategy as GcStrategy},
+    AnyTree, BlobTree, SegmentId,
+};
+use value_log::{Compressor, ValueLog};
 
 /// Functions for garbage collection strategies
 ///
@@ -179,9 +183,59 @@ pub trait GarbageCollection {
     fn gc_drop_stale_segments(&self) -> crate::Result<u64>;
 }
 
+/// Picks a fixed set of blob files, so a GC run can be split up
+struct PickedBlobFiles(Vec<SegmentId>);
+
+impl<C: Compressor + Clone> GcStrategy<C> for PickedBlobFiles {
+    fn pick(&self, _: &ValueLog<C>) -> Vec<SegmentId> {
+        self.0.clone()
+    }
+}
+
 pub(crate) struct GarbageCollector;
 
 impl GarbageCollector {
+    fn is_rate_limited(partition: &PartitionHandle) -> bool {
+        partition
+            .keyspace_config
+            .rate_limiter
+            .limit(IoActivity::GarbageCollection)
+            .is_some()
+    }
+
+    /// Rewrites blob files one at a time, paying off each blob file
+    /// in the rate limiter before it is rewritten.
+    fn rewrite_rate_limited(
+        partition: &PartitionHandle,
+        tree: &BlobTree,
+        blob_file_ids: Vec<SegmentId>,
+    ) -> crate::Result<u64> {
+        let rate_limiter = &partition.keyspace_config.rate_limiter;
+
+        let sizes = tree
+            .blobs
+            .manifest
+            .list_segments()
+            .into_iter()
+            .map(|segment| (segment.id, segment.meta.compressed_bytes))
+            .collect::<HashMap<_, _>>();
+
+        let mut bytes_freed = 0;
+
+        for id in blob_file_ids {
+            let Some(&size) = sizes.get(&id) else {
+                continue;
+            };
+
+            rate_limiter.acquire(IoActivity::GarbageCollection, size, None);
+
+            bytes_freed +=
+                tree.apply_gc_strategy(&PickedBlobFiles(vec![id]), partition.seqno.next())?;
+        }
+
+        Ok(bytes_freed)
+    }
+
     pub fn scan(partition: &PartitionHandle) -> crate::Result<GcReport> {
         if let AnyTree::Blob(tree) = &partition.tree {
             return tree
@@ -198,6 +252,10 @@ impl GarbageCollector {
         if let AnyTree::Blob(tree) = &partition.tree {
             let strategy = lsm_tree::gc::SpaceAmpStrategy::new(factor);
 
+            if Self::is_rate_limited(partition) {
+                return Self::rewrite_rate_limited(partition, tree, strategy.pick(&tree.blobs));
+            }
+
             tree.apply_gc_strategy(&strategy, partition.seqno.next())
                 .map_err(Into::into)
         } else {
@@ -212,6 +270,10 @@ impl GarbageCollector {
         if let AnyTree::Blob(tree) = &partition.tree {
             let strategy = lsm_tree::gc::StaleThresholdStrategy::new(threshold);
 
+            if Self::is_rate_limited(partition) {
+                return Self::rewrite_rate_limited(partition, tree, strategy.pick(&tree.blobs));
+            }
+
             return tree
                 .apply_gc_strategy(&strategy, partition.seqno.next())
                 .map_err(Into::into);
@@ -0,0 +1,458 @@
+// Copyright (c) 2024-present, fjall-rs
+// This source code is licensed under both the Apache 2.0 and MIT License
+// (found in the LICENSE-* files in the repository)
+
+use crate::{
+    batch::{item::Item, precondition::read_latest, PartitionKey},
+    escape::{read_escaped, write_escaped_unterminated, ESCAPE, TERMINATOR},
+    keyspace::{KeyspaceInner, Partitions},
+    Keyspace, PartitionCreateOptions, PartitionHandle,
+};
+use lsm_tree::{KvPair, SeqNo, UserKey, UserValue, ValueType};
+use std::{
+    ops::{Bound, RangeBounds},
+    sync::{Arc, Weak},
+};
+
+/// Amount of index entries written per batch while building a new index
+const BACKFILL_BATCH_SIZE: usize = 10_000;
+
+/// Key that is written to the index partition once the index is built completely
+///
+/// This is never a valid index entry key, because an escape byte is always
+/// followed by an escaped zero or a terminator, see `escape`.
+const BUILT_MARKER: &[u8] = &[ESCAPE, ESCAPE];
+
+/// Key that is written to the index partition before the index is built,
+/// storing the name of the primary partition
+///
+/// Index definitions cannot be persisted, because the extractor is a closure,
+/// but this marker lets the primary partition know about the index after reopening
+/// the keyspace, see `recover_definitions`.
+const DEFINITION_MARKER: &[u8] = &[ESCAPE, ESCAPE, ESCAPE];
+
+/// Extracts the secondary keys of a key-value pair of the primary partition
+pub type IndexExtractor = dyn Fn(&[u8], &[u8]) -> Vec<UserKey> + Send + Sync;
+
+/// An index that is registered on its primary partition
+pub struct IndexDefinition {
+    /// Partition that stores the index entries
+    pub index: PartitionHandle,
+
+    pub extractor: Arc<IndexExtractor>,
+
+    /// Keyspace, used to route plain writes of the primary partition through batches
+    pub keyspace: Weak<KeyspaceInner>,
+}
+
+impl IndexDefinition {
+    /// Returns the sorted, deduplicated secondary keys of the item.
+    fn extract(&self, key: &[u8], value: Option<&UserValue>) -> Vec<UserKey> {
+        let Some(value) = value else {
+            return vec![];
+        };
+
+        let mut keys = (self.extractor)(key, value);
+        keys.sort();
+        keys.dedup();
+        keys
+    }
+}
+
+// NOTE: Secondary keys are escaped & terminated (see `escape`), so that index entries
+// are sorted by secondary key first, and variable-length secondary keys cannot bleed
+// into the primary key
+
+/// Sorts right after every index entry of a secondary key.
+const TERMINATOR_UPPER: u8 = 0x02;
+
+fn escape(secondary_key: &[u8], terminator: u8) -> Vec<u8> {
+    let mut out = Vec::with_capacity(secondary_key.len() + 2);
+    write_escaped_unterminated(&mut out, secondary_key);
+    out.push(ESCAPE);
+    out.push(terminator);
+    out
+}
+
+/// Builds the index entry key of a secondary key pointing to a primary key.
+fn encode_entry(secondary_key: &[u8], primary_key: &[u8]) -> UserKey {
+    let mut key = escape(secondary_key, TERMINATOR);
+    key.extend_from_slice(primary_key);
+    key.into()
+}
+
+/// Splits an index entry key into its secondary key and the primary key it points to.
+fn decode_entry(entry_key: &[u8]) -> Option<(Vec<u8>, &[u8])> {
+    let mut iter = entry_key.iter();
+    let secondary_key = read_escaped(iter.by_ref().copied()).ok()?;
+    Some((secondary_key, iter.as_slice()))
+}
+
+fn lower_bound<K: AsRef<[u8]>>(bound: Bound<&K>) -> Bound<Vec<u8>> {
+    match bound {
+        Bound::Included(key) => Bound::Included(escape(key.as_ref(), TERMINATOR)),
+        Bound::Excluded(key) => Bound::Included(escape(key.as_ref(), TERMINATOR_UPPER)),
+        Bound::Unbounded => Bound::Unbounded,
+    }
+}
+
+fn upper_bound<K: AsRef<[u8]>>(bound: Bound<&K>) -> Bound<Vec<u8>> {
+    match bound {
+        Bound::Included(key) => Bound::Excluded(escape(key.as_ref(), TERMINATOR_UPPER)),
+        Bound::Excluded(key) => Bound::Excluded(escape(key.as_ref(), TERMINATOR)),
+        Bound::Unbounded => Bound::Unbounded,
+    }
+}
+
+/// Computes the index entries that need to be written (or removed)
+/// to keep all indexes of the written partitions in sync.
+///
+/// Must be called while holding the journal writer: writes to indexed partitions
+/// are only applied in batches, which hold the journal writer until their items
+/// are in the memtables, so the latest versions cannot change concurrently.
+pub fn collect_changes(items: &[Item], partitions: &Partitions) -> crate::Result<Vec<Item>> {
+    // NOTE: Only the last write of a key inside the batch is visible,
+    // so the index entries are diffed against that one
+    #[allow(clippy::mutable_key_type)]
+    let mut last_writes: crate::HashMap<(PartitionKey, UserKey), (&PartitionHandle, &Item)> =
+        crate::HashMap::default();
+
+    for item in items {
+        let Some(partition) = partitions.get(&item.partition) else {
+            continue;
+        };
+
+        if partition
+            .indexes
+            .read()
+            .expect("lock is poisoned")
+            .is_empty()
+        {
+            continue;
+        }
+
+        last_writes.insert(
+            (item.partition.clone(), item.key.clone()),
+            (partition, item),
+        );
+    }
+
+    let mut changes = vec![];
+
+    for (partition, item) in last_writes.into_values() {
+        let old_value = read_latest(&partition.tree, &item.key)?.and_then(|latest| latest.value);
+
+        let new_value = match item.value_type {
+            ValueType::Value => Some(&item.value),
+            ValueType::Tombstone | ValueType::WeakTombstone => None,
+        };
+
+        for def in partition.indexes.read().expect("lock is poisoned").iter() {
+            if def
+                .index
+                .is_deleted
+                .load(std::sync::atomic::Ordering::Relaxed)
+            {
+                continue;
+            }
+
+            let old_keys = def.extract(&item.key, old_value.as_ref());
+            let new_keys = def.extract(&item.key, new_value);
+
+            for secondary_key in old_keys.iter().filter(|k| !new_keys.contains(k)) {
+                changes.push(Item::new(
+                    def.index.name.clone(),
+                    encode_entry(secondary_key, &item.key),
+                    vec![],
+                    ValueType::Tombstone,
+                ));
+            }
+
+            for secondary_key in new_keys.iter().filter(|k| !old_keys.contains(k)) {
+                changes.push(Item::new(
+                    def.index.name.clone(),
+                    encode_entry(secondary_key, &item.key),
+                    vec![],
+                    ValueType::Value,
+                ));
+            }
+        }
+    }
+
+    Ok(changes)
+}
+
+/// Finds the index partitions of every primary partition, using their definition markers.
+///
+/// A primary partition cannot be opened until all its indexes
+/// are registered again, see [`Keyspace::create_index`].
+pub fn recover_definitions(keyspace: &Keyspace) -> crate::Result<()> {
+    let partitions = keyspace.partitions.read().expect("lock is poisoned");
+
+    for index in partitions.values() {
+        let Some(primary_name) = index.get(DEFINITION_MARKER)? else {
+            continue;
+        };
+
+        // NOTE: The primary partition may have been deleted since
+        let Some(primary) = std::str::from_utf8(&primary_name)
+            .ok()
+            .and_then(|name| partitions.get(name))
+        else {
+            continue;
+        };
+
+        log::debug!(
+            "Found unregistered index {:?} of partition {:?}",
+            index.name,
+            primary.name
+        );
+
+        primary
+            .unregistered_indexes
+            .write()
+            .expect("lock is poisoned")
+            .push(index.clone());
+    }
+
+    drop(partitions);
+
+    Ok(())
+}
+
+/// Writes the index entries of primary keys read from the snapshot at `seqno`.
+///
+/// Keys that were written after the snapshot are skipped, because those writes
+/// already updated the index themselves.
+fn backfill(
+    keyspace: &Keyspace,
+    primary: &PartitionHandle,
+    index: &PartitionHandle,
+    mut entries: Vec<(UserKey, Vec<UserKey>)>,
+    seqno: SeqNo,
+) -> crate::Result<()> {
+    loop {
+        let mut batch = keyspace.batch();
+
+        for (key, entry_keys) in &entries {
+            // NOTE: The snapshot contains writes with a seqno lower than `seqno`
+            batch.require_seqno_at_most(primary, key, seqno.saturating_sub(1));
+
+            for entry_key in entry_keys {
+                batch.insert(index, entry_key, "");
+            }
+        }
+
+        match batch.commit() {
+            Err(crate::Error::PreconditionFailed(failed)) => {
+                entries.retain(|(key, _)| !failed.iter().any(|x| x.key == *key));
+            }
+            result => return result,
+        }
+    }
+}
+
+/// A secondary index over a partition
+///
+/// The index is stored in its own partition, and kept in sync with the
+/// primary partition: every write (through [`PartitionHandle`], [`crate::Batch`]
+/// or transactions) writes the affected index entries in the same atomic batch.
+///
+/// Created using [`Keyspace::create_index`].
+#[derive(Clone)]
+#[allow(clippy::module_name_repetitions)]
+pub struct SecondaryIndex {
+    primary: PartitionHandle,
+    index: PartitionHandle,
+    def: Arc<IndexDefinition>,
+}
+
+impl SecondaryIndex {
+    pub(crate) fn create<F: Fn(&[u8], &[u8]) -> Vec<UserKey> + Send + Sync + 'static>(
+        keyspace: &Keyspace,
+        primary: &str,
+        name: &str,
+        extractor: F,
+    ) -> crate::Result<Self> {
+        // NOTE: The primary partition cannot be opened before all its indexes are registered
+        let primary =
+            keyspace.open_partition_unchecked(primary, PartitionCreateOptions::default())?;
+
+        let index = keyspace.open_partition(name, PartitionCreateOptions::default())?;
+
+        // NOTE: Written before the index is built, so an interrupted build
+        // still blocks opening the primary partition after reopening
+        if index.get(DEFINITION_MARKER)?.as_deref() != Some(primary.name.as_bytes()) {
+            index.insert(DEFINITION_MARKER, &*primary.name)?;
+        }
+
+        let def = Arc::new(IndexDefinition {
+            index: index.clone(),
+            extractor: Arc::new(extractor),
+            keyspace: Arc::downgrade(&keyspace.0),
+        });
+
+        // IMPORTANT: Register the index while holding the journal writer,
+        // so every write after the snapshot's seqno sees the index, and updates it itself
+        let seqno = {
+            let _journal_writer = keyspace.journal.get_writer();
+
+            let mut indexes = primary.indexes.write().expect("lock is poisoned");
+            indexes.retain(|other| other.index.name != index.name);
+            indexes.push(def.clone());
+            drop(indexes);
+
+            primary
+                .unregistered_indexes
+                .write()
+                .expect("lock is poisoned")
+                .retain(|other| other.name != index.name);
+
+            keyspace.instant()
+        };
+
+        // NOTE: If building the index was interrupted (e.g. by a crash), the marker is missing,
+        // so the index is built again; index entries that already exist are just rewritten
+        if !index.contains_key(BUILT_MARKER)? {
+            log::debug!(
+                "Building index {:?} of partition {:?}",
+                index.name,
+                primary.name
+            );
+
+            let snapshot = primary.snapshot_at(seqno);
+            let mut start = Bound::Unbounded;
+
+            loop {
+                let mut entries = vec![];
+                let mut entry_count = 0;
+
+                // IMPORTANT: The iterator read locks the memtable, which the batch needs to
+                // lock to check its preconditions, so read a chunk, then drop the iterator
+                for kv in snapshot.range::<UserKey, _>((start.clone(), Bound::Unbounded)) {
+                    let (key, value) = kv?;
+
+                    let entry_keys = def
+                        .extract(&key, Some(&value))
+                        .into_iter()
+                        .map(|secondary_key| encode_entry(&secondary_key, &key))
+                        .collect::<Vec<_>>();
+
+                    entry_count += entry_keys.len();
+                    entries.push((key, entry_keys));
+
+                    if entry_count >= BACKFILL_BATCH_SIZE {
+                        break;
+                    }
+                }
+
+                let Some((last_key, _)) = entries.last() else {
+                    break;
+                };
+                start = Bound::Excluded(last_key.clone());
+
+                backfill(keyspace, &primary, &index, entries, seqno)?;
+            }
+
+            index.insert(BUILT_MARKER, "")?;
+        }
+
+        Ok(Self {
+            primary,
+            index,
+            def,
+        })
+    }
+
+    /// Returns the partition that stores the index entries.
+    ///
+    /// Besides the index entries, the partition contains two marker items,
+    /// which record the primary partition, and that the index has been built completely.
+    #[must_use]
+    pub fn partition(&self) -> &PartitionHandle {
+        &self.index
+    }
+
+    /// Returns the indexed (primary) partition.
+    #[must_use]
+    pub fn primary(&self) -> &PartitionHandle {
+        &self.primary
+    }
+
+    /// Returns the key-value pairs of the primary partition
+    /// whose secondary keys are inside the given range.
+    ///
+    /// Items are sorted by secondary key, then by primary key.
+    /// An item that has multiple secondary keys inside the range is returned multiple times.
+    ///
+    /// The index and primary partition are read from the same snapshot.
+    /// Index entries whose item does not map to the secondary key anymore are skipped.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use fjall::{Config, PartitionCreateOptions};
+    /// #
+    /// # let folder = tempfile::tempdir()?;
+    /// # let keyspace = Config::new(folder).open()?;
+    /// let items = keyspace.open_partition("items", PartitionCreateOptions::default())?;
+    ///
+    /// // Index items by their value's first byte
+    /// let index = keyspace.create_index("items", "items_by_initial", |_key, value| {
+    ///     value.first().map(|b| vec![[*b].into()]).unwrap_or_default()
+    /// })?;
+    ///
+    /// items.insert("1", "apple")?;
+    /// items.insert("2", "banana")?;
+    /// items.insert("3", "avocado")?;
+    ///
+    /// let found = index.lookup("a"..="a").collect::<Result<Vec<_>, _>>()?;
+    /// assert_eq!(2, found.len());
+    /// assert_eq!(b"apple", &*found[0].1);
+    /// assert_eq!(b"avocado", &*found[1].1);
+    ///
+    /// // Index entries are kept in sync
+    /// items.remove("1")?;
+    /// items.insert("3", "cherry")?;
+    /// assert_eq!(0, index.lookup("a"..="a").count());
+    /// #
+    /// # Ok::<(), fjall::Error>(())
+    /// ```
+    pub fn lookup<K: AsRef<[u8]>, R: RangeBounds<K>>(
+        &self,
+        range: R,
+    ) -> impl DoubleEndedIterator<Item = crate::Result<KvPair>> + 'static {
+        let start = lower_bound(range.start_bound());
+        let end = upper_bound(range.end_bound());
+
+        // NOTE: The primary snapshot is moved into the iterator, which keeps
+        // the snapshot's seqno registered for both partitions
+        let seqno = self.primary.seqno.get();
+        let primary = self.primary.snapshot_at(seqno);
+        let entries = self.index.snapshot_at(seqno).range((start, end));
+
+        let def = self.def.clone();
+
+        entries.filter_map(move |kv| {
+            // NOTE: The markers are not index entries, so they cannot be decoded, and are skipped
+            let (secondary_key, primary_key) = match kv {
+                Ok((key, _)) => {
+                    let (secondary_key, primary_key) = decode_entry(&key)?;
+                    (secondary_key, UserKey::from(primary_key))
+                }
+                Err(e) => return Some(Err(e.into())),
+            };
+
+            let value = match primary.get(&primary_key) {
+                Ok(value) => value?,
+                Err(e) => return Some(Err(e.into())),
+            };
+
+            // NOTE: Skip stale index entries, whose item does not have the secondary key anymore
+            def.extract(&primary_key, Some(&value))
+                .iter()
+                .any(|key| **key == *secondary_key)
+                .then_some(Ok((primary_key, value)))
+        })
+    }
+}
@@ -2,10 +2,10 @@
 // This source code is licensed under both the Apache 2.0 and MIT License
 // (found in the LICENSE-* files in the repository)
 
-use super::reader::JournalReader;
+use super::{dump::JournalStopReason, reader::JournalReader};
 use crate::{batch::item::Item as BatchItem, journal::marker::Marker, RecoveryError};
 use lsm_tree::{coding::Encode, CompressionType, SeqNo};
-use std::{fs::OpenOptions, hash::Hasher};
+use std::hash::Hasher;
 
 macro_rules! fail_iter {
     ($e:expr) => {
@@ -22,45 +22,155 @@ pub struct Batch {
     pub(crate) items: Vec<BatchItem>,
 }
 
+/// Result of validating a journal marker, see [`BatchValidator`]
+pub enum Validated {
+    /// The marker is valid, and the batch continues
+    Continue,
+
+    /// The marker ends a valid batch with the given seqno
+    End(SeqNo),
+
+    /// The marker is invalid, so recovery stops
+    Invalid(JournalStopReason),
+}
+
+/// Checks that journal markers form complete batches
+///
+/// Used by journal recovery (see [`JournalBatchReader`]) and [`crate::JournalDump`],
+/// which keeps validating after an invalid marker, to find all batches.
+pub struct BatchValidator {
+    is_in_batch: bool,
+    batch_counter: u32,
+    batch_seqno: SeqNo,
+    checksum_builder: xxhash_rust::xxh3::Xxh3,
+}
+
+impl BatchValidator {
+    pub fn new() -> Self {
+        Self {
+            is_in_batch: false,
+            batch_counter: 0,
+            batch_seqno: 0,
+            checksum_builder: xxhash_rust::xxh3::Xxh3::new(),
+        }
+    }
+
+    /// Returns `true` if a start marker was validated, but not its end marker.
+    pub fn is_in_batch(&self) -> bool {
+        self.is_in_batch
+    }
+
+    /// Returns `true` if the checksum of the current batch's items matches.
+    pub fn checksum_matches(&self, expected_checksum: u64) -> bool {
+        self.checksum_builder.finish() == expected_checksum
+    }
+
+    /// Validates the next marker of the journal.
+    pub fn validate(&mut self, marker: &Marker) -> crate::Result<Validated> {
+        use JournalStopReason::{Broken, UnexpectedEnd, UnexpectedItem, UnexpectedStart};
+
+        match marker {
+            Marker::Start {
+                item_count, seqno, ..
+            } => {
+                let was_in_batch = self.is_in_batch;
+
+                self.is_in_batch = true;
+                self.batch_counter = *item_count;
+                self.batch_seqno = *seqno;
+                self.checksum_builder = xxhash_rust::xxh3::Xxh3::new();
+
+                if was_in_batch {
+                    log::debug!("Invalid batch: found batch start inside batch");
+                    return Ok(Validated::Invalid(UnexpectedStart));
+                }
+
+                Ok(Validated::Continue)
+            }
+            Marker::Item { .. } => {
+                let mut bytes = Vec::with_capacity(100);
+                marker.encode_into(&mut bytes)?;
+
+                self.checksum_builder.update(&bytes);
+
+                if !self.is_in_batch {
+                    log::debug!("Invalid batch: found item marker without start marker");
+                    return Ok(Validated::Invalid(UnexpectedItem));
+                }
+
+                if self.batch_counter == 0 {
+                    log::error!("Invalid batch: Expected end marker (too many items in batch)");
+                    return Ok(Validated::Invalid(Broken(RecoveryError::TooManyItems)));
+                }
+
+                self.batch_counter -= 1;
+
+                Ok(Validated::Continue)
+            }
+            Marker::End(expected_checksum) => {
+                if !self.is_in_batch {
+                    log::error!("Invalid batch: found end marker without start marker");
+                    return Ok(Validated::Invalid(UnexpectedEnd));
+                }
+
+                let got_checksum = self.checksum_builder.finish();
+                let batch_counter = self.batch_counter;
+
+                // Reset all variables
+                self.is_in_batch = false;
+                self.batch_counter = 0;
+
+                if batch_counter > 0 {
+                    log::error!("Invalid batch: insufficient length");
+                    return Ok(Validated::Invalid(Broken(
+                        RecoveryError::InsufficientLength,
+                    )));
+                }
+
+                if got_checksum != *expected_checksum {
+                    log::error!("Invalid batch: checksum check failed, expected: {expected_checksum}, got: {got_checksum}");
+                    return Ok(Validated::Invalid(Broken(RecoveryError::ChecksumMismatch)));
+                }
+
+                Ok(Validated::End(self.batch_seqno))
+            }
+        }
+    }
+}
+
 #[allow(clippy::module_name_repetitions)]
 pub struct JournalBatchReader {
     reader: JournalReader,
     items: Vec<BatchItem>,
-    is_in_batch: bool,
-    batch_counter: u32,
-    batch_seqno: SeqNo,
+    validator: BatchValidator,
     last_valid_pos: u64,
-    checksum_builder: xxhash_rust::xxh3::Xxh3,
 }
 
 impl JournalBatchReader {
     pub fn new(reader: JournalReader) -> Self {
+        let last_valid_pos = reader.last_valid_pos;
+
         Self {
             reader,
             items: Vec::with_capacity(10),
-            checksum_builder: xxhash_rust::xxh3::Xxh3::new(),
-            is_in_batch: false,
-            batch_seqno: 0,
-            last_valid_pos: 0,
-            batch_counter: 0,
+            validator: BatchValidator::new(),
+            last_valid_pos,
         }
     }
 
-    // TODO: reallocate space
-    fn truncate_to(&mut self, last_valid_pos: u64) -> crate::Result<()> {
-        log::trace!("Truncating journal to {last_valid_pos}");
+    /// Returns the position after the last complete batch.
+    pub fn position(&self) -> u64 {
+        self.last_valid_pos
+    }
 
+    // TODO: reallocate space
+    fn truncate_to(&self, last_valid_pos: u64) -> crate::Result<()> {
         // TODO: on windows, reading file probably needs to be closed first...?
-
-        let file = OpenOptions::new().write(true).open(&self.reader.path)?;
-        file.set_len(last_valid_pos)?;
-        file.sync_all()?;
-
-        Ok(())
+        self.reader.truncate_file(last_valid_pos)
     }
 
     fn on_close(&mut self) -> crate::Result<()> {
-        if self.is_in_batch {
+        if self.validator.is_in_batch() {
             log::debug!("Invalid batch: missing terminator, but last batch, so probably incomplete, discarding to keep atomicity");
 
             // Discard batch
@@ -75,8 +185,6 @@ impl Iterator for JournalBatchReader {
     type Item = crate::Result<Batch>;
 
     fn next(&mut self) -> Option<Self::Item> {
-        use crate::Error::JournalRecovery;
-
         loop {
             let Some(item) = self.reader.next() else {
                 fail_iter!(self.on_close());
@@ -86,105 +194,45 @@ impl Iterator for JournalBatchReader {
 
             let journal_file_pos = self.reader.last_valid_pos;
 
-            match item {
-                Marker::Start {
-                    item_count,
-                    seqno,
-                    compression,
-                } => {
-                    // TODO: journal compression maybe in the future
-                    assert!(
-                        compression == CompressionType::None,
-                        "journal compression not supported"
-                    );
-
-                    if self.is_in_batch {
-                        log::debug!("Invalid batch: found batch start inside batch");
-
-                        // Discard batch
-                        fail_iter!(self.truncate_to(self.last_valid_pos));
-
-                        return None;
-                    }
-
-                    self.is_in_batch = true;
-                    self.batch_counter = item_count;
-                    self.batch_seqno = seqno;
-                }
-                Marker::End(expected_checksum) => {
-                    if self.batch_counter > 0 {
-                        log::error!("Invalid batch: insufficient length");
-                        return Some(Err(JournalRecovery(RecoveryError::InsufficientLength)));
-                    }
-
-                    if !self.is_in_batch {
-                        log::error!("Invalid batch: found end marker without start marker");
-
-                        // Discard batch
-                        fail_iter!(self.truncate_to(self.last_valid_pos));
-
-                        return None;
-                    }
-
-                    let got_checksum = self.checksum_builder.finish();
-                    self.checksum_builder = xxhash_rust::xxh3::Xxh3::new();
+            if let Marker::Start { compression, .. } = &item {
+                // TODO: journal compression maybe in the future
+                assert!(
+                    *compression == CompressionType::None,
+                    "journal compression not supported"
+                );
+            }
 
-                    if got_checksum != expected_checksum {
-                        log::error!("Invalid batch: checksum check failed, expected: {expected_checksum}, got: {got_checksum}");
-                        return Some(Err(JournalRecovery(RecoveryError::ChecksumMismatch)));
+            match fail_iter!(self.validator.validate(&item)) {
+                Validated::Continue => {
+                    if let Marker::Item {
+                        partition,
+                        key,
+                        value,
+                        value_type,
+                    } = item
+                    {
+                        self.items.push(BatchItem {
+                            partition,
+                            key,
+                            value,
+                            value_type,
+                        });
                     }
-
-                    // Reset all variables
-                    self.is_in_batch = false;
-                    self.batch_counter = 0;
-
+                }
+                Validated::End(seqno) => {
                     self.last_valid_pos = journal_file_pos;
 
                     let items = std::mem::take(&mut self.items);
-                    return Some(Ok(Batch {
-                        seqno: self.batch_seqno,
-                        items,
-                    }));
+                    return Some(Ok(Batch { seqno, items }));
                 }
-                Marker::Item {
-                    partition,
-                    key,
-                    value,
-                    value_type,
-                } => {
-                    let item = Marker::Item {
-                        partition: partition.clone(),
-                        key: key.clone(),
-                        value: value.clone(),
-                        value_type,
-                    };
-                    let mut bytes = Vec::with_capacity(100);
-                    fail_iter!(item.encode_into(&mut bytes));
-
-                    self.checksum_builder.update(&bytes);
-
-                    if !self.is_in_batch {
-                        log::debug!("Invalid batch: found end marker without start marker");
-
-                        // Discard batch
-                        fail_iter!(self.truncate_to(self.last_valid_pos));
-
-                        return None;
-                    }
-
-                    if self.batch_counter == 0 {
-                        log::error!("Invalid batch: Expected end marker (too many items in batch)");
-                        return Some(Err(JournalRecovery(RecoveryError::TooManyItems)));
-                    }
-
-                    self.batch_counter -= 1;
+                Validated::Invalid(JournalStopReason::Broken(e)) => {
+                    return Some(Err(crate::Error::JournalRecovery(e)));
+                }
+                Validated::Invalid(_) => {
+                    // Discard batch
+                    fail_iter!(self.truncate_to(self.last_valid_pos));
 
-                    self.items.push(BatchItem {
-                        partition,
-                        key,
-                        value,
-                        value_type,
-                    });
+                    return None;
                 }
             }
         }
@@ -0,0 +1,291 @@
+// Copyright (c) 2024-present, fjall-rs
+// This source code is licensed under both the Apache 2.0 and MIT License
+// (found in the LICENSE-* files in the repository)
+
+use super::{
+    batch_reader::{BatchValidator, Validated},
+    error::RecoveryError,
+    marker::Marker,
+    reader::JournalReader,
+};
+use crate::{batch::PartitionKey, encryption::Keys, fs::Fs};
+use lsm_tree::{SeqNo, UserKey, ValueType};
+use std::{
+    io::{Read, Seek, SeekFrom},
+    path::{Path, PathBuf},
+    sync::Arc,
+};
+
+/// Item of a journal batch, see [`JournalDump`]
+#[derive(Clone, Debug, Eq, PartialEq)]
+#[allow(clippy::module_name_repetitions)]
+pub struct JournalDumpItem {
+    /// File offset of the item marker
+    pub offset: u64,
+
+    /// Partition the item belongs to
+    pub partition: PartitionKey,
+
+    /// Key of the item
+    pub key: UserKey,
+
+    /// Size of the value in bytes
+    pub value_size: u32,
+
+    /// Value type of the item
+    pub value_type: ValueType,
+}
+
+/// Batch of a journal, see [`JournalDump`]
+#[derive(Clone, Debug, Eq, PartialEq)]
+#[allow(clippy::module_name_repetitions)]
+pub struct JournalDumpBatch {
+    /// File offset of the start marker
+    pub offset: u64,
+
+    /// Seqno of the batch
+    pub seqno: SeqNo,
+
+    /// Amount of items the start marker announces
+    pub item_count: u32,
+
+    /// Items that follow the start marker
+    pub items: Vec<JournalDumpItem>,
+
+    /// `true` if the checksum in the end marker matches the items,
+    /// `None` if the batch has no end marker
+    pub checksum_ok: Option<bool>,
+}
+
+/// Why journal recovery stops reading a journal, see [`JournalStop`]
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+#[allow(clippy::module_name_repetitions)]
+pub enum JournalStopReason {
+    /// The bytes at the offset are not a valid marker, for example
+    /// because the journal was only partially written
+    CorruptMarker,
+
+    /// The last batch has no end marker
+    MissingEnd,
+
+    /// A start marker was found inside a batch
+    UnexpectedStart,
+
+    /// An end marker was found outside a batch
+    UnexpectedEnd,
+
+    /// An item marker was found outside a batch
+    UnexpectedItem,
+
+    /// The batch is broken, recovery fails with this error
+    /// instead of discarding the rest of the journal
+    Broken(RecoveryError),
+}
+
+/// Position at which journal recovery stops, see [`JournalDump`]
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+#[allow(clippy::module_name_repetitions)]
+pub struct JournalStop {
+    /// Recovery keeps all batches before this offset,
+    /// and truncates the journal to it (unless the batch is broken)
+    pub offset: u64,
+
+    /// Why recovery stops
+    pub reason: JournalStopReason,
+}
+
+/// Decoded contents of a journal file
+///
+/// Unlike journal recovery, this never modifies the file, so it can be
+/// used to inspect journals of a keyspace that fails to open.
+///
+/// Values are not kept, only their sizes.
+///
+/// # Examples
+///
+/// ```
+/// # use fjall::{Config, JournalDump, PersistMode, StdFs};
+/// # use std::sync::Arc;
+/// # let folder = tempfile::tempdir()?;
+/// let keyspace = Config::new(folder).open()?;
+/// let items = keyspace.open_partition("default", Default::default())?;
+/// items.insert("a", "hello")?;
+/// keyspace.persist(PersistMode::SyncAll)?;
+///
+/// let journal = keyspace.journals()?.pop().expect("should have active journal");
+/// let dump = JournalDump::from_file(journal.path, Arc::new(StdFs))?;
+///
+/// assert_eq!(1, dump.batches.len());
+/// assert_eq!(5, dump.batches[0].items[0].value_size);
+/// assert!(dump.stop.is_none());
+/// #
+/// # Ok::<(), fjall::Error>(())
+/// ```
+#[derive(Clone, Debug, Eq, PartialEq)]
+#[allow(clippy::module_name_repetitions)]
+pub struct JournalDump {
+    /// Path of the journal file
+    pub path: PathBuf,
+
+    /// File size in bytes, including pre-allocated space
+    pub size: u64,
+
+    /// Batches in file order, including batches after the stop position
+    pub batches: Vec<JournalDumpBatch>,
+
+    /// First position at which recovery would stop, `None` if the whole journal is recovered
+    pub stop: Option<JournalStop>,
+}
+
+/// Records the stop position, unless recovery already stopped before.
+fn stop_at(stop: &mut Option<JournalStop>, offset: u64, reason: JournalStopReason) {
+    stop.get_or_insert(JournalStop { offset, reason });
+}
+
+/// Returns `true` if there are only zero bytes after the offset.
+///
+/// The active journal is pre-allocated, so its unwritten tail is zeroed.
+fn is_zeroed_from<R: Read + Seek>(reader: &mut R, offset: u64) -> std::io::Result<bool> {
+    reader.seek(SeekFrom::Start(offset))?;
+
+    let mut buf = [0; 4_096];
+
+    loop {
+        let n = reader.read(&mut buf)?;
+
+        if n == 0 {
+            return Ok(true);
+        }
+
+        if buf.iter().take(n).any(|&byte| byte != 0) {
+            return Ok(false);
+        }
+    }
+}
+
+impl JournalDump {
+    /// Decodes a journal file, reading it through the given file system.
+    ///
+    /// Batches are validated like in journal recovery, but decoding continues
+    /// after batches that recovery would discard, until a marker cannot be decoded.
+    ///
+    /// # Errors
+    ///
+    /// Will return `Err` if an IO error occurs, or the journal is encrypted.
+    pub fn from_file<P: AsRef<Path>>(path: P, fs: Arc<dyn Fs>) -> crate::Result<Self> {
+        Self::open(path.as_ref(), fs, &None)
+    }
+
+    /// Decodes an encrypted journal file, see [`JournalDump::from_file`].
+    ///
+    /// The markers of an encrypted batch have no position of their own,
+    /// so their offset is the offset of the batch.
+    ///
+    /// # Errors
+    ///
+    /// Will return `Err` if an IO error occurs, or the journal cannot be decrypted.
+    #[cfg(feature = "encryption")]
+    pub fn from_encrypted_file<P: AsRef<Path>, K: crate::KeyProvider + 'static>(
+        path: P,
+        fs: Arc<dyn Fs>,
+        keys: K,
+    ) -> crate::Result<Self> {
+        Self::open(path.as_ref(), fs, &Some(Arc::new(keys)))
+    }
+
+    pub(crate) fn open(path: &Path, fs: Arc<dyn Fs>, keys: &Keys) -> crate::Result<Self> {
+        let mut reader = JournalReader::new_read_only(path, fs, keys)?;
+        let size = reader.reader.get_ref().size()?;
+
+        let mut validator = BatchValidator::new();
+        let mut batches: Vec<JournalDumpBatch> = vec![];
+        let mut stop: Option<JournalStop> = None;
+
+        // NOTE: End of the last valid batch, which is where recovery truncates to
+        let mut last_valid_pos = reader.last_valid_pos;
+
+        loop {
+            let offset = reader.last_valid_pos;
+
+            let Some(marker) = reader.next() else {
+                if !is_zeroed_from(&mut reader.reader, offset)? {
+                    stop_at(&mut stop, last_valid_pos, JournalStopReason::CorruptMarker);
+                } else if validator.is_in_batch() {
+                    stop_at(&mut stop, last_valid_pos, JournalStopReason::MissingEnd);
+                }
+
+                break;
+            };
+            let marker = marker?;
+
+            let was_in_batch = validator.is_in_batch();
+            let checksum_ok =
+                matches!(marker, Marker::End(expected) if validator.checksum_matches(expected));
+
+            let validated = validator.validate(&marker)?;
+
+            match marker {
+                Marker::Start {
+                    item_count, seqno, ..
+                } => {
+                    batches.push(JournalDumpBatch {
+                        offset,
+                        seqno,
+                        item_count,
+                        items: vec![],
+                        checksum_ok: None,
+                    });
+                }
+                Marker::Item {
+                    partition,
+                    key,
+                    value,
+                    value_type,
+                } => {
+                    if let Some(batch) = batches.last_mut().filter(|_| was_in_batch) {
+                        batch.items.push(JournalDumpItem {
+                            offset,
+                            partition,
+                            key,
+
+                            // NOTE: Truncation is not possible, the value size is encoded as u32
+                            #[allow(clippy::cast_possible_truncation)]
+                            value_size: value.len() as u32,
+
+                            value_type,
+                        });
+                    }
+                }
+                Marker::End(_) => {
+                    if let Some(batch) = batches.last_mut().filter(|_| was_in_batch) {
+                        batch.checksum_ok = Some(checksum_ok);
+                    }
+                }
+            }
+
+            match validated {
+                Validated::Continue => {}
+                Validated::End(_) => {
+                    if stop.is_none() {
+                        last_valid_pos = reader.last_valid_pos;
+                    }
+                }
+                Validated::Invalid(reason @ JournalStopReason::Broken(_)) => {
+                    // NOTE: Recovery fails at the broken batch
+                    let batch_offset = batches.last().map_or(last_valid_pos, |batch| batch.offset);
+                    stop_at(&mut stop, batch_offset, reason);
+                }
+                Validated::Invalid(reason) => {
+                    stop_at(&mut stop, last_valid_pos, reason);
+                }
+            }
+        }
+
+        Ok(Self {
+            path: path.into(),
+            size,
+            batches,
+            stop,
+        })
+    }
+}
@@ -0,0 +1,77 @@
+// Copyright (c) 2024-present, fjall-rs
+// This source code is licensed under both the Apache 2.0 and MIT License
+// (found in the LICENSE-* files in the repository)
+
+use super::{marker::Marker, reader::JournalReader};
+use crate::{encryption::Keys, fs::Fs};
+use lsm_tree::SeqNo;
+use std::{
+    path::{Path, PathBuf},
+    sync::Arc,
+};
+
+/// Information about a journal file, see [`Keyspace::journals`](crate::Keyspace::journals)
+#[derive(Clone, Debug, Eq, PartialEq)]
+#[allow(clippy::module_name_repetitions)]
+pub struct JournalInfo {
+    /// Path of the journal file
+    pub path: PathBuf,
+
+    /// `true` if the journal is sealed and waiting to be evicted,
+    /// `false` for the active journal
+    pub sealed: bool,
+
+    /// File size in bytes
+    pub size: u64,
+
+    /// Amount of batches in the journal
+    pub batch_count: u64,
+
+    /// Lowest and highest seqno of the batches in the journal, `None` if empty
+    pub seqnos: Option<(SeqNo, SeqNo)>,
+}
+
+impl JournalInfo {
+    /// Scans a journal file.
+    ///
+    /// Unlike journal recovery, this never truncates the file, so it
+    /// is safe to use on the active journal. Scanning stops at the
+    /// first marker that cannot be decoded.
+    pub(crate) fn scan<P: AsRef<Path>>(
+        path: P,
+        sealed: bool,
+        fs: Arc<dyn Fs>,
+        keys: &Keys,
+    ) -> crate::Result<Self> {
+        let path = path.as_ref();
+
+        let reader = JournalReader::new_read_only(path, fs, keys)?;
+        let size = reader.reader.get_ref().size()?;
+
+        let mut batch_count = 0;
+        let mut seqnos: Option<(SeqNo, SeqNo)> = None;
+
+        for marker in reader {
+            let Ok(marker) = marker else {
+                break;
+            };
+
+            if let Marker::Start { seqno, .. } = marker {
+                batch_count += 1;
+
+                seqnos = Some(match seqnos {
+                    Some((lo, hi)) => (lo.min(seqno), hi.max(seqno)),
+                    None => (seqno, seqno),
+                });
+            }
+        }
+
+        Ok(Self {
+            path: path.into(),
+            sealed,
+            size,
+            batch_count,
+            seqnos,
+        })
+    }
+}
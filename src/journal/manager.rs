@@ -3,7 +3,7 @@
 // (found in the LICENSE-* files in the repository)
 
 use super::writer::Writer;
-use crate::PartitionHandle;
+use crate::{fs::Fs, PartitionHandle};
 use lsm_tree::{AbstractTree, Memtable, SeqNo};
 use std::{
     path::PathBuf,
@@ -45,13 +45,18 @@ impl std::fmt::Debug for Item {
 ///
 /// Each journal may contain items of different partitions.
 #[allow(clippy::module_name_repetitions)]
-#[derive(Debug)]
 pub struct JournalManager {
     active_path: PathBuf, // TODO: remove?
     items: Vec<Item>,
 
+    /// File system that evicted journals are removed through
+    fs: Arc<dyn Fs>,
+
     // TODO: should be taking into account active journal, which is preallocated...
     disk_space_in_bytes: u64,
+
+    /// Highest seqno in journals that were evicted since the keyspace was opened
+    evicted_seqno: Option<SeqNo>,
 }
 
 impl Drop for JournalManager {
@@ -64,14 +69,16 @@ impl Drop for JournalManager {
 }
 
 impl JournalManager {
-    pub(crate) fn from_active<P: Into<PathBuf>>(path: P) -> Self {
+    pub(crate) fn from_active<P: Into<PathBuf>>(path: P, fs: Arc<dyn Fs>) -> Self {
         #[cfg(feature = "__internal_whitebox")]
         crate::drop::increment_drop_counter();
 
         Self {
             active_path: path.into(),
             items: Vec::with_capacity(10),
+            fs,
             disk_space_in_bytes: 0,
+            evicted_seqno: None,
         }
     }
 
@@ -84,6 +91,11 @@ impl JournalManager {
         self.items.push(item);
     }
 
+    /// Returns the paths of sealed journals, oldest first
+    pub(crate) fn sealed_journal_paths(&self) -> Vec<PathBuf> {
+        self.items.iter().map(|item| item.path.clone()).collect()
+    }
+
     /// Returns the amount of journals
     pub(crate) fn journal_count(&self) -> usize {
         // NOTE: + 1 = active journal
@@ -95,6 +107,11 @@ impl JournalManager {
         self.items.len()
     }
 
+    /// Returns the highest seqno in journals that were evicted since the keyspace was opened
+    pub(crate) fn evicted_seqno(&self) -> Option<SeqNo> {
+        self.evicted_seqno
+    }
+
     /// Returns the amount of bytes used on disk by journals
     pub(crate) fn disk_space_used(&self) -> u64 {
         self.disk_space_in_bytes
@@ -171,9 +188,12 @@ impl JournalManager {
             //
             // IMPORTANT: On recovery, the journals need to be flushed from oldest to newest.
             log::trace!("Removing fully flushed journal at {:?}", item.path);
-            std::fs::remove_file(&item.path)?;
+            self.fs.remove_file(&item.path)?;
 
             self.disk_space_in_bytes = self.disk_space_in_bytes.saturating_sub(item.size_in_bytes);
+            self.evicted_seqno = self
+                .evicted_seqno
+                .max(item.watermarks.iter().map(|x| x.lsn).max());
             self.items.remove(0);
         }
     }
@@ -3,26 +3,32 @@
 // (found in the LICENSE-* files in the repository)
 
 pub mod batch_reader;
+pub mod dump;
 pub mod error;
+pub mod info;
 pub mod manager;
 pub mod marker;
 pub mod reader;
 mod recovery;
+pub mod tail;
 pub mod writer;
 
 use self::writer::PersistMode;
-use crate::file::fsync_directory;
+use crate::{encryption::Keys, fs::Fs};
 use batch_reader::JournalBatchReader;
 use reader::JournalReader;
 use recovery::{recover_journals, RecoveryResult};
 use std::{
     path::{Path, PathBuf},
-    sync::{Mutex, MutexGuard},
+    sync::{Arc, Mutex, MutexGuard},
 };
 use writer::Writer;
 
 pub struct Journal {
     writer: Mutex<Writer>,
+
+    /// If `true`, the journal file is only read, never written
+    read_only: bool,
 }
 
 impl std::fmt::Debug for Journal {
@@ -33,14 +39,17 @@ impl std::fmt::Debug for Journal {
 
 impl Drop for Journal {
     fn drop(&mut self) {
-        log::trace!("Dropping journal, trying to flush");
-
-        match self.flush(PersistMode::SyncAll) {
-            Ok(()) => {
-                log::trace!("Flushed journal successfully");
-            }
-            Err(e) => {
-                log::error!("Flush error on drop: {e:?}");
+        // NOTE: A read-only journal has nothing to flush
+        if !self.read_only {
+            log::trace!("Dropping journal, trying to flush");
+
+            match self.flush(PersistMode::SyncAll) {
+                Ok(()) => {
+                    log::trace!("Flushed journal successfully");
+                }
+                Err(e) => {
+                    log::error!("Flush error on drop: {e:?}");
+                }
             }
         }
 
@@ -50,29 +59,38 @@ impl Drop for Journal {
 }
 
 impl Journal {
-    fn from_file<P: AsRef<Path>>(path: P) -> crate::Result<Self> {
+    fn from_file<P: AsRef<Path>>(path: P, fs: Arc<dyn Fs>, keys: Keys) -> crate::Result<Self> {
+        Ok(Self {
+            writer: Mutex::new(Writer::from_file(path, fs, keys)?),
+            read_only: false,
+        })
+    }
+
+    fn open_read_only<P: AsRef<Path>>(path: P, fs: Arc<dyn Fs>, keys: Keys) -> crate::Result<Self> {
         Ok(Self {
-            writer: Mutex::new(Writer::from_file(path)?),
+            writer: Mutex::new(Writer::open_read_only(path, fs, keys)?),
+            read_only: true,
         })
     }
 
-    pub fn create_new<P: AsRef<Path>>(path: P) -> crate::Result<Self> {
+    pub fn create_new<P: AsRef<Path>>(path: P, fs: Arc<dyn Fs>, keys: Keys) -> crate::Result<Self> {
         let path = path.as_ref();
         log::trace!("Creating new journal at {path:?}");
 
         let folder = path.parent().expect("parent should exist");
-        std::fs::create_dir_all(folder)?;
+        fs.create_dir_all(folder)?;
 
-        let writer = Writer::create_new(path)?;
+        let writer = Writer::create_new(path, fs, keys)?;
 
         // IMPORTANT: fsync folder on Unix
-        fsync_directory(folder)?;
+        writer.fs.sync_directory(folder)?;
 
         #[cfg(feature = "__internal_whitebox")]
         crate::drop::increment_drop_counter();
 
         Ok(Self {
             writer: Mutex::new(writer),
+            read_only: false,
         })
     }
 
@@ -86,7 +104,16 @@ impl Journal {
     }
 
     pub fn get_reader(&self) -> crate::Result<JournalBatchReader> {
-        let raw_reader = JournalReader::new(self.path())?;
+        let (path, fs, keys) = {
+            let writer = self.get_writer();
+            (writer.path.clone(), writer.fs.clone(), writer.keys.clone())
+        };
+
+        let raw_reader = if self.read_only {
+            JournalReader::new_read_only(path, fs, &keys)?
+        } else {
+            JournalReader::new(path, fs, &keys)?
+        };
         Ok(JournalBatchReader::new(raw_reader))
     }
 
@@ -96,8 +123,13 @@ impl Journal {
         lock.flush(mode).map_err(Into::into)
     }
 
-    pub fn recover<P: AsRef<Path>>(path: P) -> crate::Result<RecoveryResult> {
-        recover_journals(path)
+    pub fn recover<P: AsRef<Path>>(
+        path: P,
+        read_only: bool,
+        fs: &Arc<dyn Fs>,
+        keys: &Keys,
+    ) -> crate::Result<RecoveryResult> {
+        recover_journals(path, read_only, fs, keys)
     }
 }
 
@@ -105,7 +137,7 @@ impl Journal {
 #[allow(clippy::expect_used, clippy::unwrap_used)]
 mod tests {
     use super::*;
-    use crate::batch::item::Item as BatchItem;
+    use crate::{batch::item::Item as BatchItem, fs::StdFs};
     use lsm_tree::{coding::Encode, ValueType};
     use marker::Marker;
     use std::io::Write;
@@ -120,7 +152,7 @@ mod tests {
         let next_path = dir.path().join("1");
 
         {
-            let journal = Journal::create_new(&path)?;
+            let journal = Journal::create_new(&path, Arc::new(StdFs), None)?;
             let mut writer = journal.get_writer();
 
             writer.write_batch(
@@ -151,7 +183,7 @@ mod tests {
         let next_next_path = dir.path().join("2");
 
         {
-            let journal = Journal::create_new(&path)?;
+            let journal = Journal::create_new(&path, Arc::new(StdFs), None)?;
             let mut writer = journal.get_writer();
 
             writer.write_batch(
@@ -186,7 +218,8 @@ mod tests {
         assert!(next_path_rotated.try_exists()?);
         assert!(next_next_path.try_exists()?);
 
-        let journal_recovered = Journal::recover(dir)?;
+        let fs: Arc<dyn Fs> = Arc::new(StdFs);
+        let journal_recovered = Journal::recover(dir, false, &fs, &None)?;
         assert_eq!(journal_recovered.active.path(), next_next_path);
         assert_eq!(
             journal_recovered.sealed,
@@ -205,7 +238,7 @@ mod tests {
         let next_path = dir.path().join("1");
 
         {
-            let journal = Journal::create_new(&path)?;
+            let journal = Journal::create_new(&path, Arc::new(StdFs), None)?;
 
             {
                 let mut writer = journal.get_writer();
@@ -227,7 +260,8 @@ mod tests {
         assert!(path_rotated.try_exists()?);
         assert!(!next_path.try_exists()?);
 
-        let journal_recovered = Journal::recover(dir)?;
+        let fs: Arc<dyn Fs> = Arc::new(StdFs);
+        let journal_recovered = Journal::recover(dir, false, &fs, &None)?;
         assert_eq!(journal_recovered.active.path(), next_path);
         assert_eq!(journal_recovered.sealed, &[(0, path_rotated)]);
 
@@ -245,12 +279,12 @@ mod tests {
         ];
 
         {
-            let journal = Journal::create_new(&path)?;
+            let journal = Journal::create_new(&path, Arc::new(StdFs), None)?;
             journal.get_writer().write_batch(&values, 0)?;
         }
 
         {
-            let journal = Journal::from_file(&path)?;
+            let journal = Journal::from_file(&path, Arc::new(StdFs))?;
             let reader = journal.get_reader()?;
             let collected = reader.flatten().collect::<Vec<_>>();
             assert_eq!(
@@ -267,7 +301,7 @@ mod tests {
         }
 
         for _ in 0..10 {
-            let journal = Journal::from_file(&path)?;
+            let journal = Journal::from_file(&path, Arc::new(StdFs))?;
             let reader = journal.get_reader()?;
             let collected = reader.flatten().collect::<Vec<_>>();
             assert_eq!(
@@ -284,7 +318,7 @@ mod tests {
         }
 
         for _ in 0..10 {
-            let journal = Journal::from_file(&path)?;
+            let journal = Journal::from_file(&path, Arc::new(StdFs))?;
             let reader = journal.get_reader()?;
             let collected = reader.flatten().collect::<Vec<_>>();
             assert_eq!(
@@ -307,12 +341,12 @@ mod tests {
         ];
 
         {
-            let journal = Journal::create_new(&path)?;
+            let journal = Journal::create_new(&path, Arc::new(StdFs), None)?;
             journal.get_writer().write_batch(&values, 0)?;
         }
 
         {
-            let journal = Journal::from_file(&path)?;
+            let journal = Journal::from_file(&path, Arc::new(StdFs))?;
             let reader = journal.get_reader()?;
             let collected = reader.flatten().collect::<Vec<_>>();
             assert_eq!(
@@ -334,7 +368,7 @@ mod tests {
         }
 
         for _ in 0..10 {
-            let journal = Journal::from_file(&path)?;
+            let journal = Journal::from_file(&path, Arc::new(StdFs))?;
             let reader = journal.get_reader()?;
             let collected = reader.flatten().collect::<Vec<_>>();
             assert_eq!(
@@ -356,7 +390,7 @@ mod tests {
         }
 
         for _ in 0..10 {
-            let journal = Journal::from_file(&path)?;
+            let journal = Journal::from_file(&path, Arc::new(StdFs))?;
             let reader = journal.get_reader()?;
             let collected = reader.flatten().collect::<Vec<_>>();
             assert_eq!(
@@ -379,12 +413,12 @@ mod tests {
         ];
 
         {
-            let journal = Journal::create_new(&path)?;
+            let journal = Journal::create_new(&path, Arc::new(StdFs), None)?;
             journal.get_writer().write_batch(&values, 0)?;
         }
 
         {
-            let journal = Journal::from_file(&path)?;
+            let journal = Journal::from_file(&path, Arc::new(StdFs))?;
             let reader = journal.get_reader()?;
             let collected = reader.flatten().collect::<Vec<_>>();
             assert_eq!(
@@ -401,7 +435,7 @@ mod tests {
         }
 
         for _ in 0..10 {
-            let journal = Journal::from_file(&path)?;
+            let journal = Journal::from_file(&path, Arc::new(StdFs))?;
             let reader = journal.get_reader()?;
             let collected = reader.flatten().collect::<Vec<_>>();
             assert_eq!(
@@ -418,7 +452,7 @@ mod tests {
         }
 
         for _ in 0..10 {
-            let journal = Journal::from_file(&path)?;
+            let journal = Journal::from_file(&path, Arc::new(StdFs))?;
             let reader = journal.get_reader()?;
             let collected = reader.flatten().collect::<Vec<_>>();
             assert_eq!(
@@ -441,12 +475,12 @@ mod tests {
         ];
 
         {
-            let journal = Journal::create_new(&path)?;
+            let journal = Journal::create_new(&path, Arc::new(StdFs), None)?;
             journal.get_writer().write_batch(&values, 0)?;
         }
 
         {
-            let journal = Journal::from_file(&path)?;
+            let journal = Journal::from_file(&path, Arc::new(StdFs))?;
             let reader = journal.get_reader()?;
             let collected = reader.flatten().collect::<Vec<_>>();
             assert_eq!(
@@ -470,7 +504,7 @@ mod tests {
         }
 
         for _ in 0..10 {
-            let journal = Journal::from_file(&path)?;
+            let journal = Journal::from_file(&path, Arc::new(StdFs))?;
             let reader = journal.get_reader()?;
             let collected = reader.flatten().collect::<Vec<_>>();
             assert_eq!(
@@ -494,7 +528,7 @@ mod tests {
         }
 
         for _ in 0..10 {
-            let journal = Journal::from_file(&path)?;
+            let journal = Journal::from_file(&path, Arc::new(StdFs))?;
             let reader = journal.get_reader()?;
             let collected = reader.flatten().collect::<Vec<_>>();
             assert_eq!(
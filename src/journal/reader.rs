@@ -3,11 +3,15 @@
 // (found in the LICENSE-* files in the repository)
 
 use super::marker::Marker;
+use crate::{
+    encryption::{FileCipher, Keys, HEADER_LEN},
+    fs::{Fs, FsFile, OpenMode},
+};
 use lsm_tree::{coding::Decode, DecodeError};
 use std::{
-    fs::{File, OpenOptions},
-    io::{BufReader, Seek},
+    io::{BufReader, Cursor, Seek},
     path::{Path, PathBuf},
+    sync::Arc,
 };
 
 macro_rules! fail_iter {
@@ -24,28 +28,85 @@ macro_rules! fail_iter {
 ///
 /// Will truncate the file to the last valid position to prevent corrupt
 /// bytes at the end of the file, which would jeopardize future writes into the file.
+///
+/// A read-only reader never truncates the file.
+///
+/// Encrypted journals are decrypted one batch (frame) at a time, see [`FileCipher`].
 #[allow(clippy::module_name_repetitions)]
 pub struct JournalReader {
     pub(crate) path: PathBuf,
-    pub(crate) reader: BufReader<File>,
+    pub(crate) reader: BufReader<Box<dyn FsFile>>,
     pub(crate) last_valid_pos: u64,
+
+    /// File system that the file is read and truncated through
+    fs: Arc<dyn Fs>,
+    read_only: bool,
+
+    /// Cipher of an encrypted journal
+    cipher: Option<FileCipher>,
+
+    /// Decrypted markers of the current frame
+    frame: Cursor<Vec<u8>>,
 }
 
 impl JournalReader {
-    pub fn new<P: AsRef<Path>>(path: P) -> crate::Result<Self> {
-        let file = OpenOptions::new().read(true).write(true).open(&path)?;
+    pub fn new<P: AsRef<Path>>(path: P, fs: Arc<dyn Fs>, keys: &Keys) -> crate::Result<Self> {
+        Self::open(path.as_ref(), fs, keys, false)
+    }
+
+    pub fn new_read_only<P: AsRef<Path>>(
+        path: P,
+        fs: Arc<dyn Fs>,
+        keys: &Keys,
+    ) -> crate::Result<Self> {
+        Self::open(path.as_ref(), fs, keys, true)
+    }
+
+    fn open(path: &Path, fs: Arc<dyn Fs>, keys: &Keys, read_only: bool) -> crate::Result<Self> {
+        let file = fs.open(path, OpenMode::Read)?;
+        let mut reader = BufReader::new(file);
+
+        let cipher = FileCipher::read_header(&mut reader, keys)?;
+        let last_valid_pos = if cipher.is_some() { HEADER_LEN } else { 0 };
 
         Ok(Self {
-            path: path.as_ref().into(),
-            reader: BufReader::new(file),
-            last_valid_pos: 0,
+            path: path.into(),
+            reader,
+            last_valid_pos,
+            fs,
+            read_only,
+            cipher,
+            frame: Cursor::default(),
         })
     }
 
-    fn truncate_file(&mut self, pos: u64) -> crate::Result<()> {
+    /// Continues reading at the given position, which needs to be
+    /// the end of a batch.
+    pub fn seek_to(&mut self, pos: u64) -> crate::Result<()> {
+        // NOTE: The first batch of an encrypted journal starts after the header
+        let pos = if self.cipher.is_some() {
+            pos.max(HEADER_LEN)
+        } else {
+            pos
+        };
+
+        self.reader.seek(std::io::SeekFrom::Start(pos))?;
+        self.last_valid_pos = pos;
+        self.frame = Cursor::default();
+        Ok(())
+    }
+
+    /// Truncates the journal file, unless the reader is read-only.
+    pub(crate) fn truncate_file(&self, pos: u64) -> crate::Result<()> {
+        if self.read_only {
+            log::debug!("not truncating read-only journal to {pos}");
+            return Ok(());
+        }
+
         log::debug!("truncating journal to {pos}");
-        self.reader.get_mut().set_len(pos)?;
-        self.reader.get_mut().sync_all()?;
+        let mut file = self.fs.open(&self.path, OpenMode::Write)?;
+        file.set_len(pos)?;
+        file.sync_all()?;
         Ok(())
     }
 
@@ -60,10 +121,48 @@ impl JournalReader {
     }
 }
 
+impl JournalReader {
+    /// Reads the next marker of an encrypted journal.
+    ///
+    /// The position only advances once all markers of a frame are read,
+    /// because the markers inside a frame have no position in the file.
+    fn next_decrypted(&mut self) -> Option<crate::Result<Marker>> {
+        loop {
+            if self.frame.position() < self.frame.get_ref().len() as u64 {
+                let Ok(item) = Marker::decode_from(&mut self.frame) else {
+                    // NOTE: The frame is authentic, so its markers were encoded wrongly
+                    log::error!("Invalid marker in encrypted journal frame");
+                    fail_iter!(self.maybe_truncate_file_to_last_valid_pos());
+                    return None;
+                };
+
+                if self.frame.position() == self.frame.get_ref().len() as u64 {
+                    self.last_valid_pos = fail_iter!(self.reader.stream_position());
+                }
+
+                return Some(Ok(item));
+            }
+
+            let cipher = self.cipher.as_ref()?;
+
+            let Some(plaintext) = fail_iter!(cipher.read_frame(&mut self.reader)) else {
+                fail_iter!(self.maybe_truncate_file_to_last_valid_pos());
+                return None;
+            };
+
+            self.frame = Cursor::new(plaintext);
+        }
+    }
+}
+
 impl Iterator for JournalReader {
     type Item = crate::Result<Marker>;
 
     fn next(&mut self) -> Option<Self::Item> {
+        if self.cipher.is_some() {
+            return self.next_decrypted();
+        }
+
         match Marker::decode_from(&mut self.reader) {
             Ok(item) => {
                 self.last_valid_pos = fail_iter!(self.reader.stream_position());
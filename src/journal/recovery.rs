@@ -3,7 +3,12 @@
 // (found in the LICENSE-* files in the repository)
 
 use super::Journal;
-use std::path::{Path, PathBuf};
+use crate::{encryption::Keys, fs::Fs};
+use std::{
+    ffi::OsStr,
+    path::{Path, PathBuf},
+    sync::Arc,
+};
 
 pub type JournalId = u64;
 
@@ -15,7 +20,12 @@ pub struct RecoveryResult {
     pub(crate) was_active_created: bool,
 }
 
-pub fn recover_journals<P: AsRef<Path>>(path: P) -> crate::Result<RecoveryResult> {
+pub fn recover_journals<P: AsRef<Path>>(
+    path: P,
+    read_only: bool,
+    fs: &Arc<dyn Fs>,
+    keys: &Keys,
+) -> crate::Result<RecoveryResult> {
     let path = path.as_ref();
 
     let mut sealed = vec![];
@@ -23,14 +33,15 @@ pub fn recover_journals<P: AsRef<Path>>(path: P) -> crate::Result<RecoveryResult
     let mut max_journal_id: JournalId = 0;
     let mut was_active_created = false;
 
-    for dirent in std::fs::read_dir(path)? {
-        let dirent = dirent?;
-        let path = dirent.path();
+    for dirent in fs.read_dir(path)? {
+        let path = dirent.path;
 
-        assert!(dirent.file_type()?.is_file());
+        assert!(!dirent.is_dir);
 
-        let filename = dirent.file_name();
-        let filename = filename.to_str().expect("should be utf-8");
+        let filename = path
+            .file_name()
+            .and_then(OsStr::to_str)
+            .expect("should be utf-8");
         let is_sealed = filename.ends_with(".sealed");
 
         if is_sealed {
@@ -53,13 +64,32 @@ pub fn recover_journals<P: AsRef<Path>>(path: P) -> crate::Result<RecoveryResult
 
     sealed.sort_by(|(a, _), (b, _)| a.cmp(b));
 
+    if read_only {
+        // NOTE: A read-only keyspace can not create a new active journal,
+        // so if the active journal is missing (crash during rotation),
+        // the newest sealed journal is recovered as the active journal instead
+        let active = match active {
+            Some(active) => active,
+            None => sealed
+                .pop()
+                .map(|(_, path)| path)
+                .ok_or(crate::Error::ReadOnly)?,
+        };
+
+        return Ok(RecoveryResult {
+            active: Journal::open_read_only(active, fs.clone(), keys.clone())?,
+            sealed,
+            was_active_created: false,
+        });
+    }
+
     let active = active.map_or_else(
         || {
             was_active_created = true;
             let id: JournalId = max_journal_id + 1;
-            Journal::create_new(path.join(id.to_string()))
+            Journal::create_new(path.join(id.to_string()), fs.clone(), keys.clone())
         },
-        Journal::from_file,
+        |path| Journal::from_file(path, fs.clone(), keys.clone()),
     )?;
 
     Ok(RecoveryResult {
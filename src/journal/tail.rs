@@ -0,0 +1,84 @@
+// Copyright (c) 2024-present, fjall-rs
+// This source code is licensed under both the Apache 2.0 and MIT License
+// (found in the LICENSE-* files in the repository)
+
+use super::{
+    batch_reader::{Batch, JournalBatchReader},
+    reader::JournalReader,
+};
+use crate::{encryption::Keys, fs::Fs};
+use std::{path::Path, sync::Arc};
+
+/// Lists the IDs of all (active and sealed) journals, oldest first.
+pub fn list_journals(fs: &Arc<dyn Fs>, folder: &Path) -> crate::Result<Vec<u64>> {
+    let mut ids = vec![];
+
+    for dirent in fs.read_dir(folder)? {
+        let Some(id) = dirent
+            .path
+            .file_name()
+            .and_then(|name| name.to_str())
+            .map(|name| name.trim_end_matches(".sealed"))
+            .and_then(|name| name.parse::<u64>().ok())
+        else {
+            continue;
+        };
+
+        ids.push(id);
+    }
+
+    ids.sort_unstable();
+    ids.dedup();
+
+    Ok(ids)
+}
+
+/// Opens a journal, which may have been sealed in the meantime.
+pub fn open_journal(
+    fs: &Arc<dyn Fs>,
+    keys: &Keys,
+    folder: &Path,
+    id: u64,
+) -> crate::Result<JournalReader> {
+    let path = folder.join(id.to_string());
+
+    match JournalReader::new_read_only(&path, fs.clone(), keys) {
+        Err(crate::Error::Io(e)) if e.kind() == std::io::ErrorKind::NotFound => {
+            JournalReader::new_read_only(path.with_extension("sealed"), fs.clone(), keys)
+        }
+        result => result,
+    }
+}
+
+/// Reads the batches of a journal, starting at the given position,
+/// which needs to be the end of a batch.
+///
+/// Returns the position after the last complete batch.
+pub fn replay_journal<F: FnMut(Batch) -> crate::Result<()>>(
+    mut reader: JournalReader,
+    pos: u64,
+    is_active: bool,
+    mut f: F,
+) -> crate::Result<u64> {
+    reader.seek_to(pos)?;
+
+    let mut reader = JournalBatchReader::new(reader);
+
+    for batch in &mut reader {
+        let batch = match batch {
+            Ok(batch) => batch,
+
+            // NOTE: The batch may be written right now, so it is read again next time
+            Err(crate::Error::JournalRecovery(e)) if is_active => {
+                log::warn!("Stopping at invalid batch in active journal: {e:?}");
+                break;
+            }
+
+            Err(e) => return Err(e),
+        };
+
+        f(batch)?;
+    }
+
+    Ok(reader.position())
+}
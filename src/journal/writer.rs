@@ -3,13 +3,18 @@
 // (found in the LICENSE-* files in the repository)
 
 use super::marker::{serialize_marker_item, Marker};
-use crate::{batch::item::Item as BatchItem, file::fsync_directory, journal::recovery::JournalId};
+use crate::{
+    batch::item::Item as BatchItem,
+    encryption::{FileCipher, Keys},
+    fs::{Fs, FsFile, OpenMode},
+    journal::recovery::JournalId,
+};
 use lsm_tree::{coding::Encode, EncodeError, SeqNo, ValueType};
 use std::{
-    fs::{rename, File, OpenOptions},
     hash::Hasher,
     io::{BufWriter, Write},
     path::{Path, PathBuf},
+    sync::Arc,
 };
 
 // TODO: this should be a keyspace configuration
@@ -19,8 +24,20 @@ pub const JOURNAL_BUFFER_BYTES: usize = 8 * 1_024;
 
 pub struct Writer {
     pub(crate) path: PathBuf,
-    file: BufWriter<File>,
+    file: BufWriter<Box<dyn FsFile>>,
     buf: Vec<u8>,
+
+    /// File system the journal is written through
+    pub(crate) fs: Arc<dyn Fs>,
+
+    /// Key provider that new journals are encrypted with
+    pub(crate) keys: Keys,
+
+    /// Cipher of the journal, if it is encrypted
+    cipher: Option<FileCipher>,
+
+    /// Markers of the current batch, which are encrypted as a single frame
+    frame: Vec<u8>,
 }
 
 /// The persist mode allows setting the durability guarantee of previous writes
@@ -44,7 +61,7 @@ pub enum PersistMode {
 
 impl Writer {
     pub fn len(&self) -> crate::Result<u64> {
-        Ok(self.file.get_ref().metadata()?.len())
+        Ok(self.file.get_ref().size()?)
     }
 
     pub fn rotate(&mut self) -> crate::Result<(PathBuf, PathBuf)> {
@@ -72,7 +89,7 @@ impl Writer {
             .expect("should be valid journal ID");
 
         let sealed_path = folder.join(format!("{journal_id}.sealed"));
-        rename(&self.path, &sealed_path)?;
+        self.fs.rename(&self.path, &sealed_path)?;
 
         let new_path = folder.join((journal_id + 1).to_string());
         log::debug!("Rotating active journal to {new_path:?}");
@@ -80,51 +97,130 @@ impl Writer {
         // TODO: we clone the path on every rotation...
         // TODO: we shouldn't create + assign a new writer
         // TODO: but just change ourselves accordingly
-        *self = Self::create_new(&new_path)?;
+        *self = Self::create_new(&new_path, self.fs.clone(), self.keys.clone())?;
 
         // IMPORTANT: fsync folder on Unix
-        fsync_directory(&folder)?;
+        self.fs.sync_directory(&folder)?;
 
         Ok((sealed_path, new_path))
     }
 
-    pub fn create_new<P: AsRef<Path>>(path: P) -> crate::Result<Self> {
-        let path = path.as_ref();
-        let file = File::create(path)?;
+    /// Creates a new, pre-allocated journal file, which is encrypted if there is a key provider.
+    fn create_file(
+        path: &Path,
+        fs: &dyn Fs,
+        keys: &Keys,
+        mode: OpenMode,
+    ) -> crate::Result<(Box<dyn FsFile>, Option<FileCipher>)> {
+        let mut file = fs.open(path, mode)?;
+
+        let cipher = keys
+            .as_ref()
+            .map(|keys| FileCipher::create(keys.as_ref(), &mut file))
+            .transpose()?;
+
         file.set_len(PRE_ALLOCATED_BYTES)?;
         file.sync_all()?;
 
+        Ok((file, cipher))
+    }
+
+    pub fn create_new<P: AsRef<Path>>(path: P, fs: Arc<dyn Fs>, keys: Keys) -> crate::Result<Self> {
+        let path = path.as_ref();
+        let (file, cipher) = Self::create_file(path, fs.as_ref(), &keys, OpenMode::Create)?;
+
         Ok(Self {
             path: path.into(),
             file: BufWriter::new(file),
             buf: Vec::new(),
+            fs,
+            keys,
+            cipher,
+            frame: Vec::new(),
         })
     }
 
-    pub fn from_file<P: AsRef<Path>>(path: P) -> crate::Result<Self> {
+    pub fn from_file<P: AsRef<Path>>(path: P, fs: Arc<dyn Fs>, keys: Keys) -> crate::Result<Self> {
         let path = path.as_ref();
 
         if !path.try_exists()? {
-            let file = OpenOptions::new().create_new(true).write(true).open(path)?;
-            file.set_len(PRE_ALLOCATED_BYTES)?;
-            file.sync_all()?;
+            let (file, cipher) = Self::create_file(path, fs.as_ref(), &keys, OpenMode::CreateNew)?;
 
             return Ok(Self {
                 path: path.into(),
                 file: BufWriter::with_capacity(JOURNAL_BUFFER_BYTES, file),
                 buf: Vec::new(),
+                fs,
+                keys,
+                cipher,
+                frame: Vec::new(),
             });
         }
 
-        let file = OpenOptions::new().append(true).open(path)?;
+        // NOTE: Keep writing the journal the way it was started, so a journal
+        // written before encryption was enabled is only encrypted after rotating
+        let cipher = FileCipher::read_header(&mut fs.open(path, OpenMode::Read)?, &keys)?;
+
+        let file = fs.open(path, OpenMode::Append)?;
 
         Ok(Self {
             path: path.into(),
             file: BufWriter::with_capacity(JOURNAL_BUFFER_BYTES, file),
             buf: Vec::new(),
+            fs,
+            keys,
+            cipher,
+            frame: Vec::new(),
         })
     }
 
+    /// Opens an existing journal file without write access.
+    pub fn open_read_only<P: AsRef<Path>>(
+        path: P,
+        fs: Arc<dyn Fs>,
+        keys: Keys,
+    ) -> crate::Result<Self> {
+        let path = path.as_ref();
+        let mut file = fs.open(path, OpenMode::Read)?;
+        let cipher = FileCipher::read_header(&mut file, &keys)?;
+
+        Ok(Self {
+            path: path.into(),
+            file: BufWriter::with_capacity(JOURNAL_BUFFER_BYTES, file),
+            buf: Vec::new(),
+            fs,
+            keys,
+            cipher,
+            frame: Vec::new(),
+        })
+    }
+
+    /// Writes the encoded marker in the buffer to the journal,
+    /// or to the current frame, if the journal is encrypted.
+    fn write_buf(&mut self) -> std::io::Result<()> {
+        if self.cipher.is_some() {
+            self.frame.extend_from_slice(&self.buf);
+            Ok(())
+        } else {
+            self.file.write_all(&self.buf)
+        }
+    }
+
+    /// Encrypts the markers of the current batch, and writes them to the journal.
+    ///
+    /// Returns the amount of bytes the frame adds to the batch.
+    fn write_frame(&mut self) -> crate::Result<usize> {
+        let Some(cipher) = &self.cipher else {
+            return Ok(0);
+        };
+
+        let frame_len = cipher.write_frame(&self.frame, &mut self.file)?;
+        let overhead = frame_len - self.frame.len();
+        self.frame.clear();
+
+        Ok(overhead)
+    }
+
     /// Flushes the journal file.
     pub(crate) fn flush(&mut self, mode: PersistMode) -> std::io::Result<()> {
         log::trace!("Flush journal {:?} with mode={mode:?}", self.path);
@@ -149,7 +245,7 @@ impl Writer {
         }
         .encode_into(&mut self.buf)?;
 
-        self.file.write_all(&self.buf)?;
+        self.write_buf()?;
 
         Ok(self.buf.len())
     }
@@ -160,7 +256,7 @@ impl Writer {
 
         Marker::End(checksum).encode_into(&mut self.buf)?;
 
-        self.file.write_all(&self.buf)?;
+        self.write_buf()?;
 
         Ok(self.buf.len())
     }
@@ -182,7 +278,7 @@ impl Writer {
 
         serialize_marker_item(&mut self.buf, partition, key, value, value_type)?;
 
-        self.file.write_all(&self.buf)?;
+        self.write_buf()?;
 
         hasher.update(&self.buf);
         byte_count += self.buf.len();
@@ -190,6 +286,7 @@ impl Writer {
         self.buf.clear();
         let checksum = hasher.finish();
         byte_count += self.write_end(checksum)?;
+        byte_count += self.write_frame()?;
 
         Ok(byte_count)
     }
@@ -222,7 +319,7 @@ impl Writer {
                 item.value_type,
             )?;
 
-            self.file.write_all(&self.buf)?;
+            self.write_buf()?;
 
             hasher.update(&self.buf);
             byte_count += self.buf.len();
@@ -232,6 +329,7 @@ impl Writer {
 
         let checksum = hasher.finish();
         byte_count += self.write_end(checksum)?;
+        byte_count += self.write_frame()?;
 
         Ok(byte_count)
     }
@@ -0,0 +1,452 @@
+// Copyright (c) 2024-present, fjall-rs
+// This source code is licensed under both the Apache 2.0 and MIT License
+// (found in the LICENSE-* files in the repository)
+
+//! Order-preserving encoding of composite keys (tuples)
+//!
+//! Encoded tuples sort (bytewise) in the same order as the tuples themselves,
+//! comparing element by element, so composite keys like `(tenant_id, timestamp, uuid)`
+//! can be scanned using [`PartitionHandle::range`](crate::PartitionHandle::range) and
+//! [`PartitionHandle::prefix`](crate::PartitionHandle::prefix).
+//!
+//! The encoding is similar to the `FoundationDB` tuple layer: every element starts
+//! with a type code, so tuples can be decoded without knowing their schema.
+//! Elements of different types are ordered by type:
+//! byte strings < strings < nested tuples < integers < floats < booleans.
+//!
+//! Descending elements (see [`Element::desc`]) sort in reverse order.
+//!
+//! # Examples
+//!
+//! ```
+//! # use fjall::{Config, PartitionCreateOptions, UserValue};
+//! use fjall::keys::{self, Element};
+//! #
+//! # let folder = tempfile::tempdir()?;
+//! # let keyspace = Config::new(folder).open()?;
+//! # let partition = keyspace.open_partition("default", PartitionCreateOptions::default())?;
+//!
+//! // (tenant, timestamp descending) => event
+//! for (tenant, ts, event) in [(1, 10, "a"), (1, 20, "b"), (2, 15, "c")] {
+//!     let key = keys::encode(&[tenant.into(), Element::desc(ts)]);
+//!     partition.insert(key, event)?;
+//! }
+//!
+//! // Newest events of tenant 1 first
+//! let events = partition
+//!     .range(keys::prefix_range(&[1.into()]))
+//!     .map(|kv| kv.map(|(_, v)| v))
+//!     .collect::<fjall::Result<Vec<_>>>()?;
+//!
+//! assert_eq!(vec![UserValue::from("b"), UserValue::from("a")], events);
+//! #
+//! # Ok::<(), fjall::Error>(())
+//! ```
+
+use crate::{
+    codec::{Codec, CodecError},
+    escape::{read_escaped, write_escaped},
+};
+use lsm_tree::UserKey;
+use std::ops::Bound;
+
+const BYTES: u8 = 0x01;
+const STRING: u8 = 0x02;
+const NESTED: u8 = 0x05;
+const INT_ZERO: u8 = 0x14;
+const FLOAT: u8 = 0x21;
+const FALSE: u8 = 0x26;
+const TRUE: u8 = 0x27;
+
+/// Terminates a nested tuple (never used as type code)
+const NESTED_END: u8 = 0x00;
+
+/// Maximum amount of bytes of an encoded integer's magnitude
+const MAX_INT_LEN: u8 = 8;
+
+/// Integer of a tuple
+///
+/// Integers are in the range `-(2^64 - 1)..=2^64 - 1`,
+/// so all `i64` and `u64` values can be stored.
+#[derive(Copy, Clone, Debug, Eq, PartialEq, Ord, PartialOrd, Hash)]
+pub struct Int(i128);
+
+impl Int {
+    /// Creates an integer.
+    ///
+    /// # Errors
+    ///
+    /// Returns error if the value is outside of `-(2^64 - 1)..=2^64 - 1`.
+    pub fn new(value: i128) -> Result<Self, CodecError> {
+        if u64::try_from(value.unsigned_abs()).is_err() {
+            return Err(CodecError::new(format!(
+                "integer does not fit in 64 bits: {value}"
+            )));
+        }
+
+        Ok(Self(value))
+    }
+
+    /// Returns the value of the integer.
+    #[must_use]
+    pub fn get(self) -> i128 {
+        self.0
+    }
+
+    /// Returns the magnitude of the integer, which always fits in 64 bits.
+    #[allow(clippy::cast_possible_truncation)]
+    fn magnitude(self) -> u64 {
+        self.0.unsigned_abs() as u64
+    }
+}
+
+impl TryFrom<i128> for Int {
+    type Error = CodecError;
+
+    fn try_from(value: i128) -> Result<Self, Self::Error> {
+        Self::new(value)
+    }
+}
+
+/// An element of a tuple
+#[derive(Clone, Debug, PartialEq)]
+pub enum Element {
+    /// Byte string
+    Bytes(Vec<u8>),
+
+    /// UTF-8 string
+    String(String),
+
+    /// Nested tuple
+    Tuple(Vec<Self>),
+
+    /// Integer
+    Int(Int),
+
+    /// Floating point number
+    ///
+    /// `-NaN < -inf < ... < -0.0 < 0.0 < ... < inf < NaN`
+    Float(f64),
+
+    /// Boolean
+    Bool(bool),
+
+    /// Element that sorts in descending order
+    Desc(Box<Self>),
+}
+
+impl Element {
+    /// Wraps the element, so it sorts in descending order.
+    pub fn desc<E: Into<Self>>(element: E) -> Self {
+        Self::Desc(Box::new(element.into()))
+    }
+}
+
+macro_rules! impl_from_int {
+    ($($t:ty),*) => {$(
+        impl From<$t> for Int {
+            fn from(value: $t) -> Self {
+                Self(value.into())
+            }
+        }
+
+        impl From<$t> for Element {
+            fn from(value: $t) -> Self {
+                Self::Int(value.into())
+            }
+        }
+    )*};
+}
+
+impl_from_int!(u8, u16, u32, u64, i8, i16, i32, i64);
+
+impl From<f32> for Element {
+    fn from(value: f32) -> Self {
+        Self::Float(value.into())
+    }
+}
+
+impl From<f64> for Element {
+    fn from(value: f64) -> Self {
+        Self::Float(value)
+    }
+}
+
+impl From<bool> for Element {
+    fn from(value: bool) -> Self {
+        Self::Bool(value)
+    }
+}
+
+impl From<&str> for Element {
+    fn from(value: &str) -> Self {
+        Self::String(value.into())
+    }
+}
+
+impl From<String> for Element {
+    fn from(value: String) -> Self {
+        Self::String(value)
+    }
+}
+
+impl From<&[u8]> for Element {
+    fn from(value: &[u8]) -> Self {
+        Self::Bytes(value.into())
+    }
+}
+
+impl From<Vec<u8>> for Element {
+    fn from(value: Vec<u8>) -> Self {
+        Self::Bytes(value)
+    }
+}
+
+impl From<Vec<Self>> for Element {
+    fn from(value: Vec<Self>) -> Self {
+        Self::Tuple(value)
+    }
+}
+
+fn encode_int(buf: &mut Vec<u8>, value: Int) {
+    let magnitude = value.magnitude();
+
+    #[allow(clippy::cast_possible_truncation)]
+    let len = (8 - magnitude.leading_zeros() / 8) as u8;
+
+    let bytes = magnitude.to_be_bytes();
+    let bytes = bytes.get((8 - len as usize)..).unwrap_or_default();
+
+    if value.get() < 0 {
+        // NOTE: Longer magnitude = more negative = lower type code,
+        // and the one's complement reverses the order of equally long magnitudes
+        buf.push(INT_ZERO - len);
+        buf.extend(bytes.iter().map(|b| !b));
+    } else {
+        buf.push(INT_ZERO + len);
+        buf.extend_from_slice(bytes);
+    }
+}
+
+fn encode_float(buf: &mut Vec<u8>, value: f64) {
+    let bits = value.to_bits();
+
+    // NOTE: Negative floats are fully inverted (so larger magnitudes sort first),
+    // positive floats only get their sign bit flipped (so they sort after negative ones)
+    let bits = if bits >> 63 == 1 {
+        !bits
+    } else {
+        bits ^ (1 << 63)
+    };
+
+    buf.push(FLOAT);
+    buf.extend_from_slice(&bits.to_be_bytes());
+}
+
+fn encode_element(buf: &mut Vec<u8>, element: &Element) {
+    match element {
+        Element::Bytes(bytes) => {
+            buf.push(BYTES);
+            write_escaped(buf, bytes);
+        }
+        Element::String(s) => {
+            buf.push(STRING);
+            write_escaped(buf, s.as_bytes());
+        }
+        Element::Tuple(elements) => {
+            buf.push(NESTED);
+
+            for element in elements {
+                encode_element(buf, element);
+            }
+
+            buf.push(NESTED_END);
+        }
+        Element::Int(value) => encode_int(buf, *value),
+        Element::Float(value) => encode_float(buf, *value),
+        Element::Bool(value) => buf.push(if *value { TRUE } else { FALSE }),
+        Element::Desc(inner) => {
+            let start = buf.len();
+            encode_element(buf, inner);
+
+            // NOTE: Inverting a prefix-free encoding reverses its order
+            if let Some(encoded) = buf.get_mut(start..) {
+                for byte in encoded {
+                    *byte = !*byte;
+                }
+            }
+        }
+    }
+}
+
+/// Appends the encoded tuple to the buffer.
+pub fn encode_into(buf: &mut Vec<u8>, tuple: &[Element]) {
+    for element in tuple {
+        encode_element(buf, element);
+    }
+}
+
+/// Encodes a tuple.
+#[must_use]
+pub fn encode(tuple: &[Element]) -> Vec<u8> {
+    let mut buf = vec![];
+    encode_into(&mut buf, tuple);
+    buf
+}
+
+/// Reads bytes, optionally inverting them (inside descending elements)
+struct Reader<'a> {
+    bytes: &'a [u8],
+    inverted: bool,
+}
+
+impl Reader<'_> {
+    fn peek(&self) -> Option<u8> {
+        self.bytes
+            .first()
+            .map(|&b| if self.inverted { !b } else { b })
+    }
+
+    fn next(&mut self) -> Result<u8, CodecError> {
+        let byte = self
+            .peek()
+            .ok_or_else(|| CodecError::new("unexpected end of key"))?;
+        self.bytes = self.bytes.get(1..).unwrap_or_default();
+        Ok(byte)
+    }
+
+    fn escaped(&mut self) -> Result<Vec<u8>, CodecError> {
+        read_escaped(std::iter::from_fn(|| self.next().ok()))
+    }
+
+    fn element(&mut self) -> Result<Element, CodecError> {
+        let code = self.next()?;
+        self.element_with_code(code)
+    }
+
+    fn element_with_code(&mut self, code: u8) -> Result<Element, CodecError> {
+        match code {
+            BYTES => self.escaped().map(Element::Bytes),
+            STRING => String::from_utf8(self.escaped()?)
+                .map(Element::String)
+                .map_err(|e| CodecError::new(format!("invalid UTF-8: {e}"))),
+            NESTED => {
+                let mut elements = vec![];
+
+                while self.peek() != Some(NESTED_END) {
+                    elements.push(self.element()?);
+                }
+                self.next()?;
+
+                Ok(Element::Tuple(elements))
+            }
+            _ if is_int_code(code) => {
+                let negative = code < INT_ZERO;
+                let len = code.abs_diff(INT_ZERO);
+
+                let mut magnitude = 0_u64;
+
+                for _ in 0..len {
+                    let byte = self.next()?;
+                    let byte = if negative { !byte } else { byte };
+                    magnitude = (magnitude << 8) | u64::from(byte);
+                }
+
+                let magnitude = i128::from(magnitude);
+                Ok(Element::Int(Int(if negative {
+                    -magnitude
+                } else {
+                    magnitude
+                })))
+            }
+            FLOAT => {
+                let mut bits = 0_u64;
+
+                for _ in 0..8 {
+                    bits = (bits << 8) | u64::from(self.next()?);
+                }
+
+                let bits = if bits >> 63 == 1 {
+                    bits ^ (1 << 63)
+                } else {
+                    !bits
+                };
+
+                Ok(Element::Float(f64::from_bits(bits)))
+            }
+            FALSE => Ok(Element::Bool(false)),
+            TRUE => Ok(Element::Bool(true)),
+            _ if is_type_code(!code) => {
+                // NOTE: Descending elements are fully inverted, including their type code
+                self.inverted = !self.inverted;
+                let element = self.element_with_code(!code);
+                self.inverted = !self.inverted;
+
+                element.map(|element| Element::Desc(Box::new(element)))
+            }
+            _ => Err(CodecError::new(format!("invalid type code: 0x{code:02x}"))),
+        }
+    }
+}
+
+fn is_int_code(code: u8) -> bool {
+    (INT_ZERO - MAX_INT_LEN..=INT_ZERO + MAX_INT_LEN).contains(&code)
+}
+
+fn is_type_code(code: u8) -> bool {
+    matches!(code, BYTES | STRING | NESTED | FLOAT | FALSE | TRUE) || is_int_code(code)
+}
+
+/// Decodes a tuple.
+///
+/// # Errors
+///
+/// Returns error if the bytes are not a valid encoded tuple.
+pub fn decode(bytes: &[u8]) -> Result<Vec<Element>, CodecError> {
+    let mut reader = Reader {
+        bytes,
+        inverted: false,
+    };
+
+    let mut elements = vec![];
+
+    while !reader.bytes.is_empty() {
+        elements.push(reader.element()?);
+    }
+
+    Ok(elements)
+}
+
+/// Returns the key range of all tuples that start with the given prefix
+/// (including the prefix itself), to be passed to
+/// [`PartitionHandle::range`](crate::PartitionHandle::range).
+///
+/// An empty prefix matches all keys.
+#[must_use]
+pub fn prefix_range(prefix: &[Element]) -> (Bound<UserKey>, Bound<UserKey>) {
+    // NOTE: Because element encodings are prefix-free, the byte prefix
+    // of an encoded tuple only matches tuples that have whole elements in common
+    lsm_tree::range::prefix_to_range(&encode(prefix))
+}
+
+/// Returns the key range of all tuples that start with the given prefix
+/// and have at least one more element.
+#[must_use]
+pub fn children_range(prefix: &[Element]) -> (Bound<UserKey>, Bound<UserKey>) {
+    let (_, hi) = prefix_range(prefix);
+    (Bound::Excluded(encode(prefix).into()), hi)
+}
+
+/// Codec for tuples of [`Element`]s, see [`TypedPartition`](crate::TypedPartition)
+#[derive(Copy, Clone, Debug, Default)]
+pub struct TupleCodec;
+
+impl Codec<Vec<Element>> for TupleCodec {
+    fn encode_into(item: &Vec<Element>, buf: &mut Vec<u8>) {
+        encode_into(buf, item);
+    }
+
+    fn decode(bytes: &[u8]) -> Result<Vec<Element>, CodecError> {
+        decode(bytes)
+    }
+}
@@ -3,30 +3,36 @@
 // (found in the LICENSE-* files in the repository)
 
 use crate::{
+    background_work::BackgroundWork,
     batch::{Batch, PartitionKey},
+    close::CloseOptions,
     compaction::manager::CompactionManager,
     config::Config,
-    file::{
-        fsync_directory, FJALL_MARKER, JOURNALS_FOLDER, PARTITIONS_FOLDER, PARTITION_DELETED_MARKER,
-    },
+    file::{FJALL_MARKER, JOURNALS_FOLDER, PARTITIONS_FOLDER, PARTITION_DELETED_MARKER},
     flush::manager::FlushManager,
-    journal::{manager::JournalManager, writer::PersistMode, Journal},
+    fs::{Fs, OpenMode},
+    journal::{info::JournalInfo, manager::JournalManager, writer::PersistMode, Journal},
+    lock::LockFile,
     monitor::Monitor,
     partition::name::is_valid_partition_name,
     recovery::{recover_partitions, recover_sealed_memtables},
+    repair::{RepairOptions, RepairReport},
+    secondary::Secondary,
     snapshot_tracker::SnapshotTracker,
+    verify::{VerifyOptions, VerifyReport},
     version::Version,
     write_buffer_manager::WriteBufferManager,
-    HashMap, PartitionCreateOptions, PartitionHandle,
+    HashMap, PartitionCreateOptions, PartitionHandle, SecondaryIndex,
 };
-use lsm_tree::{AbstractTree, SequenceNumberCounter};
+use lsm_tree::{AbstractTree, SequenceNumberCounter, UserKey};
 use std::{
-    fs::{remove_dir_all, File},
+    io::Read,
     path::Path,
     sync::{
         atomic::{AtomicBool, AtomicUsize},
         Arc, RwLock,
     },
+    time::Instant,
 };
 use std_semaphore::Semaphore;
 
@@ -69,6 +75,9 @@ pub struct KeyspaceInner {
     /// Counter of background threads
     pub(crate) active_background_threads: Arc<AtomicUsize>,
 
+    /// Allows pausing flush and compaction workers
+    pub(crate) background_work: BackgroundWork,
+
     /// Keeps track of write buffer size
     pub(crate) write_buffer_manager: WriteBufferManager,
 
@@ -77,6 +86,14 @@ pub struct KeyspaceInner {
 
     #[doc(hidden)]
     pub snapshot_tracker: SnapshotTracker,
+
+    /// Mirror of the primary, if the keyspace is a secondary
+    pub(crate) secondary: Option<Secondary>,
+
+    /// Lock of the keyspace folder, `None` in read-only mode
+    ///
+    /// IMPORTANT: Needs to be the last field, so it is released after the journal is flushed
+    pub(crate) lock: Option<LockFile>,
 }
 
 impl Drop for KeyspaceInner {
@@ -85,6 +102,9 @@ impl Drop for KeyspaceInner {
 
         self.stop_signal.send();
 
+        // NOTE: Paused workers need to wake up to notice the stop signal
+        self.background_work.wake_for_stop();
+
         while self
             .active_background_threads
             .load(std::sync::atomic::Ordering::Relaxed)
@@ -99,13 +119,16 @@ impl Drop for KeyspaceInner {
 
         self.config.descriptor_table.clear();
 
-        if self.config.clean_path_on_drop {
+        if self.config.clean_path_on_drop && !self.config.read_only {
+            // NOTE: The lock file can not be deleted while it is open on Windows
+            drop(self.lock.take());
+
             log::info!(
                 "Deleting keyspace because temporary=true: {:?}",
                 self.config.path
             );
 
-            if let Err(err) = remove_dir_all(&self.config.path) {
+            if let Err(err) = self.config.fs.remove_dir_all(&self.config.path) {
                 eprintln!("Failed to clean up path: {:?} - {err}", self.config.path);
             }
         }
@@ -191,6 +214,57 @@ impl Keyspace {
         self.write_buffer_manager.get()
     }
 
+    /// Pauses background flushes and compactions.
+    ///
+    /// No new flush or compaction jobs are started, and this function
+    /// blocks until running jobs have finished.
+    /// This is useful for maintenance windows or benchmarks.
+    ///
+    /// Writes keep working, but as memtables are not flushed anymore,
+    /// they will eventually be halted by the write stall policy,
+    /// see [`Config::write_stall_policy`](crate::Config::write_stall_policy).
+    /// Functions that wait for flushes, like [`PartitionHandle::rotate_memtable_and_wait`],
+    /// block until background work is resumed.
+    ///
+    /// Pausing is not reference counted: a single call to [`Keyspace::resume_background_work`]
+    /// resumes background work, regardless of how many times it was paused.
+    pub fn pause_background_work(&self) {
+        log::info!("Pausing background work");
+        self.background_work.pause();
+    }
+
+    /// Resumes background flushes and compactions after [`Keyspace::pause_background_work`].
+    pub fn resume_background_work(&self) {
+        log::info!("Resuming background work");
+        self.background_work.resume();
+    }
+
+    /// Returns `true` if background work is paused.
+    #[must_use]
+    pub fn is_background_work_paused(&self) -> bool {
+        self.background_work.is_paused()
+    }
+
+    /// Returns the rate limiter of background I/O, which allows adjusting limits at runtime.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use fjall::{Config, IoActivity, RateLimit};
+    /// # let folder = tempfile::tempdir()?;
+    /// let keyspace = Config::new(folder).open()?;
+    ///
+    /// keyspace
+    ///     .rate_limiter()
+    ///     .set_limit(IoActivity::Compaction, Some(RateLimit::new(/* 50 MiB */ 50 * 1_024 * 1_024)));
+    /// #
+    /// # Ok::<(), fjall::Error>(())
+    /// ```
+    #[must_use]
+    pub fn rate_limiter(&self) -> &crate::RateLimiter {
+        &self.config.rate_limiter
+    }
+
     /// Returns the amount of journals on disk.
     ///
     /// # Examples
@@ -213,6 +287,164 @@ impl Keyspace {
             .journal_count()
     }
 
+    /// Lists the sealed and active journals, oldest first.
+    ///
+    /// Every journal is scanned to find its seqno range, so this is not cheap.
+    ///
+    /// # Errors
+    ///
+    /// Will return `Err` if an IO error occurs.
+    pub fn journals(&self) -> crate::Result<Vec<JournalInfo>> {
+        let sealed_paths = self
+            .journal_manager
+            .read()
+            .expect("lock is poisoned")
+            .sealed_journal_paths();
+
+        // NOTE: Make buffered writes visible to the scan
+        self.journal.flush(PersistMode::Buffer)?;
+
+        let fs = &self.config.fs;
+        let keys = &self.config.encryption;
+
+        sealed_paths
+            .iter()
+            .map(|path| JournalInfo::scan(path, true, fs.clone(), keys))
+            .chain(std::iter::once(JournalInfo::scan(
+                self.journal.path(),
+                false,
+                fs.clone(),
+                keys,
+            )))
+            .collect()
+    }
+
+    /// Checks if the keyspace in the given folder is opened for writing,
+    /// without opening it.
+    ///
+    /// Opening a keyspace (unless [read-only](crate::Config::read_only)) locks its folder
+    /// until the keyspace is dropped, so it cannot be opened twice at the same time.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use fjall::{Config, Keyspace};
+    /// # let folder = tempfile::tempdir()?;
+    /// let keyspace = Config::new(&folder).open()?;
+    ///
+    /// assert!(matches!(
+    ///     Keyspace::check_lock(&folder),
+    ///     Err(fjall::Error::Locked { pid: Some(_) }),
+    /// ));
+    /// assert!(Config::new(&folder).open().is_err());
+    ///
+    /// drop(keyspace);
+    /// assert!(Keyspace::check_lock(&folder).is_ok());
+    /// #
+    /// # Ok::<(), fjall::Error>(())
+    /// ```
+    ///
+    /// # Errors
+    ///
+    /// Will return [`Error::Locked`](crate::Error::Locked) with the PID of the holder,
+    /// if the keyspace is opened, or `Err` if an IO error occurs.
+    pub fn check_lock<P: AsRef<Path>>(path: P) -> crate::Result<()> {
+        LockFile::check(path.as_ref())
+    }
+
+    /// Repairs a damaged keyspace, so it can be opened again.
+    ///
+    /// The keyspace must not be opened while repairing, otherwise [`Error::Locked`](crate::Error::Locked)
+    /// is returned.
+    ///
+    /// Files that prevent recovery, like stray files, partitions with missing segments,
+    /// orphaned segments or duplicate active journals, are moved into a `lost+found` folder
+    /// inside the keyspace folder. Unreadable partition configs are replaced with
+    /// default options, and journals are truncated before broken batches.
+    /// Every change is written to a `repair.log` next to the quarantined files.
+    /// Partitions are checked without opening them, so nothing is deleted.
+    ///
+    /// Data of quarantined partitions and truncated journal batches is lost.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use fjall::{Config, Keyspace, RepairOptions};
+    /// # let folder = tempfile::tempdir()?;
+    /// # {
+    /// #   let keyspace = Config::new(&folder).open()?;
+    /// #   keyspace.open_partition("default", Default::default())?.insert("a", "abc")?;
+    /// # }
+    /// std::fs::write(folder.path().join("partitions").join("stray"), "")?;
+    ///
+    /// let report = Keyspace::repair(&folder, RepairOptions::default())?;
+    /// assert_eq!(1, report.actions.len());
+    ///
+    /// let keyspace = Config::new(&folder).open()?;
+    /// #
+    /// # Ok::<(), fjall::Error>(())
+    /// ```
+    ///
+    /// # Errors
+    ///
+    /// Will return `Err` if an IO error occurs, the folder does not contain a keyspace,
+    /// or the keyspace is opened.
+    pub fn repair<P: AsRef<Path>>(path: P, options: RepairOptions) -> crate::Result<RepairReport> {
+        crate::repair::repair_keyspace(path.as_ref(), options)
+    }
+
+    /// Verifies the integrity of the keyspace.
+    ///
+    /// Checks the version marker, partition configs, that the manifests
+    /// match the segment and blob files on disk and, depending on the options,
+    /// the checksums of all blocks, blobs and journal batches.
+    ///
+    /// Background work is paused while the manifests are compared to the files on disk.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use fjall::{Config, VerifyOptions};
+    /// # let folder = tempfile::tempdir()?;
+    /// let keyspace = Config::new(folder).open()?;
+    /// let items = keyspace.open_partition("default", Default::default())?;
+    /// items.insert("a", "hello")?;
+    ///
+    /// let report = keyspace.verify(VerifyOptions::default())?;
+    /// assert!(report.is_ok());
+    /// #
+    /// # Ok::<(), fjall::Error>(())
+    /// ```
+    ///
+    /// # Errors
+    ///
+    /// Will return `Err` if an IO error occurs.
+    pub fn verify(&self, options: VerifyOptions) -> crate::Result<VerifyReport> {
+        crate::verify::verify_keyspace(self, options)
+    }
+
+    /// Brings a [secondary](crate::Config::secondary) keyspace up to date with its primary.
+    ///
+    /// New batches in the primary's active journal are applied to the memtables.
+    /// If the primary flushed, compacted, rotated its journal or created or deleted partitions,
+    /// the changed partitions are mirrored again, and the memtables are rebuilt from the journals.
+    ///
+    /// Partition handles stay valid, but partitions that were deleted by the primary are
+    /// no longer listed. Iterators created before catching up may fail with an IO error,
+    /// if they read segments that were deleted by the primary.
+    ///
+    /// Writes become visible once the primary flushes them to the operating system,
+    /// which happens for every write, unless [`Config::manual_journal_persist`](crate::Config::manual_journal_persist) is set.
+    ///
+    /// Does nothing if the keyspace is not a secondary.
+    ///
+    /// # Errors
+    ///
+    /// Will return `Err` if an IO error occurs.
+    pub fn catch_up(&self) -> crate::Result<()> {
+        crate::secondary::catch_up(self)
+    }
+
     /// Returns the disk space usage of the journal.
     #[doc(hidden)]
     pub fn journal_disk_space(&self) -> u64 {
@@ -272,7 +504,13 @@ impl Keyspace {
     /// # Errors
     ///
     /// Returns error, if an IO error occurred.
+    ///
+    /// Returns [`Error::ReadOnly`](crate::Error::ReadOnly) if the keyspace is opened in read-only mode.
     pub fn persist(&self, mode: PersistMode) -> crate::Result<()> {
+        if self.config.read_only {
+            return Err(crate::Error::ReadOnly);
+        }
+
         if self.is_poisoned.load(std::sync::atomic::Ordering::Relaxed) {
             return Err(crate::Error::Poisoned);
         }
@@ -291,11 +529,156 @@ impl Keyspace {
         Ok(())
     }
 
+    /// Closes the keyspace.
+    ///
+    /// Unlike dropping the keyspace, this persists the journal and reports errors.
+    /// If [`CloseOptions::flush_memtables`] is set, all memtables are flushed,
+    /// so journals are evicted and the next recovery does not need to replay them.
+    ///
+    /// Background threads are stopped once the last handle to the keyspace is dropped,
+    /// so other handles (e.g. clones of the keyspace) should be dropped before.
+    ///
+    /// A read-only keyspace has nothing to persist or flush, so the options are ignored.
+    ///
+    /// Paused background work (see [`Keyspace::pause_background_work`]) is resumed
+    /// while waiting for compactions, and paused again afterwards.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use fjall::{CloseOptions, Config, PartitionCreateOptions};
+    /// # let folder = tempfile::tempdir()?;
+    /// let keyspace = Config::new(&folder).open()?;
+    /// let partition = keyspace.open_partition("default", PartitionCreateOptions::default())?;
+    /// partition.insert("a", "abc")?;
+    /// drop(partition);
+    ///
+    /// keyspace.close(CloseOptions::default())?;
+    /// #
+    /// # Ok::<(), fjall::Error>(())
+    /// ```
+    ///
+    /// # Errors
+    ///
+    /// Will return `Err` if an IO error occurs, or the timeout is exceeded.
+    pub fn close(self, options: CloseOptions) -> crate::Result<()> {
+        log::info!("Closing keyspace at {}", self.config.path.display());
+
+        let deadline = options.timeout.map(|timeout| Instant::now() + timeout);
+
+        // NOTE: Nothing was written, so there is nothing to persist or flush
+        if self.config.read_only {
+            return Ok(());
+        }
+
+        self.persist(PersistMode::SyncAll)?;
+
+        let was_paused = self.is_background_work_paused();
+
+        let result = self.finish_background_work(options, deadline);
+
+        // NOTE: Other handles may still use the keyspace, so restore the pause state
+        let restored = if was_paused {
+            self.background_work.pause_until(deadline)
+        } else {
+            self.background_work.resume();
+            true
+        };
+
+        result?;
+
+        if !restored {
+            return Err(crate::Error::Timeout);
+        }
+
+        log::info!("Closed keyspace at {}", self.config.path.display());
+
+        Ok(())
+    }
+
+    /// Flushes memtables and waits for compactions, as requested by the close options.
+    ///
+    /// Leaves background work paused or running, the caller needs to restore it.
+    fn finish_background_work(
+        &self,
+        options: CloseOptions,
+        deadline: Option<Instant>,
+    ) -> crate::Result<()> {
+        let timed_out = || deadline.is_some_and(|deadline| Instant::now() >= deadline);
+
+        if options.flush_memtables {
+            // NOTE: Flush on this thread, so errors can be returned
+            if !self.background_work.pause_until(deadline) {
+                return Err(crate::Error::Timeout);
+            }
+
+            self.flush_all_memtables(&timed_out)?;
+        }
+
+        if options.wait_for_compactions && self.config.compaction_workers_count > 0 {
+            self.background_work.resume();
+
+            while self.compaction_manager.len() > 0 || self.background_work.active_jobs() > 0 {
+                if timed_out() {
+                    return Err(crate::Error::Timeout);
+                }
+                std::thread::sleep(std::time::Duration::from_millis(10));
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Seals and flushes the memtables of all partitions.
+    ///
+    /// Background work needs to be paused.
+    fn flush_all_memtables(&self, timed_out: &dyn Fn() -> bool) -> crate::Result<()> {
+        let partitions = self
+            .partitions
+            .read()
+            .expect("lock is poisoned")
+            .values()
+            .cloned()
+            .collect::<Vec<_>>();
+
+        for partition in &partitions {
+            partition.rotate_memtable()?;
+        }
+
+        while !self
+            .flush_manager
+            .read()
+            .expect("lock is poisoned")
+            .is_empty()
+        {
+            if timed_out() {
+                return Err(crate::Error::Timeout);
+            }
+
+            crate::flush::worker::run(
+                &self.flush_manager,
+                &self.journal_manager,
+                &self.compaction_manager,
+                &self.write_buffer_manager,
+                &self.snapshot_tracker,
+                &self.stop_signal,
+                self.config.flush_workers_count.max(1),
+            )?;
+        }
+
+        Ok(())
+    }
+
     /// Opens a keyspace in the given directory.
     ///
+    /// The directory is locked until the keyspace is dropped,
+    /// see [`Keyspace::check_lock`].
+    ///
     /// # Errors
     ///
     /// Returns error, if an IO error occurred.
+    ///
+    /// Returns [`Error::Locked`](crate::Error::Locked) if the keyspace is already opened.
     pub fn open(config: Config) -> crate::Result<Self> {
         log::debug!(
             "block cache capacity={}MiB",
@@ -324,8 +707,11 @@ impl Keyspace {
     pub fn create_or_recover(config: Config) -> crate::Result<Self> {
         log::info!("Opening keyspace at {:?}", config.path);
 
-        if config.path.join(FJALL_MARKER).try_exists()? {
+        if config.fs.exists(&config.path.join(FJALL_MARKER))? {
             Self::recover(config)
+        } else if config.read_only {
+            log::error!("Cannot create keyspace in read-only mode");
+            Err(crate::Error::ReadOnly)
         } else {
             Self::create_new(config)
         }
@@ -336,6 +722,16 @@ impl Keyspace {
     /// Should not be called, unless in [`Keyspace::open`]
     /// and should definitely not be user-facing.
     pub(crate) fn start_background_threads(&self) -> crate::Result<()> {
+        if self.config.read_only {
+            log::debug!("Keyspace is read-only, not spawning background threads");
+
+            if let (Some(_), Some(ms)) = (&self.secondary, self.config.catch_up_ms) {
+                self.spawn_catch_up_thread(ms.into())?;
+            }
+
+            return Ok(());
+        }
+
         if self.config.flush_workers_count > 0 {
             self.spawn_flush_worker()?;
 
@@ -370,14 +766,23 @@ impl Keyspace {
     /// # Errors
     ///
     /// Will return `Err` if an IO error occurs.
+    ///
+    /// Will return [`Error::ReadOnly`](crate::Error::ReadOnly) if the keyspace is opened in read-only mode.
     pub fn delete_partition(&self, handle: PartitionHandle) -> crate::Result<()> {
+        if self.config.read_only {
+            return Err(crate::Error::ReadOnly);
+        }
+
         let partition_path = handle.path();
 
-        let file = File::create(partition_path.join(PARTITION_DELETED_MARKER))?;
+        let mut file = self.config.fs.open(
+            &partition_path.join(PARTITION_DELETED_MARKER),
+            OpenMode::Create,
+        )?;
         file.sync_all()?;
 
         // IMPORTANT: fsync folder on Unix
-        fsync_directory(partition_path)?;
+        self.config.fs.sync_directory(partition_path)?;
 
         handle
             .is_deleted
@@ -408,6 +813,13 @@ impl Keyspace {
     ///
     /// Returns error, if an IO error occurred.
     ///
+    /// Returns [`Error::ReadOnly`](crate::Error::ReadOnly) if the partition does not exist,
+    /// and the keyspace is opened in read-only mode.
+    ///
+    /// Returns [`Error::IndexNotRegistered`](crate::Error::IndexNotRegistered) if the partition
+    /// has a secondary index that was not registered since the keyspace was opened,
+    /// see [`Keyspace::create_index`].
+    ///
     /// # Panics
     ///
     /// Panics if the partition name is invalid.
@@ -415,6 +827,23 @@ impl Keyspace {
         &self,
         name: &str,
         create_options: PartitionCreateOptions,
+    ) -> crate::Result<PartitionHandle> {
+        let handle = self.open_partition_unchecked(name, create_options)?;
+
+        // NOTE: Writes to the partition would be missing from the index,
+        // reading is fine in a read-only keyspace
+        if !self.config.read_only {
+            handle.check_indexes_registered()?;
+        }
+
+        Ok(handle)
+    }
+
+    /// Creates or opens a keyspace partition, even if it has unregistered secondary indexes.
+    pub(crate) fn open_partition_unchecked(
+        &self,
+        name: &str,
+        create_options: PartitionCreateOptions,
     ) -> crate::Result<PartitionHandle> {
         assert!(is_valid_partition_name(name));
 
@@ -422,6 +851,8 @@ impl Keyspace {
 
         Ok(if let Some(partition) = partitions.get(name) {
             partition.clone()
+        } else if self.config.read_only {
+            return Err(crate::Error::ReadOnly);
         } else {
             let name: PartitionKey = name.into();
 
@@ -435,6 +866,82 @@ impl Keyspace {
         })
     }
 
+    /// Creates or opens a secondary index over the primary partition.
+    ///
+    /// The index entries are stored in the partition with the given name.
+    /// The extractor returns the secondary keys of a key-value pair of
+    /// the primary partition; return an empty list to not index the item.
+    /// If the primary partition does not exist, it is created with default options,
+    /// so open it first to use other options.
+    ///
+    /// From now on, every write to the primary partition atomically updates
+    /// the index, including removing index entries of overwritten or deleted items.
+    /// If the index has not been built yet, it is built from the
+    /// current contents of the primary partition. If building the index
+    /// is interrupted (e.g. by a crash or an error), it is built again
+    /// the next time this function is called.
+    ///
+    /// # Reopening the keyspace
+    ///
+    /// The extractor is a closure, so it cannot be persisted: **call this function
+    /// for every index every time the keyspace is opened, before opening the primary partition**.
+    /// The index partition records its primary partition, so until all its indexes
+    /// are registered again, [`Keyspace::open_partition`] returns
+    /// [`Error::IndexNotRegistered`](crate::Error::IndexNotRegistered) for the primary partition,
+    /// instead of handing out a partition whose writes would be missing from the index.
+    /// [`SecondaryIndex::primary`] returns the primary partition.
+    ///
+    /// To drop an index, delete its partition.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use fjall::{Config, PartitionCreateOptions};
+    /// #
+    /// # let folder = tempfile::tempdir()?;
+    /// # {
+    /// # let keyspace = Config::new(&folder).open()?;
+    /// let albums = keyspace.open_partition("albums", PartitionCreateOptions::default())?;
+    ///
+    /// // Value = year (u16, big endian) followed by the album name
+    /// let by_year = keyspace.create_index("albums", "albums_by_year", |_key, value| {
+    ///     value.get(0..2).map(|year| vec![year.into()]).unwrap_or_default()
+    /// })?;
+    ///
+    /// albums.insert("a", [&1_991_u16.to_be_bytes()[..], b"Loveless"].concat())?;
+    /// albums.insert("b", [&1_980_u16.to_be_bytes()[..], b"Remain in Light"].concat())?;
+    /// albums.insert("c", [&1_991_u16.to_be_bytes()[..], b"Wish"].concat())?;
+    ///
+    /// let found = by_year.lookup(1_990_u16.to_be_bytes()..2_000_u16.to_be_bytes());
+    /// assert_eq!(2, found.count());
+    /// # }
+    ///
+    /// // After reopening, register the index before opening the primary partition
+    /// # let keyspace = Config::new(&folder).open()?;
+    /// let by_year = keyspace.create_index("albums", "albums_by_year", |_key, value| {
+    ///     value.get(0..2).map(|year| vec![year.into()]).unwrap_or_default()
+    /// })?;
+    /// let albums = keyspace.open_partition("albums", PartitionCreateOptions::default())?;
+    /// #
+    /// # Ok::<(), fjall::Error>(())
+    /// ```
+    ///
+    /// # Errors
+    ///
+    /// Returns error, if an IO error occurred.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the primary or index partition name is invalid.
+    pub fn create_index<F: Fn(&[u8], &[u8]) -> Vec<UserKey> + Send + Sync + 'static>(
+        &self,
+        primary: &str,
+        name: &str,
+        extractor: F,
+    ) -> crate::Result<SecondaryIndex> {
+        SecondaryIndex::create(self, primary, name, extractor)
+    }
+
     /// Returns the amount of partitions
     #[must_use]
     pub fn partition_count(&self) -> usize {
@@ -518,8 +1025,10 @@ impl Keyspace {
         self.seqno.get()
     }
 
-    fn check_version<P: AsRef<Path>>(path: P) -> crate::Result<()> {
-        let bytes = std::fs::read(path.as_ref().join(FJALL_MARKER))?;
+    pub(crate) fn check_version<P: AsRef<Path>>(fs: &dyn Fs, path: P) -> crate::Result<()> {
+        let mut bytes = vec![];
+        fs.open(&path.as_ref().join(FJALL_MARKER), OpenMode::Read)?
+            .read_to_end(&mut bytes)?;
 
         if let Some(version) = Version::parse_file_header(&bytes) {
             if version != Version::V2 {
@@ -542,17 +1051,36 @@ impl Keyspace {
         // let recovery_mode = config.journal_recovery_mode;
 
         // Check version
-        Self::check_version(&config.path)?;
+        Self::check_version(config.fs.as_ref(), &config.path)?;
+
+        // NOTE: A read-only keyspace does not write, so it can be opened
+        // while another keyspace holds the lock
+        let lock = if config.read_only {
+            None
+        } else {
+            Some(LockFile::acquire(&config.path)?)
+        };
+
+        let secondary = config
+            .secondary_path
+            .as_deref()
+            .map(Secondary::open)
+            .transpose()?;
 
         // Reload active journal
         let journals_folder = config.path.join(JOURNALS_FOLDER);
-        let journal_recovery = Journal::recover(journals_folder)?;
+        let journal_recovery = Journal::recover(
+            journals_folder,
+            config.read_only,
+            &config.fs,
+            &config.encryption,
+        )?;
         log::debug!("journal recovery result: {journal_recovery:#?}");
 
         let active_journal = Arc::new(journal_recovery.active);
         let sealed_journals = journal_recovery.sealed;
 
-        let journal_manager = JournalManager::from_active(active_journal.path());
+        let journal_manager = JournalManager::from_active(active_journal.path(), config.fs.clone());
 
         // Construct (empty) keyspace, then fill back with partition data
         let inner = KeyspaceInner {
@@ -568,14 +1096,23 @@ impl Keyspace {
             flush_semaphore: Arc::new(Semaphore::new(0)),
             compaction_manager: CompactionManager::default(),
             stop_signal: lsm_tree::stop_signal::StopSignal::default(),
+            background_work: BackgroundWork::default(),
             active_background_threads: Arc::default(),
             write_buffer_manager: WriteBufferManager::default(),
             is_poisoned: Arc::default(),
             snapshot_tracker: SnapshotTracker::default(),
+            secondary,
+            lock,
         };
 
         let keyspace = Self(Arc::new(inner));
 
+        // NOTE: A secondary opens partitions from its mirror, instead of the primary's folder
+        if keyspace.secondary.is_some() {
+            keyspace.catch_up()?;
+            return Ok(keyspace);
+        }
+
         // Recover partitions
         recover_partitions(&keyspace)?;
 
@@ -657,6 +1194,8 @@ impl Keyspace {
             }
         }
 
+        crate::index::recover_definitions(&keyspace)?;
+
         Ok(keyspace)
     }
 
@@ -665,7 +1204,10 @@ impl Keyspace {
         let path = config.path.clone();
         log::info!("Creating keyspace at {path:?}");
 
-        std::fs::create_dir_all(&path)?;
+        let fs = config.fs.clone();
+        fs.create_dir_all(&path)?;
+
+        let lock = Some(LockFile::acquire(&path)?);
 
         let marker_path = path.join(FJALL_MARKER);
         assert!(!marker_path.try_exists()?);
@@ -673,11 +1215,12 @@ impl Keyspace {
         let journal_folder_path = path.join(JOURNALS_FOLDER);
         let partition_folder_path = path.join(PARTITIONS_FOLDER);
 
-        std::fs::create_dir_all(&journal_folder_path)?;
-        std::fs::create_dir_all(&partition_folder_path)?;
+        fs.create_dir_all(&journal_folder_path)?;
+        fs.create_dir_all(&partition_folder_path)?;
 
         let active_journal_path = journal_folder_path.join("0");
-        let journal = Journal::create_new(&active_journal_path)?;
+        let journal =
+            Journal::create_new(&active_journal_path, fs.clone(), config.encryption.clone())?;
         let journal = Arc::new(journal);
 
         let inner = KeyspaceInner {
@@ -691,26 +1234,30 @@ impl Keyspace {
             flush_manager: Arc::new(RwLock::new(FlushManager::new())),
             journal_manager: Arc::new(RwLock::new(JournalManager::from_active(
                 active_journal_path,
+                fs.clone(),
             ))),
             flush_semaphore: Arc::new(Semaphore::new(0)),
             compaction_manager: CompactionManager::default(),
             stop_signal: lsm_tree::stop_signal::StopSignal::default(),
+            background_work: BackgroundWork::default(),
             active_background_threads: Arc::default(),
             write_buffer_manager: WriteBufferManager::default(),
             is_poisoned: Arc::default(),
             snapshot_tracker: SnapshotTracker::default(),
+            secondary: None,
+            lock,
         };
 
         // NOTE: Lastly, fsync .fjall marker, which contains the version
         // -> the keyspace is fully initialized
-        let mut file = std::fs::File::create(marker_path)?;
+        let mut file = fs.open(&marker_path, OpenMode::Create)?;
         Version::V2.write_file_header(&mut file)?;
         file.sync_all()?;
 
         // IMPORTANT: fsync folders on Unix
-        fsync_directory(&journal_folder_path)?;
-        fsync_directory(&partition_folder_path)?;
-        fsync_directory(&path)?;
+        fs.sync_directory(&journal_folder_path)?;
+        fs.sync_directory(&partition_folder_path)?;
+        fs.sync_directory(&path)?;
 
         Ok(Self(Arc::new(inner)))
     }
@@ -773,11 +1320,40 @@ impl Keyspace {
         .map_err(Into::into)
     }
 
+    fn spawn_catch_up_thread(&self, ms: usize) -> crate::Result<()> {
+        // NOTE: The thread is not counted as a background thread, because it may end up
+        // dropping the keyspace itself, so it only holds a weak reference
+        let keyspace = Arc::downgrade(&self.0);
+        let stop_signal = self.stop_signal.clone();
+
+        std::thread::Builder::new()
+            .name("catch-up".into())
+            .spawn(move || {
+                while !stop_signal.is_stopped() {
+                    log::trace!("catch-up thread: sleeping {ms}ms");
+                    std::thread::sleep(std::time::Duration::from_millis(ms as u64));
+
+                    let Some(keyspace) = keyspace.upgrade().map(Self) else {
+                        break;
+                    };
+
+                    if let Err(e) = keyspace.catch_up() {
+                        log::error!("catch-up failed: {e:?}");
+                    }
+                }
+
+                log::trace!("catch-up thread: exiting because keyspace is dropping");
+            })
+            .map(|_| ())
+            .map_err(Into::into)
+    }
+
     fn spawn_compaction_worker(&self) -> crate::Result<()> {
         let compaction_manager = self.compaction_manager.clone();
         let stop_signal = self.stop_signal.clone();
         let thread_counter = self.active_background_threads.clone();
         let snapshot_tracker = self.snapshot_tracker.clone();
+        let background_work = self.background_work.clone();
 
         thread_counter.fetch_add(1, std::sync::atomic::Ordering::Relaxed);
 
@@ -788,7 +1364,15 @@ impl Keyspace {
                     log::trace!("compaction: waiting for work");
                     compaction_manager.wait_for();
 
-                    crate::compaction::worker::run(&compaction_manager, &snapshot_tracker);
+                    let Some(_job) = background_work.start_job(&stop_signal) else {
+                        break;
+                    };
+
+                    crate::compaction::worker::run(
+                        &compaction_manager,
+                        &snapshot_tracker,
+                        &stop_signal,
+                    );
                 }
 
                 log::trace!("compaction thread: exiting because keyspace is dropping");
@@ -805,16 +1389,39 @@ impl Keyspace {
     pub fn force_flush(&self) {
         let parallelism = self.config.flush_workers_count;
 
-        crate::flush::worker::run(
+        // NOTE: Errors are logged by the flush worker
+        let _ = crate::flush::worker::run(
             &self.flush_manager,
             &self.journal_manager,
             &self.compaction_manager,
             &self.write_buffer_manager,
             &self.snapshot_tracker,
+            &self.stop_signal,
             parallelism,
         );
     }
 
+    /// Only used for internal testing.
+    #[doc(hidden)]
+    #[must_use]
+    pub fn compaction_queue_len(&self) -> usize {
+        self.compaction_manager.len()
+    }
+
+    /// Only used for internal testing.
+    ///
+    /// Compacts the partition that is next in line, returning its name.
+    ///
+    /// Should NOT be called when there are compaction workers active already!!!
+    #[doc(hidden)]
+    pub fn force_compaction(&self) -> Option<PartitionKey> {
+        let partition = self.compaction_manager.pop()?;
+
+        crate::compaction::worker::compact(&partition, &self.snapshot_tracker, &self.stop_signal);
+
+        Some(partition.name.clone())
+    }
+
     fn spawn_flush_worker(&self) -> crate::Result<()> {
         let flush_manager = self.flush_manager.clone();
         let journal_manager = self.journal_manager.clone();
@@ -822,6 +1429,7 @@ impl Keyspace {
         let flush_semaphore = self.flush_semaphore.clone();
         let write_buffer_manager = self.write_buffer_manager.clone();
         let snapshot_tracker = self.snapshot_tracker.clone();
+        let background_work = self.background_work.clone();
 
         let thread_counter = self.active_background_threads.clone();
         let stop_signal = self.stop_signal.clone();
@@ -837,12 +1445,18 @@ impl Keyspace {
                     log::trace!("flush worker: acquiring flush semaphore");
                     flush_semaphore.acquire();
 
-                    crate::flush::worker::run(
+                    let Some(_job) = background_work.start_job(&stop_signal) else {
+                        break;
+                    };
+
+                    // NOTE: Errors are logged by the flush worker
+                    let _ = crate::flush::worker::run(
                         &flush_manager,
                         &journal_manager,
                         &compaction_manager,
                         &write_buffer_manager,
                         &snapshot_tracker,
+                        &stop_signal,
                         parallelism,
                     );
                 }
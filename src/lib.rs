@@ -81,64 +81,133 @@
 #![allow(clippy::missing_const_for_fn)]
 #![warn(clippy::multiple_crate_versions)]
 
+#[cfg(feature = "async")]
+mod asynchronous;
+
+mod background_work;
 mod batch;
+mod close;
+pub mod codec;
 
 /// Contains compaction strategies
 pub mod compaction;
 
 mod config;
+mod encryption;
 
 #[cfg(feature = "__internal_whitebox")]
 #[doc(hidden)]
 pub mod drop;
 
 mod error;
+mod escape;
 mod file;
 mod flush;
+mod fs;
 mod gc;
+mod index;
 mod iter;
 mod journal;
+pub mod keys;
 mod keyspace;
+mod lock;
 mod monitor;
 mod partition;
 mod path;
+mod rate_limiter;
 mod recovery;
+mod repair;
+mod replication;
+mod secondary;
 mod snapshot_nonce;
 mod snapshot_tracker;
 mod tracked_snapshot;
-
-#[cfg(any(feature = "single_writer_tx", feature = "ssi_tx"))]
+mod tree_files;
+mod typed;
+mod verify;
+
+#[cfg(any(
+    feature = "single_writer_tx",
+    feature = "ssi_tx",
+    feature = "pessimistic_tx"
+))]
 mod tx;
 
 mod version;
 mod write_buffer_manager;
+mod write_stall;
 
 pub(crate) type HashMap<K, V> = std::collections::HashMap<K, V, xxhash_rust::xxh3::Xxh3Builder>;
 pub(crate) type HashSet<K> = std::collections::HashSet<K, xxhash_rust::xxh3::Xxh3Builder>;
 
 pub use {
-    batch::Batch,
+    batch::{
+        precondition::{FailedPrecondition, Precondition},
+        Batch,
+    },
+    close::CloseOptions,
     config::Config,
-    error::{Error, Result},
+    error::{EncryptionError, Error, LockError, Result},
+    fs::{FaultyFs, Fs, FsDirEntry, FsFile, OpenMode, StdFs},
     gc::GarbageCollection,
-    journal::{error::RecoveryError, writer::PersistMode},
+    index::SecondaryIndex,
+    journal::{
+        dump::{JournalDump, JournalDumpBatch, JournalDumpItem, JournalStop, JournalStopReason},
+        error::RecoveryError,
+        info::JournalInfo,
+        writer::PersistMode,
+    },
     keyspace::Keyspace,
     partition::{
         options::CreateOptions as PartitionCreateOptions, options::KvSeparationOptions,
         PartitionHandle,
     },
+    rate_limiter::{IoActivity, RateLimit, RateLimiter},
+    repair::{QuarantineReason, RepairAction, RepairOptions, RepairReport},
+    replication::{
+        applier::ReplicaApplier,
+        source::ReplicationSource,
+        transport::{channel, ChannelReceiver, ChannelSender, StreamReceiver, StreamSender},
+        ReplicatedItem, ReplicationError, ReplicationMessage, ReplicationReceiver,
+        ReplicationSender,
+    },
     tracked_snapshot::TrackedSnapshot as Snapshot,
+    typed::{DecodeTarget, RawPartition, TypedDecodeError, TypedPartition},
+    verify::{VerifyIssue, VerifyOptions, VerifyReport},
     version::Version,
+    write_stall::{
+        DefaultWriteStallPolicy, WriteAdmission, WriteLoad, WriteStallAction, WriteStallPolicy,
+        WriteStallReason,
+    },
 };
 
-#[cfg(any(feature = "single_writer_tx", feature = "ssi_tx"))]
+#[cfg(any(
+    feature = "single_writer_tx",
+    feature = "ssi_tx",
+    feature = "pessimistic_tx"
+))]
 pub use tx::{
     keyspace::{TransactionalKeyspace, TxKeyspace},
     partition::TransactionalPartitionHandle,
     read_tx::ReadTransaction,
+    savepoint::Savepoint,
     write_tx::WriteTransaction,
 };
 
+#[cfg(feature = "encryption")]
+pub use encryption::{EncryptionKey, KeyId, KeyProvider};
+
+#[cfg(feature = "async")]
+pub use asynchronous::{
+    keyspace::AsyncKeyspace, partition::AsyncPartition, stream::KvStream, Task,
+};
+
+#[cfg(feature = "ssi_tx")]
+pub use tx::{
+    retry::{RetryPolicy, TransactionError},
+    write::ssi::{Conflict, ConflictingRead},
+};
+
 /// Alias for [`Batch`]
 pub type WriteBatch = Batch;
 
@@ -146,15 +215,27 @@ pub type WriteBatch = Batch;
 pub type Partition = PartitionHandle;
 
 /// Alias for [`TransactionalPartitionHandle`]
-#[cfg(any(feature = "single_writer_tx", feature = "ssi_tx"))]
+#[cfg(any(
+    feature = "single_writer_tx",
+    feature = "ssi_tx",
+    feature = "pessimistic_tx"
+))]
 pub type TxPartition = TransactionalPartitionHandle;
 
 /// Alias for [`TransactionalPartitionHandle`]
-#[cfg(any(feature = "single_writer_tx", feature = "ssi_tx"))]
+#[cfg(any(
+    feature = "single_writer_tx",
+    feature = "ssi_tx",
+    feature = "pessimistic_tx"
+))]
 pub type TxPartitionHandle = TransactionalPartitionHandle;
 
 /// Alias for [`TransactionalPartitionHandle`]
-#[cfg(any(feature = "single_writer_tx", feature = "ssi_tx"))]
+#[cfg(any(
+    feature = "single_writer_tx",
+    feature = "ssi_tx",
+    feature = "pessimistic_tx"
+))]
 pub type TransactionalPartition = TransactionalPartitionHandle;
 
 /// A snapshot moment
@@ -170,4 +251,5 @@ pub use lsm_tree::AbstractTree;
 
 pub use lsm_tree::{
     AnyTree, BlobCache, BlockCache, CompressionType, KvPair, Slice, TreeType, UserKey, UserValue,
+    ValueType,
 };
@@ -0,0 +1,131 @@
+// Copyright (c) 2024-present, fjall-rs
+// This source code is licensed under both the Apache 2.0 and MIT License
+// (found in the LICENSE-* files in the repository)
+
+use crate::file::LOCK_FILE;
+use std::{
+    fs::{File, OpenOptions},
+    io::Write,
+    path::Path,
+};
+
+/// Exclusive lock of a keyspace folder
+///
+/// The lock is held by an open handle to the `lock` file, so it is released
+/// when the handle is dropped, even if the process crashes. The file contains
+/// the PID of the process holding the lock.
+#[derive(Debug)]
+pub struct LockFile {
+    #[allow(dead_code)]
+    file: File,
+}
+
+/// Reads the PID of the lock holder from the lock file.
+fn read_pid(path: &Path) -> Option<u32> {
+    std::fs::read_to_string(path).ok()?.trim().parse().ok()
+}
+
+#[cfg(all(unix, not(target_os = "solaris")))]
+fn try_lock(path: &Path, write: bool) -> crate::Result<Option<File>> {
+    use rustix::fs::{flock, FlockOperation};
+
+    let file = if write {
+        OpenOptions::new()
+            .read(true)
+            .write(true)
+            .create(true)
+            .truncate(false)
+            .open(path)?
+    } else {
+        File::open(path)?
+    };
+
+    let operation = if write {
+        FlockOperation::NonBlockingLockExclusive
+    } else {
+        FlockOperation::NonBlockingLockShared
+    };
+
+    match flock(&file, operation) {
+        Ok(()) => Ok(Some(file)),
+        Err(rustix::io::Errno::WOULDBLOCK) => Ok(None),
+        Err(e) => Err(std::io::Error::from(e).into()),
+    }
+}
+
+#[cfg(windows)]
+fn try_lock(path: &Path, write: bool) -> crate::Result<Option<File>> {
+    use std::os::windows::fs::OpenOptionsExt;
+
+    const FILE_SHARE_READ: u32 = 0x1;
+    const ERROR_SHARING_VIOLATION: i32 = 32;
+
+    // NOTE: Other processes can read the PID, but not open the file for writing
+    // as long as the handle is open
+    let result = OpenOptions::new()
+        .read(true)
+        .write(true)
+        .create(write)
+        .truncate(false)
+        .share_mode(FILE_SHARE_READ)
+        .open(path);
+
+    match result {
+        Ok(file) => Ok(Some(file)),
+        Err(e) if e.raw_os_error() == Some(ERROR_SHARING_VIOLATION) => Ok(None),
+        Err(e) => Err(e.into()),
+    }
+}
+
+#[cfg(not(any(all(unix, not(target_os = "solaris")), windows)))]
+fn try_lock(path: &Path, write: bool) -> crate::Result<Option<File>> {
+    // NOTE: No file locking available, so the lock is never held
+    let file = OpenOptions::new()
+        .read(true)
+        .write(write)
+        .create(write)
+        .truncate(false)
+        .open(path)?;
+
+    Ok(Some(file))
+}
+
+impl LockFile {
+    /// Acquires the lock of a keyspace folder.
+    ///
+    /// Returns [`crate::Error::Locked`] if the lock is held by another keyspace.
+    pub fn acquire(folder: &Path) -> crate::Result<Self> {
+        let path = folder.join(LOCK_FILE);
+
+        let Some(mut file) = try_lock(&path, true)? else {
+            let pid = read_pid(&path);
+            log::error!("Keyspace at {folder:?} is locked by process {pid:?}");
+            return Err(crate::Error::Locked { pid });
+        };
+
+        file.set_len(0)?;
+        write!(file, "{}", std::process::id())?;
+        file.sync_all()?;
+
+        log::debug!("Acquired lock of keyspace at {folder:?}");
+
+        Ok(Self { file })
+    }
+
+    /// Checks if the lock of a keyspace folder is held, without acquiring it.
+    pub fn check(folder: &Path) -> crate::Result<()> {
+        let path = folder.join(LOCK_FILE);
+
+        match try_lock(&path, false) {
+            Ok(Some(_)) => Ok(()),
+            Ok(None) => Err(crate::Error::Locked {
+                pid: read_pid(&path),
+            }),
+
+            // NOTE: The keyspace was never opened for writing
+            Err(crate::Error::Io(e)) if e.kind() == std::io::ErrorKind::NotFound => Ok(()),
+
+            Err(e) => Err(e),
+        }
+    }
+}
@@ -4,7 +4,6 @@
 
 pub mod name;
 pub mod options;
-mod write_delay;
 
 use crate::{
     batch::PartitionKey,
@@ -12,7 +11,9 @@ use crate::{
     config::Config as KeyspaceConfig,
     file::{LSM_MANIFEST_FILE, PARTITIONS_FOLDER, PARTITION_CONFIG_FILE, PARTITION_DELETED_MARKER},
     flush::manager::{FlushManager, Task as FlushTask},
+    fs::OpenMode,
     gc::GarbageCollection,
+    index::IndexDefinition,
     journal::{
         manager::{EvictionWatermark, JournalManager},
         Journal,
@@ -21,7 +22,10 @@ use crate::{
     snapshot_nonce::SnapshotNonce,
     snapshot_tracker::SnapshotTracker,
     write_buffer_manager::WriteBufferManager,
-    Error, Keyspace,
+    write_stall::{
+        WriteAdmission, WriteLoad, WriteStallAction, WriteStallPolicy, WriteStallReason,
+    },
+    Batch, Error, Keyspace,
 };
 use lsm_tree::{
     gc::Report as GcReport, AbstractTree, AnyTree, KvPair, SequenceNumberCounter, UserKey,
@@ -29,14 +33,14 @@ use lsm_tree::{
 };
 use options::CreateOptions;
 use std::{
-    fs::File,
     ops::RangeBounds,
     path::Path,
-    sync::{atomic::AtomicBool, Arc, RwLock},
-    time::Duration,
+    sync::{
+        atomic::{AtomicBool, AtomicU32},
+        Arc, RwLock,
+    },
 };
 use std_semaphore::Semaphore;
-use write_delay::get_write_delay;
 
 #[allow(clippy::module_name_repetitions)]
 pub struct PartitionHandleInner {
@@ -92,6 +96,19 @@ pub struct PartitionHandleInner {
 
     /// Snapshot tracker
     pub(crate) snapshot_tracker: SnapshotTracker,
+
+    /// Secondary indexes that are maintained on every write
+    pub(crate) indexes: RwLock<Vec<Arc<IndexDefinition>>>,
+
+    /// Index partitions of this partition that were not registered
+    /// since the keyspace was opened, see `index::recover_definitions`
+    pub(crate) unregistered_indexes: RwLock<Vec<PartitionHandle>>,
+
+    /// Write stall policy, overriding the keyspace's policy
+    pub(crate) write_stall_policy: RwLock<Option<Arc<dyn WriteStallPolicy>>>,
+
+    /// Weight of the partition when scheduling compactions
+    pub(crate) compaction_priority: AtomicU32,
 }
 
 impl Drop for PartitionHandleInner {
@@ -115,10 +132,10 @@ impl Drop for PartitionHandleInner {
             match manifest_file.try_exists() {
                 Ok(exists) => {
                     if exists {
-                        if let Err(e) = std::fs::remove_file(manifest_file) {
+                        if let Err(e) = self.keyspace_config.fs.remove_file(&manifest_file) {
                             log::error!("Failed to cleanup partition manifest at {path:?}: {e}");
                         } else {
-                            if let Err(e) = std::fs::remove_dir_all(path) {
+                            if let Err(e) = self.keyspace_config.fs.remove_dir_all(path) {
                                 log::error!(
                                     "Failed to cleanup deleted partition's folder at {path:?}: {e}"
                                 );
@@ -180,14 +197,17 @@ impl GarbageCollection for PartitionHandle {
     }
 
     fn gc_with_space_amp_target(&self, factor: f32) -> crate::Result<u64> {
+        self.check_writable()?;
         crate::gc::GarbageCollector::with_space_amp_target(self, factor)
     }
 
     fn gc_with_staleness_threshold(&self, threshold: f32) -> crate::Result<u64> {
+        self.check_writable()?;
         crate::gc::GarbageCollector::with_staleness_threshold(self, threshold)
     }
 
     fn gc_drop_stale_segments(&self) -> crate::Result<u64> {
+        self.check_writable()?;
         crate::gc::GarbageCollector::drop_stale_segments(self)
     }
 }
@@ -214,6 +234,10 @@ impl PartitionHandle {
             is_deleted: AtomicBool::default(),
             is_poisoned: keyspace.is_poisoned.clone(),
             snapshot_tracker: keyspace.snapshot_tracker.clone(),
+            indexes: RwLock::default(),
+            unregistered_indexes: RwLock::default(),
+            write_stall_policy: RwLock::default(),
+            compaction_priority: AtomicU32::new(1),
             config,
         }))
     }
@@ -235,10 +259,13 @@ impl PartitionHandle {
             return Err(Error::PartitionDeleted);
         }
 
-        std::fs::create_dir_all(&base_folder)?;
+        keyspace.config.fs.create_dir_all(&base_folder)?;
 
         // Write config
-        let mut file = File::create(base_folder.join(PARTITION_CONFIG_FILE))?;
+        let mut file = keyspace
+            .config
+            .fs
+            .open(&base_folder.join(PARTITION_CONFIG_FILE), OpenMode::Create)?;
         config.encode_into(&mut file)?;
         file.sync_all()?;
 
@@ -284,9 +311,46 @@ impl PartitionHandle {
             is_deleted: AtomicBool::default(),
             is_poisoned: keyspace.is_poisoned.clone(),
             snapshot_tracker: keyspace.snapshot_tracker.clone(),
+            indexes: RwLock::default(),
+            unregistered_indexes: RwLock::default(),
+            write_stall_policy: RwLock::default(),
+            compaction_priority: AtomicU32::new(1),
         })))
     }
 
+    /// Rejects opening the partition if an index of this partition is not registered,
+    /// because the index entries of its writes would not be written.
+    pub(crate) fn check_indexes_registered(&self) -> crate::Result<()> {
+        let unregistered = self.unregistered_indexes.read().expect("lock is poisoned");
+
+        // NOTE: Deleting the index partition drops the index
+        unregistered
+            .iter()
+            .find(|index| !index.is_deleted.load(std::sync::atomic::Ordering::Relaxed))
+            .map_or(Ok(()), |index| {
+                Err(crate::Error::IndexNotRegistered(index.name.clone()))
+            })
+    }
+
+    /// Returns a batch for writing to this partition, if it has secondary indexes.
+    ///
+    /// Writes to indexed partitions need to go through batches,
+    /// so the index entries are written atomically with the item.
+    fn index_batch(&self) -> Option<Batch> {
+        let keyspace = self
+            .indexes
+            .read()
+            .expect("lock is poisoned")
+            .first()?
+            .keyspace
+            .upgrade()?;
+
+        let durability =
+            (!self.config.manual_journal_persist).then_some(crate::PersistMode::Buffer);
+
+        Some(Keyspace(keyspace).batch().durability(durability))
+    }
+
     /// Returns the underlying LSM-tree's path.
     #[must_use]
     pub fn path(&self) -> &Path {
@@ -633,6 +697,8 @@ impl PartitionHandle {
     /// Returns `true` if the memtable was indeed rotated.
     #[doc(hidden)]
     pub fn rotate_memtable(&self) -> crate::Result<bool> {
+        self.check_writable()?;
+
         log::debug!("Rotating memtable {:?}", self.name);
 
         log::trace!("partition: acquiring journal lock");
@@ -688,99 +754,143 @@ impl PartitionHandle {
         Ok(true)
     }
 
-    fn check_journal_size(&self) {
-        loop {
-            let bytes = self
-                .journal_manager
-                .read()
-                .expect("lock is poisoned")
-                .disk_space_used();
-
-            if bytes <= self.keyspace_config.max_journaling_size_in_bytes {
-                if bytes as f64 > self.keyspace_config.max_journaling_size_in_bytes as f64 * 0.9 {
-                    log::info!(
-                        "partition: write stall because 90% journal threshold has been reached"
-                    );
-                    std::thread::sleep(std::time::Duration::from_millis(500));
-                }
+    /// Rejects writes if the keyspace is opened in read-only mode.
+    fn check_writable(&self) -> crate::Result<()> {
+        if self.keyspace_config.read_only {
+            return Err(crate::Error::ReadOnly);
+        }
 
-                break;
-            }
+        Ok(())
+    }
 
-            log::info!("partition: write halt because of too many journals");
-            std::thread::sleep(std::time::Duration::from_millis(100)); // TODO: maybe exponential backoff
-        }
+    fn waits_on_write_stall(&self) -> bool {
+        self.keyspace_config.write_admission == WriteAdmission::Wait
     }
 
-    fn check_write_stall(&self) {
-        let seg_count = self.tree.first_level_segment_count();
+    /// Returns the write stall policy of this partition.
+    fn write_stall_policy(&self) -> Arc<dyn WriteStallPolicy> {
+        self.write_stall_policy
+            .read()
+            .expect("lock is poisoned")
+            .clone()
+            .unwrap_or_else(|| self.keyspace_config.write_stall_policy.clone())
+    }
 
-        if seg_count >= 20 {
-            if self.tree.is_first_level_disjoint() {
-                // NOTE: If the first level is disjoint, we are probably dealing with a monotonic series
-                // so nothing to do
-                return;
-            }
+    /// Sets the write stall policy of this partition,
+    /// overriding the keyspace's policy (see [`Config::write_stall_policy`](crate::Config::write_stall_policy)).
+    ///
+    /// Set to `None` to use the keyspace's policy again.
+    ///
+    /// The policy is not persisted, so it needs to be set every time the partition is opened.
+    pub fn set_write_stall_policy(&self, policy: Option<Arc<dyn WriteStallPolicy>>) {
+        *self.write_stall_policy.write().expect("lock is poisoned") = policy;
+    }
 
-            let sleep_us = get_write_delay(seg_count);
+    /// Returns the compaction priority of this partition.
+    #[must_use]
+    pub fn compaction_priority(&self) -> u32 {
+        self.compaction_priority
+            .load(std::sync::atomic::Ordering::Relaxed)
+    }
 
-            if sleep_us > 0 {
-                log::info!("Stalling writes by {sleep_us}µs, many segments in L0...");
-                self.compaction_manager.notify(self.clone());
-                std::thread::sleep(Duration::from_micros(sleep_us));
-            }
-        }
+    /// Sets the compaction priority of this partition.
+    ///
+    /// When multiple partitions are waiting for compaction, partitions
+    /// with a higher priority are compacted first, so bulk-loaded partitions
+    /// do not delay compactions of latency-critical ones.
+    /// Partitions that have not been compacted for a long time
+    /// are eventually compacted regardless of their priority.
+    ///
+    /// The priority is not persisted, so it needs to be set every time the partition is opened.
+    /// A priority of 0 is treated as 1.
+    ///
+    /// Default = 1
+    pub fn set_compaction_priority(&self, priority: u32) {
+        self.compaction_priority
+            .store(priority.max(1), std::sync::atomic::Ordering::Relaxed);
     }
 
-    fn check_write_halt(&self) {
-        while self.tree.first_level_segment_count() >= 32 {
-            if self.tree.is_first_level_disjoint() {
-                // NOTE: If the first level is disjoint, we are probably dealing with a monotonic series
-                // so nothing to do
-                return;
-            }
+    /// Asks the write stall policy how a write should proceed.
+    fn check_write_stall(&self, load: &PartitionWriteLoad<'_>) -> WriteStallAction {
+        let action = self.write_stall_policy().check(load);
 
-            log::info!("Halting writes until L0 is cleared up...");
+        if let WriteStallAction::Delay(WriteStallReason::L0Segments, _)
+        | WriteStallAction::Halt(WriteStallReason::L0Segments) = action
+        {
             self.compaction_manager.notify(self.clone());
-            std::thread::sleep(Duration::from_millis(10));
         }
+
+        action
     }
 
-    pub(crate) fn check_memtable_overflow(&self, size: u32) -> crate::Result<()> {
-        if size > self.config.max_memtable_size {
-            self.rotate_memtable()?;
-            self.check_journal_size();
-            self.check_write_halt();
-        }
+    /// Returns `true` if the memtables currently exceed the maximum write buffer size.
+    fn write_buffer_exceeded(&self) -> bool {
+        self.write_buffer_manager.get() > self.keyspace_config.max_write_buffer_size_in_bytes
+    }
 
-        self.check_write_stall();
+    /// Sleeps while the write stall policy delays or halts writes.
+    fn wait_for_write_stall(&self, memtable_rotated: bool) {
+        // NOTE: Whether the write buffer was exceeded is only checked once per write,
+        // so the write is still delayed after a halt, once the write buffer has been flushed
+        let load = PartitionWriteLoad {
+            partition: self,
+            memtable_rotated,
+            write_buffer_exceeded: self.write_buffer_exceeded(),
+            admission: false,
+        };
 
-        Ok(())
+        loop {
+            match self.check_write_stall(&load) {
+                WriteStallAction::Proceed => return,
+                WriteStallAction::Delay(reason, delay) => {
+                    log::info!("partition: stalling writes by {delay:?}, {reason}");
+                    std::thread::sleep(delay);
+                    return;
+                }
+                WriteStallAction::Halt(reason) => {
+                    log::info!("partition: halting writes, {reason}");
+                    std::thread::sleep(reason.halt_interval());
+                }
+            }
+        }
     }
 
-    pub(crate) fn check_write_buffer_size(&self, initial_size: u64) {
-        let limit = self.keyspace_config.max_write_buffer_size_in_bytes;
+    /// Rejects the write if writes are stalled and the keyspace fails fast.
+    pub(crate) fn check_write_admission(&self) -> crate::Result<()> {
+        if self.waits_on_write_stall() {
+            return Ok(());
+        }
 
-        if initial_size > limit {
-            let p90_limit = (limit as f64) * 0.9;
+        let load = PartitionWriteLoad {
+            partition: self,
+            memtable_rotated: false,
+            write_buffer_exceeded: self.write_buffer_exceeded(),
+            admission: true,
+        };
 
-            loop {
-                let bytes = self.write_buffer_manager.get();
+        let (reason, retry_after) = match self.check_write_stall(&load) {
+            WriteStallAction::Proceed => return Ok(()),
+            WriteStallAction::Delay(reason, delay) => (reason, delay),
+            WriteStallAction::Halt(reason) => (reason, reason.halt_interval()),
+        };
 
-                if bytes < limit {
-                    if bytes as f64 > p90_limit {
-                        log::info!(
-                            "partition: write stall because 90% write buffer threshold has been reached"
-                        );
-                        std::thread::sleep(std::time::Duration::from_millis(100));
-                    }
-                    break;
-                }
+        log::debug!("partition: rejecting write to {:?}, {reason}", self.name);
 
-                log::info!("partition: write halt because of write buffer saturation");
-                std::thread::sleep(std::time::Duration::from_millis(10));
-            }
+        Err(Error::WriteStalled {
+            reason,
+            retry_after,
+        })
+    }
+
+    /// Rotates the memtable if it is full, and applies the write stall policy.
+    pub(crate) fn check_memtable_overflow(&self, size: u32) -> crate::Result<()> {
+        let memtable_rotated = size > self.config.max_memtable_size && self.rotate_memtable()?;
+
+        if self.waits_on_write_stall() {
+            self.wait_for_write_stall(memtable_rotated);
         }
+
+        Ok(())
     }
 
     #[doc(hidden)]
@@ -789,6 +899,31 @@ impl PartitionHandle {
         self.tree.segment_count()
     }
 
+    /// Compacts all segments of the partition into the last level,
+    /// dropping overwritten and deleted items that are not visible to any snapshot anymore.
+    ///
+    /// This can take a long time for big partitions, and blocks the calling thread.
+    ///
+    /// # Errors
+    ///
+    /// Will return `Err` if an IO error occurs.
+    ///
+    /// Will return [`Error::ReadOnly`] if the keyspace is opened in read-only mode.
+    pub fn major_compact(&self) -> crate::Result<()> {
+        /// Target size of the compacted segments
+        const TARGET_SIZE: u64 = /* 64 MiB */ 64 * 1_024 * 1_024;
+
+        self.check_writable()?;
+
+        let seqno_threshold = self.snapshot_tracker.get_seqno_safe_to_gc();
+
+        match &self.tree {
+            AnyTree::Standard(tree) => tree.major_compact(TARGET_SIZE, seqno_threshold),
+            AnyTree::Blob(tree) => tree.index.major_compact(TARGET_SIZE, seqno_threshold),
+        }
+        .map_err(Into::into)
+    }
+
     /// Opens a snapshot of this partition.
     #[must_use]
     pub fn snapshot(&self) -> crate::Snapshot {
@@ -829,36 +964,53 @@ impl PartitionHandle {
     /// # Errors
     ///
     /// Will return `Err` if an IO error occurs.
+    ///
+    /// Will return [`Error::WriteStalled`] if writes are stalled,
+    /// and the keyspace uses [`WriteAdmission::FailFast`].
+    ///
+    /// Will return [`Error::ReadOnly`] if the keyspace is opened in read-only mode.
     pub fn insert<K: AsRef<[u8]>, V: AsRef<[u8]>>(&self, key: K, value: V) -> crate::Result<()> {
         if self.is_deleted.load(std::sync::atomic::Ordering::Relaxed) {
             return Err(crate::Error::PartitionDeleted);
         }
 
+        self.check_writable()?;
+
         if self.is_poisoned.load(std::sync::atomic::Ordering::Relaxed) {
             return Err(crate::Error::Poisoned);
         }
 
+        self.check_write_admission()?;
+
         let key = key.as_ref();
         let value = value.as_ref();
-        let seqno = self.seqno.next();
-
         let mut journal_writer = self.journal.get_writer();
 
+        if let Some(mut batch) = self.index_batch() {
+            drop(journal_writer);
+            batch.insert(self, key, value);
+            return batch.commit();
+        }
+
+        // IMPORTANT: Allocate the seqno while holding the journal writer,
+        // so the journal is ordered by seqno, see `ReplicationSource`
+        let seqno = self.seqno.next();
+
         journal_writer.write_raw(&self.name, key, value, lsm_tree::ValueType::Value, seqno)?;
 
         if !self.config.manual_journal_persist {
             journal_writer.flush(crate::PersistMode::Buffer)?;
         }
 
-        drop(journal_writer);
-
+        // IMPORTANT: Insert into the memtable before releasing the journal writer,
+        // so a batch cannot miss this write when checking its preconditions
         let (item_size, memtable_size) = self.tree.insert(key, value, seqno);
 
-        let write_buffer_size = self.write_buffer_manager.allocate(u64::from(item_size));
+        drop(journal_writer);
 
-        self.check_memtable_overflow(memtable_size)?;
+        self.write_buffer_manager.allocate(u64::from(item_size));
 
-        self.check_write_buffer_size(write_buffer_size);
+        self.check_memtable_overflow(memtable_size)?;
 
         Ok(())
     }
@@ -892,35 +1044,113 @@ impl PartitionHandle {
     /// # Errors
     ///
     /// Will return `Err` if an IO error occurs.
+    ///
+    /// Will return [`Error::WriteStalled`] if writes are stalled,
+    /// and the keyspace uses [`WriteAdmission::FailFast`].
+    ///
+    /// Will return [`Error::ReadOnly`] if the keyspace is opened in read-only mode.
     pub fn remove<K: AsRef<[u8]>>(&self, key: K) -> crate::Result<()> {
         if self.is_deleted.load(std::sync::atomic::Ordering::Relaxed) {
             return Err(crate::Error::PartitionDeleted);
         }
 
+        self.check_writable()?;
+
         if self.is_poisoned.load(std::sync::atomic::Ordering::Relaxed) {
             return Err(crate::Error::Poisoned);
         }
 
-        let key = key.as_ref();
-        let seqno = self.seqno.next();
+        self.check_write_admission()?;
 
+        let key = key.as_ref();
         let mut journal_writer = self.journal.get_writer();
 
+        if let Some(mut batch) = self.index_batch() {
+            drop(journal_writer);
+            batch.remove(self, key);
+            return batch.commit();
+        }
+
+        // IMPORTANT: Allocate the seqno while holding the journal writer,
+        // so the journal is ordered by seqno, see `ReplicationSource`
+        let seqno = self.seqno.next();
+
         journal_writer.write_raw(&self.name, key, &[], lsm_tree::ValueType::Tombstone, seqno)?;
 
         if !self.config.manual_journal_persist {
             journal_writer.flush(crate::PersistMode::Buffer)?;
         }
 
-        drop(journal_writer);
-
+        // IMPORTANT: Insert into the memtable before releasing the journal writer,
+        // so a batch cannot miss this write when checking its preconditions
         let (item_size, memtable_size) = self.tree.remove(key, seqno);
 
-        let write_buffer_size = self.write_buffer_manager.allocate(u64::from(item_size));
+        drop(journal_writer);
+
+        self.write_buffer_manager.allocate(u64::from(item_size));
 
         self.check_memtable_overflow(memtable_size)?;
-        self.check_write_buffer_size(write_buffer_size);
 
         Ok(())
     }
 }
+
+/// Load of a partition, computed when a write stall policy asks for it
+struct PartitionWriteLoad<'a> {
+    partition: &'a PartitionHandle,
+    memtable_rotated: bool,
+    write_buffer_exceeded: bool,
+    admission: bool,
+}
+
+impl WriteLoad for PartitionWriteLoad<'_> {
+    fn l0_segments(&self) -> usize {
+        self.partition.tree.first_level_segment_count()
+    }
+
+    fn l0_disjoint(&self) -> bool {
+        self.partition.tree.is_first_level_disjoint()
+    }
+
+    fn pending_flush_bytes(&self) -> u64 {
+        self.partition
+            .flush_manager
+            .read()
+            .expect("lock is poisoned")
+            .queued_size()
+    }
+
+    fn journal_bytes(&self) -> u64 {
+        self.partition
+            .journal_manager
+            .read()
+            .expect("lock is poisoned")
+            .disk_space_used()
+    }
+
+    fn max_journal_bytes(&self) -> u64 {
+        self.partition.keyspace_config.max_journaling_size_in_bytes
+    }
+
+    fn write_buffer_bytes(&self) -> u64 {
+        self.partition.write_buffer_manager.get()
+    }
+
+    fn max_write_buffer_bytes(&self) -> u64 {
+        self.partition
+            .keyspace_config
+            .max_write_buffer_size_in_bytes
+    }
+
+    fn memtable_rotated(&self) -> bool {
+        self.memtable_rotated
+    }
+
+    fn write_buffer_exceeded(&self) -> bool {
+        self.write_buffer_exceeded
+    }
+
+    fn admission(&self) -> bool {
+        self.admission
+    }
+}
@@ -11,7 +11,8 @@ use lsm_tree::{CompressionType, TreeType};
 #[allow(clippy::module_name_repetitions)]
 pub struct KvSeparationOptions {
     /// Compression to use for blobs.
-    pub(crate) compression: CompressionType,
+    #[doc(hidden)]
+    pub compression: CompressionType,
 
     /// Blob file (value log segment) target size in bytes
     #[doc(hidden)]
@@ -101,7 +102,8 @@ impl lsm_tree::coding::Decode for KvSeparationOptions {
 #[derive(Clone, Debug)]
 pub struct CreateOptions {
     /// Maximum size of this partition's memtable - can be changed during runtime
-    pub(crate) max_memtable_size: u32,
+    #[doc(hidden)]
+    pub max_memtable_size: u32,
 
     /// Block size of data blocks.
     #[doc(hidden)]
@@ -112,25 +114,30 @@ pub struct CreateOptions {
     pub index_block_size: u32,
 
     /// Amount of levels of the LSM tree (depth of tree).
-    pub(crate) level_count: u8,
+    #[doc(hidden)]
+    pub level_count: u8,
 
     /// Bits per key for levels that are not L0, L1, L2
     // NOTE: bloom_bits_per_key is not conditionally compiled,
     // because that would change the file format
-    pub(crate) bloom_bits_per_key: i8,
+    #[doc(hidden)]
+    pub bloom_bits_per_key: i8,
 
     /// Tree type, see [`TreeType`].
     pub(crate) tree_type: TreeType,
 
     /// Compression to use.
-    pub(crate) compression: CompressionType,
+    #[doc(hidden)]
+    pub compression: CompressionType,
 
-    pub(crate) manual_journal_persist: bool,
+    #[doc(hidden)]
+    pub manual_journal_persist: bool,
 
     #[doc(hidden)]
     pub compaction_strategy: CompactionStrategy,
 
-    pub(crate) kv_separation: Option<KvSeparationOptions>,
+    #[doc(hidden)]
+    pub kv_separation: Option<KvSeparationOptions>,
 }
 
 impl lsm_tree::coding::Encode for CreateOptions {
@@ -0,0 +1,232 @@
+// Copyright (c) 2024-present, fjall-rs
+// This source code is licensed under both the Apache 2.0 and MIT License
+// (found in the LICENSE-* files in the repository)
+
+use lsm_tree::stop_signal::StopSignal;
+use std::{
+    sync::{Arc, Mutex, MutexGuard, PoisonError},
+    time::{Duration, Instant},
+};
+
+/// Max time a background worker sleeps before checking for new limits or shutdown
+const MAX_SLEEP: Duration = Duration::from_millis(100);
+
+/// Background activity that is subject to a [`RateLimiter`]
+#[derive(Copy, Clone, Debug, Eq, PartialEq, Hash)]
+pub enum IoActivity {
+    /// Flushing memtables to disk segments
+    Flush,
+
+    /// Compacting disk segments
+    Compaction,
+
+    /// Rewriting blobs of KV-separated partitions, see [`GarbageCollection`](crate::GarbageCollection)
+    GarbageCollection,
+}
+
+/// Rate limit of an [`IoActivity`]
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+pub struct RateLimit {
+    /// Sustained amount of bytes written per second
+    pub bytes_per_second: u64,
+
+    /// Amount of bytes that may be written at once after being idle
+    pub burst: u64,
+}
+
+impl RateLimit {
+    /// Creates a rate limit with a burst of one second's worth of bytes.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `bytes_per_second` is 0.
+    #[must_use]
+    pub fn new(bytes_per_second: u64) -> Self {
+        assert!(bytes_per_second > 0, "rate limit should be > 0");
+
+        Self {
+            bytes_per_second,
+            burst: bytes_per_second,
+        }
+    }
+
+    /// Sets the burst size in bytes.
+    ///
+    /// Default = `bytes_per_second`
+    #[must_use]
+    pub fn burst(mut self, bytes: u64) -> Self {
+        self.burst = bytes;
+        self
+    }
+}
+
+struct Bucket {
+    limit: Option<RateLimit>,
+
+    /// Available bytes, may become negative (debt) if more bytes
+    /// than available are about to be written
+    tokens: f64,
+
+    last_refill: Instant,
+}
+
+impl Bucket {
+    fn unlimited() -> Self {
+        Self {
+            limit: None,
+            tokens: 0.0,
+            last_refill: Instant::now(),
+        }
+    }
+
+    #[allow(clippy::cast_precision_loss)]
+    fn refill(&mut self) {
+        let now = Instant::now();
+
+        if let Some(limit) = self.limit {
+            let elapsed = now.duration_since(self.last_refill).as_secs_f64();
+
+            self.tokens = elapsed
+                .mul_add(limit.bytes_per_second as f64, self.tokens)
+                .min(limit.burst as f64);
+        }
+
+        self.last_refill = now;
+    }
+
+    /// Returns how long to wait until the debt is paid off.
+    #[allow(clippy::cast_precision_loss)]
+    fn debt_duration(&self) -> Option<Duration> {
+        let limit = self.limit?;
+
+        if self.tokens >= 0.0 {
+            return None;
+        }
+
+        Some(Duration::from_secs_f64(
+            -self.tokens / limit.bytes_per_second as f64,
+        ))
+    }
+}
+
+struct RateLimiterInner {
+    flush: Mutex<Bucket>,
+    compaction: Mutex<Bucket>,
+    gc: Mutex<Bucket>,
+}
+
+/// Token bucket rate limiter for background I/O
+///
+/// Each [`IoActivity`] has its own budget, which is unlimited by default.
+/// Limits can be adjusted at runtime using [`Keyspace::rate_limiter`](crate::Keyspace::rate_limiter).
+///
+/// Bytes are accounted before they are written, so a worker sleeps until
+/// its next piece of work is paid off:
+///
+/// - flushes are accounted per segment, by the size of the flushed memtable
+/// - garbage collection is accounted per rewritten blob file
+/// - compactions are accounted per compaction, by the size of the merged segments,
+///   because LSM-trees write a compaction in one go
+///
+/// So a single compaction still writes at full speed once it has started,
+/// but the long-term rate does not exceed the limit.
+///
+/// Limiting flushes reduces how fast memtables can be freed, and may cause write stalls.
+#[derive(Clone)]
+#[allow(clippy::module_name_repetitions)]
+pub struct RateLimiter(Arc<RateLimiterInner>);
+
+impl Default for RateLimiter {
+    fn default() -> Self {
+        Self(Arc::new(RateLimiterInner {
+            flush: Mutex::new(Bucket::unlimited()),
+            compaction: Mutex::new(Bucket::unlimited()),
+            gc: Mutex::new(Bucket::unlimited()),
+        }))
+    }
+}
+
+impl std::fmt::Debug for RateLimiter {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("RateLimiter")
+            .field("flush", &self.limit(IoActivity::Flush))
+            .field("compaction", &self.limit(IoActivity::Compaction))
+            .field("gc", &self.limit(IoActivity::GarbageCollection))
+            .finish()
+    }
+}
+
+impl RateLimiter {
+    fn bucket(&self, activity: IoActivity) -> MutexGuard<'_, Bucket> {
+        let bucket = match activity {
+            IoActivity::Flush => &self.0.flush,
+            IoActivity::Compaction => &self.0.compaction,
+            IoActivity::GarbageCollection => &self.0.gc,
+        };
+
+        // NOTE: A bucket only holds counters, which stay valid even if a thread panicked
+        bucket.lock().unwrap_or_else(PoisonError::into_inner)
+    }
+
+    /// Returns the current rate limit of an activity, or `None` if unlimited.
+    #[must_use]
+    pub fn limit(&self, activity: IoActivity) -> Option<RateLimit> {
+        self.bucket(activity).limit
+    }
+
+    /// Sets the rate limit of an activity, `None` removes the limit.
+    ///
+    /// Takes effect immediately, also for workers that are currently being throttled.
+    #[allow(clippy::cast_precision_loss)]
+    pub fn set_limit(&self, activity: IoActivity, limit: Option<RateLimit>) {
+        let mut bucket = self.bucket(activity);
+        bucket.refill();
+
+        match (bucket.limit, limit) {
+            // NOTE: Start with a full bucket
+            (None, Some(limit)) => bucket.tokens = limit.burst as f64,
+            (Some(_), Some(limit)) => bucket.tokens = bucket.tokens.min(limit.burst as f64),
+            (_, None) => bucket.tokens = 0.0,
+        }
+
+        bucket.limit = limit;
+    }
+
+    /// Accounts bytes that are about to be written, sleeping until the activity is within its budget again.
+    ///
+    /// Returns early if the stop signal is sent.
+    #[allow(clippy::cast_precision_loss)]
+    pub(crate) fn acquire(
+        &self,
+        activity: IoActivity,
+        bytes: u64,
+        stop_signal: Option<&StopSignal>,
+    ) {
+        let mut lock = self.bucket(activity);
+        if lock.limit.is_none() {
+            return;
+        }
+        lock.refill();
+        lock.tokens -= bytes as f64;
+        drop(lock);
+
+        loop {
+            let debt = {
+                let mut lock = self.bucket(activity);
+                lock.refill();
+                lock.debt_duration()
+            };
+
+            let Some(debt) = debt else {
+                return;
+            };
+
+            if stop_signal.is_some_and(StopSignal::is_stopped) {
+                return;
+            }
+
+            log::trace!("rate limiter: throttling {activity:?} for {debt:?}");
+            std::thread::sleep(debt.min(MAX_SLEEP));
+        }
+    }
+}
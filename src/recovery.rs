@@ -5,38 +5,109 @@
 use crate::{
     batch::PartitionKey,
     file::{LSM_MANIFEST_FILE, PARTITIONS_FOLDER, PARTITION_CONFIG_FILE, PARTITION_DELETED_MARKER},
+    fs::{Fs, OpenMode},
     journal::{
         batch_reader::JournalBatchReader, manager::EvictionWatermark, reader::JournalReader,
     },
     partition::options::CreateOptions as PartitionCreateOptions,
+    tree_files::TreeFiles,
     HashMap, Keyspace, PartitionHandle,
 };
 use lsm_tree::{AbstractTree, AnyTree};
-use std::{fs::File, path::PathBuf};
+use std::path::{Path, PathBuf};
 
-/// Recovers partitions
-pub fn recover_partitions(keyspace: &Keyspace) -> crate::Result<()> {
+/// Reads the config of a partition, which is written when the partition is created
+pub fn read_partition_config(
+    fs: &dyn Fs,
+    partition_path: &Path,
+) -> crate::Result<PartitionCreateOptions> {
     use lsm_tree::coding::Decode;
 
+    let mut config_file = fs.open(&partition_path.join(PARTITION_CONFIG_FILE), OpenMode::Read)?;
+    PartitionCreateOptions::decode_from(&mut config_file).map_err(Into::into)
+}
+
+/// Opens the existing LSM-tree of a partition
+pub fn open_partition_tree(
+    keyspace: &Keyspace,
+    path: PathBuf,
+    recovered_config: &PartitionCreateOptions,
+) -> crate::Result<AnyTree> {
+    // NOTE: Recovering a tree deletes unfinished and unreferenced segments,
+    // which a read-only keyspace must not do
+    //
+    // Secondaries own their mirrored segments, so unreferenced links may be pruned
+    if keyspace.config.read_only && keyspace.config.secondary_path.is_none() {
+        let files = TreeFiles::scan(&path)?;
+
+        if !files.unreferenced.is_empty() {
+            log::error!(
+                "Cannot open partition at {} read-only, because recovery would delete {:?}",
+                path.display(),
+                files.unreferenced,
+            );
+            return Err(crate::Error::ReadOnly);
+        }
+    }
+
+    let is_blob_tree = path.join(lsm_tree::file::BLOBS_FOLDER).try_exists()?;
+
+    let mut base_config = lsm_tree::Config::new(path)
+        .descriptor_table(keyspace.config.descriptor_table.clone())
+        .block_cache(keyspace.config.block_cache.clone())
+        .blob_cache(keyspace.config.blob_cache.clone());
+
+    base_config.bloom_bits_per_key = recovered_config.bloom_bits_per_key;
+    base_config.data_block_size = recovered_config.data_block_size;
+    base_config.index_block_size = recovered_config.index_block_size;
+    base_config.bloom_bits_per_key = recovered_config.bloom_bits_per_key;
+    base_config.compression = recovered_config.compression;
+
+    if let Some(kv_opts) = &recovered_config.kv_separation {
+        base_config = base_config
+            .blob_compression(kv_opts.compression)
+            .blob_file_separation_threshold(kv_opts.separation_threshold)
+            .blob_file_target_size(kv_opts.file_target_size);
+    }
+
+    Ok(if is_blob_tree {
+        AnyTree::Blob(base_config.open_as_blob_tree()?)
+    } else {
+        AnyTree::Standard(base_config.open()?)
+    })
+}
+
+/// Recovers partitions
+pub fn recover_partitions(keyspace: &Keyspace) -> crate::Result<()> {
+    let fs = &keyspace.config.fs;
     let partitions_folder = keyspace.config.path.join(PARTITIONS_FOLDER);
 
     #[allow(clippy::significant_drop_tightening)]
     let mut partitions_lock = keyspace.partitions.write().expect("lock is poisoned");
 
-    for dirent in std::fs::read_dir(&partitions_folder)? {
-        let dirent = dirent?;
-        let partition_name = dirent.file_name();
-        let partition_path = dirent.path();
-
-        assert!(
-            dirent.file_type()?.is_dir(),
-            "Found stray file in partitions folder",
-        );
+    for dirent in fs.read_dir(&partitions_folder)? {
+        let partition_path = dirent.path;
+        // NOTE: Folder entries always have a file name
+        let partition_name = partition_path
+            .file_name()
+            .unwrap_or_default()
+            .to_os_string();
+
+        // NOTE: Stray files are reported by Keyspace::verify
+        if !dirent.is_dir {
+            log::warn!("Ignoring stray file {partition_path:?} in partitions folder");
+            continue;
+        }
 
         log::trace!("Recovering partition {:?}", partition_name);
 
         // NOTE: Check deletion marker
-        if partition_path.join(PARTITION_DELETED_MARKER).try_exists()? {
+        if fs.exists(&partition_path.join(PARTITION_DELETED_MARKER))? {
+            if keyspace.config.read_only {
+                log::debug!("Skipping deleted partition {partition_name:?}");
+                continue;
+            }
+
             log::debug!("Deleting deleted partition {:?}", partition_name);
 
             // IMPORTANT: First, delete the manifest,
@@ -47,18 +118,23 @@ pub fn recover_partitions(keyspace: &Keyspace) -> crate::Result<()> {
             // deleting the `.deleted` marker first, we would end up resurrecting
             // the partition
             let manifest_file = partition_path.join(LSM_MANIFEST_FILE);
-            if manifest_file.try_exists()? {
-                std::fs::remove_file(manifest_file)?;
+            if fs.exists(&manifest_file)? {
+                fs.remove_file(&manifest_file)?;
             }
 
-            std::fs::remove_dir_all(partition_path)?;
+            fs.remove_dir_all(&partition_path)?;
             continue;
         }
 
         // NOTE: Check for marker, maybe the partition is not fully initialized
-        if !partition_path.join(LSM_MANIFEST_FILE).try_exists()? {
+        if !fs.exists(&partition_path.join(LSM_MANIFEST_FILE))? {
+            if keyspace.config.read_only {
+                log::debug!("Skipping uninitialized partition {partition_name:?}");
+                continue;
+            }
+
             log::debug!("Deleting uninitialized partition {:?}", partition_name);
-            std::fs::remove_dir_all(partition_path)?;
+            fs.remove_dir_all(&partition_path)?;
             continue;
         }
 
@@ -68,36 +144,8 @@ pub fn recover_partitions(keyspace: &Keyspace) -> crate::Result<()> {
 
         let path = partitions_folder.join(partition_name);
 
-        let mut config_file = File::open(partition_path.join(PARTITION_CONFIG_FILE))?;
-        let recovered_config = PartitionCreateOptions::decode_from(&mut config_file)?;
-
-        let mut base_config = lsm_tree::Config::new(path)
-            .descriptor_table(keyspace.config.descriptor_table.clone())
-            .block_cache(keyspace.config.block_cache.clone())
-            .blob_cache(keyspace.config.blob_cache.clone());
-
-        base_config.bloom_bits_per_key = recovered_config.bloom_bits_per_key;
-        base_config.data_block_size = recovered_config.data_block_size;
-        base_config.index_block_size = recovered_config.index_block_size;
-        base_config.bloom_bits_per_key = recovered_config.bloom_bits_per_key;
-        base_config.compression = recovered_config.compression;
-
-        if let Some(kv_opts) = &recovered_config.kv_separation {
-            base_config = base_config
-                .blob_compression(kv_opts.compression)
-                .blob_file_separation_threshold(kv_opts.separation_threshold)
-                .blob_file_target_size(kv_opts.file_target_size);
-        }
-
-        let is_blob_tree = partition_path
-            .join(lsm_tree::file::BLOBS_FOLDER)
-            .try_exists()?;
-
-        let tree = if is_blob_tree {
-            AnyTree::Blob(base_config.open_as_blob_tree()?)
-        } else {
-            AnyTree::Standard(base_config.open()?)
-        };
+        let recovered_config = read_partition_config(fs.as_ref(), &partition_path)?;
+        let tree = open_partition_tree(keyspace, path, &recovered_config)?;
 
         let partition =
             PartitionHandle::from_keyspace(keyspace, tree, partition_name.into(), recovered_config);
@@ -128,11 +176,22 @@ pub fn recover_sealed_memtables(
     for journal_path in sealed_journal_paths {
         log::debug!("Recovering sealed journal: {journal_path:?}");
 
-        let journal_size = journal_path.metadata()?.len();
-
         log::debug!("Reading sealed journal at {journal_path:?}");
 
-        let raw_reader = JournalReader::new(journal_path)?;
+        let raw_reader = if keyspace.config.read_only {
+            JournalReader::new_read_only(
+                journal_path,
+                keyspace.config.fs.clone(),
+                &keyspace.config.encryption,
+            )?
+        } else {
+            JournalReader::new(
+                journal_path,
+                keyspace.config.fs.clone(),
+                &keyspace.config.encryption,
+            )?
+        };
+        let journal_size = raw_reader.reader.get_ref().size()?;
         let reader = JournalBatchReader::new(raw_reader);
 
         let mut watermarks: HashMap<PartitionKey, EvictionWatermark> = HashMap::default();
@@ -0,0 +1,469 @@
+// Copyright (c) 2024-present, fjall-rs
+// This source code is licensed under both the Apache 2.0 and MIT License
+// (found in the LICENSE-* files in the repository)
+
+use crate::{
+    batch::PartitionKey,
+    file::{
+        FJALL_MARKER, JOURNALS_FOLDER, LOST_AND_FOUND_FOLDER, LSM_MANIFEST_FILE, PARTITIONS_FOLDER,
+        PARTITION_CONFIG_FILE, PARTITION_DELETED_MARKER, REPAIR_LOG_FILE,
+    },
+    fs::{Fs, OpenMode, StdFs},
+    journal::dump::{JournalDump, JournalStopReason},
+    lock::LockFile,
+    partition::{name::is_valid_partition_name, options::CreateOptions as PartitionCreateOptions},
+    tree_files::TreeFiles,
+    version::Version,
+    KvSeparationOptions,
+};
+use lsm_tree::{
+    coding::{Decode, Encode},
+    file::BLOBS_FOLDER,
+};
+use std::{
+    fs::File,
+    io::Write,
+    path::{Path, PathBuf},
+    sync::Arc,
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+/// Options for [`Keyspace::repair`](crate::Keyspace::repair)
+#[derive(Copy, Clone, Debug, Default, Eq, PartialEq)]
+pub struct RepairOptions {
+    /// Only report what would be repaired, without changing any files.
+    ///
+    /// Default = false
+    pub dry_run: bool,
+}
+
+/// Why a file or folder was moved into the lost+found folder
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+#[non_exhaustive]
+pub enum QuarantineReason {
+    /// The file does not belong into its folder
+    StrayFile,
+
+    /// The partition folder has no manifest
+    MissingManifest,
+
+    /// The partition's manifests are unreadable, or segments listed in them are missing
+    BrokenPartition,
+
+    /// The segment is unfinished or not in the partition's manifest
+    OrphanedSegment,
+
+    /// There is a newer active journal
+    DuplicateActiveJournal,
+}
+
+/// Action taken by [`Keyspace::repair`](crate::Keyspace::repair)
+#[derive(Debug, Eq, PartialEq)]
+#[non_exhaustive]
+pub enum RepairAction {
+    /// Wrote a missing or unreadable version marker
+    WroteVersionMarker,
+
+    /// Created a missing folder
+    CreatedFolder(PathBuf),
+
+    /// Moved a file or folder into the lost+found folder
+    Quarantined {
+        /// Original path
+        path: PathBuf,
+
+        /// Why the file was moved
+        reason: QuarantineReason,
+    },
+
+    /// Replaced an unreadable partition config with default options,
+    /// the old config is copied into the lost+found folder
+    RewrotePartitionConfig {
+        /// Partition name
+        partition: PartitionKey,
+    },
+
+    /// Truncated a journal before a broken batch, the old journal is copied into the lost+found folder
+    ///
+    /// All batches starting from the broken batch are lost.
+    TruncatedJournal {
+        /// Path of the journal file
+        path: PathBuf,
+
+        /// Offset the journal was truncated to
+        offset: u64,
+    },
+}
+
+/// Result of [`Keyspace::repair`](crate::Keyspace::repair)
+#[derive(Debug)]
+pub struct RepairReport {
+    /// Folder in `lost+found` that quarantined files and the `repair.log` were written into,
+    /// `None` if nothing was repaired or in dry run mode
+    pub lost_and_found: Option<PathBuf>,
+
+    /// Actions that were taken, or would have been taken in dry run mode
+    pub actions: Vec<RepairAction>,
+}
+
+impl RepairReport {
+    /// Returns `true` if nothing needed to be repaired.
+    #[must_use]
+    pub fn is_clean(&self) -> bool {
+        self.actions.is_empty()
+    }
+}
+
+struct Repair<'a> {
+    path: &'a Path,
+    fs: &'a dyn Fs,
+    options: RepairOptions,
+    run_folder: PathBuf,
+    report: RepairReport,
+}
+
+impl<'a> Repair<'a> {
+    fn new(path: &'a Path, fs: &'a dyn Fs, options: RepairOptions) -> Self {
+        let timestamp = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .unwrap_or_default()
+            .as_millis();
+
+        Self {
+            path,
+            fs,
+            options,
+            run_folder: path.join(LOST_AND_FOUND_FOLDER).join(timestamp.to_string()),
+            report: RepairReport {
+                lost_and_found: None,
+                actions: vec![],
+            },
+        }
+    }
+
+    /// Returns where a file is moved or copied to in the lost+found folder.
+    fn lost_and_found_path(&self, path: &Path) -> crate::Result<PathBuf> {
+        let relative = path.strip_prefix(self.path).unwrap_or(path);
+        let target = self.run_folder.join(relative);
+
+        if let Some(parent) = target.parent() {
+            self.fs.create_dir_all(parent)?;
+        }
+
+        Ok(target)
+    }
+
+    fn quarantine(&mut self, path: &Path, reason: QuarantineReason) -> crate::Result<()> {
+        log::warn!("repair: quarantining {} ({reason:?})", path.display());
+
+        if !self.options.dry_run {
+            let target = self.lost_and_found_path(path)?;
+            self.fs.rename(path, target.as_path())?;
+        }
+
+        self.report.actions.push(RepairAction::Quarantined {
+            path: path.into(),
+            reason,
+        });
+
+        Ok(())
+    }
+
+    fn backup(&self, path: &Path) -> crate::Result<()> {
+        let target = self.lost_and_found_path(path)?;
+
+        let mut file = self.fs.open(&target, OpenMode::Create)?;
+        std::io::copy(&mut File::open(path)?, &mut file)?;
+        file.sync_all()?;
+
+        Ok(())
+    }
+
+    fn ensure_folder(&mut self, folder: &Path) -> crate::Result<()> {
+        if folder.try_exists()? {
+            return Ok(());
+        }
+
+        log::warn!("repair: creating missing folder {}", folder.display());
+
+        if !self.options.dry_run {
+            self.fs.create_dir_all(folder)?;
+        }
+
+        self.report
+            .actions
+            .push(RepairAction::CreatedFolder(folder.into()));
+
+        Ok(())
+    }
+
+    fn repair_version_marker(&mut self) -> crate::Result<()> {
+        let marker_path = self.path.join(FJALL_MARKER);
+
+        let version = if marker_path.try_exists()? {
+            Version::parse_file_header(&std::fs::read(&marker_path)?)
+        } else {
+            None
+        };
+
+        match version {
+            Some(Version::V2) => return Ok(()),
+            Some(version) => return Err(crate::Error::InvalidVersion(Some(version))),
+            None => {}
+        }
+
+        let has_keyspace_folders = self.path.join(PARTITIONS_FOLDER).try_exists()?
+            || self.path.join(JOURNALS_FOLDER).try_exists()?;
+
+        // NOTE: Don't turn arbitrary folders into keyspaces
+        if !has_keyspace_folders {
+            return Err(crate::Error::InvalidVersion(None));
+        }
+
+        log::warn!("repair: writing version marker");
+
+        if !self.options.dry_run {
+            let mut file = self.fs.open(&marker_path, OpenMode::Create)?;
+            Version::V2.write_file_header(&mut file)?;
+            file.sync_all()?;
+        }
+
+        self.report.actions.push(RepairAction::WroteVersionMarker);
+
+        Ok(())
+    }
+
+    fn repair_partition_config(&mut self, name: &str, path: &Path) -> crate::Result<()> {
+        let config_path = path.join(PARTITION_CONFIG_FILE);
+
+        let is_readable = File::open(&config_path)
+            .map_err(crate::Error::from)
+            .and_then(|mut file| Ok(PartitionCreateOptions::decode_from(&mut file)?))
+            .is_ok();
+
+        if is_readable {
+            return Ok(());
+        }
+
+        log::warn!("repair: rewriting config of partition {name:?}");
+
+        if !self.options.dry_run {
+            if config_path.try_exists()? {
+                self.backup(&config_path)?;
+            }
+
+            let mut config = PartitionCreateOptions::default();
+
+            if path.join(BLOBS_FOLDER).try_exists()? {
+                config = config.with_kv_separation(KvSeparationOptions::default());
+            }
+
+            let mut file = self.fs.open(&config_path, OpenMode::Create)?;
+            config.encode_into(&mut file)?;
+            file.sync_all()?;
+        }
+
+        self.report
+            .actions
+            .push(RepairAction::RewrotePartitionConfig {
+                partition: name.into(),
+            });
+
+        Ok(())
+    }
+
+    fn repair_partitions(&mut self) -> crate::Result<()> {
+        let partitions_folder = self.path.join(PARTITIONS_FOLDER);
+        self.ensure_folder(&partitions_folder)?;
+
+        if !partitions_folder.try_exists()? {
+            return Ok(());
+        }
+
+        for dirent in std::fs::read_dir(&partitions_folder)? {
+            let dirent = dirent?;
+            let path = dirent.path();
+
+            let file_name = dirent.file_name();
+            let name = file_name.to_str().unwrap_or_default();
+
+            if !dirent.file_type()?.is_dir() || !is_valid_partition_name(name) {
+                self.quarantine(&path, QuarantineReason::StrayFile)?;
+                continue;
+            }
+
+            // NOTE: Deleted partitions are cleaned up by recovery
+            if path.join(PARTITION_DELETED_MARKER).try_exists()? {
+                continue;
+            }
+
+            if !path.join(LSM_MANIFEST_FILE).try_exists()? {
+                self.quarantine(&path, QuarantineReason::MissingManifest)?;
+                continue;
+            }
+
+            self.repair_partition_config(name, &path)?;
+
+            // NOTE: Don't open the tree, because recovery deletes unreferenced segments
+            let files = match TreeFiles::scan(&path) {
+                Ok(files) if files.missing.is_empty() => files,
+                Ok(files) => {
+                    log::error!(
+                        "repair: partition {name:?} is missing files: {:?}",
+                        files.missing
+                    );
+                    self.quarantine(&path, QuarantineReason::BrokenPartition)?;
+                    continue;
+                }
+                Err(e) => {
+                    log::error!("repair: partition {name:?} could not be checked: {e:?}");
+                    self.quarantine(&path, QuarantineReason::BrokenPartition)?;
+                    continue;
+                }
+            };
+
+            for file in files.unreferenced {
+                self.quarantine(&file, QuarantineReason::OrphanedSegment)?;
+            }
+        }
+
+        Ok(())
+    }
+
+    fn repair_journals(&mut self) -> crate::Result<()> {
+        let journals_folder = self.path.join(JOURNALS_FOLDER);
+        self.ensure_folder(&journals_folder)?;
+
+        if !journals_folder.try_exists()? {
+            return Ok(());
+        }
+
+        let mut journals = vec![];
+
+        for dirent in std::fs::read_dir(&journals_folder)? {
+            let dirent = dirent?;
+            let path = dirent.path();
+
+            let file_name = dirent.file_name();
+            let file_name = file_name.to_str().unwrap_or_default();
+            let is_sealed = file_name.ends_with(".sealed");
+
+            let id = file_name
+                .trim_end_matches(".sealed")
+                .parse::<u64>()
+                .ok()
+                .filter(|_| dirent.file_type().is_ok_and(|x| x.is_file()));
+
+            match id {
+                Some(id) => journals.push((id, is_sealed, path)),
+                None => self.quarantine(&path, QuarantineReason::StrayFile)?,
+            }
+        }
+
+        journals.sort();
+
+        // NOTE: Recovery only allows a single active journal
+        let newest_active = journals
+            .iter()
+            .filter(|(_, is_sealed, _)| !is_sealed)
+            .map(|(id, _, _)| *id)
+            .max();
+
+        for (id, is_sealed, path) in journals {
+            if !is_sealed && Some(id) != newest_active {
+                self.quarantine(&path, QuarantineReason::DuplicateActiveJournal)?;
+                continue;
+            }
+
+            let dump = match JournalDump::from_file(&path, Arc::new(StdFs)) {
+                Ok(dump) => dump,
+
+                // NOTE: Repair has no keys, so encrypted journals are left to recovery
+                Err(crate::Error::Encryption(_)) => {
+                    log::debug!("repair: skipping encrypted journal {}", path.display());
+                    continue;
+                }
+
+                Err(e) => return Err(e),
+            };
+
+            // NOTE: Other stops are handled by recovery, which truncates the journal
+            let Some(stop) = dump
+                .stop
+                .filter(|stop| matches!(stop.reason, JournalStopReason::Broken(_)))
+            else {
+                continue;
+            };
+
+            log::warn!(
+                "repair: truncating journal {} to {} ({:?})",
+                path.display(),
+                stop.offset,
+                stop.reason
+            );
+
+            if !self.options.dry_run {
+                self.backup(&path)?;
+
+                let mut file = self.fs.open(&path, OpenMode::Write)?;
+                file.set_len(stop.offset)?;
+                file.sync_all()?;
+            }
+
+            self.report.actions.push(RepairAction::TruncatedJournal {
+                path,
+                offset: stop.offset,
+            });
+        }
+
+        Ok(())
+    }
+
+    fn write_log(&mut self) -> crate::Result<()> {
+        self.fs.create_dir_all(&self.run_folder)?;
+
+        let mut file = self
+            .fs
+            .open(&self.run_folder.join(REPAIR_LOG_FILE), OpenMode::Create)?;
+
+        for action in &self.report.actions {
+            writeln!(file, "{action:?}")?;
+        }
+
+        file.sync_all()?;
+
+        self.fs.sync_directory(&self.run_folder)?;
+        self.fs.sync_directory(self.path)?;
+
+        self.report.lost_and_found = Some(self.run_folder.clone());
+
+        Ok(())
+    }
+}
+
+/// Repairs a keyspace that is not opened, see [`Keyspace::repair`](crate::Keyspace::repair).
+pub fn repair_keyspace(path: &Path, options: RepairOptions) -> crate::Result<RepairReport> {
+    log::info!("Repairing keyspace at {}", path.display());
+
+    // NOTE: Repairing a keyspace that is opened would corrupt it
+    LockFile::check(path)?;
+
+    let mut repair = Repair::new(path, &StdFs, options);
+
+    repair.repair_version_marker()?;
+
+    let _lock = if options.dry_run {
+        None
+    } else {
+        Some(LockFile::acquire(path)?)
+    };
+
+    repair.repair_partitions()?;
+    repair.repair_journals()?;
+
+    if !repair.report.is_clean() && !options.dry_run {
+        repair.write_log()?;
+    }
+
+    Ok(repair.report)
+}
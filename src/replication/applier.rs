@@ -0,0 +1,144 @@
+// Copyright (c) 2024-present, fjall-rs
+// This source code is licensed under both the Apache 2.0 and MIT License
+// (found in the LICENSE-* files in the repository)
+
+use super::{ReplicatedItem, ReplicationError, ReplicationMessage, ReplicationReceiver};
+use crate::{batch::item::Item as BatchItem, Keyspace};
+use lsm_tree::SeqNo;
+use std::sync::atomic::Ordering;
+
+/// Applies replicated batches of a [`ReplicationSource`](crate::ReplicationSource)
+/// to a standby keyspace
+///
+/// Batches are written with the seqnos of the primary, so the standby
+/// can resume shipping from [`ReplicaApplier::next_seqno`] after a restart.
+///
+/// The standby keyspace should not be written to by anything else.
+pub struct ReplicaApplier {
+    keyspace: Keyspace,
+    is_in_snapshot: bool,
+}
+
+impl ReplicaApplier {
+    /// Creates an applier writing into the keyspace.
+    #[must_use]
+    pub fn new(keyspace: Keyspace) -> Self {
+        Self {
+            keyspace,
+            is_in_snapshot: false,
+        }
+    }
+
+    /// Returns the seqno of the next batch the replica needs.
+    #[must_use]
+    pub fn next_seqno(&self) -> SeqNo {
+        self.keyspace.instant()
+    }
+
+    /// Applies a single message.
+    ///
+    /// Batches that were already applied are skipped.
+    ///
+    /// If a snapshot is interrupted, the replica needs to be emptied
+    /// before it can be bootstrapped again.
+    ///
+    /// # Errors
+    ///
+    /// Will return `Err` if an IO error occurs, or the message cannot be applied.
+    pub fn apply(&mut self, message: ReplicationMessage) -> crate::Result<()> {
+        match message {
+            ReplicationMessage::Partition { name, options } => {
+                // NOTE: Replicated batches already contain the index changes of the primary
+                self.keyspace.open_partition_unchecked(&name, options)?;
+            }
+            ReplicationMessage::SnapshotStart => {
+                for name in self.keyspace.list_partitions() {
+                    let partition = self.keyspace.open_partition_unchecked(
+                        &name,
+                        crate::PartitionCreateOptions::default(),
+                    )?;
+
+                    if !partition.is_empty()? {
+                        return Err(ReplicationError::NotEmpty.into());
+                    }
+                }
+
+                self.is_in_snapshot = true;
+            }
+            ReplicationMessage::SnapshotItems(items) => {
+                if !self.is_in_snapshot {
+                    return Err(ReplicationError::UnexpectedMessage.into());
+                }
+
+                // NOTE: Snapshot items are older than any replicated batch
+                self.commit(0, items)?;
+            }
+            ReplicationMessage::SnapshotEnd { seqno } => {
+                if !self.is_in_snapshot {
+                    return Err(ReplicationError::UnexpectedMessage.into());
+                }
+
+                self.is_in_snapshot = false;
+                self.keyspace.seqno.fetch_max(seqno, Ordering::AcqRel);
+            }
+            ReplicationMessage::Batch { seqno, items } => {
+                // NOTE: While bootstrapping, batches that are part of the snapshot
+                // are applied again, so the latest versions are not lost
+                if !self.is_in_snapshot && seqno < self.next_seqno() {
+                    log::trace!("Skipping replicated batch {seqno}, already applied");
+                    return Ok(());
+                }
+
+                self.commit(seqno, items)?;
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Applies messages until the receiver is disconnected,
+    /// returning the amount of applied messages.
+    ///
+    /// # Errors
+    ///
+    /// Will return `Err` if an IO error occurs, or a message cannot be applied.
+    pub fn receive<R: ReplicationReceiver>(&mut self, receiver: &mut R) -> crate::Result<usize> {
+        let mut count = 0;
+
+        while let Some(message) = receiver.recv()? {
+            self.apply(message)?;
+            count += 1;
+        }
+
+        Ok(count)
+    }
+
+    fn commit(&self, seqno: SeqNo, items: Vec<ReplicatedItem>) -> crate::Result<()> {
+        if items.is_empty() {
+            self.keyspace.seqno.fetch_max(seqno + 1, Ordering::AcqRel);
+            return Ok(());
+        }
+
+        for item in &items {
+            if !self.keyspace.partition_exists(&item.partition) {
+                return Err(ReplicationError::UnknownPartition(item.partition.clone()).into());
+            }
+        }
+
+        let mut batch = self.keyspace.batch();
+
+        batch.data = items
+            .into_iter()
+            .map(|item| BatchItem {
+                partition: item.partition,
+                key: item.key,
+                value: item.value,
+                value_type: item.value_type,
+            })
+            .collect();
+
+        batch.commit_with_seqno(Some(seqno))?;
+
+        Ok(())
+    }
+}
@@ -0,0 +1,311 @@
+// Copyright (c) 2024-present, fjall-rs
+// This source code is licensed under both the Apache 2.0 and MIT License
+// (found in the LICENSE-* files in the repository)
+
+pub mod applier;
+pub mod source;
+pub mod transport;
+
+use crate::{batch::PartitionKey, PartitionCreateOptions};
+use byteorder::{BigEndian, ReadBytesExt, WriteBytesExt};
+use lsm_tree::{
+    coding::{Decode, Encode},
+    DecodeError, EncodeError, SeqNo, UserKey, UserValue, ValueType,
+};
+use std::io::{Read, Write};
+
+/// Errors that may occur when applying replicated batches
+#[derive(Debug)]
+#[allow(clippy::module_name_repetitions)]
+pub enum ReplicationError {
+    /// A snapshot can only be applied to an empty replica
+    NotEmpty,
+
+    /// A message was received out of order, for example snapshot items outside of a snapshot
+    UnexpectedMessage,
+
+    /// A batch writes to a partition that does not exist on the replica,
+    /// and was not announced by the source
+    UnknownPartition(PartitionKey),
+}
+
+impl std::fmt::Display for ReplicationError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{self:?}")
+    }
+}
+
+impl std::error::Error for ReplicationError {}
+
+/// Item of a replicated batch or snapshot
+#[derive(Clone, Debug, Eq, PartialEq)]
+#[allow(clippy::module_name_repetitions)]
+pub struct ReplicatedItem {
+    /// Partition the item belongs to
+    pub partition: PartitionKey,
+
+    /// Key of the item
+    pub key: UserKey,
+
+    /// Value of the item, empty for tombstones
+    pub value: UserValue,
+
+    /// Value type of the item
+    pub value_type: ValueType,
+}
+
+/// Message sent from a [`ReplicationSource`](crate::ReplicationSource)
+/// to a [`ReplicaApplier`](crate::ReplicaApplier)
+#[derive(Clone, Debug)]
+#[allow(clippy::module_name_repetitions)]
+pub enum ReplicationMessage {
+    /// Partition that following messages write to,
+    /// which is created on the replica if it does not exist
+    Partition {
+        /// Name of the partition
+        name: PartitionKey,
+
+        /// Options the partition was created with on the primary
+        options: PartitionCreateOptions,
+    },
+
+    /// Start of a snapshot, which is sent if the journals
+    /// that the replica needs were already evicted on the primary
+    ///
+    /// The replica needs to be empty.
+    SnapshotStart,
+
+    /// Items of a snapshot
+    SnapshotItems(Vec<ReplicatedItem>),
+
+    /// End of a snapshot, the replica has applied everything up to the seqno
+    SnapshotEnd {
+        /// Seqno the snapshot was taken at
+        seqno: SeqNo,
+    },
+
+    /// Committed batch of the primary
+    Batch {
+        /// Seqno the batch was committed with
+        seqno: SeqNo,
+
+        /// Items of the batch
+        items: Vec<ReplicatedItem>,
+    },
+}
+
+/// Sends replication messages to a replica
+#[allow(clippy::module_name_repetitions)]
+pub trait ReplicationSender {
+    /// Sends a message.
+    ///
+    /// # Errors
+    ///
+    /// Will return `Err` if the message could not be sent.
+    fn send(&mut self, message: ReplicationMessage) -> crate::Result<()>;
+}
+
+/// Receives replication messages from a source
+#[allow(clippy::module_name_repetitions)]
+pub trait ReplicationReceiver {
+    /// Receives the next message, blocking until one is available.
+    ///
+    /// Returns `None` if the source is disconnected.
+    ///
+    /// # Errors
+    ///
+    /// Will return `Err` if the message could not be received.
+    fn recv(&mut self) -> crate::Result<Option<ReplicationMessage>>;
+}
+
+enum Tag {
+    Partition = 1,
+    SnapshotStart = 2,
+    SnapshotItems = 3,
+    SnapshotEnd = 4,
+    Batch = 5,
+}
+
+impl TryFrom<u8> for Tag {
+    type Error = DecodeError;
+
+    fn try_from(value: u8) -> Result<Self, Self::Error> {
+        match value {
+            1 => Ok(Self::Partition),
+            2 => Ok(Self::SnapshotStart),
+            3 => Ok(Self::SnapshotItems),
+            4 => Ok(Self::SnapshotEnd),
+            5 => Ok(Self::Batch),
+            _ => Err(DecodeError::InvalidTag(("ReplicationMessageTag", value))),
+        }
+    }
+}
+
+impl From<Tag> for u8 {
+    fn from(val: Tag) -> Self {
+        val as Self
+    }
+}
+
+fn encode_items<W: Write>(items: &[ReplicatedItem], writer: &mut W) -> Result<(), EncodeError> {
+    // NOTE: Truncation is okay, batches cannot be that large
+    #[allow(clippy::cast_possible_truncation)]
+    writer.write_u32::<BigEndian>(items.len() as u32)?;
+
+    for item in items {
+        crate::journal::marker::serialize_marker_item(
+            writer,
+            &item.partition,
+            &item.key,
+            &item.value,
+            item.value_type,
+        )?;
+    }
+
+    Ok(())
+}
+
+fn decode_items<R: Read>(reader: &mut R) -> Result<Vec<ReplicatedItem>, DecodeError> {
+    use crate::journal::marker::Marker;
+
+    let item_count = reader.read_u32::<BigEndian>()?;
+    let mut items = Vec::with_capacity(item_count.min(1_000) as usize);
+
+    for _ in 0..item_count {
+        let Marker::Item {
+            partition,
+            key,
+            value,
+            value_type,
+        } = Marker::decode_from(reader)?
+        else {
+            return Err(DecodeError::InvalidHeader("ReplicatedItem"));
+        };
+
+        items.push(ReplicatedItem {
+            partition,
+            key,
+            value,
+            value_type,
+        });
+    }
+
+    Ok(items)
+}
+
+impl Encode for ReplicationMessage {
+    fn encode_into<W: Write>(&self, writer: &mut W) -> Result<(), EncodeError> {
+        match self {
+            Self::Partition { name, options } => {
+                writer.write_u8(Tag::Partition.into())?;
+
+                // NOTE: Partition names are at most 255 bytes long
+                #[allow(clippy::cast_possible_truncation)]
+                writer.write_u8(name.len() as u8)?;
+                writer.write_all(name.as_bytes())?;
+
+                options.encode_into(writer)?;
+            }
+            Self::SnapshotStart => {
+                writer.write_u8(Tag::SnapshotStart.into())?;
+            }
+            Self::SnapshotItems(items) => {
+                writer.write_u8(Tag::SnapshotItems.into())?;
+                encode_items(items, writer)?;
+            }
+            Self::SnapshotEnd { seqno } => {
+                writer.write_u8(Tag::SnapshotEnd.into())?;
+                writer.write_u64::<BigEndian>(*seqno)?;
+            }
+            Self::Batch { seqno, items } => {
+                writer.write_u8(Tag::Batch.into())?;
+                writer.write_u64::<BigEndian>(*seqno)?;
+                encode_items(items, writer)?;
+            }
+        }
+
+        Ok(())
+    }
+}
+
+impl Decode for ReplicationMessage {
+    fn decode_from<R: Read>(reader: &mut R) -> Result<Self, DecodeError> {
+        match reader.read_u8()?.try_into()? {
+            Tag::Partition => {
+                let name_len = reader.read_u8()?;
+                let mut name = vec![0; name_len.into()];
+                reader.read_exact(&mut name)?;
+                let name = std::str::from_utf8(&name)?;
+
+                let options = PartitionCreateOptions::decode_from(reader)?;
+
+                Ok(Self::Partition {
+                    name: name.into(),
+                    options,
+                })
+            }
+            Tag::SnapshotStart => Ok(Self::SnapshotStart),
+            Tag::SnapshotItems => Ok(Self::SnapshotItems(decode_items(reader)?)),
+            Tag::SnapshotEnd => Ok(Self::SnapshotEnd {
+                seqno: reader.read_u64::<BigEndian>()?,
+            }),
+            Tag::Batch => {
+                let seqno = reader.read_u64::<BigEndian>()?;
+                let items = decode_items(reader)?;
+                Ok(Self::Batch { seqno, items })
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use test_log::test;
+
+    #[test]
+    fn replication_message_roundtrip() -> crate::Result<()> {
+        let item = ReplicatedItem {
+            partition: "default".into(),
+            key: (*b"abc").into(),
+            value: (*b"def").into(),
+            value_type: ValueType::Value,
+        };
+
+        let messages = [
+            ReplicationMessage::Partition {
+                name: "default".into(),
+                options: PartitionCreateOptions::default(),
+            },
+            ReplicationMessage::SnapshotStart,
+            ReplicationMessage::SnapshotItems(vec![item.clone()]),
+            ReplicationMessage::SnapshotEnd { seqno: 5 },
+            ReplicationMessage::Batch {
+                seqno: 7,
+                items: vec![
+                    item,
+                    ReplicatedItem {
+                        partition: "other".into(),
+                        key: (*b"xyz").into(),
+                        value: UserValue::from(&[][..]),
+                        value_type: ValueType::Tombstone,
+                    },
+                ],
+            },
+        ];
+
+        let mut bytes = vec![];
+        for message in &messages {
+            message.encode_into(&mut bytes)?;
+        }
+
+        let mut reader = &bytes[..];
+        for message in &messages {
+            let decoded = ReplicationMessage::decode_from(&mut reader)?;
+            assert_eq!(format!("{message:?}"), format!("{decoded:?}"));
+        }
+        assert!(reader.is_empty());
+
+        Ok(())
+    }
+}
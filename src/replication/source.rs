@@ -0,0 +1,350 @@
+// Copyright (c) 2024-present, fjall-rs
+// This source code is licensed under both the Apache 2.0 and MIT License
+// (found in the LICENSE-* files in the repository)
+
+use super::{ReplicatedItem, ReplicationMessage, ReplicationSender};
+use crate::{
+    batch::PartitionKey,
+    encryption::Keys,
+    file::JOURNALS_FOLDER,
+    journal::{
+        batch_reader::{Batch, JournalBatchReader},
+        reader::JournalReader,
+        tail::{list_journals, open_journal, replay_journal},
+        writer::PersistMode,
+    },
+    Fs, HashMap, Keyspace,
+};
+use lsm_tree::SeqNo;
+use std::{
+    path::Path,
+    sync::{Arc, Mutex, MutexGuard, PoisonError},
+};
+
+/// Maximum amount of items per snapshot message
+const SNAPSHOT_CHUNK_ITEMS: usize = 1_000;
+
+/// Maximum size of keys and values per snapshot message
+const SNAPSHOT_CHUNK_BYTES: usize = 1_024 * 1_024;
+
+/// Opened journals, oldest first
+type Journals = Vec<(u64, JournalReader)>;
+
+/// Position after the last shipped batch, so shipping can resume
+/// without reading the journals from the start
+#[derive(Copy, Clone)]
+struct Cursor {
+    journal_id: u64,
+    pos: u64,
+    next_seqno: SeqNo,
+}
+
+/// Ships the committed batches of a keyspace to a replica, see [`ReplicaApplier`](crate::ReplicaApplier)
+///
+/// Batches are read from the journals, so they are shipped in seqno order,
+/// keeping the seqnos of the primary.
+///
+/// If journals that a replica still needs were already evicted,
+/// the replica is bootstrapped from a full snapshot instead.
+///
+/// Partition deletions are not replicated.
+pub struct ReplicationSource {
+    keyspace: Keyspace,
+    cursor: Mutex<Option<Cursor>>,
+}
+
+impl ReplicationSource {
+    /// Creates a replication source for the keyspace.
+    #[must_use]
+    pub fn new(keyspace: Keyspace) -> Self {
+        Self {
+            keyspace,
+            cursor: Mutex::new(None),
+        }
+    }
+
+    fn cursor(&self) -> MutexGuard<'_, Option<Cursor>> {
+        // NOTE: The cursor is only ever replaced as a whole, so it is valid even if a thread panicked
+        self.cursor.lock().unwrap_or_else(PoisonError::into_inner)
+    }
+
+    /// Sends all batches with a seqno of at least `from` that are currently committed,
+    /// returning the seqno to continue from.
+    ///
+    /// `from` is usually [`ReplicaApplier::next_seqno`](crate::ReplicaApplier::next_seqno).
+    ///
+    /// If any of those batches were evicted from the journals, a snapshot is sent instead,
+    /// which can only be applied to an empty replica.
+    /// After a restart of the primary, it is unknown which journals were evicted before,
+    /// so a snapshot may be sent although the replica is only slightly behind.
+    ///
+    /// # Errors
+    ///
+    /// Will return `Err` if an IO error occurs, or the sender fails.
+    pub fn ship<S: ReplicationSender>(&self, from: SeqNo, sender: &mut S) -> crate::Result<SeqNo> {
+        let instant = self.keyspace.instant();
+
+        // NOTE: Batches are written while holding the journal writer,
+        // so all batches below the instant are readable afterwards
+        self.keyspace.journal.flush(PersistMode::Buffer)?;
+
+        let folder = self.keyspace.config.path.join(JOURNALS_FOLDER);
+        let (journals, first_seqno) = open_journals(
+            &self.keyspace.config.fs,
+            &self.keyspace.config.encryption,
+            &folder,
+        )?;
+
+        // NOTE: Only reads a seqno, so a poisoned lock is fine
+        let evicted_seqno = self
+            .keyspace
+            .journal_manager
+            .read()
+            .unwrap_or_else(PoisonError::into_inner)
+            .evicted_seqno();
+
+        let oldest_id = journals.first().map(|(id, _)| *id).unwrap_or_default();
+
+        // NOTE: Journal IDs start at 0, so if the first journal is missing,
+        // batches older than the oldest journal were lost, unless the replica
+        // is known to have received all evicted batches
+        let needs_snapshot = oldest_id > 0
+            && from < first_seqno.unwrap_or(instant)
+            && evicted_seqno.map_or(true, |seqno| from <= seqno);
+
+        let mut announced = HashMap::default();
+
+        if needs_snapshot {
+            log::debug!("Replica at seqno {from} needs evicted batches, sending snapshot");
+
+            sender.send(ReplicationMessage::SnapshotStart)?;
+            self.send_snapshot(instant, &mut announced, sender)?;
+
+            let next = self.send_batches(journals, 0, None, &mut announced, sender)?;
+            let seqno = next.max(instant);
+
+            sender.send(ReplicationMessage::SnapshotEnd { seqno })?;
+
+            Ok(seqno)
+        } else {
+            let cursor = *self.cursor();
+
+            // NOTE: Skip the journals that were already shipped
+            let cursor = cursor.filter(|cursor| {
+                cursor.next_seqno == from && journals.iter().any(|(id, _)| *id == cursor.journal_id)
+            });
+
+            let journals = if let Some(cursor) = cursor {
+                journals
+                    .into_iter()
+                    .filter(|(id, _)| *id >= cursor.journal_id)
+                    .collect()
+            } else {
+                journals
+            };
+
+            self.send_batches(journals, from, cursor, &mut announced, sender)
+        }
+    }
+
+    fn send_snapshot<S: ReplicationSender>(
+        &self,
+        instant: SeqNo,
+        announced: &mut HashMap<PartitionKey, bool>,
+        sender: &mut S,
+    ) -> crate::Result<()> {
+        let partitions = self
+            .keyspace
+            .partitions
+            .read()
+            .unwrap_or_else(PoisonError::into_inner)
+            .values()
+            .cloned()
+            .collect::<Vec<_>>();
+
+        for partition in partitions {
+            if partition
+                .is_deleted
+                .load(std::sync::atomic::Ordering::Relaxed)
+            {
+                continue;
+            }
+
+            sender.send(ReplicationMessage::Partition {
+                name: partition.name.clone(),
+                options: partition.config.clone(),
+            })?;
+            announced.insert(partition.name.clone(), true);
+
+            let mut items = vec![];
+            let mut chunk_size = 0;
+
+            for kv in partition.snapshot_at(instant).iter() {
+                let (key, value) = kv?;
+
+                chunk_size += key.len() + value.len();
+                items.push(ReplicatedItem {
+                    partition: partition.name.clone(),
+                    key,
+                    value,
+                    value_type: lsm_tree::ValueType::Value,
+                });
+
+                if items.len() >= SNAPSHOT_CHUNK_ITEMS || chunk_size >= SNAPSHOT_CHUNK_BYTES {
+                    sender.send(ReplicationMessage::SnapshotItems(std::mem::take(
+                        &mut items,
+                    )))?;
+                    chunk_size = 0;
+                }
+            }
+
+            if !items.is_empty() {
+                sender.send(ReplicationMessage::SnapshotItems(items))?;
+            }
+        }
+
+        Ok(())
+    }
+
+    fn send_batches<S: ReplicationSender>(
+        &self,
+        journals: Journals,
+        from: SeqNo,
+        cursor: Option<Cursor>,
+        announced: &mut HashMap<PartitionKey, bool>,
+        sender: &mut S,
+    ) -> crate::Result<SeqNo> {
+        let mut next_seqno = from;
+        let mut last_cursor = None;
+
+        let journal_count = journals.len();
+
+        for (idx, (id, reader)) in journals.into_iter().enumerate() {
+            let is_active = idx + 1 == journal_count;
+
+            let pos = cursor
+                .filter(|cursor| cursor.journal_id == id)
+                .map_or(0, |cursor| cursor.pos);
+
+            let pos = replay_journal(reader, pos, is_active, |batch| {
+                if batch.seqno < from {
+                    return Ok(());
+                }
+
+                next_seqno = next_seqno.max(batch.seqno + 1);
+                self.send_batch(batch, announced, sender)
+            })?;
+
+            last_cursor = Some(Cursor {
+                journal_id: id,
+                pos,
+                next_seqno,
+            });
+        }
+
+        *self.cursor() = last_cursor;
+
+        Ok(next_seqno)
+    }
+
+    fn send_batch<S: ReplicationSender>(
+        &self,
+        batch: Batch,
+        announced: &mut HashMap<PartitionKey, bool>,
+        sender: &mut S,
+    ) -> crate::Result<()> {
+        let mut items = Vec::with_capacity(batch.items.len());
+
+        for item in batch.items {
+            let is_known = if let Some(is_known) = announced.get(&item.partition) {
+                *is_known
+            } else {
+                // NOTE: Items of deleted partitions are dropped, because
+                // the replica cannot create the partition anymore
+                let partition = self
+                    .keyspace
+                    .partitions
+                    .read()
+                    .unwrap_or_else(PoisonError::into_inner)
+                    .get(&item.partition)
+                    .filter(|partition| {
+                        !partition
+                            .is_deleted
+                            .load(std::sync::atomic::Ordering::Relaxed)
+                    })
+                    .cloned();
+
+                if let Some(partition) = &partition {
+                    sender.send(ReplicationMessage::Partition {
+                        name: partition.name.clone(),
+                        options: partition.config.clone(),
+                    })?;
+                }
+
+                announced.insert(item.partition.clone(), partition.is_some());
+                partition.is_some()
+            };
+
+            if is_known {
+                items.push(ReplicatedItem {
+                    partition: item.partition,
+                    key: item.key,
+                    value: item.value,
+                    value_type: item.value_type,
+                });
+            }
+        }
+
+        sender.send(ReplicationMessage::Batch {
+            seqno: batch.seqno,
+            items,
+        })
+    }
+}
+
+/// Opens all journals, so they can still be read if they are evicted while shipping,
+/// and reads the seqno of the first batch of the oldest journal.
+fn open_journals(
+    fs: &Arc<dyn Fs>,
+    keys: &Keys,
+    folder: &Path,
+) -> crate::Result<(Journals, Option<SeqNo>)> {
+    let mut attempt = 0;
+
+    loop {
+        attempt += 1;
+
+        match try_open_journals(fs, keys, folder) {
+            // NOTE: A journal was evicted after listing, so just list again
+            Err(crate::Error::Io(e)) if e.kind() == std::io::ErrorKind::NotFound && attempt < 3 => {
+                log::debug!("Journal was evicted while opening, retrying");
+            }
+            result => return result,
+        }
+    }
+}
+
+fn try_open_journals(
+    fs: &Arc<dyn Fs>,
+    keys: &Keys,
+    folder: &Path,
+) -> crate::Result<(Journals, Option<SeqNo>)> {
+    let ids = list_journals(fs, folder)?;
+
+    let first_seqno = if let Some(&id) = ids.first() {
+        match JournalBatchReader::new(open_journal(fs, keys, folder, id)?).next() {
+            Some(Ok(batch)) => Some(batch.seqno),
+            Some(Err(crate::Error::JournalRecovery(_))) | None => None,
+            Some(Err(e)) => return Err(e),
+        }
+    } else {
+        None
+    };
+
+    let journals = ids
+        .into_iter()
+        .map(|id| Ok((id, open_journal(fs, keys, folder, id)?)))
+        .collect::<crate::Result<Vec<_>>>()?;
+
+    Ok((journals, first_seqno))
+}
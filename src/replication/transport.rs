@@ -0,0 +1,84 @@
+// Copyright (c) 2024-present, fjall-rs
+// This source code is licensed under both the Apache 2.0 and MIT License
+// (found in the LICENSE-* files in the repository)
+
+use super::{ReplicationMessage, ReplicationReceiver, ReplicationSender};
+use lsm_tree::coding::{Decode, Encode};
+use std::{
+    io::{BufRead, BufReader, BufWriter, Read, Write},
+    sync::mpsc::{Receiver, Sender},
+};
+
+/// Creates an in-process transport, for example to replicate
+/// into another keyspace of the same process
+#[must_use]
+pub fn channel() -> (ChannelSender, ChannelReceiver) {
+    let (tx, rx) = std::sync::mpsc::channel();
+    (ChannelSender(tx), ChannelReceiver(rx))
+}
+
+/// Sending half of an in-process transport, see [`channel`]
+#[derive(Clone)]
+pub struct ChannelSender(Sender<ReplicationMessage>);
+
+impl ReplicationSender for ChannelSender {
+    fn send(&mut self, message: ReplicationMessage) -> crate::Result<()> {
+        self.0.send(message).map_err(|_| {
+            crate::Error::Io(std::io::Error::new(
+                std::io::ErrorKind::BrokenPipe,
+                "replica disconnected",
+            ))
+        })
+    }
+}
+
+/// Receiving half of an in-process transport, see [`channel`]
+pub struct ChannelReceiver(Receiver<ReplicationMessage>);
+
+impl ReplicationReceiver for ChannelReceiver {
+    fn recv(&mut self) -> crate::Result<Option<ReplicationMessage>> {
+        Ok(self.0.recv().ok())
+    }
+}
+
+/// Sends messages over a byte stream, for example a [`std::net::TcpStream`]
+///
+/// Every sent message is flushed to the stream.
+pub struct StreamSender<W: Write>(BufWriter<W>);
+
+impl<W: Write> StreamSender<W> {
+    /// Creates a new sender writing to the stream.
+    pub fn new(stream: W) -> Self {
+        Self(BufWriter::new(stream))
+    }
+}
+
+impl<W: Write> ReplicationSender for StreamSender<W> {
+    fn send(&mut self, message: ReplicationMessage) -> crate::Result<()> {
+        message.encode_into(&mut self.0)?;
+        self.0.flush()?;
+        Ok(())
+    }
+}
+
+/// Receives messages from a byte stream, for example a [`std::net::TcpStream`]
+///
+/// The end of the stream disconnects the receiver.
+pub struct StreamReceiver<R: Read>(BufReader<R>);
+
+impl<R: Read> StreamReceiver<R> {
+    /// Creates a new receiver reading from the stream.
+    pub fn new(stream: R) -> Self {
+        Self(BufReader::new(stream))
+    }
+}
+
+impl<R: Read> ReplicationReceiver for StreamReceiver<R> {
+    fn recv(&mut self) -> crate::Result<Option<ReplicationMessage>> {
+        if self.0.fill_buf()?.is_empty() {
+            return Ok(None);
+        }
+
+        Ok(Some(ReplicationMessage::decode_from(&mut self.0)?))
+    }
+}
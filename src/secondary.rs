@@ -0,0 +1,540 @@
+// Copyright (c) 2024-present, fjall-rs
+// This source code is licensed under both the Apache 2.0 and MIT License
+// (found in the LICENSE-* files in the repository)
+
+use crate::{
+    batch::PartitionKey,
+    file::{JOURNALS_FOLDER, LSM_MANIFEST_FILE, PARTITIONS_FOLDER, PARTITION_DELETED_MARKER},
+    journal::tail::{list_journals, open_journal, replay_journal},
+    lock::LockFile,
+    recovery::{open_partition_tree, read_partition_config},
+    HashMap, Keyspace, PartitionHandle,
+};
+use lsm_tree::{file::SEGMENTS_FOLDER, AbstractTree, AnyTree, InternalValue, Memtable, SeqNo};
+use std::{
+    fs::Metadata,
+    path::{Path, PathBuf},
+    sync::{Mutex, PoisonError, RwLock, RwLockReadGuard, RwLockWriteGuard},
+};
+
+/// Metadata files (everything but segments) of a partition, and their contents
+type PartitionManifest = Vec<(PathBuf, Vec<u8>)>;
+
+/// What the secondary has applied of its primary
+#[derive(Default)]
+struct State {
+    /// IDs of the primary's journals, as of the last rebuild
+    journals: Vec<u64>,
+
+    /// Position after the last applied batch in the newest journal
+    journal_pos: u64,
+
+    /// Manifests of the primary's partitions, as of the last rebuild
+    manifests: HashMap<PartitionKey, PartitionManifest>,
+}
+
+/// State of a secondary keyspace, see [`crate::Config::secondary`]
+pub struct Secondary {
+    /// Folder that mirrors the primary's partitions
+    path: PathBuf,
+
+    state: Mutex<State>,
+
+    #[allow(dead_code)]
+    lock: LockFile,
+}
+
+impl Secondary {
+    /// Locks the given folder and clears its mirrored partitions.
+    pub fn open(path: &Path) -> crate::Result<Self> {
+        std::fs::create_dir_all(path)?;
+
+        let lock = LockFile::acquire(path)?;
+
+        // NOTE: The primary may have changed arbitrarily since the mirror was last used
+        let partitions_folder = path.join(PARTITIONS_FOLDER);
+        if partitions_folder.try_exists()? {
+            std::fs::remove_dir_all(&partitions_folder)?;
+        }
+        std::fs::create_dir_all(&partitions_folder)?;
+
+        Ok(Self {
+            path: path.into(),
+            state: Mutex::default(),
+            lock,
+        })
+    }
+}
+
+/// Returns `true` if the path is inside a segments folder,
+/// so the file is never changed after being written.
+fn is_immutable(relative_path: &Path) -> bool {
+    relative_path
+        .parent()
+        .is_some_and(|parent| parent.ends_with(SEGMENTS_FOLDER))
+}
+
+/// Returns `true` if the file is temporary, and should not be mirrored.
+fn is_temporary(path: &Path) -> bool {
+    path.file_name()
+        .and_then(|name| name.to_str())
+        .is_some_and(|name| name.starts_with("tmp") || name.starts_with(".tmp"))
+}
+
+/// Lists all files in a folder, relative to the folder.
+fn list_files(folder: &Path) -> crate::Result<Vec<PathBuf>> {
+    list_folder(folder, |_| Ok(()))
+}
+
+/// Lists all files in a folder, relative to the folder,
+/// calling `on_dir` for every (nested) folder.
+fn list_folder<F: FnMut(&Path) -> crate::Result<()>>(
+    folder: &Path,
+    mut on_dir: F,
+) -> crate::Result<Vec<PathBuf>> {
+    let mut files = vec![];
+    let mut queue = vec![PathBuf::new()];
+
+    while let Some(relative_dir) = queue.pop() {
+        let dirents = match std::fs::read_dir(folder.join(&relative_dir)) {
+            Ok(dirents) => dirents,
+
+            // NOTE: The folder may have been deleted by the primary in the meantime
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => continue,
+
+            Err(e) => return Err(e.into()),
+        };
+
+        for dirent in dirents {
+            let dirent = dirent?;
+            let relative_path = relative_dir.join(dirent.file_name());
+
+            if dirent.file_type()?.is_dir() {
+                on_dir(&relative_path)?;
+                queue.push(relative_path);
+            } else if !is_temporary(&relative_path) {
+                files.push(relative_path);
+            }
+        }
+    }
+
+    files.sort();
+
+    Ok(files)
+}
+
+/// Reads the metadata files of a partition of the primary.
+fn read_manifest(partition_path: &Path) -> crate::Result<PartitionManifest> {
+    let mut manifest = vec![];
+
+    for relative_path in list_files(partition_path)? {
+        if is_immutable(&relative_path) || relative_path.as_os_str() == PARTITION_DELETED_MARKER {
+            continue;
+        }
+
+        match std::fs::read(partition_path.join(&relative_path)) {
+            Ok(bytes) => manifest.push((relative_path, bytes)),
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => {}
+            Err(e) => return Err(e.into()),
+        }
+    }
+
+    Ok(manifest)
+}
+
+/// Lists the partitions of the primary that are fully initialized and not deleted.
+fn list_partitions(partitions_folder: &Path) -> crate::Result<Vec<(PartitionKey, PathBuf)>> {
+    let mut partitions = vec![];
+
+    for dirent in std::fs::read_dir(partitions_folder)? {
+        let dirent = dirent?;
+        let partition_path = dirent.path();
+
+        if !dirent.file_type()?.is_dir()
+            || partition_path.join(PARTITION_DELETED_MARKER).try_exists()?
+            || !partition_path.join(LSM_MANIFEST_FILE).try_exists()?
+        {
+            continue;
+        }
+
+        let Some(name) = dirent.file_name().to_str().map(PartitionKey::from) else {
+            continue;
+        };
+
+        partitions.push((name, partition_path));
+    }
+
+    Ok(partitions)
+}
+
+#[cfg(unix)]
+fn is_same_file(a: &Metadata, b: &Metadata) -> bool {
+    use std::os::unix::fs::MetadataExt;
+
+    a.dev() == b.dev() && a.ino() == b.ino()
+}
+
+#[cfg(not(unix))]
+fn is_same_file(a: &Metadata, b: &Metadata) -> bool {
+    a.len() == b.len()
+}
+
+/// Hard links (or copies) the segment files of a partition into the mirror.
+fn link_segments(src: &Path, dst: &Path) -> crate::Result<()> {
+    let files = list_folder(src, |relative_path| {
+        std::fs::create_dir_all(dst.join(relative_path)).map_err(Into::into)
+    })?;
+
+    for relative_path in files {
+        if !is_immutable(&relative_path) {
+            continue;
+        }
+
+        let src_path = src.join(&relative_path);
+        let dst_path = dst.join(&relative_path);
+
+        // NOTE: The primary may have deleted the segment in the meantime,
+        // if it is still needed, opening the tree fails
+        let Ok(src_metadata) = std::fs::metadata(&src_path) else {
+            continue;
+        };
+
+        if let Ok(dst_metadata) = std::fs::metadata(&dst_path) {
+            if is_same_file(&src_metadata, &dst_metadata) {
+                continue;
+            }
+            std::fs::remove_file(&dst_path)?;
+        }
+
+        if let Err(e) = std::fs::hard_link(&src_path, &dst_path) {
+            log::trace!(
+                "Failed to hard link {}, copying instead: {e:?}",
+                src_path.display()
+            );
+
+            match std::fs::copy(&src_path, &dst_path) {
+                Ok(_) => {}
+                Err(e) if e.kind() == std::io::ErrorKind::NotFound => {}
+                Err(e) => return Err(e.into()),
+            }
+        }
+    }
+
+    Ok(())
+}
+
+/// Deletes mirrored segment files that were deleted by the primary.
+fn prune_segments(src: &Path, dst: &Path) -> crate::Result<()> {
+    for relative_path in list_files(dst)? {
+        if is_immutable(&relative_path) && !src.join(&relative_path).try_exists()? {
+            log::trace!("Pruning mirrored segment {}", relative_path.display());
+            std::fs::remove_file(dst.join(&relative_path))?;
+        }
+    }
+
+    Ok(())
+}
+
+/// Mirrors a partition of the primary, and opens its LSM-tree.
+fn mirror_partition(
+    keyspace: &Keyspace,
+    src: &Path,
+    dst: &Path,
+    manifest: &PartitionManifest,
+) -> crate::Result<AnyTree> {
+    // NOTE: Segments are linked before and after writing the manifest, so every
+    // segment referenced by the manifest is linked, unless it was deleted in the meantime
+    link_segments(src, dst)?;
+
+    for (relative_path, bytes) in manifest {
+        let path = dst.join(relative_path);
+
+        if let Some(parent) = path.parent() {
+            std::fs::create_dir_all(parent)?;
+        }
+        std::fs::write(path, bytes)?;
+    }
+
+    link_segments(src, dst)?;
+
+    let config = read_partition_config(keyspace.config.fs.as_ref(), dst)?;
+    open_partition_tree(keyspace, dst.into(), &config)
+}
+
+// NOTE: The locked values are only read, swapped or replaced as a whole,
+// so they stay consistent even if a thread panicked while holding the lock
+fn read<T>(lock: &RwLock<T>) -> RwLockReadGuard<'_, T> {
+    lock.read().unwrap_or_else(PoisonError::into_inner)
+}
+
+fn write<T>(lock: &RwLock<T>) -> RwLockWriteGuard<'_, T> {
+    lock.write().unwrap_or_else(PoisonError::into_inner)
+}
+
+/// Swaps the contents of two LSM-trees of the same type.
+///
+/// Returns `false` if the types do not match.
+fn swap_trees(a: &AnyTree, b: &AnyTree) -> bool {
+    match (a, b) {
+        (AnyTree::Standard(a), AnyTree::Standard(b)) => {
+            std::mem::swap(&mut *write(&a.levels), &mut *write(&b.levels));
+            true
+        }
+        (AnyTree::Blob(a), AnyTree::Blob(b)) => {
+            std::mem::swap(&mut *write(&a.index.levels), &mut *write(&b.index.levels));
+            std::mem::swap(
+                &mut *write(&a.blobs.manifest.segments),
+                &mut *write(&b.blobs.manifest.segments),
+            );
+            true
+        }
+        _ => false,
+    }
+}
+
+/// Returns `true` if the error may be caused by the primary
+/// deleting files while they are being read.
+fn is_not_found(error: &crate::Error) -> bool {
+    match error {
+        crate::Error::Io(e) | crate::Error::Storage(lsm_tree::Error::Io(e)) => {
+            e.kind() == std::io::ErrorKind::NotFound
+        }
+        _ => false,
+    }
+}
+
+/// Mirrors all changed partitions and rebuilds all memtables from the journals.
+#[allow(clippy::too_many_lines)]
+fn rebuild(
+    keyspace: &Keyspace,
+    secondary: &Secondary,
+    state: &mut State,
+    journals: Vec<u64>,
+    manifests: HashMap<PartitionKey, (PathBuf, PartitionManifest)>,
+) -> crate::Result<()> {
+    let journals_folder = keyspace.config.path.join(JOURNALS_FOLDER);
+    let mirror_folder = secondary.path.join(PARTITIONS_FOLDER);
+
+    log::debug!("Rebuilding secondary from journals {journals:?}");
+
+    let mut trees = HashMap::default();
+
+    for (name, (src, manifest)) in &manifests {
+        if state.manifests.get(name) == Some(manifest) {
+            continue;
+        }
+
+        log::debug!("Mirroring changed partition {name:?}");
+
+        let dst = mirror_folder.join(&**name);
+        let tree = mirror_partition(keyspace, src, &dst, manifest)?;
+        let config = read_partition_config(keyspace.config.fs.as_ref(), &dst)?;
+
+        trees.insert(name.clone(), (tree, config));
+    }
+
+    let partitions = read(&keyspace.partitions).clone();
+
+    let memtables: HashMap<PartitionKey, (Memtable, Option<SeqNo>)> = manifests
+        .keys()
+        .filter_map(|name| {
+            let persisted_seqno = match trees.get(name) {
+                Some((tree, _)) => tree.get_highest_persisted_seqno(),
+                None => partitions.get(name)?.tree.get_highest_persisted_seqno(),
+            };
+
+            Some((name.clone(), (Memtable::default(), persisted_seqno)))
+        })
+        .collect();
+
+    let mut journal_pos = 0;
+    let mut max_seqno = None;
+
+    for (idx, &id) in journals.iter().enumerate() {
+        let is_active = idx + 1 == journals.len();
+
+        let reader = open_journal(
+            &keyspace.config.fs,
+            &keyspace.config.encryption,
+            &journals_folder,
+            id,
+        )?;
+
+        let pos = replay_journal(reader, 0, is_active, |batch| {
+            for item in batch.items {
+                if let Some((memtable, persisted_seqno)) = memtables.get(&item.partition) {
+                    // NOTE: Skip items that are already flushed into segments
+                    if persisted_seqno.map_or(true, |persisted| batch.seqno > persisted) {
+                        memtable.insert(InternalValue::from_components(
+                            item.key,
+                            item.value,
+                            batch.seqno,
+                            item.value_type,
+                        ));
+                    }
+                }
+            }
+
+            max_seqno = max_seqno.max(Some(batch.seqno));
+            Ok(())
+        })?;
+
+        if is_active {
+            journal_pos = pos;
+        }
+    }
+
+    {
+        let mut partitions = write(&keyspace.partitions);
+
+        partitions.retain(|name, _| manifests.contains_key(name));
+
+        for (name, (tree, config)) in trees {
+            let is_swapped = partitions
+                .get(&name)
+                .is_some_and(|handle| swap_trees(&handle.tree, &tree));
+
+            if !is_swapped {
+                let handle = PartitionHandle::from_keyspace(keyspace, tree, name.clone(), config);
+                partitions.insert(name, handle);
+            }
+        }
+
+        for (name, (memtable, _)) in memtables {
+            if let Some(handle) = partitions.get(&name) {
+                handle.tree.set_active_memtable(memtable);
+            }
+        }
+    }
+
+    if let Some(seqno) = max_seqno {
+        keyspace
+            .seqno
+            .fetch_max(seqno + 1, std::sync::atomic::Ordering::AcqRel);
+    }
+
+    for name in state.manifests.keys() {
+        let dst = mirror_folder.join(&**name);
+
+        if !manifests.contains_key(name) {
+            log::debug!("Removing mirror of deleted partition {name:?}");
+            std::fs::remove_dir_all(dst)?;
+        }
+    }
+
+    for (name, (src, _)) in &manifests {
+        prune_segments(src, &mirror_folder.join(&**name))?;
+    }
+
+    state.journals = journals;
+    state.journal_pos = journal_pos;
+    state.manifests = manifests
+        .into_iter()
+        .map(|(name, (_, manifest))| (name, manifest))
+        .collect();
+
+    Ok(())
+}
+
+/// Applies new batches of the active journal.
+fn tail(keyspace: &Keyspace, state: &mut State) -> crate::Result<()> {
+    let Some(&id) = state.journals.last() else {
+        return Ok(());
+    };
+
+    let journals_folder = keyspace.config.path.join(JOURNALS_FOLDER);
+    let reader = open_journal(
+        &keyspace.config.fs,
+        &keyspace.config.encryption,
+        &journals_folder,
+        id,
+    )?;
+
+    let partitions = read(&keyspace.partitions).clone();
+    let mut max_seqno = None;
+
+    state.journal_pos = replay_journal(reader, state.journal_pos, true, |batch| {
+        for item in batch.items {
+            if let Some(handle) = partitions.get(&item.partition) {
+                handle
+                    .tree
+                    .lock_active_memtable()
+                    .insert(InternalValue::from_components(
+                        item.key,
+                        item.value,
+                        batch.seqno,
+                        item.value_type,
+                    ));
+            }
+        }
+
+        max_seqno = max_seqno.max(Some(batch.seqno));
+        Ok(())
+    })?;
+
+    if let Some(seqno) = max_seqno {
+        keyspace
+            .seqno
+            .fetch_max(seqno + 1, std::sync::atomic::Ordering::AcqRel);
+    }
+
+    Ok(())
+}
+
+fn try_catch_up(
+    keyspace: &Keyspace,
+    secondary: &Secondary,
+    state: &mut State,
+) -> crate::Result<()> {
+    // IMPORTANT: List journals first, so no journal
+    // can be evicted before its data is in the manifests
+    let journals = list_journals(
+        &keyspace.config.fs,
+        &keyspace.config.path.join(JOURNALS_FOLDER),
+    )?;
+
+    let mut manifests = HashMap::default();
+
+    for (name, path) in list_partitions(&keyspace.config.path.join(PARTITIONS_FOLDER))? {
+        let manifest = read_manifest(&path)?;
+        manifests.insert(name, (path, manifest));
+    }
+
+    let is_changed = journals != state.journals
+        || manifests.len() != state.manifests.len()
+        || manifests
+            .iter()
+            .any(|(name, (_, manifest))| state.manifests.get(name) != Some(manifest));
+
+    if is_changed {
+        rebuild(keyspace, secondary, state, journals, manifests)
+    } else {
+        tail(keyspace, state)
+    }
+}
+
+/// Brings a secondary keyspace up to date with its primary.
+pub fn catch_up(keyspace: &Keyspace) -> crate::Result<()> {
+    const MAX_ATTEMPTS: usize = 3;
+
+    let Some(secondary) = &keyspace.secondary else {
+        return Ok(());
+    };
+
+    // NOTE: The state is only written after a catch-up succeeded, so it stays consistent even if a thread panicked
+    let mut state = secondary
+        .state
+        .lock()
+        .unwrap_or_else(PoisonError::into_inner);
+
+    for attempt in 1..=MAX_ATTEMPTS {
+        match try_catch_up(keyspace, secondary, &mut state) {
+            Err(e) if attempt < MAX_ATTEMPTS && is_not_found(&e) => {
+                log::debug!("Primary deleted files during catch-up, retrying: {e:?}");
+            }
+            result => return result,
+        }
+    }
+
+    Ok(())
+}
@@ -0,0 +1,159 @@
+// Copyright (c) 2024-present, fjall-rs
+// This source code is licensed under both the Apache 2.0 and MIT License
+// (found in the LICENSE-* files in the repository)
+
+//! Inspects the files of a partition's LSM-tree without opening it.
+//!
+//! Opening a tree deletes unfinished (`tmp_*`) and unreferenced segment files,
+//! so code that must not change the keyspace folder (read-only mode, repair)
+//! checks the folder with these helpers instead.
+
+use crate::HashSet;
+use byteorder::{BigEndian, ReadBytesExt};
+use lsm_tree::{
+    file::{BLOBS_FOLDER, LEVELS_MANIFEST_FILE, MAGIC_BYTES, SEGMENTS_FOLDER},
+    DecodeError, SegmentId,
+};
+use std::{
+    io::{Cursor, Read},
+    path::{Path, PathBuf},
+};
+
+/// Manifest of the value log of a blob tree
+const VLOG_MANIFEST_FILE: &str = "vlog_manifest";
+
+/// Loads the segment IDs of a tree's level manifest.
+pub fn load_segment_ids(tree_path: &Path) -> crate::Result<HashSet<SegmentId>> {
+    let mut reader = Cursor::new(std::fs::read(tree_path.join(LEVELS_MANIFEST_FILE))?);
+
+    let mut magic = [0u8; MAGIC_BYTES.len()];
+    reader.read_exact(&mut magic)?;
+
+    if magic != MAGIC_BYTES {
+        return Err(crate::Error::Decode(DecodeError::InvalidHeader(
+            "LevelManifest",
+        )));
+    }
+
+    let mut ids = HashSet::default();
+
+    let level_count = reader.read_u8()?;
+
+    for _ in 0..level_count {
+        let segment_count = reader.read_u32::<BigEndian>()?;
+
+        for _ in 0..segment_count {
+            ids.insert(reader.read_u64::<BigEndian>()?);
+        }
+    }
+
+    Ok(ids)
+}
+
+/// Loads the blob file IDs of a blob tree's value log manifest.
+pub fn load_blob_file_ids(tree_path: &Path) -> crate::Result<HashSet<SegmentId>> {
+    let manifest_path = tree_path.join(BLOBS_FOLDER).join(VLOG_MANIFEST_FILE);
+    let mut reader = Cursor::new(std::fs::read(manifest_path)?);
+
+    let count = reader.read_u64::<BigEndian>()?;
+
+    let mut ids = HashSet::default();
+
+    for _ in 0..count {
+        ids.insert(reader.read_u64::<BigEndian>()?);
+    }
+
+    Ok(ids)
+}
+
+/// Lists the files in a segments folder, with their segment ID if the file is named by an ID.
+pub fn list_segment_files(folder: &Path) -> crate::Result<Vec<(Option<SegmentId>, PathBuf)>> {
+    if !folder.try_exists()? {
+        return Ok(vec![]);
+    }
+
+    let mut files = vec![];
+
+    for dirent in std::fs::read_dir(folder)? {
+        let path = dirent?.path();
+
+        let id = path
+            .file_name()
+            .and_then(|name| name.to_str())
+            .and_then(|name| name.parse::<SegmentId>().ok());
+
+        files.push((id, path));
+    }
+
+    Ok(files)
+}
+
+/// Files of a tree compared to its manifests
+#[derive(Debug, Default)]
+pub struct TreeFiles {
+    /// Segments and blob files that are in a manifest, but have no file
+    pub missing: Vec<PathBuf>,
+
+    /// Files that opening the tree would delete
+    ///
+    /// These are unfinished `tmp_*` segments, files that are not in a manifest
+    /// and files that are not named by an ID.
+    pub unreferenced: Vec<PathBuf>,
+}
+
+impl TreeFiles {
+    /// Compares the files of a tree to its manifests, without changing anything.
+    pub fn scan(tree_path: &Path) -> crate::Result<Self> {
+        let mut files = Self::default();
+
+        let unreferenced = files.compare(
+            &tree_path.join(SEGMENTS_FOLDER),
+            &load_segment_ids(tree_path)?,
+        )?;
+        files.unreferenced.extend(unreferenced);
+
+        if tree_path.join(BLOBS_FOLDER).try_exists()? {
+            let unreferenced = files.compare(
+                &tree_path.join(BLOBS_FOLDER).join(SEGMENTS_FOLDER),
+                &load_blob_file_ids(tree_path)?,
+            )?;
+
+            // NOTE: The value log only deletes unregistered folders (legacy blob file format),
+            // unregistered blob files are kept until GC
+            files
+                .unreferenced
+                .extend(unreferenced.into_iter().filter(|path| path.is_dir()));
+        }
+
+        files.missing.sort();
+        files.unreferenced.sort();
+
+        Ok(files)
+    }
+
+    /// Adds missing files, and returns files that are not in the manifest.
+    fn compare(
+        &mut self,
+        folder: &Path,
+        manifest_ids: &HashSet<SegmentId>,
+    ) -> crate::Result<Vec<PathBuf>> {
+        let files = list_segment_files(folder)?;
+
+        let file_ids = files
+            .iter()
+            .filter_map(|(id, _)| *id)
+            .collect::<HashSet<_>>();
+
+        self.missing.extend(
+            manifest_ids
+                .difference(&file_ids)
+                .map(|id| folder.join(id.to_string())),
+        );
+
+        Ok(files
+            .into_iter()
+            .filter(|(id, _)| !id.is_some_and(|id| manifest_ids.contains(&id)))
+            .map(|(_, path)| path)
+            .collect())
+    }
+}
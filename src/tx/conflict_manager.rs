@@ -1,6 +1,9 @@
-use crate::batch::PartitionKey;
+use crate::{
+    batch::PartitionKey,
+    tx::write::ssi::{Conflict, ConflictingRead},
+};
 use core::ops::Bound;
-use lsm_tree::Slice;
+use lsm_tree::{SeqNo, Slice};
 use std::{
     collections::{BTreeMap, BTreeSet},
     ops::RangeBounds,
@@ -16,13 +19,75 @@ enum Read {
     All,
 }
 
+impl From<Read> for ConflictingRead {
+    fn from(value: Read) -> Self {
+        match value {
+            Read::Single(key) => Self::Key(key),
+            Read::Range { start, end } => Self::Range { start, end },
+            Read::All => Self::Full,
+        }
+    }
+}
+
+/// Position in the read & write sets of a [`ConflictManager`]
+#[derive(Clone, Debug, Default)]
+pub struct ConflictMark {
+    reads: BTreeMap<PartitionKey, usize>,
+    conflict_keys_len: usize,
+}
+
 #[derive(Default, Debug)]
 pub struct ConflictManager {
     reads: BTreeMap<PartitionKey, Vec<Read>>,
     conflict_keys: BTreeMap<PartitionKey, BTreeSet<Slice>>,
+
+    /// Conflict keys in order of insertion, only tracked after the first savepoint
+    conflict_log: Option<Vec<(PartitionKey, Slice)>>,
 }
 
 impl ConflictManager {
+    /// Marks the current position, so it can be rolled back to later.
+    pub fn savepoint(&mut self) -> ConflictMark {
+        let conflict_log = self.conflict_log.get_or_insert_with(Vec::new);
+
+        ConflictMark {
+            reads: self
+                .reads
+                .iter()
+                .map(|(partition, reads)| (partition.clone(), reads.len()))
+                .collect(),
+            conflict_keys_len: conflict_log.len(),
+        }
+    }
+
+    /// Forgets all reads and writes that were recorded after the mark was created.
+    pub fn rollback_to(&mut self, mark: &ConflictMark) {
+        self.reads
+            .retain(|partition, reads| match mark.reads.get(partition) {
+                Some(len) => {
+                    reads.truncate(*len);
+                    true
+                }
+                None => false,
+            });
+
+        if let Some(conflict_log) = &mut self.conflict_log {
+            while conflict_log.len() > mark.conflict_keys_len {
+                let Some((partition, key)) = conflict_log.pop() else {
+                    break;
+                };
+
+                if let Some(tbl) = self.conflict_keys.get_mut(&partition) {
+                    tbl.remove(&key);
+
+                    if tbl.is_empty() {
+                        self.conflict_keys.remove(&partition);
+                    }
+                }
+            }
+        }
+    }
+
     fn push_read(&mut self, partition: &PartitionKey, read: Read) {
         if let Some(tbl) = self.reads.get_mut(partition) {
             tbl.push(read);
@@ -36,13 +101,19 @@ impl ConflictManager {
     }
 
     pub fn mark_conflict(&mut self, partition: &PartitionKey, key: &[u8]) {
-        if let Some(tbl) = self.conflict_keys.get_mut(partition) {
-            tbl.insert(key.into());
+        let inserted = if let Some(tbl) = self.conflict_keys.get_mut(partition) {
+            tbl.insert(key.into())
         } else {
             self.conflict_keys
                 .entry(partition.clone())
                 .or_default()
-                .insert(key.into());
+                .insert(key.into())
+        };
+
+        if inserted {
+            if let Some(conflict_log) = &mut self.conflict_log {
+                conflict_log.push((partition.clone(), key.into()));
+            }
         }
     }
 
@@ -68,116 +139,36 @@ impl ConflictManager {
         self.push_read(partition, read);
     }
 
-    #[allow(clippy::too_many_lines)]
-    pub fn has_conflict(&self, other: &Self) -> bool {
+    /// Returns the first read of this transaction that conflicts
+    /// with the writes of the other (committed) transaction, if any.
+    pub fn find_conflict(&self, other: &Self, other_seqno: SeqNo) -> Option<Conflict> {
         if self.reads.is_empty() {
-            return false;
+            return None;
         }
 
         for (partition, keys) in &self.reads {
             if let Some(other_conflict_keys) = other.conflict_keys.get(partition) {
                 for ro in keys {
-                    match ro {
-                        Read::Single(k) => {
-                            if other_conflict_keys.contains(k) {
-                                return true;
-                            }
-                        }
-                        Read::Range { start, end } => match (start, end) {
-                            (Bound::Included(start), Bound::Included(end)) => {
-                                if other_conflict_keys
-                                    .range::<Slice, _>((
-                                        Bound::Included(start),
-                                        Bound::Included(end),
-                                    ))
-                                    .next()
-                                    .is_some()
-                                {
-                                    return true;
-                                }
-                            }
-                            (Bound::Included(start), Bound::Excluded(end)) => {
-                                if other_conflict_keys
-                                    .range::<Slice, _>((
-                                        Bound::Included(start),
-                                        Bound::Excluded(end),
-                                    ))
-                                    .next()
-                                    .is_some()
-                                {
-                                    return true;
-                                }
-                            }
-                            (Bound::Included(start), Bound::Unbounded) => {
-                                if other_conflict_keys
-                                    .range::<Slice, _>((Bound::Included(start), Bound::Unbounded))
-                                    .next()
-                                    .is_some()
-                                {
-                                    return true;
-                                }
-                            }
-                            (Bound::Excluded(start), Bound::Included(end)) => {
-                                if other_conflict_keys
-                                    .range::<Slice, _>((
-                                        Bound::Excluded(start),
-                                        Bound::Included(end),
-                                    ))
-                                    .next()
-                                    .is_some()
-                                {
-                                    return true;
-                                }
-                            }
-                            (Bound::Excluded(start), Bound::Excluded(end)) => {
-                                if other_conflict_keys
-                                    .range::<Slice, _>((
-                                        Bound::Excluded(start),
-                                        Bound::Excluded(end),
-                                    ))
-                                    .next()
-                                    .is_some()
-                                {
-                                    return true;
-                                }
-                            }
-                            (Bound::Excluded(start), Bound::Unbounded) => {
-                                if other_conflict_keys
-                                    .range::<Slice, _>((Bound::Excluded(start), Bound::Unbounded))
-                                    .next()
-                                    .is_some()
-                                {
-                                    return true;
-                                }
-                            }
-                            (Bound::Unbounded, Bound::Included(end)) => {
-                                let range = ..=end;
-                                for write in other_conflict_keys {
-                                    if range.contains(&write) {
-                                        return true;
-                                    }
-                                }
-                            }
-                            (Bound::Unbounded, Bound::Excluded(end)) => {
-                                let range = ..end;
-                                for write in other_conflict_keys {
-                                    if range.contains(&write) {
-                                        return true;
-                                    }
-                                }
-                            }
-                            (Bound::Unbounded, Bound::Unbounded) => unreachable!(),
-                        },
-                        Read::All => {
-                            if !other_conflict_keys.is_empty() {
-                                return true;
-                            }
-                        }
+                    let written_key = match ro {
+                        Read::Single(k) => other_conflict_keys.get(k),
+                        Read::Range { start, end } => other_conflict_keys
+                            .range::<Slice, _>((start.as_ref(), end.as_ref()))
+                            .next(),
+                        Read::All => other_conflict_keys.first(),
+                    };
+
+                    if let Some(written_key) = written_key {
+                        return Some(Conflict {
+                            partition: partition.clone(),
+                            read: ro.clone().into(),
+                            written_key: written_key.clone(),
+                            seqno: other_seqno,
+                        });
                     }
                 }
             }
         }
 
-        false
+        None
     }
 }
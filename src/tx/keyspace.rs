@@ -4,13 +4,22 @@
 
 use super::{read_tx::ReadTransaction, write_tx::WriteTransaction};
 use crate::{
-    batch::PartitionKey, snapshot_nonce::SnapshotNonce, Config, Keyspace, PartitionCreateOptions,
-    PersistMode, TxPartitionHandle,
+    batch::PartitionKey, snapshot_nonce::SnapshotNonce, CloseOptions, Config, Keyspace,
+    PartitionCreateOptions, PersistMode, TxPartitionHandle, VerifyOptions, VerifyReport,
 };
-use std::sync::{Arc, Mutex};
+use std::sync::Arc;
+
+#[cfg(any(feature = "single_writer_tx", feature = "ssi_tx"))]
+use std::sync::Mutex;
+
+#[cfg(feature = "pessimistic_tx")]
+use super::lock_manager::LockManager;
 
 #[cfg(feature = "ssi_tx")]
-use super::oracle::Oracle;
+use super::{
+    oracle::Oracle,
+    retry::{Retrier, RetryPolicy, TransactionError},
+};
 
 /// Transactional keyspace
 #[derive(Clone)]
@@ -24,6 +33,9 @@ pub struct TransactionalKeyspace {
 
     #[cfg(feature = "single_writer_tx")]
     single_writer_lock: Arc<Mutex<()>>,
+
+    #[cfg(feature = "pessimistic_tx")]
+    pub(super) lock_manager: Arc<LockManager>,
 }
 
 /// Alias for [`TransactionalKeyspace`]
@@ -51,6 +63,27 @@ impl TxKeyspace {
         write_tx
     }
 
+    /// Starts a new writeable transaction.
+    ///
+    /// Keys are locked when they are written or read using
+    /// [`WriteTransaction::get_for_update`], and released on commit or rollback.
+    #[cfg(feature = "pessimistic_tx")]
+    #[must_use]
+    pub fn write_tx(&self) -> WriteTransaction {
+        let instant = self.inner.instant();
+
+        let mut write_tx = WriteTransaction::new(
+            self.clone(),
+            SnapshotNonce::new(instant, self.inner.snapshot_tracker.clone()),
+        );
+
+        if !self.inner.config.manual_journal_persist {
+            write_tx = write_tx.durability(Some(PersistMode::Buffer));
+        }
+
+        write_tx
+    }
+
     /// Starts a new writeable transaction.
     ///
     /// # Errors
@@ -79,6 +112,92 @@ impl TxKeyspace {
         Ok(write_tx)
     }
 
+    /// Runs the closure inside a write transaction and commits it,
+    /// retrying on conflict using the default [`RetryPolicy`].
+    ///
+    /// See [`TxKeyspace::transaction_with`].
+    ///
+    /// # Errors
+    ///
+    /// Will return `Err` if the closure fails, an IO error occurs,
+    /// or the transaction could not be committed after retrying.
+    #[cfg(feature = "ssi_tx")]
+    pub fn transaction<T, E, F: FnMut(&mut WriteTransaction) -> Result<T, E>>(
+        &self,
+        f: F,
+    ) -> Result<T, TransactionError<E>> {
+        self.transaction_with(&RetryPolicy::default(), f)
+    }
+
+    /// Runs the closure inside a write transaction and commits it.
+    ///
+    /// If the transaction conflicts, the closure is run again in a new transaction
+    /// (after some backoff), until the retry policy's attempts or deadline are exhausted.
+    ///
+    /// If the closure returns an error, the transaction is rolled back and not retried.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use fjall::{Config, PartitionCreateOptions, RetryPolicy, TransactionError};
+    /// # use std::time::Duration;
+    /// #
+    /// # let folder = tempfile::tempdir()?;
+    /// # let keyspace = Config::new(folder).open_transactional()?;
+    /// # let partition = keyspace.open_partition("default", PartitionCreateOptions::default())?;
+    /// let policy = RetryPolicy::default()
+    ///     .max_attempts(5)
+    ///     .deadline(Some(Duration::from_secs(1)));
+    ///
+    /// let prev = keyspace.transaction_with(&policy, |tx| {
+    ///     let prev = tx.get(&partition, "counter")?;
+    ///     tx.insert(&partition, "counter", "1");
+    ///     Ok::<_, fjall::Error>(prev)
+    /// });
+    ///
+    /// match prev {
+    ///     Ok(prev) => assert!(prev.is_none()),
+    ///     Err(TransactionError::RetriesExhausted { .. } | TransactionError::DeadlineExceeded { .. }) => {
+    ///         // too much contention
+    ///     }
+    ///     Err(e) => panic!("{e}"),
+    /// }
+    /// #
+    /// # Ok::<(), fjall::Error>(())
+    /// ```
+    ///
+    /// # Errors
+    ///
+    /// Will return `Err` if the closure fails, an IO error occurs,
+    /// or the transaction could not be committed after retrying.
+    #[cfg(feature = "ssi_tx")]
+    pub fn transaction_with<T, E, F: FnMut(&mut WriteTransaction) -> Result<T, E>>(
+        &self,
+        policy: &RetryPolicy,
+        mut f: F,
+    ) -> Result<T, TransactionError<E>> {
+        let mut retrier = Retrier::new(policy);
+
+        loop {
+            retrier.attempt();
+
+            let mut tx = self.write_tx()?;
+
+            let value = match f(&mut tx) {
+                Ok(value) => value,
+                Err(e) => {
+                    tx.rollback();
+                    return Err(TransactionError::User(e));
+                }
+            };
+
+            match tx.commit()? {
+                Ok(()) => return Ok(value),
+                Err(conflict) => retrier.backoff(conflict)?,
+            }
+        }
+    }
+
     /// Starts a new read-only transaction.
     #[must_use]
     pub fn read_tx(&self) -> ReadTransaction {
@@ -185,6 +304,49 @@ impl TxKeyspace {
         self.inner.disk_space()
     }
 
+    /// Verifies the integrity of the keyspace, see [`Keyspace::verify`].
+    ///
+    /// # Errors
+    ///
+    /// Will return `Err` if an IO error occurs.
+    pub fn verify(&self, options: VerifyOptions) -> crate::Result<VerifyReport> {
+        self.inner.verify(options)
+    }
+
+    /// Brings a secondary keyspace up to date with its primary, see [`Keyspace::catch_up`].
+    ///
+    /// # Errors
+    ///
+    /// Will return `Err` if an IO error occurs.
+    pub fn catch_up(&self) -> crate::Result<()> {
+        self.inner.catch_up()
+    }
+
+    /// Closes the keyspace, see [`Keyspace::close`].
+    ///
+    /// # Errors
+    ///
+    /// Will return `Err` if an IO error occurs, or the timeout is exceeded.
+    pub fn close(self, options: CloseOptions) -> crate::Result<()> {
+        self.inner.close(options)
+    }
+
+    /// Pauses background flushes and compactions, see [`Keyspace::pause_background_work`].
+    pub fn pause_background_work(&self) {
+        self.inner.pause_background_work();
+    }
+
+    /// Resumes background flushes and compactions, see [`Keyspace::resume_background_work`].
+    pub fn resume_background_work(&self) {
+        self.inner.resume_background_work();
+    }
+
+    /// Returns `true` if background work is paused.
+    #[must_use]
+    pub fn is_background_work_paused(&self) -> bool {
+        self.inner.is_background_work_paused()
+    }
+
     /// Opens a keyspace in the given directory.
     ///
     /// # Errors
@@ -201,6 +363,8 @@ impl TxKeyspace {
                 seqno: inner.seqno.clone(),
                 snapshot_tracker: inner.snapshot_tracker.clone(),
             }),
+            #[cfg(feature = "pessimistic_tx")]
+            lock_manager: Arc::new(LockManager::new(inner.config.tx_lock_timeout)),
             inner,
             #[cfg(feature = "single_writer_tx")]
             single_writer_lock: Default::default(),
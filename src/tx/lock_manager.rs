@@ -0,0 +1,261 @@
+// Copyright (c) 2024-present, fjall-rs
+// This source code is licensed under both the Apache 2.0 and MIT License
+// (found in the LICENSE-* files in the repository)
+
+use crate::{batch::PartitionKey, HashMap, LockError};
+use lsm_tree::UserKey;
+use std::{
+    collections::{BTreeMap, HashSet},
+    ops::Bound,
+    sync::{Arc, Condvar, Mutex, MutexGuard},
+    time::{Duration, Instant},
+};
+
+pub type TxId = u64;
+
+struct RangeLock {
+    owner: TxId,
+    start: Bound<UserKey>,
+    end: Bound<UserKey>,
+}
+
+#[derive(Default)]
+struct PartitionLocks {
+    keys: BTreeMap<UserKey, TxId>,
+    ranges: Vec<RangeLock>,
+}
+
+#[derive(Default)]
+struct State {
+    partitions: HashMap<PartitionKey, PartitionLocks>,
+
+    /// Wait-for graph, a waiting transaction waits for all owners of conflicting locks
+    waits_for: HashMap<TxId, Vec<TxId>>,
+}
+
+impl State {
+    /// Returns the transactions that are blocking the lock.
+    fn blockers(
+        &self,
+        tx_id: TxId,
+        partition: &PartitionKey,
+        start: Bound<&UserKey>,
+        end: Bound<&UserKey>,
+    ) -> Vec<TxId> {
+        let Some(locks) = self.partitions.get(partition) else {
+            return vec![];
+        };
+
+        if is_empty_range(start, end) {
+            return vec![];
+        }
+
+        let mut owners = locks
+            .keys
+            .range::<UserKey, _>((start, end))
+            .map(|(_, owner)| *owner)
+            .chain(
+                locks
+                    .ranges
+                    .iter()
+                    .filter(|range| overlaps(start, end, range.start.as_ref(), range.end.as_ref()))
+                    .map(|range| range.owner),
+            )
+            .filter(|owner| *owner != tx_id)
+            .collect::<Vec<_>>();
+
+        owners.sort_unstable();
+        owners.dedup();
+        owners
+    }
+
+    /// Returns `true` if waiting for `owners` would close a cycle in the wait-for graph.
+    fn would_deadlock(&self, tx_id: TxId, owners: &[TxId]) -> bool {
+        let mut visited = HashSet::new();
+        let mut stack = owners.to_vec();
+
+        while let Some(owner) = stack.pop() {
+            if owner == tx_id {
+                return true;
+            }
+
+            if !visited.insert(owner) {
+                continue;
+            }
+
+            if let Some(next) = self.waits_for.get(&owner) {
+                stack.extend(next);
+            }
+        }
+
+        false
+    }
+}
+
+fn is_empty_range(start: Bound<&UserKey>, end: Bound<&UserKey>) -> bool {
+    match (start, end) {
+        (Bound::Included(s), Bound::Included(e)) => s > e,
+        (Bound::Included(s) | Bound::Excluded(s), Bound::Excluded(e))
+        | (Bound::Excluded(s), Bound::Included(e)) => s >= e,
+        _ => false,
+    }
+}
+
+/// Returns `true` if the range [start, ...) does not begin after the end bound.
+fn starts_before(start: Bound<&UserKey>, end: Bound<&UserKey>) -> bool {
+    match (start, end) {
+        (Bound::Unbounded, _) | (_, Bound::Unbounded) => true,
+        (Bound::Included(s), Bound::Included(e)) => s <= e,
+        (Bound::Included(s) | Bound::Excluded(s), Bound::Excluded(e))
+        | (Bound::Excluded(s), Bound::Included(e)) => s < e,
+    }
+}
+
+fn overlaps(
+    a_start: Bound<&UserKey>,
+    a_end: Bound<&UserKey>,
+    b_start: Bound<&UserKey>,
+    b_end: Bound<&UserKey>,
+) -> bool {
+    starts_before(a_start, b_end) && starts_before(b_start, a_end)
+}
+
+/// Grants exclusive key & range locks to pessimistic transactions
+pub struct LockManager {
+    state: Mutex<State>,
+    released: Condvar,
+    timeout: Duration,
+}
+
+impl LockManager {
+    pub fn new(timeout: Duration) -> Self {
+        Self {
+            state: Mutex::default(),
+            released: Condvar::new(),
+            timeout,
+        }
+    }
+
+    /// Locks a single key, waiting until it is available.
+    pub fn lock_key(
+        &self,
+        tx_id: TxId,
+        partition: &PartitionKey,
+        key: &UserKey,
+    ) -> Result<(), LockError> {
+        let mut state = self.lock(tx_id, partition, Bound::Included(key), Bound::Included(key))?;
+
+        state
+            .partitions
+            .entry(partition.clone())
+            .or_default()
+            .keys
+            .insert(key.clone(), tx_id);
+
+        drop(state);
+
+        Ok(())
+    }
+
+    /// Locks a range of keys, waiting until it is available.
+    ///
+    /// Keys inside the range cannot be locked by other transactions,
+    /// which prevents them from inserting into (or removing from) the range.
+    pub fn lock_range(
+        &self,
+        tx_id: TxId,
+        partition: &PartitionKey,
+        start: Bound<UserKey>,
+        end: Bound<UserKey>,
+    ) -> Result<(), LockError> {
+        let mut state = self.lock(tx_id, partition, start.as_ref(), end.as_ref())?;
+
+        state
+            .partitions
+            .entry(partition.clone())
+            .or_default()
+            .ranges
+            .push(RangeLock {
+                owner: tx_id,
+                start,
+                end,
+            });
+
+        drop(state);
+
+        Ok(())
+    }
+
+    /// Waits until no other transaction holds a lock that overlaps the range.
+    ///
+    /// Returns the state guard, so the caller can register its lock atomically.
+    fn lock(
+        &self,
+        tx_id: TxId,
+        partition: &PartitionKey,
+        start: Bound<&UserKey>,
+        end: Bound<&UserKey>,
+    ) -> Result<MutexGuard<'_, State>, LockError> {
+        let deadline = Instant::now() + self.timeout;
+
+        let mut state = self.state.lock().expect("lock is poisoned");
+
+        loop {
+            let owners = state.blockers(tx_id, partition, start, end);
+
+            if owners.is_empty() {
+                state.waits_for.remove(&tx_id);
+                return Ok(state);
+            }
+
+            if state.would_deadlock(tx_id, &owners) {
+                log::debug!("tx {tx_id}: deadlock detected while waiting for txs {owners:?}");
+                state.waits_for.remove(&tx_id);
+                return Err(LockError::Deadlock);
+            }
+
+            let remaining = deadline.saturating_duration_since(Instant::now());
+
+            if remaining.is_zero() {
+                state.waits_for.remove(&tx_id);
+                return Err(LockError::Timeout);
+            }
+
+            state.waits_for.insert(tx_id, owners);
+
+            state = self
+                .released
+                .wait_timeout(state, remaining)
+                .expect("lock is poisoned")
+                .0;
+        }
+    }
+
+    /// Releases all locks of the transaction.
+    pub fn release(&self, tx_id: TxId) {
+        let mut state = self.state.lock().expect("lock is poisoned");
+
+        state.partitions.retain(|_, locks| {
+            locks.keys.retain(|_, owner| *owner != tx_id);
+            locks.ranges.retain(|range| range.owner != tx_id);
+            !locks.keys.is_empty() || !locks.ranges.is_empty()
+        });
+        state.waits_for.remove(&tx_id);
+
+        drop(state);
+
+        self.released.notify_all();
+    }
+}
+
+/// Releases all locks of a transaction when dropped
+pub struct TxLocks {
+    pub(crate) tx_id: TxId,
+    pub(crate) manager: Arc<LockManager>,
+}
+
+impl Drop for TxLocks {
+    fn drop(&mut self) {
+        self.manager.release(self.tx_id);
+    }
+}
@@ -8,6 +8,8 @@ pub mod partition;
 #[allow(clippy::module_name_repetitions)]
 pub mod read_tx;
 
+pub mod savepoint;
+
 #[allow(clippy::module_name_repetitions)]
 pub mod write_tx;
 
@@ -17,4 +19,10 @@ mod conflict_manager;
 #[cfg(feature = "ssi_tx")]
 mod oracle;
 
+#[cfg(feature = "pessimistic_tx")]
+pub mod lock_manager;
+
+#[cfg(feature = "ssi_tx")]
+pub mod retry;
+
 pub(crate) mod write;
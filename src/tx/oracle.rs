@@ -2,7 +2,8 @@ use crate::snapshot_tracker::SnapshotTracker;
 use crate::Instant;
 
 use super::conflict_manager::ConflictManager;
-use lsm_tree::SequenceNumberCounter;
+use super::write::ssi::Conflict;
+use lsm_tree::{SeqNo, SequenceNumberCounter};
 use std::collections::BTreeMap;
 use std::fmt;
 use std::sync::{Mutex, MutexGuard, PoisonError};
@@ -10,18 +11,18 @@ use std::sync::{Mutex, MutexGuard, PoisonError};
 pub enum CommitOutcome<E> {
     Ok,
     Aborted(E),
-    Conflicted,
+    Conflicted(Conflict),
 }
 
 pub struct Oracle {
-    pub(super) write_serialize_lock: Mutex<BTreeMap<u64, ConflictManager>>,
+    pub(super) write_serialize_lock: Mutex<BTreeMap<u64, (SeqNo, ConflictManager)>>,
     pub(super) seqno: SequenceNumberCounter,
     pub(super) snapshot_tracker: SnapshotTracker,
 }
 
 impl Oracle {
     #[allow(clippy::nursery)]
-    pub(super) fn with_commit<E, F: FnOnce() -> Result<(), E>>(
+    pub(super) fn with_commit<E, F: FnOnce() -> Result<Option<SeqNo>, E>>(
         &self,
         instant: Instant,
         conflict_checker: ConflictManager,
@@ -38,33 +39,35 @@ impl Oracle {
         // This change assumes linearizability. Lack of linearizability could
         // cause the read ts of a new txn to be lower than the commit ts of
         // a txn before it.
-        let conflicted =
-            committed_txns
-                .range((instant + 1)..)
-                .any(|(_ts, other_conflict_checker)| {
-                    conflict_checker.has_conflict(other_conflict_checker)
-                });
+        let conflict = committed_txns.range((instant + 1)..).find_map(
+            |(_ts, (other_seqno, other_conflict_checker))| {
+                conflict_checker.find_conflict(other_conflict_checker, *other_seqno)
+            },
+        );
 
         self.snapshot_tracker.close(instant);
         let safe_to_gc = self.snapshot_tracker.get_seqno_safe_to_gc();
         committed_txns.retain(|ts, _| *ts > safe_to_gc);
 
-        if conflicted {
-            return Ok(CommitOutcome::Conflicted);
+        if let Some(conflict) = conflict {
+            return Ok(CommitOutcome::Conflicted(conflict));
         }
 
-        if let Err(e) = f() {
-            return Ok(CommitOutcome::Aborted(e));
+        match f() {
+            // NOTE: A transaction without writes cannot cause conflicts
+            Ok(None) => {}
+            Ok(Some(seqno)) => {
+                committed_txns.insert(self.seqno.get(), (seqno, conflict_checker));
+            }
+            Err(e) => return Ok(CommitOutcome::Aborted(e)),
         }
 
-        committed_txns.insert(self.seqno.get(), conflict_checker);
-
         Ok(CommitOutcome::Ok)
     }
 
     pub(super) fn write_serialize_lock(
         &self,
-    ) -> crate::Result<MutexGuard<BTreeMap<u64, ConflictManager>>> {
+    ) -> crate::Result<MutexGuard<BTreeMap<u64, (SeqNo, ConflictManager)>>> {
         self.write_serialize_lock
             .lock()
             .map_err(|_| crate::Error::Poisoned)
@@ -63,6 +63,9 @@ impl TransactionalPartitionHandle {
     /// # Errors
     ///
     /// Will return `Err` if an IO error occurs.
+    ///
+    /// With `pessimistic_tx`, will return [`Error::Lock`](crate::Error::Lock) if another transaction
+    /// holds the key's lock for longer than `Config::tx_lock_timeout`.
     pub fn take<K: AsRef<[u8]>>(&self, key: K) -> crate::Result<Option<UserValue>> {
         self.fetch_update(key, |_| None)
     }
@@ -120,6 +123,9 @@ impl TransactionalPartitionHandle {
     /// # Errors
     ///
     /// Will return `Err` if an IO error occurs.
+    ///
+    /// With `pessimistic_tx`, will return [`Error::Lock`](crate::Error::Lock) if another transaction
+    /// holds the key's lock for longer than `Config::tx_lock_timeout`.
     #[allow(unused_mut)]
     pub fn fetch_update<K: AsRef<[u8]>, F: FnMut(Option<&UserValue>) -> Option<UserValue>>(
         &self,
@@ -144,6 +150,16 @@ impl TransactionalPartitionHandle {
                 return Ok(prev);
             }
         }
+
+        // NOTE: A single lock cannot deadlock, so a lock error is a timeout, which is returned
+        #[cfg(feature = "pessimistic_tx")]
+        {
+            let mut tx = self.keyspace.write_tx();
+            let prev = tx.fetch_update(self, key, f)?;
+            tx.commit()?;
+
+            Ok(prev)
+        }
     }
 
     /// Atomically updates an item and returns the new value.
@@ -199,6 +215,9 @@ impl TransactionalPartitionHandle {
     /// # Errors
     ///
     /// Will return `Err` if an IO error occurs.
+    ///
+    /// With `pessimistic_tx`, will return [`Error::Lock`](crate::Error::Lock) if another transaction
+    /// holds the key's lock for longer than `Config::tx_lock_timeout`.
     #[allow(unused_mut)]
     pub fn update_fetch<K: AsRef<[u8]>, F: FnMut(Option<&UserValue>) -> Option<UserValue>>(
         &self,
@@ -222,6 +241,16 @@ impl TransactionalPartitionHandle {
                 return Ok(updated);
             }
         }
+
+        // NOTE: A single lock cannot deadlock, so a lock error is a timeout, which is returned
+        #[cfg(feature = "pessimistic_tx")]
+        {
+            let mut tx = self.keyspace.write_tx();
+            let updated = tx.update_fetch(self, key, f)?;
+            tx.commit()?;
+
+            Ok(updated)
+        }
     }
 
     /// Inserts a key-value pair into the partition.
@@ -251,6 +280,9 @@ impl TransactionalPartitionHandle {
     /// # Errors
     ///
     /// Will return `Err` if an IO error occurs.
+    ///
+    /// With `pessimistic_tx`, will return [`Error::Lock`](crate::Error::Lock) if another transaction
+    /// holds the key's lock for longer than `Config::tx_lock_timeout`.
     pub fn insert<K: AsRef<[u8]>, V: AsRef<[u8]>>(&self, key: K, value: V) -> crate::Result<()> {
         #[cfg(feature = "single_writer_tx")]
         {
@@ -267,6 +299,13 @@ impl TransactionalPartitionHandle {
             tx.commit()?.expect("blind insert should not conflict ever");
             Ok(())
         }
+
+        #[cfg(feature = "pessimistic_tx")]
+        {
+            let mut tx = self.keyspace.write_tx();
+            tx.insert(self, key, value)?;
+            tx.commit()
+        }
     }
 
     /// Removes an item from the partition.
@@ -296,6 +335,9 @@ impl TransactionalPartitionHandle {
     /// # Errors
     ///
     /// Will return `Err` if an IO error occurs.
+    ///
+    /// With `pessimistic_tx`, will return [`Error::Lock`](crate::Error::Lock) if another transaction
+    /// holds the key's lock for longer than `Config::tx_lock_timeout`.
     pub fn remove<K: AsRef<[u8]>>(&self, key: K) -> crate::Result<()> {
         #[cfg(feature = "single_writer_tx")]
         {
@@ -312,6 +354,13 @@ impl TransactionalPartitionHandle {
             tx.commit()?.expect("blind remove should not conflict ever");
             Ok(())
         }
+
+        #[cfg(feature = "pessimistic_tx")]
+        {
+            let mut tx = self.keyspace.write_tx();
+            tx.remove(self, key)?;
+            tx.commit()
+        }
     }
 
     /// Retrieves an item from the partition.
@@ -0,0 +1,183 @@
+// Copyright (c) 2024-present, fjall-rs
+// This source code is licensed under both the Apache 2.0 and MIT License
+// (found in the LICENSE-* files in the repository)
+
+use super::write::ssi::Conflict;
+use std::{
+    hash::{BuildHasher, Hasher},
+    time::{Duration, Instant},
+};
+
+/// Controls how [`TxKeyspace::transaction`](crate::TxKeyspace::transaction)
+/// retries transactions that failed because of a [`Conflict`]
+#[derive(Clone, Debug)]
+pub struct RetryPolicy {
+    pub(crate) max_attempts: u32,
+    pub(crate) min_backoff: Duration,
+    pub(crate) max_backoff: Duration,
+    pub(crate) deadline: Option<Duration>,
+}
+
+impl Default for RetryPolicy {
+    fn default() -> Self {
+        Self {
+            max_attempts: 10,
+            min_backoff: Duration::from_millis(1),
+            max_backoff: Duration::from_millis(100),
+            deadline: None,
+        }
+    }
+}
+
+impl RetryPolicy {
+    /// Sets the maximum amount of times the transaction is run.
+    ///
+    /// Default = 10
+    ///
+    /// # Panics
+    ///
+    /// Panics if `n` is 0.
+    #[must_use]
+    pub fn max_attempts(mut self, n: u32) -> Self {
+        assert!(n > 0, "max_attempts must be at least 1");
+
+        self.max_attempts = n;
+        self
+    }
+
+    /// Sets the backoff range.
+    ///
+    /// The backoff starts at `min` and doubles after every conflict, up to `max`.
+    /// The actual time slept is chosen randomly between 0 and the current backoff,
+    /// so concurrently conflicting transactions do not retry in lockstep.
+    ///
+    /// Default = 1ms..100ms
+    #[must_use]
+    pub fn backoff(mut self, min: Duration, max: Duration) -> Self {
+        self.min_backoff = min;
+        self.max_backoff = max.max(min);
+        self
+    }
+
+    /// Sets the maximum total time spent trying to commit the transaction.
+    ///
+    /// No retry is started if its backoff would end after the deadline.
+    ///
+    /// Default = None
+    #[must_use]
+    pub fn deadline(mut self, deadline: Option<Duration>) -> Self {
+        self.deadline = deadline;
+        self
+    }
+
+    /// Returns the randomized backoff for the given (0-based) retry.
+    pub(crate) fn jittered_backoff(&self, retry: u32) -> Duration {
+        let backoff = self
+            .min_backoff
+            .saturating_mul(1 << retry.min(16))
+            .min(self.max_backoff);
+
+        // NOTE: Every RandomState is randomly seeded, which is plenty of randomness for jitter
+        let random = std::collections::hash_map::RandomState::new()
+            .build_hasher()
+            .finish();
+
+        #[allow(clippy::cast_possible_truncation)]
+        let nanos = backoff.as_nanos() as u64;
+
+        Duration::from_nanos(random % nanos.saturating_add(1))
+    }
+}
+
+/// Error returned by [`TxKeyspace::transaction`](crate::TxKeyspace::transaction)
+#[derive(Debug)]
+pub enum TransactionError<E> {
+    /// Error inside the storage engine
+    Storage(crate::Error),
+
+    /// The closure returned an error, so the transaction was rolled back
+    User(E),
+
+    /// The transaction conflicted on every attempt
+    RetriesExhausted {
+        /// Amount of times the transaction was run
+        attempts: u32,
+
+        /// Conflict of the last attempt
+        conflict: Conflict,
+    },
+
+    /// The deadline passed before the transaction could be committed
+    DeadlineExceeded {
+        /// Amount of times the transaction was run
+        attempts: u32,
+
+        /// Conflict of the last attempt
+        conflict: Conflict,
+    },
+}
+
+impl<E: std::fmt::Debug> std::fmt::Display for TransactionError<E> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "TransactionError: {self:?}")
+    }
+}
+
+impl<E: std::fmt::Debug> std::error::Error for TransactionError<E> {}
+
+impl<E> From<crate::Error> for TransactionError<E> {
+    fn from(value: crate::Error) -> Self {
+        Self::Storage(value)
+    }
+}
+
+/// Tracks attempts & time spent while retrying a transaction
+pub struct Retrier<'a> {
+    policy: &'a RetryPolicy,
+    start: Instant,
+    attempts: u32,
+}
+
+impl<'a> Retrier<'a> {
+    pub fn new(policy: &'a RetryPolicy) -> Self {
+        Self {
+            policy,
+            start: Instant::now(),
+            attempts: 0,
+        }
+    }
+
+    /// Registers a new attempt.
+    pub fn attempt(&mut self) {
+        self.attempts += 1;
+    }
+
+    /// Sleeps before the next attempt, or returns an error if no attempts are left.
+    pub fn backoff<E>(&self, conflict: Conflict) -> Result<(), TransactionError<E>> {
+        if self.attempts >= self.policy.max_attempts {
+            return Err(TransactionError::RetriesExhausted {
+                attempts: self.attempts,
+                conflict,
+            });
+        }
+
+        let sleep = self.policy.jittered_backoff(self.attempts - 1);
+
+        if let Some(deadline) = self.policy.deadline {
+            if self.start.elapsed() + sleep >= deadline {
+                return Err(TransactionError::DeadlineExceeded {
+                    attempts: self.attempts,
+                    conflict,
+                });
+            }
+        }
+
+        log::trace!(
+            "tx: conflict on attempt {}, retrying in {sleep:?}",
+            self.attempts
+        );
+        std::thread::sleep(sleep);
+
+        Ok(())
+    }
+}
@@ -0,0 +1,23 @@
+// Copyright (c) 2024-present, fjall-rs
+// This source code is licensed under both the Apache 2.0 and MIT License
+// (found in the LICENSE-* files in the repository)
+
+#[cfg(feature = "ssi_tx")]
+use super::conflict_manager::ConflictMark;
+
+/// A point inside a write transaction that can be rolled back to
+///
+/// Created by `WriteTransaction::savepoint`; use `WriteTransaction::rollback_to`
+/// to undo all writes that were done after the savepoint was created.
+///
+/// A savepoint is only valid for the transaction that created it.
+#[derive(Clone, Debug)]
+#[must_use]
+pub struct Savepoint {
+    pub(crate) tx_id: u64,
+    pub(crate) id: u64,
+    pub(crate) undo_len: usize,
+
+    #[cfg(feature = "ssi_tx")]
+    pub(crate) reads: ConflictMark,
+}
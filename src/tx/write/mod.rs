@@ -8,13 +8,29 @@ pub mod single_writer;
 #[cfg(feature = "ssi_tx")]
 pub mod ssi;
 
+#[cfg(feature = "pessimistic_tx")]
+pub mod pessimistic;
+
 use crate::{
     batch::{item::Item, PartitionKey},
     snapshot_nonce::SnapshotNonce,
+    tx::savepoint::Savepoint,
     Batch, HashMap, PersistMode, TxKeyspace, TxPartitionHandle,
 };
 use lsm_tree::{AbstractTree, InternalValue, KvPair, Memtable, SeqNo, UserKey, UserValue};
-use std::{ops::RangeBounds, sync::Arc};
+use std::{
+    ops::RangeBounds,
+    sync::{
+        atomic::{AtomicU64, Ordering},
+        Arc,
+    },
+};
+
+/// Source of transaction IDs
+///
+/// IDs are unique in the process, not only in a keyspace, so savepoints
+/// of transactions of other keyspaces are detected as well.
+static NEXT_TX_ID: AtomicU64 = AtomicU64::new(0);
 
 fn ignore_tombstone_value(item: InternalValue) -> Option<InternalValue> {
     if item.is_tombstone() {
@@ -24,6 +40,13 @@ fn ignore_tombstone_value(item: InternalValue) -> Option<InternalValue> {
     }
 }
 
+/// Previous transaction-local version of a written key
+struct Undo {
+    partition: PartitionKey,
+    key: UserKey,
+    prev: Option<InternalValue>,
+}
+
 /// A single-writer (serialized) cross-partition transaction
 ///
 /// Use [`WriteTransaction::commit`] to commit changes to the keyspace.
@@ -35,15 +58,29 @@ pub(super) struct BaseTransaction {
     memtables: HashMap<PartitionKey, Arc<Memtable>>,
 
     nonce: SnapshotNonce,
+
+    /// Undo entries of all writes, only tracked after the first savepoint
+    undo_log: Option<Vec<Undo>>,
+
+    /// ID of this transaction, stored in its savepoints and used for locking
+    tx_id: u64,
+
+    /// IDs of savepoints that can still be rolled back to
+    savepoints: Vec<u64>,
+    next_savepoint_id: u64,
 }
 
 impl BaseTransaction {
     pub(crate) fn new(keyspace: TxKeyspace, nonce: SnapshotNonce) -> Self {
         Self {
+            tx_id: NEXT_TX_ID.fetch_add(1, Ordering::Relaxed),
             keyspace,
             memtables: HashMap::default(),
             nonce,
             durability: None,
+            undo_log: None,
+            savepoints: Vec::new(),
+            next_savepoint_id: 0,
         }
     }
 
@@ -330,6 +367,8 @@ impl BaseTransaction {
         key: K,
         value: V,
     ) {
+        self.track_undo(partition, key.as_ref());
+
         // TODO: PERF: slow??
         self.memtables
             .entry(partition.inner.name.clone())
@@ -353,6 +392,8 @@ impl BaseTransaction {
     ///
     /// Will return `Err` if an IO error occurs.
     pub(super) fn remove<K: AsRef<[u8]>>(&mut self, partition: &TxPartitionHandle, key: K) {
+        self.track_undo(partition, key.as_ref());
+
         // TODO: PERF: slow??
         self.memtables
             .entry(partition.inner.name.clone())
@@ -365,15 +406,95 @@ impl BaseTransaction {
             ));
     }
 
-    /// Commits the transaction.
+    /// Remembers the transaction-local version of a key before it is overwritten,
+    /// if there are any savepoints.
+    fn track_undo(&mut self, partition: &TxPartitionHandle, key: &[u8]) {
+        if let Some(undo_log) = &mut self.undo_log {
+            let prev = self
+                .memtables
+                .get(&partition.inner.name)
+                .and_then(|memtable| memtable.get(key, None));
+
+            undo_log.push(Undo {
+                partition: partition.inner.name.clone(),
+                key: key.into(),
+                prev,
+            });
+        }
+    }
+
+    /// Creates a savepoint that can later be rolled back to.
+    pub(super) fn savepoint(&mut self) -> Savepoint {
+        let id = self.next_savepoint_id;
+        self.next_savepoint_id += 1;
+        self.savepoints.push(id);
+
+        Savepoint {
+            tx_id: self.tx_id,
+            id,
+            undo_len: self.undo_log.get_or_insert_with(Vec::new).len(),
+
+            #[cfg(feature = "ssi_tx")]
+            reads: crate::tx::conflict_manager::ConflictMark::default(),
+        }
+    }
+
+    /// Undoes all writes that were done after the savepoint was created.
+    ///
+    /// Savepoints that were created after the given one are released.
+    ///
+    /// # Errors
+    ///
+    /// Will return [`Error::InvalidSavepoint`](crate::Error::InvalidSavepoint) if the savepoint
+    /// was released or belongs to another transaction, in which case nothing is undone.
+    pub(super) fn rollback_to(&mut self, savepoint: &Savepoint) -> crate::Result<()> {
+        if savepoint.tx_id != self.tx_id {
+            return Err(crate::Error::InvalidSavepoint);
+        }
+
+        let Some(idx) = self.savepoints.iter().position(|&id| id == savepoint.id) else {
+            return Err(crate::Error::InvalidSavepoint);
+        };
+        self.savepoints.truncate(idx + 1);
+
+        let Some(undo_log) = &mut self.undo_log else {
+            return Ok(());
+        };
+
+        while undo_log.len() > savepoint.undo_len {
+            let Some(undo) = undo_log.pop() else {
+                break;
+            };
+
+            let Some(memtable) = self.memtables.get(&undo.partition) else {
+                continue;
+            };
+
+            // NOTE: The approximate size of the memtable is not decreased,
+            // which is fine, because it is never read for transaction-local memtables
+            if let Some(prev) = undo.prev {
+                memtable.insert(prev);
+            } else if let Some(item) = memtable.get(&undo.key, None) {
+                memtable.items.remove(&item.key);
+            }
+
+            if memtable.is_empty() {
+                self.memtables.remove(&undo.partition);
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Commits the transaction, returning the sequence number of the written batch.
     ///
     /// # Errors
     ///
     /// Will return `Err` if an IO error occurs.
-    pub(super) fn commit(self) -> crate::Result<()> {
+    pub(super) fn commit(self) -> crate::Result<Option<SeqNo>> {
         // skip all the logic if no keys were written to
         if self.memtables.is_empty() {
-            return Ok(());
+            return Ok(None);
         }
 
         let mut batch = Batch::new(self.keyspace.inner).durability(self.durability);
@@ -392,9 +513,9 @@ impl BaseTransaction {
         // TODO: instead of using batch, write batch::commit as a generic function that takes
         // a impl Iterator<BatchItem>
         // that way, we don't have to move the memtable(s) into the batch first to commit
-        batch.commit()?;
+        let seqno = batch.commit_with_seqno(None)?;
 
-        Ok(())
+        Ok(Some(seqno))
     }
 
     /// More explicit alternative to dropping the transaction
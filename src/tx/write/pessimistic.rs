@@ -0,0 +1,521 @@
+use super::BaseTransaction;
+use crate::{
+    snapshot_nonce::SnapshotNonce,
+    tx::{lock_manager::TxLocks, savepoint::Savepoint},
+    PersistMode, TxKeyspace, TxPartitionHandle,
+};
+use lsm_tree::{KvPair, UserKey, UserValue};
+use std::ops::{Bound, RangeBounds};
+
+/// A pessimistic (key-locking) cross-partition transaction
+///
+/// Keys are exclusively locked when they are written, or read using
+/// [`WriteTransaction::get_for_update`]. Ranges can be locked using
+/// [`WriteTransaction::lock_range`], which prevents other transactions
+/// from writing into them.
+///
+/// Whenever a lock is acquired, the transaction's snapshot is moved
+/// to the latest state of the keyspace, so locked keys are always read fresh.
+/// This means that reads are **not** repeatable: reading the same unlocked key
+/// before and after acquiring a lock may return different values.
+/// To read a consistent state of multiple keys, lock all of them
+/// (or a range containing them) before reading.
+///
+/// If a lock cannot be acquired, [`Error::Lock`](crate::Error::Lock) is returned,
+/// and the transaction should be rolled back and retried.
+///
+/// All locks are released when the transaction is committed or rolled back.
+///
+/// Use [`WriteTransaction::commit`] to commit changes to the partition(s).
+///
+/// Drop the transaction to rollback changes.
+pub struct WriteTransaction {
+    inner: BaseTransaction,
+    locks: TxLocks,
+}
+
+impl WriteTransaction {
+    pub(crate) fn new(keyspace: TxKeyspace, nonce: SnapshotNonce) -> Self {
+        let manager = keyspace.lock_manager.clone();
+        let inner = BaseTransaction::new(keyspace, nonce);
+
+        Self {
+            locks: TxLocks {
+                tx_id: inner.tx_id,
+                manager,
+            },
+            inner,
+        }
+    }
+
+    /// Sets the durability level.
+    #[must_use]
+    pub fn durability(mut self, mode: Option<PersistMode>) -> Self {
+        self.inner = self.inner.durability(mode);
+        self
+    }
+
+    /// Moves the transaction's snapshot to the latest state of the keyspace.
+    ///
+    /// Called after a lock was acquired: everything that was written by the previous
+    /// lock holder has been committed before the lock was released, so it is visible now.
+    fn refresh_snapshot(&mut self) {
+        let keyspace = &self.inner.keyspace.inner;
+        let instant = keyspace.instant();
+
+        if instant != self.inner.nonce.instant {
+            self.inner.nonce = SnapshotNonce::new(instant, keyspace.snapshot_tracker.clone());
+        }
+    }
+
+    /// Exclusively locks a key until the transaction ends.
+    ///
+    /// Waits until the key is not locked by any other transaction,
+    /// then moves the transaction's snapshot to the latest state of the keyspace.
+    ///
+    /// # Errors
+    ///
+    /// Will return [`Error::Lock`](crate::Error::Lock) with [`LockError::Deadlock`](crate::LockError::Deadlock)
+    /// if waiting would cause a deadlock, or with [`LockError::Timeout`](crate::LockError::Timeout)
+    /// if the lock could not be acquired in time.
+    pub fn lock<K: AsRef<[u8]>>(
+        &mut self,
+        partition: &TxPartitionHandle,
+        key: K,
+    ) -> crate::Result<()> {
+        self.locks.manager.lock_key(
+            self.locks.tx_id,
+            &partition.inner.name,
+            &key.as_ref().into(),
+        )?;
+        self.refresh_snapshot();
+        Ok(())
+    }
+
+    /// Exclusively locks a range of keys until the transaction ends.
+    ///
+    /// Other transactions cannot lock, and thus not write, any key inside the range.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use fjall::{Config, Keyspace, PartitionCreateOptions};
+    /// #
+    /// # let folder = tempfile::tempdir()?;
+    /// # let keyspace = Config::new(folder).open_transactional()?;
+    /// # let partition = keyspace.open_partition("default", PartitionCreateOptions::default())?;
+    /// partition.insert("a", "abc")?;
+    ///
+    /// let mut tx = keyspace.write_tx();
+    /// tx.lock_range(&partition, "a".."c")?;
+    ///
+    /// // Nobody can add or remove keys in [a, c) until the transaction ends
+    /// assert_eq!(1, tx.range(&partition, "a".."c").count());
+    /// tx.commit()?;
+    /// #
+    /// # Ok::<(), fjall::Error>(())
+    /// ```
+    ///
+    /// # Errors
+    ///
+    /// Will return [`Error::Lock`](crate::Error::Lock) with [`LockError::Deadlock`](crate::LockError::Deadlock)
+    /// if waiting would cause a deadlock, or with [`LockError::Timeout`](crate::LockError::Timeout)
+    /// if the lock could not be acquired in time.
+    pub fn lock_range<K: AsRef<[u8]>, R: RangeBounds<K>>(
+        &mut self,
+        partition: &TxPartitionHandle,
+        range: R,
+    ) -> crate::Result<()> {
+        // TODO: Bound::map 1.77
+        let start: Bound<UserKey> = match range.start_bound() {
+            Bound::Included(k) => Bound::Included(k.as_ref().into()),
+            Bound::Excluded(k) => Bound::Excluded(k.as_ref().into()),
+            Bound::Unbounded => Bound::Unbounded,
+        };
+        // TODO: Bound::map 1.77
+        let end: Bound<UserKey> = match range.end_bound() {
+            Bound::Included(k) => Bound::Included(k.as_ref().into()),
+            Bound::Excluded(k) => Bound::Excluded(k.as_ref().into()),
+            Bound::Unbounded => Bound::Unbounded,
+        };
+
+        self.locks
+            .manager
+            .lock_range(self.locks.tx_id, &partition.inner.name, start, end)?;
+        self.refresh_snapshot();
+        Ok(())
+    }
+
+    /// Exclusively locks all keys with the given prefix until the transaction ends.
+    ///
+    /// # Errors
+    ///
+    /// Will return [`Error::Lock`](crate::Error::Lock) with [`LockError::Deadlock`](crate::LockError::Deadlock)
+    /// if waiting would cause a deadlock, or with [`LockError::Timeout`](crate::LockError::Timeout)
+    /// if the lock could not be acquired in time.
+    pub fn lock_prefix<K: AsRef<[u8]>>(
+        &mut self,
+        partition: &TxPartitionHandle,
+        prefix: K,
+    ) -> crate::Result<()> {
+        let range = lsm_tree::range::prefix_to_range(prefix.as_ref());
+        self.lock_range(partition, range)
+    }
+
+    /// Locks the key, then retrieves it from the transaction's state.
+    ///
+    /// The value is read from the latest state of the keyspace, and cannot be
+    /// changed by other transactions until this transaction ends.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use fjall::{Config, Keyspace, PartitionCreateOptions};
+    /// #
+    /// # let folder = tempfile::tempdir()?;
+    /// # let keyspace = Config::new(folder).open_transactional()?;
+    /// # let partition = keyspace.open_partition("default", PartitionCreateOptions::default())?;
+    /// partition.insert("a", "abc")?;
+    ///
+    /// let mut tx = keyspace.write_tx();
+    ///
+    /// let item = tx.get_for_update(&partition, "a")?;
+    /// assert_eq!(Some("abc".as_bytes().into()), item);
+    ///
+    /// tx.insert(&partition, "a", "def")?;
+    /// tx.commit()?;
+    /// #
+    /// # Ok::<(), fjall::Error>(())
+    /// ```
+    ///
+    /// # Errors
+    ///
+    /// Will return `Err` if an IO error occurs.
+    ///
+    /// Will return [`Error::Lock`](crate::Error::Lock) if the key could not be locked.
+    pub fn get_for_update<K: AsRef<[u8]>>(
+        &mut self,
+        partition: &TxPartitionHandle,
+        key: K,
+    ) -> crate::Result<Option<UserValue>> {
+        self.lock(partition, key.as_ref())?;
+        self.inner.get(partition, key)
+    }
+
+    /// Locks the key, then removes it and returns its value if it existed.
+    ///
+    /// ```
+    /// # use fjall::{Config, Keyspace, PartitionCreateOptions};
+    /// #
+    /// # let folder = tempfile::tempdir()?;
+    /// # let keyspace = Config::new(folder).open_transactional()?;
+    /// # let partition = keyspace.open_partition("default", PartitionCreateOptions::default())?;
+    /// partition.insert("a", "abc")?;
+    ///
+    /// let mut tx = keyspace.write_tx();
+    ///
+    /// let taken = tx.take(&partition, "a")?.unwrap();
+    /// assert_eq!(b"abc", &*taken);
+    /// tx.commit()?;
+    ///
+    /// let item = partition.get("a")?;
+    /// assert!(item.is_none());
+    /// #
+    /// # Ok::<(), fjall::Error>(())
+    /// ```
+    ///
+    /// # Errors
+    ///
+    /// Will return `Err` if an IO error occurs.
+    ///
+    /// Will return [`Error::Lock`](crate::Error::Lock) if the key could not be locked.
+    pub fn take<K: AsRef<[u8]>>(
+        &mut self,
+        partition: &TxPartitionHandle,
+        key: K,
+    ) -> crate::Result<Option<UserValue>> {
+        self.lock(partition, key.as_ref())?;
+        self.inner.take(partition, key)
+    }
+
+    /// Locks the key, then atomically updates it and returns the new value.
+    ///
+    /// Returning `None` removes the item if it existed before.
+    ///
+    /// # Errors
+    ///
+    /// Will return `Err` if an IO error occurs.
+    ///
+    /// Will return [`Error::Lock`](crate::Error::Lock) if the key could not be locked.
+    pub fn update_fetch<K: AsRef<[u8]>, F: FnMut(Option<&UserValue>) -> Option<UserValue>>(
+        &mut self,
+        partition: &TxPartitionHandle,
+        key: K,
+        f: F,
+    ) -> crate::Result<Option<UserValue>> {
+        self.lock(partition, key.as_ref())?;
+        self.inner.update_fetch(partition, key, f)
+    }
+
+    /// Locks the key, then atomically updates it and returns the previous value.
+    ///
+    /// Returning `None` removes the item if it existed before.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use fjall::{Config, Keyspace, PartitionCreateOptions, Slice};
+    /// #
+    /// # let folder = tempfile::tempdir()?;
+    /// # let keyspace = Config::new(folder).open_transactional()?;
+    /// # let partition = keyspace.open_partition("default", PartitionCreateOptions::default())?;
+    /// partition.insert("a", "abc")?;
+    ///
+    /// let mut tx = keyspace.write_tx();
+    ///
+    /// let prev = tx.fetch_update(&partition, "a", |_| Some(Slice::from(*b"def")))?.unwrap();
+    /// assert_eq!(b"abc", &*prev);
+    /// tx.commit()?;
+    ///
+    /// let item = partition.get("a")?;
+    /// assert_eq!(Some("def".as_bytes().into()), item);
+    /// #
+    /// # Ok::<(), fjall::Error>(())
+    /// ```
+    ///
+    /// # Errors
+    ///
+    /// Will return `Err` if an IO error occurs.
+    ///
+    /// Will return [`Error::Lock`](crate::Error::Lock) if the key could not be locked.
+    pub fn fetch_update<K: AsRef<[u8]>, F: FnMut(Option<&UserValue>) -> Option<UserValue>>(
+        &mut self,
+        partition: &TxPartitionHandle,
+        key: K,
+        f: F,
+    ) -> crate::Result<Option<UserValue>> {
+        self.lock(partition, key.as_ref())?;
+        self.inner.fetch_update(partition, key, f)
+    }
+
+    /// Retrieves an item from the transaction's state, without locking it.
+    ///
+    /// The item is read from the snapshot of the last acquired lock,
+    /// so it may be changed by other transactions in the meantime.
+    ///
+    /// The transaction allows reading your own writes (RYOW).
+    ///
+    /// Use [`WriteTransaction::get_for_update`] to prevent other
+    /// transactions from changing the item.
+    ///
+    /// # Errors
+    ///
+    /// Will return `Err` if an IO error occurs.
+    pub fn get<K: AsRef<[u8]>>(
+        &self,
+        partition: &TxPartitionHandle,
+        key: K,
+    ) -> crate::Result<Option<UserValue>> {
+        self.inner.get(partition, key)
+    }
+
+    /// Returns `true` if the transaction's state contains the specified key.
+    ///
+    /// # Errors
+    ///
+    /// Will return `Err` if an IO error occurs.
+    pub fn contains_key<K: AsRef<[u8]>>(
+        &self,
+        partition: &TxPartitionHandle,
+        key: K,
+    ) -> crate::Result<bool> {
+        self.inner.contains_key(partition, key)
+    }
+
+    /// Returns the first key-value pair in the transaction's state.
+    /// The key in this pair is the minimum key in the transaction's state.
+    ///
+    /// # Errors
+    ///
+    /// Will return `Err` if an IO error occurs.
+    pub fn first_key_value(&self, partition: &TxPartitionHandle) -> crate::Result<Option<KvPair>> {
+        self.inner.first_key_value(partition)
+    }
+
+    /// Returns the last key-value pair in the transaction's state.
+    /// The key in this pair is the maximum key in the transaction's state.
+    ///
+    /// # Errors
+    ///
+    /// Will return `Err` if an IO error occurs.
+    pub fn last_key_value(&self, partition: &TxPartitionHandle) -> crate::Result<Option<KvPair>> {
+        self.inner.last_key_value(partition)
+    }
+
+    /// Scans the entire partition, returning the amount of items.
+    ///
+    /// # Errors
+    ///
+    /// Will return `Err` if an IO error occurs.
+    pub fn len(&self, partition: &TxPartitionHandle) -> crate::Result<usize> {
+        self.inner.len(partition)
+    }
+
+    /// Iterates over the transaction's state.
+    ///
+    /// Avoid using this function, or limit it as otherwise it may scan a lot of items.
+    #[must_use]
+    pub fn iter<'b>(
+        &'b self,
+        partition: &TxPartitionHandle,
+    ) -> impl DoubleEndedIterator<Item = crate::Result<KvPair>> + 'b {
+        self.inner.iter(partition)
+    }
+
+    /// Iterates over the transaction's state, returning keys only.
+    ///
+    /// Avoid using this function, or limit it as otherwise it may scan a lot of items.
+    #[must_use]
+    pub fn keys<'b>(
+        &'b self,
+        partition: &TxPartitionHandle,
+    ) -> impl DoubleEndedIterator<Item = crate::Result<UserKey>> + 'b {
+        self.inner.keys(partition)
+    }
+
+    /// Iterates over the transaction's state, returning values only.
+    ///
+    /// Avoid using this function, or limit it as otherwise it may scan a lot of items.
+    #[must_use]
+    pub fn values<'b>(
+        &'b self,
+        partition: &TxPartitionHandle,
+    ) -> impl DoubleEndedIterator<Item = crate::Result<UserValue>> + 'b {
+        self.inner.values(partition)
+    }
+
+    /// Iterates over a range of the transaction's state.
+    ///
+    /// Avoid using full or unbounded ranges as they may scan a lot of items (unless limited).
+    #[must_use]
+    pub fn range<'b, K: AsRef<[u8]> + 'b, R: RangeBounds<K> + 'b>(
+        &'b self,
+        partition: &'b TxPartitionHandle,
+        range: R,
+    ) -> impl DoubleEndedIterator<Item = crate::Result<KvPair>> + 'b {
+        self.inner.range(partition, range)
+    }
+
+    /// Iterates over a range of the transaction's state.
+    ///
+    /// Avoid using an empty prefix as it may scan a lot of items (unless limited).
+    #[must_use]
+    pub fn prefix<'b, K: AsRef<[u8]> + 'b>(
+        &'b self,
+        partition: &'b TxPartitionHandle,
+        prefix: K,
+    ) -> impl DoubleEndedIterator<Item = crate::Result<KvPair>> + 'b {
+        self.inner.prefix(partition, prefix)
+    }
+
+    /// Locks the key, then inserts a key-value pair into the partition.
+    ///
+    /// Keys may be up to 65536 bytes long, values up to 2^32 bytes.
+    /// Shorter keys and values result in better performance.
+    ///
+    /// If the key already exists, the item will be overwritten.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use fjall::{Config, Keyspace, PartitionCreateOptions};
+    /// #
+    /// # let folder = tempfile::tempdir()?;
+    /// # let keyspace = Config::new(folder).open_transactional()?;
+    /// # let partition = keyspace.open_partition("default", PartitionCreateOptions::default())?;
+    /// partition.insert("a", "previous_value")?;
+    ///
+    /// let mut tx = keyspace.write_tx();
+    /// tx.insert(&partition, "a", "new_value")?;
+    ///
+    /// drop(tx);
+    ///
+    /// // Write was not committed
+    /// assert_eq!(b"previous_value", &*partition.get("a")?.unwrap());
+    /// #
+    /// # Ok::<(), fjall::Error>(())
+    /// ```
+    ///
+    /// # Errors
+    ///
+    /// Will return [`Error::Lock`](crate::Error::Lock) if the key could not be locked.
+    pub fn insert<K: AsRef<[u8]>, V: AsRef<[u8]>>(
+        &mut self,
+        partition: &TxPartitionHandle,
+        key: K,
+        value: V,
+    ) -> crate::Result<()> {
+        self.lock(partition, key.as_ref())?;
+        self.inner.insert(partition, key, value);
+        Ok(())
+    }
+
+    /// Locks the key, then removes the item from the partition.
+    ///
+    /// The key may be up to 65536 bytes long.
+    /// Shorter keys result in better performance.
+    ///
+    /// # Errors
+    ///
+    /// Will return [`Error::Lock`](crate::Error::Lock) if the key could not be locked.
+    pub fn remove<K: AsRef<[u8]>>(
+        &mut self,
+        partition: &TxPartitionHandle,
+        key: K,
+    ) -> crate::Result<()> {
+        self.lock(partition, key.as_ref())?;
+        self.inner.remove(partition, key);
+        Ok(())
+    }
+
+    /// Creates a savepoint that can later be rolled back to using [`WriteTransaction::rollback_to`].
+    pub fn savepoint(&mut self) -> Savepoint {
+        self.inner.savepoint()
+    }
+
+    /// Undoes all writes that were done after the savepoint was created.
+    ///
+    /// Locks that were acquired after the savepoint are kept until the transaction ends.
+    ///
+    /// The savepoint stays valid, so it can be rolled back to again;
+    /// savepoints that were created after it are released.
+    ///
+    /// # Errors
+    ///
+    /// Will return [`Error::InvalidSavepoint`](crate::Error::InvalidSavepoint) if the savepoint
+    /// was released or belongs to another transaction.
+    pub fn rollback_to(&mut self, savepoint: &Savepoint) -> crate::Result<()> {
+        self.inner.rollback_to(savepoint)
+    }
+
+    /// Commits the transaction, then releases all its locks.
+    ///
+    /// # Errors
+    ///
+    /// Will return `Err` if an IO error occurs.
+    pub fn commit(self) -> crate::Result<()> {
+        let Self { inner, locks } = self;
+
+        // IMPORTANT: Only release the locks after the write is visible
+        inner.commit()?;
+        drop(locks);
+
+        Ok(())
+    }
+
+    /// More explicit alternative to dropping the transaction
+    /// to roll it back.
+    pub fn rollback(self) {
+        self.inner.rollback();
+    }
+}
@@ -1,5 +1,8 @@
 use super::BaseTransaction as InnerWriteTransaction;
-use crate::{snapshot_nonce::SnapshotNonce, PersistMode, TxKeyspace, TxPartitionHandle};
+use crate::{
+    snapshot_nonce::SnapshotNonce, tx::savepoint::Savepoint, PersistMode, TxKeyspace,
+    TxPartitionHandle,
+};
 use lsm_tree::{KvPair, UserKey, UserValue};
 use std::{ops::RangeBounds, sync::MutexGuard};
 
@@ -561,13 +564,56 @@ impl<'a> WriteTransaction<'a> {
         self.inner.remove(partition, key);
     }
 
+    /// Creates a savepoint that can later be rolled back to using [`WriteTransaction::rollback_to`].
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use fjall::{Config, Keyspace, PartitionCreateOptions};
+    /// #
+    /// # let folder = tempfile::tempdir()?;
+    /// # let keyspace = Config::new(folder).open_transactional()?;
+    /// # let partition = keyspace.open_partition("default", PartitionCreateOptions::default())?;
+    /// let mut tx = keyspace.write_tx();
+    /// tx.insert(&partition, "a", "abc");
+    ///
+    /// let savepoint = tx.savepoint();
+    /// tx.insert(&partition, "a", "def");
+    /// tx.insert(&partition, "b", "def");
+    ///
+    /// tx.rollback_to(&savepoint)?;
+    /// assert_eq!(b"abc", &*tx.get(&partition, "a")?.unwrap());
+    /// assert!(!tx.contains_key(&partition, "b")?);
+    ///
+    /// tx.commit()?;
+    /// assert!(!partition.contains_key("b")?);
+    /// #
+    /// # Ok::<(), fjall::Error>(())
+    /// ```
+    pub fn savepoint(&mut self) -> Savepoint {
+        self.inner.savepoint()
+    }
+
+    /// Undoes all writes that were done after the savepoint was created.
+    ///
+    /// The savepoint stays valid, so it can be rolled back to again;
+    /// savepoints that were created after it are released.
+    ///
+    /// # Errors
+    ///
+    /// Will return [`Error::InvalidSavepoint`](crate::Error::InvalidSavepoint) if the savepoint
+    /// was released or belongs to another transaction.
+    pub fn rollback_to(&mut self, savepoint: &Savepoint) -> crate::Result<()> {
+        self.inner.rollback_to(savepoint)
+    }
+
     /// Commits the transaction.
     ///
     /// # Errors
     ///
     /// Will return `Err` if an IO error occurs.
     pub fn commit(self) -> crate::Result<()> {
-        self.inner.commit()
+        self.inner.commit().map(|_| ())
     }
 
     /// More explicit alternative to dropping the transaction
@@ -1,23 +1,63 @@
 use super::BaseTransaction;
 use crate::{
+    batch::PartitionKey,
     snapshot_nonce::SnapshotNonce,
-    tx::{conflict_manager::ConflictManager, oracle::CommitOutcome},
+    tx::{conflict_manager::ConflictManager, oracle::CommitOutcome, savepoint::Savepoint},
     PersistMode, TxKeyspace, TxPartitionHandle,
 };
-use lsm_tree::{KvPair, Slice, UserKey, UserValue};
+use lsm_tree::{KvPair, SeqNo, Slice, UserKey, UserValue};
 use std::{
     fmt,
     ops::{Bound, RangeBounds, RangeFull},
 };
 
-#[derive(Debug)]
-pub struct Conflict;
+/// A read of a transaction that was invalidated by another transaction's write
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub enum ConflictingRead {
+    /// Point read of a single key
+    Key(UserKey),
+
+    /// Read of a range of keys (including prefix reads)
+    Range {
+        /// Start bound of the range
+        start: Bound<UserKey>,
+
+        /// End bound of the range
+        end: Bound<UserKey>,
+    },
+
+    /// Read of the entire partition
+    Full,
+}
+
+/// Describes why a transaction could not be committed
+///
+/// A transaction conflicts if some data it has read was written by another
+/// transaction that was committed after the conflicting transaction was started.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct Conflict {
+    /// Partition the conflict occurred in
+    pub partition: PartitionKey,
+
+    /// The read that was invalidated
+    pub read: ConflictingRead,
+
+    /// Key written by the committed transaction that invalidated the read
+    pub written_key: UserKey,
+
+    /// Sequence number of the committed transaction that invalidated the read
+    pub seqno: SeqNo,
+}
 
 impl std::error::Error for Conflict {}
 
 impl fmt::Display for Conflict {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
-        "transaction conflict".fmt(f)
+        write!(
+            f,
+            "transaction conflict in partition {:?}: key {:?} was written by transaction with seqno {}",
+            self.partition, self.written_key, self.seqno,
+        )
     }
 }
 
@@ -640,8 +680,61 @@ impl WriteTransaction {
         self.cm.mark_conflict(&partition.inner.name, key);
     }
 
+    /// Creates a savepoint that can later be rolled back to using [`WriteTransaction::rollback_to`].
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use fjall::{Config, Keyspace, PartitionCreateOptions};
+    /// #
+    /// # let folder = tempfile::tempdir()?;
+    /// # let keyspace = Config::new(folder).open_transactional()?;
+    /// # let partition = keyspace.open_partition("default", PartitionCreateOptions::default())?;
+    /// let mut tx = keyspace.write_tx()?;
+    /// tx.insert(&partition, "a", "abc");
+    ///
+    /// let savepoint = tx.savepoint();
+    /// tx.insert(&partition, "a", "def");
+    /// tx.insert(&partition, "b", "def");
+    ///
+    /// tx.rollback_to(&savepoint)?;
+    /// assert_eq!(b"abc", &*tx.get(&partition, "a")?.unwrap());
+    /// assert!(!tx.contains_key(&partition, "b")?);
+    ///
+    /// tx.commit()?.unwrap();
+    /// assert!(!partition.contains_key("b")?);
+    /// #
+    /// # Ok::<(), fjall::Error>(())
+    /// ```
+    pub fn savepoint(&mut self) -> Savepoint {
+        let mut savepoint = self.inner.savepoint();
+        savepoint.reads = self.cm.savepoint();
+        savepoint
+    }
+
+    /// Undoes all writes that were done after the savepoint was created.
+    ///
+    /// Reads that were done after the savepoint are forgotten as well,
+    /// so they cannot cause the transaction to conflict anymore.
+    ///
+    /// The savepoint stays valid, so it can be rolled back to again;
+    /// savepoints that were created after it are released.
+    ///
+    /// # Errors
+    ///
+    /// Will return [`Error::InvalidSavepoint`](crate::Error::InvalidSavepoint) if the savepoint
+    /// was released or belongs to another transaction.
+    pub fn rollback_to(&mut self, savepoint: &Savepoint) -> crate::Result<()> {
+        self.inner.rollback_to(savepoint)?;
+        self.cm.rollback_to(&savepoint.reads);
+        Ok(())
+    }
+
     /// Commits the transaction.
     ///
+    /// If the transaction conflicts with another transaction, it is not committed,
+    /// and [`Conflict`] describes the first read that was invalidated.
+    ///
     /// # Errors
     ///
     /// Will return `Err` if an IO error occurs.
@@ -659,7 +752,7 @@ impl WriteTransaction {
         })? {
             CommitOutcome::Ok => Ok(Ok(())),
             CommitOutcome::Aborted(e) => Err(e),
-            CommitOutcome::Conflicted => Ok(Err(Conflict)),
+            CommitOutcome::Conflicted(conflict) => Ok(Err(conflict)),
         }
     }
 
@@ -744,7 +837,7 @@ mod tests {
         tx2.insert(&env.part, "a3", 300u64.to_be_bytes());
         assert_eq!(300, val);
         tx2.commit()??;
-        assert!(matches!(tx1.commit()?, Err(Conflict)));
+        assert!(matches!(tx1.commit()?, Err(Conflict { .. })));
 
         let mut tx3 = env.ks.write_tx()?;
         let val = tx3
@@ -805,7 +898,7 @@ mod tests {
         tx2.insert(&env.part, "a3", 300u64.to_be_bytes());
         assert_eq!(300, val);
         tx2.commit()??;
-        assert!(matches!(tx1.commit()?, Err(Conflict)));
+        assert!(matches!(tx1.commit()?, Err(Conflict { .. })));
 
         let mut tx3 = env.ks.write_tx()?;
         let val = tx3
@@ -842,7 +935,7 @@ mod tests {
         assert_eq!(tx2.get(&env.part, "hello")?, None);
 
         tx2.insert(&env.part, "hello", "world2");
-        assert!(matches!(tx2.commit()?, Err(Conflict)));
+        assert!(matches!(tx2.commit()?, Err(Conflict { .. })));
 
         let mut tx1 = env.ks.write_tx()?;
         let mut tx2 = env.ks.write_tx()?;
@@ -903,7 +996,7 @@ mod tests {
         }
 
         tx1.commit()??;
-        assert!(matches!(tx2.commit()?, Err(Conflict)));
+        assert!(matches!(tx2.commit()?, Err(Conflict { .. })));
 
         Ok(())
     }
@@ -986,7 +1079,7 @@ mod tests {
 
         t1.insert(&env.part, [1u8], [0u8]);
 
-        assert!(matches!(t1.commit()?, Err(Conflict)));
+        assert!(matches!(t1.commit()?, Err(Conflict { .. })));
 
         Ok(())
     }
@@ -1004,7 +1097,7 @@ mod tests {
         assert_eq!(old, None);
 
         t1.commit()??;
-        assert!(matches!(t2.commit()?, Err(Conflict)));
+        assert!(matches!(t2.commit()?, Err(Conflict { .. })));
 
         assert_eq!(env.part.get("hello")?, Some("world".into()));
 
@@ -1039,7 +1132,7 @@ mod tests {
         t2.insert(&env.part, "hello", "world");
 
         t2.commit()??;
-        assert!(matches!(t1.commit()?, Err(Conflict)));
+        assert!(matches!(t1.commit()?, Err(Conflict { .. })));
 
         let mut t1 = env.ks.write_tx()?;
         let mut t2 = env.ks.write_tx()?;
@@ -1070,7 +1163,7 @@ mod tests {
         t2.insert(&env.part, "hello", "world");
 
         t2.commit()??;
-        assert!(matches!(t1.commit()?, Err(Conflict)));
+        assert!(matches!(t1.commit()?, Err(Conflict { .. })));
 
         let mut t1 = env.ks.write_tx()?;
         let mut t2 = env.ks.write_tx()?;
@@ -2,8 +2,12 @@
 // This source code is licensed under both the Apache 2.0 and MIT License
 // (found in the LICENSE-* files in the repository)
 
-#[cfg(all(feature = "single_writer_tx", feature = "ssi_tx"))]
-compile_error!("Either single_writer_tx or ssi_tx can be enabled at once");
+#[cfg(any(
+    all(feature = "single_writer_tx", feature = "ssi_tx"),
+    all(feature = "single_writer_tx", feature = "pessimistic_tx"),
+    all(feature = "ssi_tx", feature = "pessimistic_tx"),
+))]
+compile_error!("Only one of single_writer_tx, ssi_tx or pessimistic_tx can be enabled at once");
 
 #[cfg(feature = "single_writer_tx")]
 pub use super::write::single_writer::WriteTransaction;
@@ -11,6 +15,9 @@ pub use super::write::single_writer::WriteTransaction;
 #[cfg(feature = "ssi_tx")]
 pub use super::write::ssi::WriteTransaction;
 
+#[cfg(feature = "pessimistic_tx")]
+pub use super::write::pessimistic::WriteTransaction;
+
 // TODO:
 // use https://github.com/rust-lang/rust/issues/43781
 // when stable
@@ -0,0 +1,382 @@
+// Copyright (c) 2024-present, fjall-rs
+// This source code is licensed under both the Apache 2.0 and MIT License
+// (found in the LICENSE-* files in the repository)
+
+use crate::{
+    batch::PartitionKey,
+    codec::{Codec, CodecError, OrderedCodec},
+    PartitionHandle,
+};
+use lsm_tree::{KvPair, UserKey, UserValue};
+use std::{
+    marker::PhantomData,
+    ops::{Bound, RangeBounds},
+};
+
+/// Only used as marker, so the typed partition is always `Send` + `Sync` if its partition is
+type Types<K, V, C> = fn() -> (K, V, C);
+
+type BoxedIter = Box<dyn DoubleEndedIterator<Item = crate::Result<KvPair>> + 'static>;
+
+/// A partition that can be wrapped by a [`TypedPartition`]
+///
+/// Implemented for [`PartitionHandle`] and the transactional partition handle.
+pub trait RawPartition {
+    /// Returns the partition name.
+    fn name(&self) -> &PartitionKey;
+
+    /// Retrieves an item from the partition.
+    ///
+    /// # Errors
+    ///
+    /// Will return `Err` if an IO error occurs.
+    fn get(&self, key: &[u8]) -> crate::Result<Option<UserValue>>;
+
+    /// Returns `true` if the partition contains the specified key.
+    ///
+    /// # Errors
+    ///
+    /// Will return `Err` if an IO error occurs.
+    fn contains_key(&self, key: &[u8]) -> crate::Result<bool>;
+
+    /// Inserts a key-value pair into the partition.
+    ///
+    /// # Errors
+    ///
+    /// Will return `Err` if an IO error occurs.
+    fn insert(&self, key: &[u8], value: &[u8]) -> crate::Result<()>;
+
+    /// Removes an item from the partition.
+    ///
+    /// # Errors
+    ///
+    /// Will return `Err` if an IO error occurs.
+    fn remove(&self, key: &[u8]) -> crate::Result<()>;
+
+    /// Iterates over a range of the partition.
+    fn range(&self, range: (Bound<UserKey>, Bound<UserKey>)) -> BoxedIter;
+}
+
+impl RawPartition for PartitionHandle {
+    fn name(&self) -> &PartitionKey {
+        &self.name
+    }
+
+    fn get(&self, key: &[u8]) -> crate::Result<Option<UserValue>> {
+        Self::get(self, key)
+    }
+
+    fn contains_key(&self, key: &[u8]) -> crate::Result<bool> {
+        Self::contains_key(self, key)
+    }
+
+    fn insert(&self, key: &[u8], value: &[u8]) -> crate::Result<()> {
+        Self::insert(self, key, value)
+    }
+
+    fn remove(&self, key: &[u8]) -> crate::Result<()> {
+        Self::remove(self, key)
+    }
+
+    fn range(&self, range: (Bound<UserKey>, Bound<UserKey>)) -> BoxedIter {
+        Box::new(Self::range(self, range))
+    }
+}
+
+#[cfg(any(
+    feature = "single_writer_tx",
+    feature = "ssi_tx",
+    feature = "pessimistic_tx"
+))]
+impl RawPartition for crate::TxPartitionHandle {
+    fn name(&self) -> &PartitionKey {
+        &self.inner.name
+    }
+
+    fn get(&self, key: &[u8]) -> crate::Result<Option<UserValue>> {
+        Self::get(self, key)
+    }
+
+    fn contains_key(&self, key: &[u8]) -> crate::Result<bool> {
+        Self::contains_key(self, key)
+    }
+
+    fn insert(&self, key: &[u8], value: &[u8]) -> crate::Result<()> {
+        Self::insert(self, key, value)
+    }
+
+    fn remove(&self, key: &[u8]) -> crate::Result<()> {
+        Self::remove(self, key)
+    }
+
+    fn range(&self, range: (Bound<UserKey>, Bound<UserKey>)) -> BoxedIter {
+        Box::new(self.keyspace.read_tx().range(self, range))
+    }
+}
+
+/// Part of a stored item that could not be decoded
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+pub enum DecodeTarget {
+    /// The key could not be decoded
+    Key,
+
+    /// The value could not be decoded
+    Value,
+}
+
+/// A stored item could not be decoded by a [`TypedPartition`]
+#[derive(Debug)]
+pub struct TypedDecodeError {
+    /// Partition the item was read from
+    pub partition: PartitionKey,
+
+    /// Raw key of the item
+    pub key: UserKey,
+
+    /// Which part of the item could not be decoded
+    pub target: DecodeTarget,
+
+    /// Type that was decoded into
+    pub type_name: &'static str,
+
+    /// Error returned by the codec
+    pub source: CodecError,
+}
+
+impl std::fmt::Display for TypedDecodeError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "could not decode {:?} of item {:?} in partition {:?} as {}: {}",
+            self.target,
+            self.key,
+            self.partition,
+            self.type_name,
+            self.source.message()
+        )
+    }
+}
+
+impl std::error::Error for TypedDecodeError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        Some(&self.source)
+    }
+}
+
+/// A partition with typed keys & values
+///
+/// Keys and values are encoded using the codec `C`, which needs to implement
+/// [`Codec`] for both the key and value types. Using an order-preserving key
+/// encoding (like [`OrderedCodec`], the default), ranges and iteration
+/// follow the order of the typed keys.
+///
+/// Wraps a [`PartitionHandle`] by default, or a transactional partition handle,
+/// see [`TypedPartition::new`].
+///
+/// # Examples
+///
+/// ```
+/// # use fjall::{Config, PartitionCreateOptions, TypedPartition};
+/// #
+/// # let folder = tempfile::tempdir()?;
+/// # let keyspace = Config::new(folder).open()?;
+/// let partition = keyspace.open_partition("events", PartitionCreateOptions::default())?;
+///
+/// // (tenant, timestamp) => message
+/// let events = TypedPartition::<(u32, i64), String>::new(partition);
+///
+/// events.insert(&(1, -5), &"hello".to_owned())?;
+/// events.insert(&(1, 10), &"world".to_owned())?;
+/// events.insert(&(2, 0), &"other tenant".to_owned())?;
+///
+/// assert_eq!(Some("hello".to_owned()), events.get(&(1, -5))?);
+///
+/// let tenant_events = events.prefix(&(1_u32,)).collect::<fjall::Result<Vec<_>>>()?;
+/// assert_eq!(
+///     vec![((1, -5), "hello".to_owned()), ((1, 10), "world".to_owned())],
+///     tenant_events,
+/// );
+/// #
+/// # Ok::<(), fjall::Error>(())
+/// ```
+#[allow(clippy::module_name_repetitions)]
+pub struct TypedPartition<K, V, C = OrderedCodec, P = PartitionHandle> {
+    inner: P,
+    phantom: PhantomData<Types<K, V, C>>,
+}
+
+impl<K, V, C, P: Clone> Clone for TypedPartition<K, V, C, P> {
+    fn clone(&self) -> Self {
+        Self {
+            inner: self.inner.clone(),
+            phantom: PhantomData,
+        }
+    }
+}
+
+impl<K, V, C, P> TypedPartition<K, V, C, P>
+where
+    K: 'static,
+    V: 'static,
+    C: Codec<K> + Codec<V> + 'static,
+    P: RawPartition,
+{
+    /// Wraps a partition.
+    ///
+    /// The partition should only contain items that were written using the same codec.
+    pub fn new(partition: P) -> Self {
+        Self {
+            inner: partition,
+            phantom: PhantomData,
+        }
+    }
+
+    /// Returns the underlying (untyped) partition.
+    pub fn inner(&self) -> &P {
+        &self.inner
+    }
+
+    fn decode_error<T>(
+        partition: &PartitionKey,
+        key: &UserKey,
+        target: DecodeTarget,
+        source: CodecError,
+    ) -> crate::Error {
+        crate::Error::TypedDecode(TypedDecodeError {
+            partition: partition.clone(),
+            key: key.clone(),
+            target,
+            type_name: std::any::type_name::<T>(),
+            source,
+        })
+    }
+
+    fn decode_value(partition: &PartitionKey, key: &UserKey, value: &[u8]) -> crate::Result<V> {
+        <C as Codec<V>>::decode(value)
+            .map_err(|e| Self::decode_error::<V>(partition, key, DecodeTarget::Value, e))
+    }
+
+    fn decode_kv(partition: &PartitionKey, (key, value): KvPair) -> crate::Result<(K, V)> {
+        let typed_key = <C as Codec<K>>::decode(&key)
+            .map_err(|e| Self::decode_error::<K>(partition, &key, DecodeTarget::Key, e))?;
+
+        let typed_value = Self::decode_value(partition, &key, &value)?;
+
+        Ok((typed_key, typed_value))
+    }
+
+    fn typed_iter(&self, range: (Bound<UserKey>, Bound<UserKey>)) -> TypedIter<K, V> {
+        let partition = self.inner.name().clone();
+
+        Box::new(
+            self.inner
+                .range(range)
+                .map(move |kv| Self::decode_kv(&partition, kv?)),
+        )
+    }
+
+    /// Retrieves an item from the partition.
+    ///
+    /// # Errors
+    ///
+    /// Will return `Err` if an IO error occurs, or the value cannot be decoded.
+    pub fn get(&self, key: &K) -> crate::Result<Option<V>> {
+        let key: UserKey = <C as Codec<K>>::encode(key).into();
+
+        self.inner
+            .get(&key)?
+            .map(|value| Self::decode_value(self.inner.name(), &key, &value))
+            .transpose()
+    }
+
+    /// Returns `true` if the partition contains the specified key.
+    ///
+    /// # Errors
+    ///
+    /// Will return `Err` if an IO error occurs.
+    pub fn contains_key(&self, key: &K) -> crate::Result<bool> {
+        self.inner.contains_key(&<C as Codec<K>>::encode(key))
+    }
+
+    /// Inserts a key-value pair into the partition.
+    ///
+    /// # Errors
+    ///
+    /// Will return `Err` if an IO error occurs.
+    pub fn insert(&self, key: &K, value: &V) -> crate::Result<()> {
+        self.inner.insert(
+            &<C as Codec<K>>::encode(key),
+            &<C as Codec<V>>::encode(value),
+        )
+    }
+
+    /// Removes an item from the partition.
+    ///
+    /// # Errors
+    ///
+    /// Will return `Err` if an IO error occurs.
+    pub fn remove(&self, key: &K) -> crate::Result<()> {
+        self.inner.remove(&<C as Codec<K>>::encode(key))
+    }
+
+    /// Returns an iterator that scans through the entire partition.
+    ///
+    /// Avoid using this function, or limit it as otherwise it may scan a lot of items.
+    #[must_use]
+    pub fn iter(&self) -> impl DoubleEndedIterator<Item = crate::Result<(K, V)>> + 'static {
+        self.typed_iter((Bound::Unbounded, Bound::Unbounded))
+    }
+
+    /// Returns an iterator over a range of typed keys.
+    ///
+    /// Avoid using full or unbounded ranges as they may scan a lot of items (unless limited).
+    pub fn range<R: RangeBounds<K>>(
+        &self,
+        range: R,
+    ) -> impl DoubleEndedIterator<Item = crate::Result<(K, V)>> + 'static {
+        let encode = |bound: Bound<&K>| match bound {
+            Bound::Included(key) => Bound::Included(<C as Codec<K>>::encode(key).into()),
+            Bound::Excluded(key) => Bound::Excluded(<C as Codec<K>>::encode(key).into()),
+            Bound::Unbounded => Bound::Unbounded,
+        };
+
+        self.typed_iter((encode(range.start_bound()), encode(range.end_bound())))
+    }
+
+    /// Returns an iterator over all items whose encoded key starts with the encoded prefix.
+    ///
+    /// With [`OrderedCodec`], the prefix can be the first component(s) of a tuple key,
+    /// e.g. `(tenant_id,)` for keys of type `(tenant_id, timestamp)`.
+    ///
+    /// Avoid using an empty prefix as it may scan a lot of items (unless limited).
+    pub fn prefix<Q>(
+        &self,
+        prefix: &Q,
+    ) -> impl DoubleEndedIterator<Item = crate::Result<(K, V)>> + 'static
+    where
+        C: Codec<Q>,
+    {
+        let prefix = <C as Codec<Q>>::encode(prefix);
+        self.typed_iter(lsm_tree::range::prefix_to_range(&prefix))
+    }
+
+    /// Returns the first key-value pair in the partition.
+    ///
+    /// # Errors
+    ///
+    /// Will return `Err` if an IO error occurs, or the item cannot be decoded.
+    pub fn first_key_value(&self) -> crate::Result<Option<(K, V)>> {
+        self.iter().next().transpose()
+    }
+
+    /// Returns the last key-value pair in the partition.
+    ///
+    /// # Errors
+    ///
+    /// Will return `Err` if an IO error occurs, or the item cannot be decoded.
+    pub fn last_key_value(&self) -> crate::Result<Option<(K, V)>> {
+        self.iter().next_back().transpose()
+    }
+}
+
+type TypedIter<K, V> = Box<dyn DoubleEndedIterator<Item = crate::Result<(K, V)>> + 'static>;
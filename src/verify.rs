@@ -0,0 +1,433 @@
+// Copyright (c) 2024-present, fjall-rs
+// This source code is licensed under both the Apache 2.0 and MIT License
+// (found in the LICENSE-* files in the repository)
+
+use crate::{
+    batch::PartitionKey,
+    file::{PARTITIONS_FOLDER, PARTITION_DELETED_MARKER},
+    fs::Fs,
+    journal::{
+        dump::{JournalDump, JournalStop},
+        writer::PersistMode,
+    },
+    recovery::read_partition_config,
+    tree_files::list_segment_files,
+    HashSet, Keyspace, PartitionHandle,
+};
+use lsm_tree::{
+    blob_tree::value::MaybeInlineValue,
+    coding::Decode,
+    file::{BLOBS_FOLDER, SEGMENTS_FOLDER},
+    AbstractTree, AnyTree, SegmentId, UserKey,
+};
+use std::path::{Path, PathBuf};
+
+/// Options for [`Keyspace::verify`]
+///
+/// The version marker, partition configs and manifests are always checked.
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+pub struct VerifyOptions {
+    /// Read all blocks of all disk segments and check their checksums.
+    ///
+    /// Default = true
+    pub check_blocks: bool,
+
+    /// Read all blobs of KV-separated partitions and check their checksums,
+    /// and check that every key points to an existing blob file.
+    ///
+    /// Default = true
+    pub check_blobs: bool,
+
+    /// Read all journals and check their batch checksums.
+    ///
+    /// Default = true
+    pub check_journals: bool,
+}
+
+impl Default for VerifyOptions {
+    fn default() -> Self {
+        Self {
+            check_blocks: true,
+            check_blobs: true,
+            check_journals: true,
+        }
+    }
+}
+
+/// Problem found by [`Keyspace::verify`]
+#[derive(Debug)]
+#[non_exhaustive]
+pub enum VerifyIssue {
+    /// The `version` marker is missing or does not match the disk format
+    InvalidVersionMarker(crate::Error),
+
+    /// A file in the partitions folder that is not a partition folder
+    StrayFile(PathBuf),
+
+    /// The config of a partition cannot be read
+    InvalidPartitionConfig {
+        /// Partition name
+        partition: PartitionKey,
+
+        /// Error that occurred while reading the config
+        error: crate::Error,
+    },
+
+    /// A disk segment is in the manifest, but its file is missing
+    MissingSegmentFile {
+        /// Partition name
+        partition: PartitionKey,
+
+        /// ID of the missing segment
+        segment_id: SegmentId,
+    },
+
+    /// A segment file that is not in the manifest
+    OrphanedSegmentFile {
+        /// Partition name
+        partition: PartitionKey,
+
+        /// Path of the file
+        path: PathBuf,
+    },
+
+    /// Blocks of disk segments that could not be read or failed their checksum check
+    CorruptBlocks {
+        /// Partition name
+        partition: PartitionKey,
+
+        /// Amount of broken blocks
+        count: usize,
+    },
+
+    /// A blob file is in the manifest, but the file is missing
+    MissingBlobFile {
+        /// Partition name
+        partition: PartitionKey,
+
+        /// ID of the missing blob file
+        blob_file_id: SegmentId,
+    },
+
+    /// A blob file that is not in the manifest
+    OrphanedBlobFile {
+        /// Partition name
+        partition: PartitionKey,
+
+        /// Path of the file
+        path: PathBuf,
+    },
+
+    /// Blobs that failed their checksum check
+    CorruptBlobs {
+        /// Partition name
+        partition: PartitionKey,
+
+        /// Amount of broken blobs
+        count: usize,
+    },
+
+    /// A key points to a blob file that does not exist
+    DanglingBlobReference {
+        /// Partition name
+        partition: PartitionKey,
+
+        /// Key of the item
+        key: UserKey,
+
+        /// ID of the blob file the key points to
+        blob_file_id: SegmentId,
+    },
+
+    /// Journal recovery would not read the journal to its end
+    CorruptJournal {
+        /// Path of the journal file
+        path: PathBuf,
+
+        /// Where recovery would stop, see [`JournalDump`] for details
+        stop: JournalStop,
+    },
+}
+
+/// Result of [`Keyspace::verify`]
+#[derive(Debug, Default)]
+pub struct VerifyReport {
+    /// Amount of partitions that were checked
+    pub partitions_checked: usize,
+
+    /// Amount of journals that were checked
+    pub journals_checked: usize,
+
+    /// Problems that were found
+    pub issues: Vec<VerifyIssue>,
+}
+
+impl VerifyReport {
+    /// Returns `true` if no problems were found.
+    #[must_use]
+    pub fn is_ok(&self) -> bool {
+        self.issues.is_empty()
+    }
+}
+
+/// Compares the segment IDs of a manifest to the files in the segments folder.
+///
+/// Returns the amount of missing files.
+fn verify_files(
+    folder: &Path,
+    manifest_ids: &HashSet<SegmentId>,
+    on_missing: impl Fn(SegmentId) -> VerifyIssue,
+    on_orphaned: impl Fn(PathBuf) -> VerifyIssue,
+    issues: &mut Vec<VerifyIssue>,
+) -> crate::Result<usize> {
+    let files = list_segment_files(folder)?;
+
+    let file_ids = files
+        .iter()
+        .filter_map(|(id, _)| *id)
+        .collect::<HashSet<_>>();
+
+    let mut missing = manifest_ids
+        .difference(&file_ids)
+        .copied()
+        .collect::<Vec<_>>();
+    missing.sort_unstable();
+
+    let missing_count = missing.len();
+    issues.extend(missing.into_iter().map(on_missing));
+
+    issues.extend(
+        files
+            .into_iter()
+            .filter(|(id, _)| !id.is_some_and(|id| manifest_ids.contains(&id)))
+            .map(|(_, path)| on_orphaned(path)),
+    );
+
+    Ok(missing_count)
+}
+
+fn verify_partition(
+    fs: &dyn Fs,
+    partition: &PartitionHandle,
+    options: VerifyOptions,
+    issues: &mut Vec<VerifyIssue>,
+) -> crate::Result<()> {
+    let name = &partition.name;
+    let path = partition.path();
+
+    if let Err(error) = read_partition_config(fs, path) {
+        issues.push(VerifyIssue::InvalidPartitionConfig {
+            partition: name.clone(),
+            error,
+        });
+    }
+
+    let index = match &partition.tree {
+        AnyTree::Standard(tree) => tree,
+        AnyTree::Blob(tree) => &tree.index,
+    };
+
+    let segment_ids = index
+        .levels
+        .read()
+        .expect("lock is poisoned")
+        .resolved_view()
+        .into_iter()
+        .flat_map(|level| level.segments)
+        .map(|segment| segment.metadata.id)
+        .collect::<HashSet<_>>();
+
+    let missing_segments = verify_files(
+        &path.join(SEGMENTS_FOLDER),
+        &segment_ids,
+        |segment_id| VerifyIssue::MissingSegmentFile {
+            partition: name.clone(),
+            segment_id,
+        },
+        |path| VerifyIssue::OrphanedSegmentFile {
+            partition: name.clone(),
+            path,
+        },
+        issues,
+    )?;
+
+    // NOTE: Reading blocks of missing segments would fail
+    if options.check_blocks && missing_segments == 0 {
+        let count = index.verify()?;
+
+        if count > 0 {
+            issues.push(VerifyIssue::CorruptBlocks {
+                partition: name.clone(),
+                count,
+            });
+        }
+    }
+
+    let AnyTree::Blob(tree) = &partition.tree else {
+        return Ok(());
+    };
+
+    let blob_file_ids = tree
+        .blobs
+        .manifest
+        .list_segment_ids()
+        .into_iter()
+        .collect::<HashSet<_>>();
+
+    let missing_blob_files = verify_files(
+        &path.join(BLOBS_FOLDER).join(SEGMENTS_FOLDER),
+        &blob_file_ids,
+        |blob_file_id| VerifyIssue::MissingBlobFile {
+            partition: name.clone(),
+            blob_file_id,
+        },
+        |path| VerifyIssue::OrphanedBlobFile {
+            partition: name.clone(),
+            path,
+        },
+        issues,
+    )?;
+
+    if options.check_blobs {
+        // NOTE: Reading blobs of missing blob files would fail
+        if missing_blob_files == 0 {
+            let count = tree.blobs.verify().map_err(lsm_tree::Error::from)?;
+
+            if count > 0 {
+                issues.push(VerifyIssue::CorruptBlobs {
+                    partition: name.clone(),
+                    count,
+                });
+            }
+        }
+
+        for kv in tree.index.iter() {
+            let (key, value) = kv?;
+
+            let mut cursor = std::io::Cursor::new(value);
+
+            if let MaybeInlineValue::Indirect { vhandle, .. } =
+                MaybeInlineValue::decode_from(&mut cursor)?
+            {
+                if !blob_file_ids.contains(&vhandle.segment_id) {
+                    issues.push(VerifyIssue::DanglingBlobReference {
+                        partition: name.clone(),
+                        key,
+                        blob_file_id: vhandle.segment_id,
+                    });
+                }
+            }
+        }
+    }
+
+    Ok(())
+}
+
+fn verify_journals(keyspace: &Keyspace, report: &mut VerifyReport) -> crate::Result<()> {
+    let sealed_paths = keyspace
+        .journal_manager
+        .read()
+        .expect("lock is poisoned")
+        .sealed_journal_paths();
+
+    let mut check = |path: &Path| -> crate::Result<()> {
+        let dump = match JournalDump::open(
+            path,
+            keyspace.config.fs.clone(),
+            &keyspace.config.encryption,
+        ) {
+            Ok(dump) => dump,
+
+            // NOTE: The journal was evicted in the meantime
+            Err(crate::Error::Io(e)) if e.kind() == std::io::ErrorKind::NotFound => {
+                return Ok(());
+            }
+
+            Err(e) => return Err(e),
+        };
+
+        report.journals_checked += 1;
+
+        if let Some(stop) = dump.stop {
+            report.issues.push(VerifyIssue::CorruptJournal {
+                path: path.into(),
+                stop,
+            });
+        }
+
+        Ok(())
+    };
+
+    for path in &sealed_paths {
+        check(path)?;
+    }
+
+    // IMPORTANT: Hold the writer lock, otherwise a batch may be half-written while reading
+    let mut writer = keyspace.journal.get_writer();
+    writer.flush(PersistMode::Buffer)?;
+    check(&writer.path)?;
+
+    Ok(())
+}
+
+fn verify_partitions(
+    keyspace: &Keyspace,
+    options: VerifyOptions,
+    report: &mut VerifyReport,
+) -> crate::Result<()> {
+    let fs = keyspace.config.fs.as_ref();
+
+    for dirent in fs.read_dir(&keyspace.config.path.join(PARTITIONS_FOLDER))? {
+        if !dirent.is_dir {
+            report.issues.push(VerifyIssue::StrayFile(dirent.path));
+        }
+    }
+
+    let partitions = keyspace
+        .partitions
+        .read()
+        .expect("lock is poisoned")
+        .values()
+        .cloned()
+        .collect::<Vec<_>>();
+
+    for partition in partitions {
+        // NOTE: The partition was deleted in the meantime
+        if fs.exists(&partition.path().join(PARTITION_DELETED_MARKER))? {
+            continue;
+        }
+
+        verify_partition(fs, &partition, options, &mut report.issues)?;
+        report.partitions_checked += 1;
+    }
+
+    Ok(())
+}
+
+/// Verifies the integrity of a keyspace, see [`Keyspace::verify`].
+pub fn verify_keyspace(keyspace: &Keyspace, options: VerifyOptions) -> crate::Result<VerifyReport> {
+    let mut report = VerifyReport::default();
+
+    if let Err(e) = Keyspace::check_version(keyspace.config.fs.as_ref(), &keyspace.config.path) {
+        report.issues.push(VerifyIssue::InvalidVersionMarker(e));
+    }
+
+    // NOTE: Flushes and compactions write segment files before adding them to the manifest,
+    // and delete old segment files afterwards, so pause them while comparing files
+    let was_paused = keyspace.is_background_work_paused();
+    keyspace.pause_background_work();
+
+    let result = verify_partitions(keyspace, options, &mut report);
+
+    if !was_paused {
+        keyspace.resume_background_work();
+    }
+
+    result?;
+
+    if options.check_journals {
+        verify_journals(keyspace, &mut report)?;
+    }
+
+    Ok(report)
+}
@@ -0,0 +1,225 @@
+// Copyright (c) 2024-present, fjall-rs
+// This source code is licensed under both the Apache 2.0 and MIT License
+// (found in the LICENSE-* files in the repository)
+
+use std::time::Duration;
+
+/// How writes are admitted when the keyspace is overloaded
+///
+/// Writes are stalled (slowed down) or halted when flushing or compaction
+/// cannot keep up with the write rate, see [`WriteStallPolicy`].
+#[derive(Copy, Clone, Debug, Default, Eq, PartialEq)]
+pub enum WriteAdmission {
+    /// Writes sleep until the keyspace has caught up (default)
+    #[default]
+    Wait,
+
+    /// Writes that would be stalled or halted are rejected with
+    /// [`Error::WriteStalled`](crate::Error::WriteStalled), without being written.
+    ///
+    /// This allows latency-sensitive applications to shed load instead.
+    FailFast,
+}
+
+/// Reason why a write was stalled
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+pub enum WriteStallReason {
+    /// The journals exceed their maximum size, see [`Config::max_journaling_size`](crate::Config::max_journaling_size)
+    JournalSize,
+
+    /// The memtables exceed the maximum write buffer size, see [`Config::max_write_buffer_size`](crate::Config::max_write_buffer_size)
+    WriteBufferSize,
+
+    /// Too many segments in the first level of a partition, compaction needs to catch up
+    L0Segments,
+}
+
+impl WriteStallReason {
+    /// Time between checks while writes are halted
+    pub(crate) fn halt_interval(self) -> Duration {
+        match self {
+            Self::JournalSize => Duration::from_millis(100),
+            Self::WriteBufferSize | Self::L0Segments => Duration::from_millis(10),
+        }
+    }
+}
+
+impl std::fmt::Display for WriteStallReason {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::JournalSize => write!(f, "journal size limit reached"),
+            Self::WriteBufferSize => write!(f, "write buffer size limit reached"),
+            Self::L0Segments => write!(f, "too many L0 segments"),
+        }
+    }
+}
+
+/// Current load of the keyspace & partition a write goes to,
+/// see [`WriteStallPolicy`]
+///
+/// Values are computed when they are queried, so a policy only pays
+/// for the values it actually looks at.
+pub trait WriteLoad {
+    /// Amount of segments in the first level of the partition
+    fn l0_segments(&self) -> usize;
+
+    /// `true` if the segments in the first level of the partition do not overlap,
+    /// which is typical for monotonic keys, in which case they are cheap to compact
+    fn l0_disjoint(&self) -> bool;
+
+    /// Size of sealed memtables that are waiting to be flushed, in bytes
+    fn pending_flush_bytes(&self) -> u64;
+
+    /// Size of all journals, in bytes
+    fn journal_bytes(&self) -> u64;
+
+    /// Max size of all journals, see [`Config::max_journaling_size`](crate::Config::max_journaling_size)
+    fn max_journal_bytes(&self) -> u64;
+
+    /// Size of all memtables, in bytes
+    fn write_buffer_bytes(&self) -> u64;
+
+    /// Max size of all memtables, see [`Config::max_write_buffer_size`](crate::Config::max_write_buffer_size)
+    fn max_write_buffer_bytes(&self) -> u64;
+
+    /// `true` if the write filled up the partition's memtable, so it was sealed
+    fn memtable_rotated(&self) -> bool;
+
+    /// `true` if the memtables exceeded the maximum write buffer size when the write was made
+    ///
+    /// This does not change while a write is stalled, even if the memtables are flushed in the meantime.
+    fn write_buffer_exceeded(&self) -> bool;
+
+    /// `true` if the write has not been made yet, and is only checked for admission,
+    /// see [`WriteAdmission::FailFast`]
+    fn admission(&self) -> bool;
+}
+
+/// Decision of a [`WriteStallPolicy`]
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+pub enum WriteStallAction {
+    /// Write without waiting
+    Proceed,
+
+    /// Wait for the given time, then proceed
+    Delay(WriteStallReason, Duration),
+
+    /// Wait until the policy does not halt anymore
+    Halt(WriteStallReason),
+}
+
+/// Decides if writes need to be slowed down, so flushing and compaction can keep up
+///
+/// The policy is consulted after every write (and before every write when using
+/// [`WriteAdmission::FailFast`]), so it should be cheap.
+///
+/// A policy can be set per keyspace ([`Config::write_stall_policy`](crate::Config::write_stall_policy))
+/// and overridden per partition ([`PartitionHandle::set_write_stall_policy`](crate::PartitionHandle::set_write_stall_policy)).
+pub trait WriteStallPolicy: Send + Sync {
+    /// Decides how a write to the partition should proceed.
+    fn check(&self, load: &dyn WriteLoad) -> WriteStallAction;
+}
+
+/// The built-in write stall policy
+///
+/// After a write that sealed a memtable, and before every write when using [`WriteAdmission::FailFast`]:
+///
+/// - Halts if the journals exceed their maximum size
+/// - Halts if there are 32 or more overlapping L0 segments
+///
+/// After a write that sealed a memtable:
+///
+/// - Delays by 500ms if the journals exceed 90% of their maximum size
+///
+/// After every write:
+///
+/// - Delays by 10µs - 1ms if there are 20 - 31 overlapping L0 segments
+///
+/// After a write that exceeded the maximum write buffer size:
+///
+/// - Halts until the memtables are smaller than the maximum write buffer size
+/// - Then delays by 100ms if the memtables still exceed 90% of the maximum write buffer size
+///
+/// Halts are checked first, then at most one delay is applied.
+#[derive(Copy, Clone, Debug, Default)]
+pub struct DefaultWriteStallPolicy;
+
+impl DefaultWriteStallPolicy {
+    /// Write delay in microseconds by the amount of L0 segments
+    #[must_use]
+    pub fn l0_write_delay(l0_segments: usize) -> u64 {
+        match l0_segments {
+            20 => 10,
+            21 => 20,
+            22 => 30,
+            23 => 40,
+            24 => 50,
+            25 => 60,
+            26 => 70,
+            27 => 80,
+            28 => 100,
+            29 => 200,
+            30 => 500,
+            31 => 1_000,
+            _ => 0,
+        }
+    }
+}
+
+impl WriteStallPolicy for DefaultWriteStallPolicy {
+    #[allow(clippy::cast_precision_loss)]
+    fn check(&self, load: &dyn WriteLoad) -> WriteStallAction {
+        let memtable_rotated = load.memtable_rotated();
+        let write_buffer_exceeded = load.write_buffer_exceeded();
+
+        // NOTE: If the first level is disjoint, we are probably dealing with a monotonic series
+        // so nothing to do
+        let l0_segments = load.l0_segments();
+        let l0_overlapping = |min| l0_segments >= min && !load.l0_disjoint();
+
+        // NOTE: Journals only grow when a memtable is sealed, but a write that is
+        // admitted must not slip through while the journals or L0 are over their limits
+        let check_halts = memtable_rotated || load.admission();
+
+        if check_halts && load.journal_bytes() > load.max_journal_bytes() {
+            return WriteStallAction::Halt(WriteStallReason::JournalSize);
+        }
+
+        if check_halts && l0_overlapping(32) {
+            return WriteStallAction::Halt(WriteStallReason::L0Segments);
+        }
+
+        if write_buffer_exceeded && load.write_buffer_bytes() >= load.max_write_buffer_bytes() {
+            return WriteStallAction::Halt(WriteStallReason::WriteBufferSize);
+        }
+
+        if memtable_rotated && load.journal_bytes() as f64 > load.max_journal_bytes() as f64 * 0.9 {
+            return WriteStallAction::Delay(
+                WriteStallReason::JournalSize,
+                Duration::from_millis(500),
+            );
+        }
+
+        if l0_overlapping(20) {
+            let delay_us = Self::l0_write_delay(l0_segments);
+
+            if delay_us > 0 {
+                return WriteStallAction::Delay(
+                    WriteStallReason::L0Segments,
+                    Duration::from_micros(delay_us),
+                );
+            }
+        }
+
+        if write_buffer_exceeded
+            && load.write_buffer_bytes() as f64 > load.max_write_buffer_bytes() as f64 * 0.9
+        {
+            return WriteStallAction::Delay(
+                WriteStallReason::WriteBufferSize,
+                Duration::from_millis(100),
+            );
+        }
+
+        WriteStallAction::Proceed
+    }
+}
@@ -0,0 +1,143 @@
+#![cfg(feature = "async")]
+
+use fjall::{AsyncKeyspace, Config, PartitionCreateOptions, UserKey, UserValue};
+use futures::{executor::block_on, StreamExt, TryStreamExt};
+
+const ITEM_COUNT: usize = 1_000;
+
+fn key(idx: usize) -> UserKey {
+    format!("{idx:05}").as_bytes().into()
+}
+
+#[test_log::test]
+fn async_keyspace_point_ops() -> fjall::Result<()> {
+    let folder = tempfile::tempdir()?;
+
+    block_on(async {
+        let keyspace = AsyncKeyspace::open(Config::new(&folder)).await?;
+        let items = keyspace
+            .open_partition("items", PartitionCreateOptions::default())
+            .await?;
+
+        assert!(items.is_empty().await?);
+
+        items.insert("a", "abc").await?;
+        items.insert("b", "def").await?;
+        assert_eq!(Some(UserValue::from("abc")), items.get("a").await?);
+        assert!(items.contains_key("b").await?);
+        assert_eq!(2, items.len().await?);
+
+        items.remove("a").await?;
+        assert_eq!(None, items.get("a").await?);
+
+        let mut batch = keyspace.batch();
+        batch.insert(items.inner(), "c", "ghi");
+        batch.remove(items.inner(), "b");
+        keyspace.commit(batch).await?;
+
+        assert_eq!(
+            Some((UserKey::from("c"), UserValue::from("ghi"))),
+            items.first_key_value().await?
+        );
+        assert_eq!(1, items.len().await?);
+
+        keyspace.persist(fjall::PersistMode::SyncAll).await?;
+
+        let count = keyspace.spawn_blocking(|| 1 + 1).await;
+        assert_eq!(2, count);
+
+        assert!(keyspace.partition_exists("items"));
+        keyspace.delete_partition(items).await?;
+        assert!(!keyspace.partition_exists("items"));
+
+        Ok(())
+    })
+}
+
+#[test_log::test]
+fn async_keyspace_streams() -> fjall::Result<()> {
+    let folder = tempfile::tempdir()?;
+
+    let keyspace = AsyncKeyspace::with_pool_size(Config::new(&folder).open()?, 2)?;
+    let items = keyspace.wrap_partition(
+        keyspace
+            .inner()
+            .open_partition("items", PartitionCreateOptions::default())?,
+    );
+
+    block_on(async {
+        for idx in 0..ITEM_COUNT {
+            items.insert(key(idx), idx.to_string()).await?;
+        }
+
+        // Spans multiple chunks
+        let keys = items
+            .iter()
+            .map_ok(|(k, _)| k)
+            .try_collect::<Vec<_>>()
+            .await?;
+        assert_eq!((0..ITEM_COUNT).map(key).collect::<Vec<_>>(), keys);
+
+        let keys = items
+            .iter()
+            .rev()
+            .map_ok(|(k, _)| k)
+            .try_collect::<Vec<_>>()
+            .await?;
+        assert_eq!((0..ITEM_COUNT).rev().map(key).collect::<Vec<_>>(), keys);
+
+        let keys = items
+            .range(key(100)..=key(400))
+            .map_ok(|(k, _)| k)
+            .try_collect::<Vec<_>>()
+            .await?;
+        assert_eq!((100..=400).map(key).collect::<Vec<_>>(), keys);
+
+        assert_eq!(100, items.prefix("002").count().await);
+
+        // Reversing a polled stream continues with the remaining items
+        let mut stream = items.range(key(0)..key(300));
+        let first = stream.next().await.unwrap()?;
+        assert_eq!(key(0), first.0);
+
+        let keys = stream
+            .rev()
+            .map_ok(|(k, _)| k)
+            .try_collect::<Vec<_>>()
+            .await?;
+        assert_eq!((1..300).rev().map(key).collect::<Vec<_>>(), keys);
+
+        Ok(())
+    })
+}
+
+#[test_log::test]
+fn async_keyspace_stream_snapshot() -> fjall::Result<()> {
+    let folder = tempfile::tempdir()?;
+
+    block_on(async {
+        let keyspace = AsyncKeyspace::open(Config::new(&folder)).await?;
+        let items = keyspace
+            .open_partition("items", PartitionCreateOptions::default())
+            .await?;
+
+        for idx in 0..ITEM_COUNT {
+            items.insert(key(idx), "old").await?;
+        }
+
+        let mut stream = items.iter();
+        assert_eq!(UserValue::from("old"), stream.next().await.unwrap()?.1);
+
+        // Writes after opening the stream are not visible
+        for idx in 0..ITEM_COUNT {
+            items.insert(key(idx), "new").await?;
+        }
+        items.insert(key(ITEM_COUNT), "new").await?;
+
+        let values = stream.map_ok(|(_, v)| v).try_collect::<Vec<_>>().await?;
+        assert_eq!(ITEM_COUNT - 1, values.len());
+        assert!(values.iter().all(|v| &**v == b"old"));
+
+        Ok(())
+    })
+}
@@ -0,0 +1,63 @@
+use fjall::{AbstractTree, Config, PartitionCreateOptions};
+use std::time::Duration;
+
+#[test_log::test]
+fn background_work_pause() -> fjall::Result<()> {
+    let folder = tempfile::tempdir()?;
+
+    let keyspace = Config::new(&folder).open()?;
+    let partition = keyspace.open_partition("default", PartitionCreateOptions::default())?;
+
+    assert!(!keyspace.is_background_work_paused());
+    keyspace.pause_background_work();
+    assert!(keyspace.is_background_work_paused());
+
+    partition.insert("a", "abc")?;
+    assert!(partition.rotate_memtable()?);
+
+    // Writes keep working, but nothing is flushed
+    partition.insert("b", "abc")?;
+    std::thread::sleep(Duration::from_millis(250));
+    assert_eq!(0, partition.tree.segment_count());
+    assert!(partition.contains_key("a")?);
+
+    keyspace.resume_background_work();
+    assert!(!keyspace.is_background_work_paused());
+
+    partition.rotate_memtable_and_wait()?;
+    assert_eq!(2, partition.tree.segment_count());
+    assert!(partition.contains_key("a")?);
+    assert!(partition.contains_key("b")?);
+
+    Ok(())
+}
+
+#[test_log::test]
+fn background_work_pause_drop() -> fjall::Result<()> {
+    let folder = tempfile::tempdir()?;
+
+    {
+        let keyspace = Config::new(&folder).open()?;
+        let partition = keyspace.open_partition("default", PartitionCreateOptions::default())?;
+
+        keyspace.pause_background_work();
+
+        partition.insert("a", "abc")?;
+        assert!(partition.rotate_memtable()?);
+    }
+
+    // NOTE: Workers exit without flushing when the keyspace is dropped while paused
+    let segments_folder = folder
+        .path()
+        .join("partitions")
+        .join("default")
+        .join("segments");
+    assert_eq!(0, std::fs::read_dir(segments_folder)?.count());
+
+    // NOTE: Paused keyspace can be dropped, the memtable is recovered from the journal
+    let keyspace = Config::new(&folder).open()?;
+    let partition = keyspace.open_partition("default", PartitionCreateOptions::default())?;
+    assert!(partition.contains_key("a")?);
+
+    Ok(())
+}
@@ -0,0 +1,194 @@
+use fjall::{Config, KvSeparationOptions, PartitionCreateOptions, Precondition};
+use test_log::test;
+
+#[test]
+fn batch_require_absent() -> fjall::Result<()> {
+    let folder = tempfile::tempdir()?;
+
+    let keyspace = Config::new(folder).open()?;
+    let partition = keyspace.open_partition("default", PartitionCreateOptions::default())?;
+
+    let mut batch = keyspace.batch();
+    batch.require_absent(&partition, "a");
+    batch.insert(&partition, "a", "1");
+    batch.commit()?;
+    assert_eq!(&*partition.get("a")?.unwrap(), b"1");
+
+    let mut batch = keyspace.batch();
+    batch.require_absent(&partition, "a");
+    batch.insert(&partition, "a", "2");
+    batch.insert(&partition, "b", "2");
+
+    match batch.commit() {
+        Err(fjall::Error::PreconditionFailed(failed)) => {
+            assert_eq!(1, failed.len());
+            assert_eq!(&*failed[0].key, b"a");
+            assert_eq!(failed[0].precondition, Precondition::Absent);
+        }
+        other => panic!("unexpected commit result: {other:?}"),
+    }
+
+    assert_eq!(&*partition.get("a")?.unwrap(), b"1");
+    assert!(!partition.contains_key("b")?);
+
+    partition.remove("a")?;
+
+    let mut batch = keyspace.batch();
+    batch.require_absent(&partition, "a");
+    batch.insert(&partition, "a", "3");
+    batch.commit()?;
+    assert_eq!(&*partition.get("a")?.unwrap(), b"3");
+
+    Ok(())
+}
+
+#[test]
+fn batch_require_value() -> fjall::Result<()> {
+    let folder = tempfile::tempdir()?;
+
+    let keyspace = Config::new(folder).open()?;
+    let partition = keyspace.open_partition("default", PartitionCreateOptions::default())?;
+    let other = keyspace.open_partition("other", PartitionCreateOptions::default())?;
+
+    partition.insert("a", "1")?;
+    partition.rotate_memtable_and_wait()?;
+
+    let mut batch = keyspace.batch();
+    batch.require_value(&partition, "a", "1");
+    batch.insert(&other, "a", "1");
+    batch.commit()?;
+    assert!(other.contains_key("a")?);
+
+    let mut batch = keyspace.batch();
+    batch.require_value(&partition, "a", "2");
+    batch.require_value(&partition, "missing", "2");
+    batch.remove(&other, "a");
+
+    match batch.commit() {
+        Err(fjall::Error::PreconditionFailed(failed)) => assert_eq!(2, failed.len()),
+        other => panic!("unexpected commit result: {other:?}"),
+    }
+
+    assert!(other.contains_key("a")?);
+
+    Ok(())
+}
+
+#[test]
+fn batch_require_seqno_at_most() -> fjall::Result<()> {
+    let folder = tempfile::tempdir()?;
+
+    let keyspace = Config::new(folder).open()?;
+    let partition = keyspace.open_partition("default", PartitionCreateOptions::default())?;
+
+    partition.insert("a", "1")?;
+    let instant = keyspace.instant();
+
+    let mut batch = keyspace.batch();
+    batch.require_seqno_at_most(&partition, "a", instant - 1);
+    batch.require_seqno_at_most(&partition, "never_written", 0);
+    batch.insert(&partition, "a", "2");
+    batch.commit()?;
+
+    let mut batch = keyspace.batch();
+    batch.require_seqno_at_most(&partition, "a", instant - 1);
+    batch.insert(&partition, "a", "3");
+
+    match batch.commit() {
+        Err(fjall::Error::PreconditionFailed(failed)) => {
+            assert_eq!(1, failed.len());
+            assert_eq!(Some(instant), failed[0].actual_seqno);
+        }
+        other => panic!("unexpected commit result: {other:?}"),
+    }
+
+    assert_eq!(&*partition.get("a")?.unwrap(), b"2");
+
+    Ok(())
+}
+
+#[test]
+fn blob_batch_require_value() -> fjall::Result<()> {
+    let folder = tempfile::tempdir()?;
+
+    let keyspace = Config::new(folder).open()?;
+    let partition = keyspace.open_partition(
+        "default",
+        PartitionCreateOptions::default().with_kv_separation(KvSeparationOptions::default()),
+    )?;
+
+    let blob = "oxygen".repeat(128_000);
+
+    partition.insert("a", &blob)?;
+
+    let mut batch = keyspace.batch();
+    batch.require_value(&partition, "a", &blob);
+    batch.insert(&partition, "b", "1");
+    batch.commit()?;
+
+    partition.rotate_memtable_and_wait()?;
+
+    let mut batch = keyspace.batch();
+    batch.require_value(&partition, "a", &blob);
+    batch.require_value(&partition, "b", "1");
+    batch.insert(&partition, "c", "1");
+    batch.commit()?;
+
+    let mut batch = keyspace.batch();
+    batch.require_value(&partition, "a", "oxygen");
+    batch.insert(&partition, "d", "1");
+    assert!(matches!(
+        batch.commit(),
+        Err(fjall::Error::PreconditionFailed(_))
+    ));
+
+    Ok(())
+}
+
+#[test]
+fn batch_require_absent_concurrent_single_writes() -> fjall::Result<()> {
+    const ROUNDS: u32 = 20_000;
+
+    let folder = tempfile::tempdir()?;
+
+    let keyspace = Config::new(folder).open()?;
+    let partition = keyspace.open_partition("default", PartitionCreateOptions::default())?;
+
+    let barrier = std::sync::Arc::new(std::sync::Barrier::new(2));
+
+    let writer = {
+        let partition = partition.clone();
+        let barrier = barrier.clone();
+
+        std::thread::spawn(move || -> fjall::Result<()> {
+            for x in 0..ROUNDS {
+                barrier.wait();
+                partition.insert(x.to_be_bytes(), "single")?;
+            }
+            Ok(())
+        })
+    };
+
+    for x in 0..ROUNDS {
+        barrier.wait();
+
+        let mut batch = keyspace.batch();
+        batch.require_absent(&partition, x.to_be_bytes());
+        batch.insert(&partition, x.to_be_bytes(), "batch");
+
+        match batch.commit() {
+            Ok(()) | Err(fjall::Error::PreconditionFailed(_)) => {}
+            Err(e) => return Err(e),
+        }
+    }
+
+    writer.join().expect("should join")?;
+
+    // NOTE: If the batch was written after the single write, it must have seen it,
+    // so the single write always ends up being the latest version
+    for x in 0..ROUNDS {
+        assert_eq!(&*partition.get(x.to_be_bytes())?.unwrap(), b"single");
+    }
+
+    Ok(())
+}
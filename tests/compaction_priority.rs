@@ -0,0 +1,56 @@
+use fjall::{Config, PartitionCreateOptions};
+
+#[test_log::test]
+fn compaction_priority() -> fjall::Result<()> {
+    let folder = tempfile::tempdir()?;
+
+    let keyspace = Config::new(&folder).compaction_workers(0).open()?;
+    let bulk = keyspace.open_partition("bulk", PartitionCreateOptions::default())?;
+    let critical = keyspace.open_partition("critical", PartitionCreateOptions::default())?;
+
+    assert_eq!(1, critical.compaction_priority());
+    critical.set_compaction_priority(10);
+    assert_eq!(10, critical.compaction_priority());
+
+    for x in 0..3_u8 {
+        bulk.insert([x], "abc")?;
+        bulk.rotate_memtable_and_wait()?;
+    }
+
+    // Partitions are only queued once
+    assert_eq!(1, keyspace.compaction_queue_len());
+
+    critical.insert("a", "abc")?;
+    critical.rotate_memtable_and_wait()?;
+    assert_eq!(2, keyspace.compaction_queue_len());
+
+    assert_eq!(Some("critical".into()), keyspace.force_compaction());
+    assert_eq!(Some("bulk".into()), keyspace.force_compaction());
+    assert_eq!(None, keyspace.force_compaction());
+    assert_eq!(0, keyspace.compaction_queue_len());
+
+    Ok(())
+}
+
+#[test_log::test]
+fn compaction_priority_l0_segments() -> fjall::Result<()> {
+    let folder = tempfile::tempdir()?;
+
+    let keyspace = Config::new(&folder).compaction_workers(0).open()?;
+    let a = keyspace.open_partition("a", PartitionCreateOptions::default())?;
+    let b = keyspace.open_partition("b", PartitionCreateOptions::default())?;
+
+    a.insert("a", "abc")?;
+    a.rotate_memtable_and_wait()?;
+
+    for x in 0..3_u8 {
+        b.insert([x], "abc")?;
+        b.rotate_memtable_and_wait()?;
+    }
+
+    // With the same priority, the partition with more L0 segments goes first
+    assert_eq!(Some("b".into()), keyspace.force_compaction());
+    assert_eq!(Some("a".into()), keyspace.force_compaction());
+
+    Ok(())
+}
@@ -0,0 +1,151 @@
+use fjall::{Config, FaultyFs, JournalDump, JournalStopReason, PersistMode, RecoveryError, StdFs};
+use std::{path::PathBuf, sync::Arc};
+use test_log::test;
+
+fn write_journal(folder: &std::path::Path) -> fjall::Result<PathBuf> {
+    let keyspace = Config::new(folder).open()?;
+    let partition = keyspace.open_partition("default", Default::default())?;
+
+    partition.insert("a", "hello")?;
+
+    let mut batch = keyspace.batch();
+    batch.insert(&partition, "b", "world");
+    batch.remove(&partition, "a");
+    batch.commit()?;
+
+    keyspace.persist(PersistMode::SyncAll)?;
+
+    let journal = keyspace
+        .journals()?
+        .pop()
+        .expect("should have active journal");
+    Ok(journal.path)
+}
+
+#[test]
+fn journal_dump_valid() -> fjall::Result<()> {
+    let folder = tempfile::tempdir()?;
+    let path = write_journal(folder.path())?;
+
+    let dump = JournalDump::from_file(&path, Arc::new(StdFs))?;
+    assert_eq!(2, dump.batches.len());
+
+    // NOTE: The pre-allocated tail of the active journal is not corrupt
+    assert_eq!(None, dump.stop);
+
+    let first = dump.batches.first().expect("should exist");
+    assert_eq!(0, first.offset);
+    assert_eq!(0, first.seqno);
+    assert_eq!(1, first.item_count);
+    assert_eq!(Some(true), first.checksum_ok);
+    assert_eq!(5, first.items.first().expect("should exist").value_size);
+
+    let second = dump.batches.get(1).expect("should exist");
+    assert_eq!(1, second.seqno);
+    assert_eq!(2, second.items.len());
+    assert_eq!(Some(true), second.checksum_ok);
+
+    let tombstone = second.items.get(1).expect("should exist");
+    assert_eq!(&*tombstone.partition, "default");
+    assert_eq!(&*tombstone.key, b"a");
+    assert_eq!(fjall::ValueType::Tombstone, tombstone.value_type);
+
+    Ok(())
+}
+
+#[test]
+fn journal_dump_truncated_tail() -> fjall::Result<()> {
+    let folder = tempfile::tempdir()?;
+    let path = write_journal(folder.path())?;
+
+    let last_item = JournalDump::from_file(&path, Arc::new(StdFs))?
+        .batches
+        .pop()
+        .and_then(|batch| batch.items.last().cloned())
+        .expect("should exist");
+
+    // NOTE: Cut the journal in the middle of the last item
+    let size = last_item.offset + 2;
+    std::fs::OpenOptions::new()
+        .write(true)
+        .open(&path)?
+        .set_len(size)?;
+
+    let dump = JournalDump::from_file(&path, Arc::new(StdFs))?;
+    assert_eq!(2, dump.batches.len());
+
+    let second = dump.batches.get(1).expect("should exist");
+    assert_eq!(None, second.checksum_ok);
+
+    let stop = dump.stop.expect("should stop");
+    assert_eq!(second.offset, stop.offset);
+    assert_eq!(JournalStopReason::CorruptMarker, stop.reason);
+
+    // NOTE: Dumping never truncates the journal
+    assert_eq!(size, std::fs::metadata(&path)?.len());
+
+    Ok(())
+}
+
+#[test]
+fn journal_dump_checksum_mismatch() -> fjall::Result<()> {
+    let folder = tempfile::tempdir()?;
+    let path = write_journal(folder.path())?;
+
+    let mut bytes = std::fs::read(&path)?;
+    let pos = bytes
+        .windows(5)
+        .position(|window| window == b"hello")
+        .expect("should contain value");
+    *bytes.get_mut(pos).expect("should exist") = b'j';
+    std::fs::write(&path, &bytes)?;
+
+    let dump = JournalDump::from_file(&path, Arc::new(StdFs))?;
+    assert_eq!(2, dump.batches.len());
+    assert_eq!(
+        Some(false),
+        dump.batches.first().expect("should exist").checksum_ok
+    );
+    assert_eq!(
+        Some(true),
+        dump.batches.get(1).expect("should exist").checksum_ok
+    );
+
+    let stop = dump.stop.expect("should stop");
+    assert_eq!(0, stop.offset);
+    assert_eq!(
+        JournalStopReason::Broken(RecoveryError::ChecksumMismatch),
+        stop.reason
+    );
+
+    assert!(matches!(
+        Config::new(folder.path()).open(),
+        Err(fjall::Error::JournalRecovery(
+            RecoveryError::ChecksumMismatch
+        ))
+    ));
+
+    Ok(())
+}
+
+#[test]
+fn journal_dump_reads_through_fs() -> fjall::Result<()> {
+    let folder = tempfile::tempdir()?;
+    let path = write_journal(folder.path())?;
+
+    let fs = FaultyFs::default();
+    assert_eq!(
+        2,
+        JournalDump::from_file(&path, Arc::new(fs.clone()))?
+            .batches
+            .len()
+    );
+
+    fs.fail_reads(true);
+    assert!(matches!(
+        JournalDump::from_file(&path, Arc::new(fs)),
+        Err(fjall::Error::Io(_))
+    ));
+
+    Ok(())
+}
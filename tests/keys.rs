@@ -0,0 +1,206 @@
+use fjall::{
+    keys::{self, Element, Int, TupleCodec},
+    Config, PartitionCreateOptions, TypedPartition,
+};
+
+fn assert_sorted(tuples: &[Vec<Element>]) {
+    for pair in tuples.windows(2) {
+        let (a, b) = (&pair[0], &pair[1]);
+        assert!(keys::encode(a) < keys::encode(b), "expected {a:?} < {b:?}");
+    }
+}
+
+#[test_log::test]
+fn keys_order() {
+    let ints = [
+        -(i128::from(u64::MAX)),
+        i128::from(i64::MIN),
+        -256,
+        -255,
+        -1,
+        0,
+        1,
+        255,
+        256,
+        i128::from(i64::MAX),
+        i128::from(u64::MAX),
+    ];
+    assert_sorted(&ints.map(|i| vec![Element::Int(Int::new(i).unwrap())]));
+
+    // Integers need to fit in 64 bits
+    assert!(Int::new(i128::from(u64::MAX) + 1).is_err());
+    assert!(Int::new(-i128::from(u64::MAX) - 1).is_err());
+
+    let floats = [
+        f64::NEG_INFINITY,
+        -1.5,
+        -0.0,
+        0.0,
+        f64::MIN_POSITIVE,
+        2.0,
+        f64::INFINITY,
+    ];
+    assert_sorted(&floats.map(|f| vec![f.into()]));
+
+    assert_sorted(&[
+        vec!["".into()],
+        vec!["\0".into()],
+        vec!["a".into()],
+        vec!["a\0".into()],
+        vec!["a\0".into(), 0.into()],
+        vec!["ab".into()],
+    ]);
+
+    // Elements are compared one by one
+    assert_sorted(&[
+        vec![1.into()],
+        vec![1.into(), "a".into()],
+        vec![1.into(), "b".into()],
+        vec![2.into()],
+    ]);
+
+    // Different types are ordered by type
+    assert_sorted(&[
+        vec![b"z".as_slice().into()],
+        vec!["a".into()],
+        vec![Element::Tuple(vec![])],
+        vec![(-5).into()],
+        vec![1.0.into()],
+        vec![false.into()],
+        vec![true.into()],
+    ]);
+
+    // Nested tuples sort like their elements
+    assert_sorted(&[
+        vec![vec![Element::from(1)].into()],
+        vec![vec![Element::from(1), "a".into()].into()],
+        vec![vec![Element::from(2)].into(), 0.into()],
+    ]);
+}
+
+#[test_log::test]
+fn keys_desc() {
+    assert_sorted(&[
+        vec![1.into(), Element::desc(300), 1.into()],
+        vec![1.into(), Element::desc(20), 0.into()],
+        vec![1.into(), Element::desc(20), 1.into()],
+        vec![1.into(), Element::desc(-1)],
+        vec![2.into(), Element::desc(100)],
+    ]);
+
+    assert_sorted(&[
+        vec![Element::desc("ab")],
+        vec![Element::desc("a\0")],
+        vec![Element::desc("a")],
+        vec![Element::desc("a"), 1.into()],
+        vec![Element::desc("")],
+    ]);
+
+    assert_sorted(&[
+        vec![Element::desc(vec![Element::from(2)])],
+        vec![Element::desc(vec![Element::from(1), "a".into()])],
+        vec![Element::desc(vec![Element::from(1)])],
+    ]);
+}
+
+#[test_log::test]
+fn keys_roundtrip() {
+    let tuple = vec![
+        Element::from(b"\0\xFFx".as_slice()),
+        "tenant\0".into(),
+        0.into(),
+        (-70_000).into(),
+        u64::MAX.into(),
+        i64::MIN.into(),
+        (-2.5).into(),
+        true.into(),
+        vec![
+            Element::from("nested"),
+            Element::desc(vec![Element::from(7)]),
+        ]
+        .into(),
+        Element::desc("desc"),
+        Element::desc(-3),
+        Element::desc(f64::INFINITY),
+    ];
+
+    let encoded = keys::encode(&tuple);
+    assert_eq!(tuple, keys::decode(&encoded).unwrap());
+
+    assert_eq!(Vec::<Element>::new(), keys::decode(&[]).unwrap());
+
+    // Truncated
+    assert!(keys::decode(encoded.get(..encoded.len() - 1).unwrap()).is_err());
+
+    // Unknown type code
+    assert!(keys::decode(&[0x80]).is_err());
+}
+
+#[test_log::test]
+fn keys_prefix_range() -> fjall::Result<()> {
+    let folder = tempfile::tempdir()?;
+
+    let keyspace = Config::new(&folder).open()?;
+    let partition = keyspace.open_partition("default", PartitionCreateOptions::default())?;
+
+    for (idx, tuple) in [
+        vec![Element::from("a")],
+        vec!["a".into(), 1.into()],
+        vec!["a".into(), 2.into()],
+        vec!["a\0".into(), 1.into()],
+        vec!["ab".into(), 1.into()],
+    ]
+    .iter()
+    .enumerate()
+    {
+        partition.insert(keys::encode(tuple), idx.to_string())?;
+    }
+
+    let values = |range| {
+        partition
+            .range(range)
+            .map(|kv| kv.map(|(_, v)| String::from_utf8(v.to_vec()).unwrap()))
+            .collect::<fjall::Result<Vec<_>>>()
+    };
+
+    // Prefix matches whole elements only
+    assert_eq!(
+        vec!["0", "1", "2"],
+        values(keys::prefix_range(&["a".into()]))?
+    );
+    assert_eq!(vec!["1", "2"], values(keys::children_range(&["a".into()]))?);
+    assert_eq!(
+        vec!["0", "1", "2", "3", "4"],
+        values(keys::prefix_range(&[]))?
+    );
+    assert!(values(keys::prefix_range(&["b".into()]))?.is_empty());
+
+    Ok(())
+}
+
+#[test_log::test]
+fn keys_typed_partition() -> fjall::Result<()> {
+    let folder = tempfile::tempdir()?;
+
+    let keyspace = Config::new(&folder).open()?;
+    let partition = keyspace.open_partition("default", PartitionCreateOptions::default())?;
+
+    let typed = TypedPartition::<Vec<Element>, Vec<Element>, TupleCodec>::new(partition);
+
+    typed.insert(&vec![1.into(), "a".into()], &vec![true.into()])?;
+    typed.insert(&vec![0.into(), "b".into()], &vec![false.into()])?;
+
+    assert_eq!(
+        Some((vec![0.into(), "b".into()], vec![false.into()])),
+        typed.first_key_value()?
+    );
+    assert_eq!(
+        vec![vec![Element::from(1), "a".into()]],
+        typed
+            .prefix(&vec![1.into()])
+            .map(|kv| kv.map(|(k, _)| k))
+            .collect::<fjall::Result<Vec<_>>>()?
+    );
+
+    Ok(())
+}
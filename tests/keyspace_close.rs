@@ -0,0 +1,137 @@
+use fjall::{AbstractTree, CloseOptions, Config, PartitionCreateOptions};
+use std::time::Duration;
+
+#[test_log::test]
+fn keyspace_close_flush() -> fjall::Result<()> {
+    let folder = tempfile::tempdir()?;
+
+    {
+        let keyspace = Config::new(&folder).open()?;
+        let partition = keyspace.open_partition("default", PartitionCreateOptions::default())?;
+
+        for x in 0..100_u64 {
+            partition.insert(x.to_be_bytes(), "abc")?;
+        }
+        drop(partition);
+
+        keyspace.close(CloseOptions::default())?;
+    }
+
+    {
+        let keyspace = Config::new(&folder).open()?;
+        let partition = keyspace.open_partition("default", PartitionCreateOptions::default())?;
+
+        // Nothing was replayed from the journal
+        assert_eq!(0, keyspace.write_buffer_size());
+        assert_eq!(1, keyspace.journal_count());
+        assert_eq!(1, partition.tree.segment_count());
+        assert_eq!(100, partition.len()?);
+    }
+
+    Ok(())
+}
+
+#[test_log::test]
+fn keyspace_close_no_flush() -> fjall::Result<()> {
+    let folder = tempfile::tempdir()?;
+
+    {
+        let keyspace = Config::new(&folder).open()?;
+        let partition = keyspace.open_partition("default", PartitionCreateOptions::default())?;
+        partition.insert("a", "abc")?;
+        drop(partition);
+
+        keyspace.close(CloseOptions {
+            flush_memtables: false,
+            ..Default::default()
+        })?;
+    }
+
+    {
+        let keyspace = Config::new(&folder).open()?;
+        let partition = keyspace.open_partition("default", PartitionCreateOptions::default())?;
+
+        assert_eq!(0, partition.tree.segment_count());
+        assert!(partition.contains_key("a")?);
+    }
+
+    Ok(())
+}
+
+#[test_log::test]
+fn keyspace_close_wait_for_compactions() -> fjall::Result<()> {
+    let folder = tempfile::tempdir()?;
+
+    {
+        let keyspace = Config::new(&folder).open()?;
+        let partition = keyspace.open_partition("default", PartitionCreateOptions::default())?;
+
+        for x in 0..10_u64 {
+            partition.insert(x.to_be_bytes(), "abc")?;
+            partition.rotate_memtable_and_wait()?;
+        }
+        drop(partition);
+
+        keyspace.close(CloseOptions {
+            wait_for_compactions: true,
+            timeout: Some(Duration::from_secs(60)),
+            ..Default::default()
+        })?;
+    }
+
+    {
+        let keyspace = Config::new(&folder).open()?;
+        let partition = keyspace.open_partition("default", PartitionCreateOptions::default())?;
+        assert_eq!(10, partition.len()?);
+    }
+
+    Ok(())
+}
+
+#[test_log::test]
+fn keyspace_close_timeout() -> fjall::Result<()> {
+    let folder = tempfile::tempdir()?;
+
+    let keyspace = Config::new(&folder).open()?;
+    let partition = keyspace.open_partition("default", PartitionCreateOptions::default())?;
+    partition.insert("a", "abc")?;
+
+    assert!(matches!(
+        keyspace.close(CloseOptions {
+            timeout: Some(Duration::ZERO),
+            ..Default::default()
+        }),
+        Err(fjall::Error::Timeout)
+    ));
+
+    Ok(())
+}
+
+#[test_log::test]
+fn keyspace_close_restores_paused_background_work() -> fjall::Result<()> {
+    let folder = tempfile::tempdir()?;
+
+    let keyspace = Config::new(&folder).open()?;
+    let partition = keyspace.open_partition("default", PartitionCreateOptions::default())?;
+    partition.insert("a", "abc")?;
+
+    let other = keyspace.clone();
+    other.pause_background_work();
+
+    keyspace.close(CloseOptions {
+        wait_for_compactions: true,
+        timeout: Some(Duration::from_secs(60)),
+        ..Default::default()
+    })?;
+
+    assert!(other.is_background_work_paused());
+    assert_eq!(1, partition.segment_count());
+
+    other.resume_background_work();
+
+    let keyspace = other.clone();
+    keyspace.close(CloseOptions::default())?;
+    assert!(!other.is_background_work_paused());
+
+    Ok(())
+}
@@ -0,0 +1,311 @@
+#![cfg(feature = "encryption")]
+
+use fjall::{
+    Config, EncryptionError, EncryptionKey, JournalDump, KeyId, KeyProvider,
+    PartitionCreateOptions, PersistMode, StdFs,
+};
+use std::{path::Path, sync::Arc};
+use test_log::test;
+
+struct Keys {
+    current: KeyId,
+    keys: Vec<(KeyId, EncryptionKey)>,
+}
+
+impl Keys {
+    fn single(id: KeyId, key: u8) -> Self {
+        Self {
+            current: id,
+            keys: vec![(id, [key; 32])],
+        }
+    }
+}
+
+impl KeyProvider for Keys {
+    fn current_key_id(&self) -> KeyId {
+        self.current
+    }
+
+    fn key(&self, id: KeyId) -> Option<EncryptionKey> {
+        self.keys
+            .iter()
+            .find(|(key_id, _)| *key_id == id)
+            .map(|(_, key)| *key)
+    }
+}
+
+fn journal_bytes(folder: &Path) -> fjall::Result<Vec<u8>> {
+    let mut bytes = vec![];
+
+    for dirent in std::fs::read_dir(folder.join("journals"))? {
+        bytes.extend(std::fs::read(dirent?.path())?);
+    }
+
+    Ok(bytes)
+}
+
+fn contains(haystack: &[u8], needle: &[u8]) -> bool {
+    haystack
+        .windows(needle.len())
+        .any(|window| window == needle)
+}
+
+fn is_encryption_error(result: fjall::Result<fjall::Keyspace>, expected: EncryptionError) -> bool {
+    matches!(result, Err(fjall::Error::Encryption(e)) if e == expected)
+}
+
+#[test]
+fn keyspace_encryption_recover() -> fjall::Result<()> {
+    let folder = tempfile::tempdir()?;
+
+    {
+        let keyspace = Config::new(&folder).encryption(Keys::single(0, 1)).open()?;
+        let tree = keyspace.open_partition("default", PartitionCreateOptions::default())?;
+
+        tree.insert("a", "secret value")?;
+
+        let mut batch = keyspace.batch();
+        batch.insert(&tree, "b", "another secret");
+        batch.remove(&tree, "c");
+        batch.commit()?;
+
+        keyspace.persist(PersistMode::SyncAll)?;
+
+        let journal = keyspace
+            .journals()?
+            .pop()
+            .expect("should have active journal");
+        assert_eq!(2, journal.batch_count);
+        assert_eq!(Some((0, 1)), journal.seqnos);
+    }
+
+    let bytes = journal_bytes(folder.path())?;
+    assert!(!contains(&bytes, b"secret value"));
+    assert!(!contains(&bytes, b"another secret"));
+
+    {
+        let keyspace = Config::new(&folder).encryption(Keys::single(0, 1)).open()?;
+        let tree = keyspace.open_partition("default", PartitionCreateOptions::default())?;
+
+        assert_eq!(2, tree.len()?);
+        assert_eq!(Some("secret value".as_bytes().into()), tree.get("a")?);
+        assert_eq!(Some("another secret".as_bytes().into()), tree.get("b")?);
+
+        // NOTE: The recovered journal is appended to
+        tree.insert("c", "more")?;
+    }
+
+    {
+        let keyspace = Config::new(&folder).encryption(Keys::single(0, 1)).open()?;
+        let tree = keyspace.open_partition("default", PartitionCreateOptions::default())?;
+
+        assert_eq!(3, tree.len()?);
+        assert!(keyspace.verify(Default::default())?.is_ok());
+    }
+
+    Ok(())
+}
+
+#[test]
+fn keyspace_encryption_key_errors() -> fjall::Result<()> {
+    let folder = tempfile::tempdir()?;
+
+    {
+        let keyspace = Config::new(&folder).encryption(Keys::single(0, 1)).open()?;
+        let tree = keyspace.open_partition("default", PartitionCreateOptions::default())?;
+        tree.insert("a", "a")?;
+    }
+
+    assert!(is_encryption_error(
+        Config::new(&folder).encryption(Keys::single(0, 2)).open(),
+        EncryptionError::WrongKey(0),
+    ));
+
+    assert!(is_encryption_error(
+        Config::new(&folder).encryption(Keys::single(1, 1)).open(),
+        EncryptionError::UnknownKey(0),
+    ));
+
+    assert!(is_encryption_error(
+        Config::new(&folder).open(),
+        EncryptionError::NoKeyProvider,
+    ));
+
+    assert!(is_encryption_error(
+        Config::new(&folder).read_only(true).open(),
+        EncryptionError::NoKeyProvider,
+    ));
+
+    // NOTE: Failed opens do not change the journal
+    let keyspace = Config::new(&folder).encryption(Keys::single(0, 1)).open()?;
+    let tree = keyspace.open_partition("default", PartitionCreateOptions::default())?;
+    assert_eq!(Some("a".as_bytes().into()), tree.get("a")?);
+
+    Ok(())
+}
+
+#[test]
+fn keyspace_encryption_key_rotation() -> fjall::Result<()> {
+    let folder = tempfile::tempdir()?;
+
+    let keys = |current| Keys {
+        current,
+        keys: vec![(0, [1; 32]), (1, [2; 32])],
+    };
+
+    {
+        let keyspace = Config::new(&folder).encryption(keys(0)).open()?;
+        let tree = keyspace.open_partition("default", PartitionCreateOptions::default())?;
+        let other = keyspace.open_partition("other", PartitionCreateOptions::default())?;
+
+        // NOTE: Keeps the sealed journal from being evicted
+        other.insert("pinned", "pinned")?;
+
+        tree.insert("a", "a")?;
+        tree.rotate_memtable()?;
+        tree.insert("b", "b")?;
+    }
+
+    // NOTE: Existing journals still need the old key
+    assert!(is_encryption_error(
+        Config::new(&folder).encryption(Keys::single(1, 2)).open(),
+        EncryptionError::UnknownKey(0),
+    ));
+
+    {
+        let keyspace = Config::new(&folder).encryption(keys(1)).open()?;
+        let tree = keyspace.open_partition("default", PartitionCreateOptions::default())?;
+        let other = keyspace.open_partition("other", PartitionCreateOptions::default())?;
+
+        assert_eq!(2, tree.len()?);
+        assert_eq!(1, other.len()?);
+
+        tree.rotate_memtable()?;
+        tree.insert("c", "c")?;
+        keyspace.persist(PersistMode::SyncAll)?;
+
+        // NOTE: Only the new journal is encrypted with the current key
+        let journal = keyspace
+            .journals()?
+            .pop()
+            .expect("should have active journal");
+
+        let dump =
+            JournalDump::from_encrypted_file(&journal.path, Arc::new(StdFs), Keys::single(1, 2))?;
+        assert_eq!(1, dump.batches.len());
+
+        assert!(matches!(
+            JournalDump::from_encrypted_file(&journal.path, Arc::new(StdFs), Keys::single(0, 1)),
+            Err(fjall::Error::Encryption(EncryptionError::UnknownKey(1)))
+        ));
+    }
+
+    {
+        let keyspace = Config::new(&folder).encryption(keys(1)).open()?;
+        let tree = keyspace.open_partition("default", PartitionCreateOptions::default())?;
+        assert_eq!(3, tree.len()?);
+    }
+
+    Ok(())
+}
+
+#[test]
+fn keyspace_encryption_corrupt_tail() -> fjall::Result<()> {
+    let folder = tempfile::tempdir()?;
+
+    let path = {
+        let keyspace = Config::new(&folder).encryption(Keys::single(0, 1)).open()?;
+        let tree = keyspace.open_partition("default", PartitionCreateOptions::default())?;
+
+        tree.insert("a", "a")?;
+        tree.insert("b", "b")?;
+
+        keyspace.persist(PersistMode::SyncAll)?;
+
+        keyspace
+            .journals()?
+            .pop()
+            .expect("should have active journal")
+            .path
+    };
+
+    let dump = JournalDump::from_encrypted_file(&path, Arc::new(StdFs), Keys::single(0, 1))?;
+    assert_eq!(2, dump.batches.len());
+    assert_eq!(None, dump.stop);
+
+    let offset = dump.batches.get(1).expect("should exist").offset;
+
+    // NOTE: Flip a byte of the last frame's ciphertext
+    {
+        let mut bytes = std::fs::read(&path)?;
+        let byte = bytes
+            .get_mut(usize::try_from(offset).expect("should fit") + 32)
+            .expect("should exist");
+        *byte ^= 0xFF;
+        std::fs::write(&path, bytes)?;
+    }
+
+    {
+        let keyspace = Config::new(&folder).encryption(Keys::single(0, 1)).open()?;
+        let tree = keyspace.open_partition("default", PartitionCreateOptions::default())?;
+
+        assert_eq!(1, tree.len()?);
+        assert_eq!(Some("a".as_bytes().into()), tree.get("a")?);
+
+        // NOTE: The journal was truncated, so writing continues after the first batch
+        tree.insert("c", "c")?;
+    }
+
+    {
+        let keyspace = Config::new(&folder).encryption(Keys::single(0, 1)).open()?;
+        let tree = keyspace.open_partition("default", PartitionCreateOptions::default())?;
+
+        assert_eq!(2, tree.len()?);
+        assert_eq!(Some("c".as_bytes().into()), tree.get("c")?);
+    }
+
+    Ok(())
+}
+
+#[test]
+fn keyspace_encryption_enable_later() -> fjall::Result<()> {
+    let folder = tempfile::tempdir()?;
+
+    {
+        let keyspace = Config::new(&folder).open()?;
+        let tree = keyspace.open_partition("default", PartitionCreateOptions::default())?;
+        tree.insert("a", "plaintext value")?;
+    }
+
+    {
+        let keyspace = Config::new(&folder).encryption(Keys::single(0, 1)).open()?;
+        let tree = keyspace.open_partition("default", PartitionCreateOptions::default())?;
+
+        assert_eq!(Some("plaintext value".as_bytes().into()), tree.get("a")?);
+
+        // NOTE: The plaintext journal stays plaintext until it is rotated
+        tree.insert("b", "b")?;
+        tree.rotate_memtable_and_wait()?;
+        tree.insert("c", "secret value")?;
+        keyspace.persist(PersistMode::SyncAll)?;
+
+        let journal = keyspace
+            .journals()?
+            .pop()
+            .expect("should have active journal");
+        assert!(matches!(
+            JournalDump::from_file(&journal.path, Arc::new(StdFs)),
+            Err(fjall::Error::Encryption(EncryptionError::NoKeyProvider))
+        ));
+    }
+
+    assert!(!contains(&journal_bytes(folder.path())?, b"secret value"));
+
+    {
+        let keyspace = Config::new(&folder).encryption(Keys::single(0, 1)).open()?;
+        let tree = keyspace.open_partition("default", PartitionCreateOptions::default())?;
+        assert_eq!(3, tree.len()?);
+    }
+
+    Ok(())
+}
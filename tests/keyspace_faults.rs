@@ -0,0 +1,260 @@
+use fjall::{Config, FaultyFs, PartitionCreateOptions, PersistMode};
+use test_log::test;
+
+#[test]
+fn keyspace_faults_crash_drops_unsynced() -> fjall::Result<()> {
+    let folder = tempfile::tempdir()?;
+    let fs = FaultyFs::default();
+
+    {
+        let keyspace = Config::new(&folder).fs(fs.clone()).open()?;
+        let partition = keyspace.open_partition("default", PartitionCreateOptions::default())?;
+
+        for x in 0..10_u64 {
+            partition.insert(x.to_be_bytes(), "abc")?;
+        }
+        keyspace.persist(PersistMode::SyncAll)?;
+
+        for x in 10..20_u64 {
+            partition.insert(x.to_be_bytes(), "abc")?;
+        }
+
+        // NOTE: Written to the OS, but not synced
+        keyspace.persist(PersistMode::Buffer)?;
+
+        fs.fail_sync(true);
+    }
+
+    fs.crash()?;
+
+    let keyspace = Config::new(&folder).fs(fs.clone()).open()?;
+    let partition = keyspace.open_partition("default", PartitionCreateOptions::default())?;
+    assert_eq!(10, partition.len()?);
+    assert!(partition.contains_key(9_u64.to_be_bytes())?);
+    assert!(!partition.contains_key(10_u64.to_be_bytes())?);
+
+    // NOTE: The recovered journal can be written to again
+    partition.insert("a", "def")?;
+    keyspace.persist(PersistMode::SyncAll)?;
+    drop(partition);
+    drop(keyspace);
+
+    fs.crash()?;
+
+    let keyspace = Config::new(&folder).open()?;
+    let partition = keyspace.open_partition("default", PartitionCreateOptions::default())?;
+    assert_eq!(11, partition.len()?);
+
+    Ok(())
+}
+
+#[test]
+fn keyspace_faults_fsync_poisons() -> fjall::Result<()> {
+    let folder = tempfile::tempdir()?;
+    let fs = FaultyFs::default();
+
+    {
+        let keyspace = Config::new(&folder).fs(fs.clone()).open()?;
+        let partition = keyspace.open_partition("default", PartitionCreateOptions::default())?;
+
+        fs.fail_sync(true);
+
+        partition.insert("a", "abc")?;
+
+        assert!(matches!(
+            keyspace.persist(PersistMode::SyncAll),
+            Err(fjall::Error::Poisoned)
+        ));
+        assert!(matches!(
+            partition.insert("b", "def"),
+            Err(fjall::Error::Poisoned)
+        ));
+
+        let mut batch = keyspace.batch().durability(Some(PersistMode::SyncData));
+        batch.insert(&partition, "c", "ghi");
+        assert!(matches!(batch.commit(), Err(fjall::Error::Poisoned)));
+
+        fs.fail_sync(false);
+    }
+
+    let keyspace = Config::new(&folder).open()?;
+    let partition = keyspace.open_partition("default", PartitionCreateOptions::default())?;
+    assert_eq!(Some("abc".as_bytes().into()), partition.get("a")?);
+    assert!(!partition.contains_key("b")?);
+
+    Ok(())
+}
+
+#[test]
+fn keyspace_faults_batch_poisons() -> fjall::Result<()> {
+    let folder = tempfile::tempdir()?;
+    let fs = FaultyFs::default();
+
+    let keyspace = Config::new(&folder).fs(fs.clone()).open()?;
+    let partition = keyspace.open_partition("default", PartitionCreateOptions::default())?;
+
+    fs.fail_sync(true);
+
+    let mut batch = keyspace.batch().durability(Some(PersistMode::SyncAll));
+    batch.insert(&partition, "a", "abc");
+    assert!(matches!(batch.commit(), Err(fjall::Error::Poisoned)));
+
+    fs.fail_sync(false);
+
+    assert!(matches!(
+        keyspace.persist(PersistMode::SyncAll),
+        Err(fjall::Error::Poisoned)
+    ));
+
+    Ok(())
+}
+
+#[test]
+fn keyspace_faults_torn_write() -> fjall::Result<()> {
+    let folder = tempfile::tempdir()?;
+    let fs = FaultyFs::default();
+
+    {
+        let keyspace = Config::new(&folder).fs(fs.clone()).open()?;
+        let partition = keyspace.open_partition("default", PartitionCreateOptions::default())?;
+
+        partition.insert("a", "abc")?;
+        keyspace.persist(PersistMode::SyncAll)?;
+
+        fs.tear_next_write();
+
+        let mut batch = keyspace.batch().durability(Some(PersistMode::SyncAll));
+        batch.insert(&partition, "b", "def");
+        batch.insert(&partition, "c", "ghi");
+        assert!(matches!(batch.commit(), Err(fjall::Error::Poisoned)));
+
+        // NOTE: Keep the torn batch as it is
+        fs.set_space_left(Some(0));
+    }
+
+    fs.set_space_left(None);
+
+    let keyspace = Config::new(&folder).fs(fs).open()?;
+    let partition = keyspace.open_partition("default", PartitionCreateOptions::default())?;
+    assert_eq!(1, partition.len()?);
+    assert!(partition.contains_key("a")?);
+
+    partition.insert("d", "jkl")?;
+    assert_eq!(2, partition.len()?);
+
+    Ok(())
+}
+
+#[test]
+fn keyspace_faults_no_space() -> fjall::Result<()> {
+    let folder = tempfile::tempdir()?;
+    let fs = FaultyFs::default();
+
+    {
+        let keyspace = Config::new(&folder).fs(fs.clone()).open()?;
+        let partition = keyspace.open_partition(
+            "default",
+            PartitionCreateOptions::default().manual_journal_persist(true),
+        )?;
+
+        partition.insert("a", "abc")?;
+        keyspace.persist(PersistMode::SyncAll)?;
+
+        fs.set_space_left(Some(100));
+
+        // NOTE: The journal is buffered, so the write only fails when persisting
+        partition.insert("b", "x".repeat(1_000))?;
+        assert!(matches!(
+            keyspace.persist(PersistMode::Buffer),
+            Err(fjall::Error::Poisoned)
+        ));
+    }
+
+    fs.crash()?;
+
+    let keyspace = Config::new(&folder).open()?;
+    let partition = keyspace.open_partition("default", PartitionCreateOptions::default())?;
+    assert_eq!(1, partition.len()?);
+    drop(partition);
+    drop(keyspace);
+
+    // NOTE: Without manual persistence, every write is flushed to the OS immediately
+    let keyspace = Config::new(&folder).fs(fs.clone()).open()?;
+    let partition = keyspace.open_partition("other", PartitionCreateOptions::default())?;
+
+    fs.set_space_left(Some(0));
+
+    assert!(matches!(
+        partition.insert("c", "ghi"),
+        Err(fjall::Error::Io(e)) if e.raw_os_error().is_some()
+    ));
+
+    Ok(())
+}
+
+#[test]
+fn keyspace_faults_recovery_read_error() -> fjall::Result<()> {
+    let folder = tempfile::tempdir()?;
+    let fs = FaultyFs::default();
+
+    {
+        let keyspace = Config::new(&folder).fs(fs.clone()).open()?;
+        let partition = keyspace.open_partition("default", PartitionCreateOptions::default())?;
+
+        partition.insert("a", "abc")?;
+        keyspace.persist(PersistMode::SyncAll)?;
+    }
+
+    fs.fail_reads(true);
+
+    assert!(matches!(
+        Config::new(&folder).fs(fs.clone()).open(),
+        Err(fjall::Error::Io(_))
+    ));
+
+    fs.fail_reads(false);
+
+    let keyspace = Config::new(&folder).fs(fs).open()?;
+    let partition = keyspace.open_partition("default", PartitionCreateOptions::default())?;
+    assert_eq!(Some("abc".as_bytes().into()), partition.get("a")?);
+
+    Ok(())
+}
+
+#[test]
+fn keyspace_faults_crash_after_journal_rotation() -> fjall::Result<()> {
+    let folder = tempfile::tempdir()?;
+    let fs = FaultyFs::default();
+
+    {
+        let keyspace = Config::new(&folder).fs(fs.clone()).open()?;
+        let a = keyspace.open_partition("a", PartitionCreateOptions::default())?;
+        let b = keyspace.open_partition("b", PartitionCreateOptions::default())?;
+
+        a.insert("a", "abc")?;
+        b.insert("b", "def")?;
+        keyspace.persist(PersistMode::SyncAll)?;
+
+        // NOTE: Seals the journal, which still holds the data of partition b
+        a.rotate_memtable()?;
+
+        a.insert("c", "ghi")?;
+        b.insert("d", "jkl")?;
+
+        // NOTE: The keyspace cannot sync on drop anymore
+        fs.fail_sync(true);
+    }
+
+    fs.crash()?;
+
+    let keyspace = Config::new(&folder).fs(fs).open()?;
+    let a = keyspace.open_partition("a", PartitionCreateOptions::default())?;
+    let b = keyspace.open_partition("b", PartitionCreateOptions::default())?;
+
+    assert_eq!(Some("abc".as_bytes().into()), a.get("a")?);
+    assert_eq!(Some("def".as_bytes().into()), b.get("b")?);
+    assert!(!a.contains_key("c")?);
+    assert!(!b.contains_key("d")?);
+
+    Ok(())
+}
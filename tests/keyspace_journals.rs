@@ -0,0 +1,54 @@
+use fjall::{Config, PartitionCreateOptions};
+
+#[test_log::test]
+fn keyspace_journals() -> fjall::Result<()> {
+    let folder = tempfile::tempdir()?;
+
+    let keyspace = Config::new(&folder).flush_workers(0).open()?;
+    let partition = keyspace.open_partition("default", PartitionCreateOptions::default())?;
+
+    let journals = keyspace.journals()?;
+    assert_eq!(1, journals.len());
+    assert!(!journals[0].sealed);
+    assert_eq!(0, journals[0].batch_count);
+    assert_eq!(None, journals[0].seqnos);
+
+    partition.insert("a", "abc")?;
+    partition.insert("b", "abc")?;
+    partition.rotate_memtable()?;
+    partition.insert("c", "abc")?;
+
+    let journals = keyspace.journals()?;
+    assert_eq!(2, journals.len());
+
+    assert!(journals[0].sealed);
+    assert_eq!(2, journals[0].batch_count);
+    assert_eq!(Some((0, 1)), journals[0].seqnos);
+
+    assert!(!journals[1].sealed);
+    assert_eq!(1, journals[1].batch_count);
+    assert_eq!(Some((2, 2)), journals[1].seqnos);
+    assert!(journals[1].size > 0);
+
+    Ok(())
+}
+
+#[test_log::test]
+fn partition_major_compact() -> fjall::Result<()> {
+    let folder = tempfile::tempdir()?;
+
+    let keyspace = Config::new(&folder).compaction_workers(0).open()?;
+    let partition = keyspace.open_partition("default", PartitionCreateOptions::default())?;
+
+    for x in 0..5_u64 {
+        partition.insert(x.to_be_bytes(), "abc")?;
+        partition.rotate_memtable_and_wait()?;
+    }
+    assert_eq!(5, partition.segment_count());
+
+    partition.major_compact()?;
+    assert_eq!(1, partition.segment_count());
+    assert_eq!(5, partition.len()?);
+
+    Ok(())
+}
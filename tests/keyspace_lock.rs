@@ -0,0 +1,80 @@
+use fjall::{Config, Keyspace, PartitionCreateOptions, RepairOptions};
+use test_log::test;
+
+#[test]
+fn keyspace_lock_double_open() -> fjall::Result<()> {
+    let folder = tempfile::tempdir()?;
+
+    let keyspace = Config::new(&folder).open()?;
+    let partition = keyspace.open_partition("default", PartitionCreateOptions::default())?;
+    partition.insert("a", "abc")?;
+
+    let pid = std::process::id();
+
+    assert!(matches!(
+        Config::new(&folder).open(),
+        Err(fjall::Error::Locked { pid: Some(x) }) if x == pid
+    ));
+    assert!(matches!(
+        Config::new(&folder).open_transactional(),
+        Err(fjall::Error::Locked { .. })
+    ));
+    assert!(matches!(
+        Keyspace::check_lock(&folder),
+        Err(fjall::Error::Locked { pid: Some(x) }) if x == pid
+    ));
+
+    drop(partition);
+    drop(keyspace);
+
+    Keyspace::check_lock(&folder)?;
+
+    let keyspace = Config::new(&folder).open()?;
+    let partition = keyspace.open_partition("default", PartitionCreateOptions::default())?;
+    assert!(partition.contains_key("a")?);
+
+    Ok(())
+}
+
+#[test]
+fn keyspace_lock_read_only() -> fjall::Result<()> {
+    let folder = tempfile::tempdir()?;
+
+    let keyspace = Config::new(&folder).open()?;
+    let partition = keyspace.open_partition("default", PartitionCreateOptions::default())?;
+    partition.insert("a", "abc")?;
+
+    {
+        let keyspace = Config::new(&folder).read_only(true).open()?;
+        let partition = keyspace.open_partition("default", PartitionCreateOptions::default())?;
+        assert!(partition.contains_key("a")?);
+    }
+
+    // NOTE: The read-only keyspace did not release the lock
+    assert!(matches!(
+        Keyspace::check_lock(&folder),
+        Err(fjall::Error::Locked { .. })
+    ));
+
+    Ok(())
+}
+
+#[test]
+fn keyspace_lock_repair() -> fjall::Result<()> {
+    let folder = tempfile::tempdir()?;
+
+    let keyspace = Config::new(&folder).open()?;
+
+    for dry_run in [false, true] {
+        assert!(matches!(
+            Keyspace::repair(&folder, RepairOptions { dry_run }),
+            Err(fjall::Error::Locked { .. })
+        ));
+    }
+
+    drop(keyspace);
+
+    assert!(Keyspace::repair(&folder, RepairOptions::default())?.is_clean());
+
+    Ok(())
+}
@@ -0,0 +1,207 @@
+use fjall::{CloseOptions, Config, PartitionCreateOptions, PersistMode};
+use std::path::{Path, PathBuf};
+use test_log::test;
+
+fn snapshot_folder(folder: &Path) -> fjall::Result<Vec<(PathBuf, Vec<u8>)>> {
+    let mut files = vec![];
+    let mut queue = vec![folder.to_path_buf()];
+
+    while let Some(dir) = queue.pop() {
+        for dirent in std::fs::read_dir(dir)? {
+            let path = dirent?.path();
+
+            if path.is_dir() {
+                queue.push(path);
+            } else {
+                let content = std::fs::read(&path)?;
+                files.push((path, content));
+            }
+        }
+    }
+
+    files.sort();
+
+    Ok(files)
+}
+
+#[test]
+fn keyspace_read_only_recover() -> fjall::Result<()> {
+    let folder = tempfile::tempdir()?;
+
+    {
+        let keyspace = Config::new(&folder).open()?;
+        let partition = keyspace.open_partition("default", PartitionCreateOptions::default())?;
+        partition.insert("a", "abc")?;
+        partition.rotate_memtable_and_wait()?;
+        partition.insert("b", "def")?;
+        partition.remove("a")?;
+    }
+
+    let before = snapshot_folder(folder.path())?;
+
+    {
+        let keyspace = Config::new(&folder).read_only(true).open()?;
+        assert_eq!(1, keyspace.partition_count());
+
+        let partition = keyspace.open_partition("default", PartitionCreateOptions::default())?;
+        assert!(!partition.contains_key("a")?);
+        assert_eq!(Some("def".as_bytes().into()), partition.get("b")?);
+        assert_eq!(1, partition.len()?);
+    }
+
+    assert_eq!(before, snapshot_folder(folder.path())?);
+
+    Ok(())
+}
+
+#[test]
+fn keyspace_read_only_writes() -> fjall::Result<()> {
+    let folder = tempfile::tempdir()?;
+
+    {
+        let keyspace = Config::new(&folder).open()?;
+        let partition = keyspace.open_partition("default", PartitionCreateOptions::default())?;
+        partition.insert("a", "abc")?;
+    }
+
+    let before = snapshot_folder(folder.path())?;
+
+    {
+        let keyspace = Config::new(&folder).read_only(true).open()?;
+        let partition = keyspace.open_partition("default", PartitionCreateOptions::default())?;
+
+        assert!(matches!(
+            partition.insert("b", "def"),
+            Err(fjall::Error::ReadOnly)
+        ));
+        assert!(matches!(partition.remove("a"), Err(fjall::Error::ReadOnly)));
+        assert!(matches!(
+            partition.rotate_memtable(),
+            Err(fjall::Error::ReadOnly)
+        ));
+        assert!(matches!(
+            partition.major_compact(),
+            Err(fjall::Error::ReadOnly)
+        ));
+
+        let mut batch = keyspace.batch();
+        batch.insert(&partition, "c", "ghi");
+        assert!(matches!(batch.commit(), Err(fjall::Error::ReadOnly)));
+
+        assert!(matches!(
+            keyspace.open_partition("new", PartitionCreateOptions::default()),
+            Err(fjall::Error::ReadOnly)
+        ));
+        assert!(matches!(
+            keyspace.persist(PersistMode::SyncAll),
+            Err(fjall::Error::ReadOnly)
+        ));
+        assert!(matches!(
+            keyspace.delete_partition(partition.clone()),
+            Err(fjall::Error::ReadOnly)
+        ));
+
+        assert_eq!(Some("abc".as_bytes().into()), partition.get("a")?);
+        assert!(!partition.contains_key("b")?);
+
+        drop(partition);
+        keyspace.close(CloseOptions::default())?;
+    }
+
+    assert_eq!(before, snapshot_folder(folder.path())?);
+
+    Ok(())
+}
+
+#[test]
+fn keyspace_read_only_no_active_journal() -> fjall::Result<()> {
+    let folder = tempfile::tempdir()?;
+
+    {
+        let keyspace = Config::new(&folder).open()?;
+        let partition = keyspace.open_partition("default", PartitionCreateOptions::default())?;
+        partition.insert("a", "abc")?;
+    }
+
+    // NOTE: Simulate a crash during journal rotation
+    let journals_folder = folder.path().join("journals");
+    std::fs::rename(journals_folder.join("0"), journals_folder.join("0.sealed"))?;
+
+    let before = snapshot_folder(folder.path())?;
+
+    {
+        let keyspace = Config::new(&folder).read_only(true).open()?;
+        let partition = keyspace.open_partition("default", PartitionCreateOptions::default())?;
+        assert_eq!(Some("abc".as_bytes().into()), partition.get("a")?);
+    }
+
+    assert_eq!(before, snapshot_folder(folder.path())?);
+
+    Ok(())
+}
+
+#[test]
+fn keyspace_read_only_not_a_keyspace() -> fjall::Result<()> {
+    let folder = tempfile::tempdir()?;
+
+    assert!(matches!(
+        Config::new(&folder).read_only(true).open(),
+        Err(fjall::Error::ReadOnly)
+    ));
+    assert_eq!(0, std::fs::read_dir(&folder)?.count());
+
+    Ok(())
+}
+
+#[test]
+fn keyspace_read_only_keeps_unreferenced_segments() -> fjall::Result<()> {
+    let folder = tempfile::tempdir()?;
+
+    {
+        let keyspace = Config::new(&folder).open()?;
+        let partition = keyspace.open_partition("default", PartitionCreateOptions::default())?;
+        partition.insert("a", "abc")?;
+        partition.rotate_memtable_and_wait()?;
+    }
+
+    let segments_folder = folder
+        .path()
+        .join("partitions")
+        .join("default")
+        .join("segments");
+    let segment = std::fs::read_dir(&segments_folder)?
+        .next()
+        .expect("should have segment")?
+        .path();
+
+    let unfinished = segments_folder.join("tmp_1");
+    let unreferenced = segments_folder.join("99");
+    std::fs::copy(&segment, &unfinished)?;
+    std::fs::copy(&segment, &unreferenced)?;
+
+    let before = snapshot_folder(folder.path())?;
+
+    assert!(matches!(
+        Config::new(&folder).read_only(true).open(),
+        Err(fjall::Error::ReadOnly)
+    ));
+
+    assert!(unfinished.try_exists()?);
+    assert!(unreferenced.try_exists()?);
+    assert_eq!(before, snapshot_folder(folder.path())?);
+
+    // NOTE: Opening for writing cleans up the segments
+    {
+        let keyspace = Config::new(&folder).open()?;
+        let partition = keyspace.open_partition("default", PartitionCreateOptions::default())?;
+        assert!(partition.contains_key("a")?);
+    }
+    assert!(!unfinished.try_exists()?);
+    assert!(!unreferenced.try_exists()?);
+
+    let keyspace = Config::new(&folder).read_only(true).open()?;
+    let partition = keyspace.open_partition("default", PartitionCreateOptions::default())?;
+    assert!(partition.contains_key("a")?);
+
+    Ok(())
+}
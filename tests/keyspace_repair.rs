@@ -0,0 +1,331 @@
+use fjall::{
+    Config, Keyspace, PartitionCreateOptions, QuarantineReason, RecoveryError, RepairAction,
+    RepairOptions,
+};
+use std::{collections::BTreeMap, path::Path, path::PathBuf};
+use test_log::test;
+
+/// Reads every file below a folder, so a folder can be compared byte for byte.
+fn read_folder(folder: &Path) -> fjall::Result<BTreeMap<PathBuf, Option<Vec<u8>>>> {
+    let mut files = BTreeMap::new();
+
+    for dirent in std::fs::read_dir(folder)? {
+        let path = dirent?.path();
+
+        if path.is_dir() {
+            files.insert(path.clone(), None);
+            files.extend(read_folder(&path)?);
+        } else {
+            files.insert(path.clone(), Some(std::fs::read(&path)?));
+        }
+    }
+
+    Ok(files)
+}
+
+fn corrupt_journal_value(folder: &std::path::Path, value: &[u8]) -> fjall::Result<()> {
+    for dirent in std::fs::read_dir(folder.join("journals"))? {
+        let path = dirent?.path();
+
+        let mut bytes = std::fs::read(&path)?;
+
+        if let Some(pos) = bytes
+            .windows(value.len())
+            .position(|window| window == value)
+        {
+            *bytes.get_mut(pos).expect("should exist") ^= 0xFF;
+            std::fs::write(&path, &bytes)?;
+        }
+    }
+
+    Ok(())
+}
+
+#[test]
+fn keyspace_repair_clean() -> fjall::Result<()> {
+    let folder = tempfile::tempdir()?;
+
+    {
+        let keyspace = Config::new(&folder).open()?;
+        let partition = keyspace.open_partition("default", PartitionCreateOptions::default())?;
+        partition.insert("a", "abc")?;
+    }
+
+    let report = Keyspace::repair(&folder, RepairOptions::default())?;
+    assert!(report.is_clean());
+    assert_eq!(None, report.lost_and_found);
+    assert!(!folder.path().join("lost+found").try_exists()?);
+
+    Ok(())
+}
+
+#[test]
+fn keyspace_repair_corrupt_journal() -> fjall::Result<()> {
+    let folder = tempfile::tempdir()?;
+
+    {
+        let keyspace = Config::new(&folder).open()?;
+        let partition = keyspace.open_partition("default", PartitionCreateOptions::default())?;
+        partition.insert("a", "first")?;
+        partition.insert("b", "second")?;
+        partition.insert("c", "third")?;
+    }
+
+    corrupt_journal_value(folder.path(), b"second")?;
+
+    assert!(matches!(
+        Config::new(&folder).open(),
+        Err(fjall::Error::JournalRecovery(
+            RecoveryError::ChecksumMismatch
+        ))
+    ));
+
+    let report = Keyspace::repair(
+        &folder,
+        RepairOptions {
+            dry_run: true,
+            ..Default::default()
+        },
+    )?;
+    assert_eq!(1, report.actions.len());
+    assert_eq!(None, report.lost_and_found);
+    assert!(Config::new(&folder).open().is_err());
+
+    let report = Keyspace::repair(&folder, RepairOptions::default())?;
+    assert!(matches!(
+        report.actions.as_slice(),
+        [RepairAction::TruncatedJournal { .. }]
+    ));
+
+    let lost_and_found = report.lost_and_found.expect("should exist");
+    assert!(lost_and_found.join("repair.log").try_exists()?);
+    assert!(lost_and_found.join("journals").try_exists()?);
+
+    let keyspace = Config::new(&folder).open()?;
+    let partition = keyspace.open_partition("default", PartitionCreateOptions::default())?;
+    assert_eq!(Some("first".as_bytes().into()), partition.get("a")?);
+    assert!(!partition.contains_key("b")?);
+    assert!(!partition.contains_key("c")?);
+
+    Ok(())
+}
+
+#[test]
+fn keyspace_repair_partitions() -> fjall::Result<()> {
+    let folder = tempfile::tempdir()?;
+
+    {
+        let keyspace = Config::new(&folder).open()?;
+
+        for name in ["a", "b", "c"] {
+            let partition = keyspace.open_partition(name, PartitionCreateOptions::default())?;
+            partition.insert(name, name)?;
+            partition.rotate_memtable_and_wait()?;
+        }
+    }
+
+    let partitions_folder = folder.path().join("partitions");
+    std::fs::write(partitions_folder.join("stray"), "")?;
+    std::fs::write(partitions_folder.join("b").join("config"), "garbage")?;
+    std::fs::remove_file(partitions_folder.join("c").join("manifest"))?;
+
+    let report = Keyspace::repair(&folder, RepairOptions::default())?;
+    assert_eq!(3, report.actions.len(), "{report:?}");
+    assert!(report.actions.contains(&RepairAction::Quarantined {
+        path: partitions_folder.join("stray"),
+        reason: QuarantineReason::StrayFile,
+    }));
+    assert!(report.actions.contains(&RepairAction::Quarantined {
+        path: partitions_folder.join("c"),
+        reason: QuarantineReason::MissingManifest,
+    }));
+    assert!(report
+        .actions
+        .contains(&RepairAction::RewrotePartitionConfig {
+            partition: "b".into(),
+        }));
+
+    let lost_and_found = report.lost_and_found.expect("should exist");
+    assert!(lost_and_found
+        .join("partitions")
+        .join("stray")
+        .try_exists()?);
+    assert!(lost_and_found.join("partitions").join("c").try_exists()?);
+    assert!(lost_and_found
+        .join("partitions")
+        .join("b")
+        .join("config")
+        .try_exists()?);
+
+    let keyspace = Config::new(&folder).open()?;
+    assert_eq!(2, keyspace.partition_count());
+
+    for name in ["a", "b"] {
+        let partition = keyspace.open_partition(name, PartitionCreateOptions::default())?;
+        assert!(partition.contains_key(name)?);
+    }
+
+    Ok(())
+}
+
+#[test]
+fn keyspace_repair_journals_folder() -> fjall::Result<()> {
+    let folder = tempfile::tempdir()?;
+
+    {
+        let keyspace = Config::new(&folder).open()?;
+        let partition = keyspace.open_partition("default", PartitionCreateOptions::default())?;
+        partition.insert("a", "abc")?;
+    }
+
+    let journals_folder = folder.path().join("journals");
+    std::fs::write(journals_folder.join("stray"), "")?;
+    std::fs::copy(journals_folder.join("0"), journals_folder.join("7"))?;
+    std::fs::rename(journals_folder.join("0"), journals_folder.join("0.sealed"))?;
+
+    let report = Keyspace::repair(&folder, RepairOptions::default())?;
+    assert_eq!(1, report.actions.len(), "{report:?}");
+    assert!(report.actions.contains(&RepairAction::Quarantined {
+        path: journals_folder.join("stray"),
+        reason: QuarantineReason::StrayFile,
+    }));
+
+    std::fs::copy(journals_folder.join("7"), journals_folder.join("5"))?;
+
+    let report = Keyspace::repair(&folder, RepairOptions::default())?;
+    assert_eq!(
+        report.actions,
+        [RepairAction::Quarantined {
+            path: journals_folder.join("5"),
+            reason: QuarantineReason::DuplicateActiveJournal,
+        }]
+    );
+
+    let keyspace = Config::new(&folder).open()?;
+    let partition = keyspace.open_partition("default", PartitionCreateOptions::default())?;
+    assert!(partition.contains_key("a")?);
+
+    Ok(())
+}
+
+#[test]
+fn keyspace_repair_not_a_keyspace() -> fjall::Result<()> {
+    let folder = tempfile::tempdir()?;
+
+    assert!(matches!(
+        Keyspace::repair(&folder, RepairOptions::default()),
+        Err(fjall::Error::InvalidVersion(None))
+    ));
+
+    Ok(())
+}
+
+#[test]
+fn keyspace_repair_broken_partition() -> fjall::Result<()> {
+    let folder = tempfile::tempdir()?;
+
+    {
+        let keyspace = Config::new(&folder).open()?;
+
+        for name in ["a", "b"] {
+            let partition = keyspace.open_partition(name, PartitionCreateOptions::default())?;
+            partition.insert(name, name)?;
+            partition.rotate_memtable_and_wait()?;
+        }
+    }
+
+    let partition_folder = folder.path().join("partitions").join("b");
+    for dirent in std::fs::read_dir(partition_folder.join("segments"))? {
+        std::fs::remove_file(dirent?.path())?;
+    }
+    assert!(Config::new(&folder).open().is_err());
+
+    let report = Keyspace::repair(&folder, RepairOptions::default())?;
+    assert_eq!(
+        report.actions,
+        [RepairAction::Quarantined {
+            path: partition_folder,
+            reason: QuarantineReason::BrokenPartition,
+        }]
+    );
+
+    let keyspace = Config::new(&folder).open()?;
+    assert_eq!(1, keyspace.partition_count());
+
+    Ok(())
+}
+
+#[test]
+fn keyspace_repair_orphaned_segments() -> fjall::Result<()> {
+    let folder = tempfile::tempdir()?;
+
+    {
+        let keyspace = Config::new(&folder).open()?;
+        let partition = keyspace.open_partition("default", PartitionCreateOptions::default())?;
+        partition.insert("a", "abc")?;
+        partition.rotate_memtable_and_wait()?;
+    }
+
+    let segments_folder = folder
+        .path()
+        .join("partitions")
+        .join("default")
+        .join("segments");
+    let segment = std::fs::read_dir(&segments_folder)?
+        .next()
+        .expect("should have segment")?
+        .path();
+
+    let unfinished = segments_folder.join("tmp_1");
+    let unreferenced = segments_folder.join("99");
+    std::fs::copy(&segment, &unfinished)?;
+    std::fs::copy(&segment, &unreferenced)?;
+
+    let before = read_folder(folder.path())?;
+
+    let report = Keyspace::repair(
+        &folder,
+        RepairOptions {
+            dry_run: true,
+            ..Default::default()
+        },
+    )?;
+    assert_eq!(
+        report.actions,
+        [
+            RepairAction::Quarantined {
+                path: unreferenced.clone(),
+                reason: QuarantineReason::OrphanedSegment,
+            },
+            RepairAction::Quarantined {
+                path: unfinished.clone(),
+                reason: QuarantineReason::OrphanedSegment,
+            },
+        ]
+    );
+    assert_eq!(before, read_folder(folder.path())?);
+
+    let report = Keyspace::repair(&folder, RepairOptions::default())?;
+    assert_eq!(2, report.actions.len(), "{report:?}");
+    assert!(!unfinished.try_exists()?);
+    assert!(!unreferenced.try_exists()?);
+    assert!(segment.try_exists()?);
+
+    let lost_and_found = report.lost_and_found.expect("should exist");
+    let quarantined = lost_and_found
+        .join("partitions")
+        .join("default")
+        .join("segments");
+    assert!(quarantined.join("tmp_1").try_exists()?);
+    assert!(quarantined.join("99").try_exists()?);
+
+    let log = std::fs::read_to_string(lost_and_found.join("repair.log"))?;
+    assert!(log.contains("tmp_1"));
+    assert!(log.contains("OrphanedSegment"));
+
+    let keyspace = Config::new(&folder).open()?;
+    let partition = keyspace.open_partition("default", PartitionCreateOptions::default())?;
+    assert!(partition.contains_key("a")?);
+
+    Ok(())
+}
@@ -0,0 +1,176 @@
+use fjall::{Config, KvSeparationOptions, PartitionCreateOptions};
+use std::time::{Duration, Instant};
+use test_log::test;
+
+#[test]
+fn keyspace_secondary_catch_up() -> fjall::Result<()> {
+    let folder = tempfile::tempdir()?;
+    let secondary_folder = tempfile::tempdir()?;
+
+    let primary = Config::new(&folder).open()?;
+    let partition = primary.open_partition("default", PartitionCreateOptions::default())?;
+    partition.insert("a", "abc")?;
+
+    let secondary = Config::new(&folder).secondary(&secondary_folder).open()?;
+    let secondary_partition =
+        secondary.open_partition("default", PartitionCreateOptions::default())?;
+    assert_eq!(Some("abc".as_bytes().into()), secondary_partition.get("a")?);
+
+    partition.insert("b", "def")?;
+    partition.remove("a")?;
+    assert!(secondary_partition.contains_key("a")?);
+    assert!(!secondary_partition.contains_key("b")?);
+
+    secondary.catch_up()?;
+    assert!(!secondary_partition.contains_key("a")?);
+    assert_eq!(Some("def".as_bytes().into()), secondary_partition.get("b")?);
+    assert_eq!(primary.instant(), secondary.instant());
+
+    // NOTE: Nothing changed
+    secondary.catch_up()?;
+    assert_eq!(1, secondary_partition.len()?);
+
+    assert!(matches!(
+        secondary_partition.insert("c", "ghi"),
+        Err(fjall::Error::ReadOnly)
+    ));
+
+    Ok(())
+}
+
+#[test]
+fn keyspace_secondary_flush_and_compact() -> fjall::Result<()> {
+    let folder = tempfile::tempdir()?;
+    let secondary_folder = tempfile::tempdir()?;
+
+    let primary = Config::new(&folder).open()?;
+    let partition = primary.open_partition("default", PartitionCreateOptions::default())?;
+    let blobs = primary.open_partition(
+        "blobs",
+        PartitionCreateOptions::default().with_kv_separation(KvSeparationOptions::default()),
+    )?;
+
+    let secondary = Config::new(&folder).secondary(&secondary_folder).open()?;
+    let secondary_partition =
+        secondary.open_partition("default", PartitionCreateOptions::default())?;
+    let secondary_blobs = secondary.open_partition("blobs", PartitionCreateOptions::default())?;
+
+    for batch in 0..4_u8 {
+        for idx in 0..10_u8 {
+            partition.insert([batch, idx], [idx; 10])?;
+            blobs.insert([batch, idx], [idx; 10_000])?;
+        }
+        partition.rotate_memtable_and_wait()?;
+        blobs.rotate_memtable_and_wait()?;
+
+        secondary.catch_up()?;
+        assert_eq!((usize::from(batch) + 1) * 10, secondary_partition.len()?);
+        assert_eq!((usize::from(batch) + 1) * 10, secondary_blobs.len()?);
+    }
+
+    partition.major_compact()?;
+    partition.remove([0, 0])?;
+    blobs.remove([0, 0])?;
+
+    secondary.catch_up()?;
+    assert_eq!(39, secondary_partition.len()?);
+    assert_eq!(39, secondary_blobs.len()?);
+    assert_eq!(Some([5; 10_000].into()), secondary_blobs.get([3, 5])?);
+
+    drop(secondary_partition);
+    drop(secondary_blobs);
+    drop(secondary);
+
+    // NOTE: The primary's files are not touched by the secondary
+    drop(partition);
+    drop(blobs);
+    drop(primary);
+
+    let primary = Config::new(&folder).open()?;
+    let partition = primary.open_partition("default", PartitionCreateOptions::default())?;
+    assert_eq!(39, partition.len()?);
+
+    Ok(())
+}
+
+#[test]
+fn keyspace_secondary_partitions() -> fjall::Result<()> {
+    let folder = tempfile::tempdir()?;
+    let secondary_folder = tempfile::tempdir()?;
+
+    let primary = Config::new(&folder).open()?;
+    let partition = primary.open_partition("default", PartitionCreateOptions::default())?;
+    partition.insert("a", "abc")?;
+
+    let secondary = Config::new(&folder).secondary(&secondary_folder).open()?;
+    assert_eq!(1, secondary.partition_count());
+
+    let other = primary.open_partition("other", PartitionCreateOptions::default())?;
+    other.insert("b", "def")?;
+    primary.delete_partition(partition)?;
+
+    secondary.catch_up()?;
+    assert_eq!(1, secondary.partition_count());
+    assert!(secondary.partition_exists("other"));
+
+    let secondary_other = secondary.open_partition("other", PartitionCreateOptions::default())?;
+    assert_eq!(Some("def".as_bytes().into()), secondary_other.get("b")?);
+
+    Ok(())
+}
+
+#[test]
+fn keyspace_secondary_catch_up_interval() -> fjall::Result<()> {
+    let folder = tempfile::tempdir()?;
+    let secondary_folder = tempfile::tempdir()?;
+
+    let primary = Config::new(&folder).open()?;
+    let partition = primary.open_partition("default", PartitionCreateOptions::default())?;
+
+    let secondary = Config::new(&folder)
+        .secondary(&secondary_folder)
+        .catch_up_ms(Some(10))
+        .open()?;
+    let secondary_partition =
+        secondary.open_partition("default", PartitionCreateOptions::default())?;
+
+    partition.insert("a", "abc")?;
+
+    let start = Instant::now();
+    while !secondary_partition.contains_key("a")? {
+        assert!(
+            start.elapsed() < Duration::from_secs(10),
+            "did not catch up"
+        );
+        std::thread::sleep(Duration::from_millis(10));
+    }
+
+    Ok(())
+}
+
+#[test]
+fn keyspace_secondary_lock() -> fjall::Result<()> {
+    let folder = tempfile::tempdir()?;
+    let secondary_folder = tempfile::tempdir()?;
+    let other_secondary_folder = tempfile::tempdir()?;
+
+    let primary = Config::new(&folder).open()?;
+
+    let secondary = Config::new(&folder).secondary(&secondary_folder).open()?;
+    let _other = Config::new(&folder)
+        .secondary(&other_secondary_folder)
+        .open()?;
+
+    assert!(matches!(
+        Config::new(&folder).secondary(&secondary_folder).open(),
+        Err(fjall::Error::Locked { .. })
+    ));
+
+    drop(secondary);
+    let _secondary = Config::new(&folder).secondary(&secondary_folder).open()?;
+
+    drop(primary);
+    let _primary = Config::new(&folder).open()?;
+
+    Ok(())
+}
@@ -1,11 +1,36 @@
 use fjall::Config;
+use std::path::Path;
 use test_log::test;
 
+/// Copies a fixture into a temporary folder, so opening it does not change the checked-in files.
+fn copy_fixture(name: &str) -> std::io::Result<tempfile::TempDir> {
+    fn copy_dir(src: &Path, dst: &Path) -> std::io::Result<()> {
+        std::fs::create_dir_all(dst)?;
+
+        for dirent in std::fs::read_dir(src)? {
+            let dirent = dirent?;
+            let dst = dst.join(dirent.file_name());
+
+            if dirent.file_type()?.is_dir() {
+                copy_dir(&dirent.path(), &dst)?;
+            } else {
+                std::fs::copy(dirent.path(), dst)?;
+            }
+        }
+
+        Ok(())
+    }
+
+    let folder = tempfile::tempdir()?;
+    copy_dir(&Path::new("test_fixture").join(name), folder.path())?;
+    Ok(folder)
+}
+
 #[test]
 fn keyspace_load_v1() -> fjall::Result<()> {
-    let folder = "test_fixture/v1_keyspace";
+    let folder = copy_fixture("v1_keyspace")?;
 
-    let result = Config::new(folder).open();
+    let result = Config::new(&folder).open();
 
     matches!(
         result,
@@ -17,9 +42,9 @@ fn keyspace_load_v1() -> fjall::Result<()> {
 
 #[test]
 fn keyspace_load_v1_corrupt_journal() -> fjall::Result<()> {
-    let folder = "test_fixture/v1_keyspace_corrupt_journal";
+    let folder = copy_fixture("v1_keyspace_corrupt_journal")?;
 
-    let result = Config::new(folder).open();
+    let result = Config::new(&folder).open();
 
     matches!(
         result,
@@ -28,3 +53,17 @@ fn keyspace_load_v1_corrupt_journal() -> fjall::Result<()> {
 
     Ok(())
 }
+
+#[test]
+fn keyspace_load_v1_read_only() -> fjall::Result<()> {
+    for folder in [
+        "test_fixture/v1_keyspace",
+        "test_fixture/v1_keyspace_corrupt_journal",
+    ] {
+        let result = Config::new(folder).read_only(true).open();
+
+        assert!(matches!(result, Err(fjall::Error::InvalidVersion(_))));
+    }
+
+    Ok(())
+}
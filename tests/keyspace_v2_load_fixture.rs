@@ -1,11 +1,36 @@
 use fjall::{Config, KvSeparationOptions, PartitionCreateOptions, RecoveryError};
+use std::path::Path;
 use test_log::test;
 
+/// Copies a fixture into a temporary folder, so opening it does not change the checked-in files.
+fn copy_fixture(name: &str) -> std::io::Result<tempfile::TempDir> {
+    fn copy_dir(src: &Path, dst: &Path) -> std::io::Result<()> {
+        std::fs::create_dir_all(dst)?;
+
+        for dirent in std::fs::read_dir(src)? {
+            let dirent = dirent?;
+            let dst = dst.join(dirent.file_name());
+
+            if dirent.file_type()?.is_dir() {
+                copy_dir(&dirent.path(), &dst)?;
+            } else {
+                std::fs::copy(dirent.path(), dst)?;
+            }
+        }
+
+        Ok(())
+    }
+
+    let folder = tempfile::tempdir()?;
+    copy_dir(&Path::new("test_fixture").join(name), folder.path())?;
+    Ok(folder)
+}
+
 #[test]
 fn keyspace_load_v2() -> fjall::Result<()> {
-    let folder = "test_fixture/v2_keyspace";
+    let folder = copy_fixture("v2_keyspace")?;
 
-    let keyspace = Config::new(folder).open()?;
+    let keyspace = Config::new(&folder).open()?;
     let tree1 = keyspace.open_partition("default1", PartitionCreateOptions::default())?;
     let tree2 = keyspace.open_partition(
         "default2",
@@ -20,9 +45,9 @@ fn keyspace_load_v2() -> fjall::Result<()> {
 
 #[test]
 fn keyspace_load_v2_corrupt_journal() -> fjall::Result<()> {
-    let folder = "test_fixture/v2_keyspace_corrupt_journal";
+    let folder = copy_fixture("v2_keyspace_corrupt_journal")?;
 
-    let result = Config::new(folder).open();
+    let result = Config::new(&folder).open();
     matches!(
         result,
         Err(fjall::Error::JournalRecovery(
@@ -32,3 +57,39 @@ fn keyspace_load_v2_corrupt_journal() -> fjall::Result<()> {
 
     Ok(())
 }
+
+#[test]
+fn keyspace_load_v2_read_only() -> fjall::Result<()> {
+    let folder = "test_fixture/v2_keyspace";
+
+    let keyspace = Config::new(folder).read_only(true).open()?;
+    let tree1 = keyspace.open_partition("default1", PartitionCreateOptions::default())?;
+    let tree2 = keyspace.open_partition(
+        "default2",
+        PartitionCreateOptions::default().with_kv_separation(KvSeparationOptions::default()),
+    )?;
+
+    assert_eq!(6, tree1.len()?);
+    assert_eq!(6, tree2.len()?);
+
+    assert!(matches!(
+        tree1.insert("a", "b"),
+        Err(fjall::Error::ReadOnly)
+    ));
+
+    Ok(())
+}
+
+#[test]
+fn keyspace_load_v2_corrupt_journal_read_only() -> fjall::Result<()> {
+    let folder = "test_fixture/v2_keyspace_corrupt_journal";
+
+    // NOTE: The fixture has no journals folder, which a read-only keyspace cannot create
+    let result = Config::new(folder).read_only(true).open();
+    assert!(matches!(
+        result,
+        Err(fjall::Error::Io(e)) if e.kind() == std::io::ErrorKind::NotFound
+    ));
+
+    Ok(())
+}
@@ -0,0 +1,196 @@
+use fjall::{
+    Config, FaultyFs, JournalStopReason, KvSeparationOptions, PartitionCreateOptions,
+    RecoveryError, VerifyIssue, VerifyOptions,
+};
+use test_log::test;
+
+#[test]
+fn keyspace_verify_ok() -> fjall::Result<()> {
+    let folder = tempfile::tempdir()?;
+
+    let keyspace = Config::new(&folder).open()?;
+    let standard = keyspace.open_partition("standard", PartitionCreateOptions::default())?;
+    let blobs = keyspace.open_partition(
+        "blobs",
+        PartitionCreateOptions::default().with_kv_separation(KvSeparationOptions::default()),
+    )?;
+
+    for x in 0..10_u64 {
+        standard.insert(x.to_be_bytes(), "abc")?;
+        blobs.insert(x.to_be_bytes(), "a".repeat(10_000))?;
+    }
+    standard.rotate_memtable_and_wait()?;
+    blobs.rotate_memtable_and_wait()?;
+
+    let report = keyspace.verify(VerifyOptions::default())?;
+    assert!(report.is_ok(), "{report:?}");
+    assert_eq!(2, report.partitions_checked);
+    assert!(report.journals_checked >= 1);
+
+    Ok(())
+}
+
+#[test]
+fn keyspace_verify_reads_through_fs() -> fjall::Result<()> {
+    let folder = tempfile::tempdir()?;
+    let fs = FaultyFs::default();
+
+    let keyspace = Config::new(&folder).fs(fs.clone()).open()?;
+    let partition = keyspace.open_partition("default", PartitionCreateOptions::default())?;
+    partition.insert("a", "abc")?;
+
+    let report = keyspace.verify(VerifyOptions::default())?;
+    assert!(report.is_ok(), "{report:?}");
+
+    // NOTE: Partition folders and journals are read through the keyspace's file system
+    fs.fail_reads(true);
+    assert!(matches!(
+        keyspace.verify(VerifyOptions::default()),
+        Err(fjall::Error::Io(_))
+    ));
+    fs.fail_reads(false);
+
+    Ok(())
+}
+
+#[test]
+fn keyspace_verify_files() -> fjall::Result<()> {
+    let folder = tempfile::tempdir()?;
+
+    let keyspace = Config::new(&folder).open()?;
+    let partition = keyspace.open_partition("default", PartitionCreateOptions::default())?;
+
+    for x in 0..10_u64 {
+        partition.insert(x.to_be_bytes(), "abc")?;
+    }
+    partition.rotate_memtable_and_wait()?;
+
+    let segments_folder = partition.path().join("segments");
+    let segment_file = std::fs::read_dir(&segments_folder)?
+        .next()
+        .expect("should have segment")?
+        .path();
+    let orphan = segments_folder.join("999");
+    std::fs::copy(&segment_file, &orphan)?;
+    std::fs::remove_file(&segment_file)?;
+
+    std::fs::write(partition.path().join("config"), b"garbage")?;
+
+    let stray = folder.path().join("partitions").join("stray");
+    std::fs::write(&stray, b"")?;
+
+    let report = keyspace.verify(VerifyOptions::default())?;
+    assert_eq!(4, report.issues.len(), "{report:?}");
+
+    assert!(report.issues.iter().any(|issue| matches!(
+        issue,
+        VerifyIssue::StrayFile(path) if path == &stray
+    )));
+    assert!(report.issues.iter().any(|issue| matches!(
+        issue,
+        VerifyIssue::InvalidPartitionConfig { partition, .. } if &**partition == "default"
+    )));
+    assert!(report
+        .issues
+        .iter()
+        .any(|issue| matches!(issue, VerifyIssue::MissingSegmentFile { .. })));
+    assert!(report.issues.iter().any(|issue| matches!(
+        issue,
+        VerifyIssue::OrphanedSegmentFile { path, .. } if path == &orphan
+    )));
+
+    Ok(())
+}
+
+#[test]
+fn keyspace_verify_journal() -> fjall::Result<()> {
+    let folder = tempfile::tempdir()?;
+
+    let keyspace = Config::new(&folder).open()?;
+    let partition = keyspace.open_partition("default", PartitionCreateOptions::default())?;
+    partition.insert("a", "hello")?;
+    partition.insert("b", "world")?;
+
+    let journal = keyspace
+        .journals()?
+        .pop()
+        .expect("should have active journal");
+
+    let mut bytes = std::fs::read(&journal.path)?;
+    let pos = bytes
+        .windows(5)
+        .position(|window| window == b"hello")
+        .expect("should contain value");
+    *bytes.get_mut(pos).expect("should exist") = b'j';
+    std::fs::write(&journal.path, &bytes)?;
+
+    let report = keyspace.verify(VerifyOptions {
+        check_journals: false,
+        ..Default::default()
+    })?;
+    assert!(report.is_ok(), "{report:?}");
+    assert_eq!(0, report.journals_checked);
+
+    let report = keyspace.verify(VerifyOptions::default())?;
+    assert_eq!(1, report.issues.len(), "{report:?}");
+    assert!(matches!(
+        report.issues.first(),
+        Some(VerifyIssue::CorruptJournal { path, stop })
+            if path == &journal.path
+                && stop.offset == 0
+                && stop.reason == JournalStopReason::Broken(RecoveryError::ChecksumMismatch)
+    ));
+
+    Ok(())
+}
+
+#[test]
+fn keyspace_verify_blob_files() -> fjall::Result<()> {
+    let folder = tempfile::tempdir()?;
+
+    let keyspace = Config::new(&folder).open()?;
+    let partition = keyspace.open_partition(
+        "default",
+        PartitionCreateOptions::default().with_kv_separation(KvSeparationOptions::default()),
+    )?;
+
+    for x in 0..10_u64 {
+        partition.insert(x.to_be_bytes(), "a".repeat(10_000))?;
+    }
+    partition.rotate_memtable_and_wait()?;
+
+    let blob_folder = partition.path().join("blobs").join("segments");
+    for dirent in std::fs::read_dir(&blob_folder)? {
+        std::fs::remove_file(dirent?.path())?;
+    }
+
+    let report = keyspace.verify(VerifyOptions::default())?;
+    assert_eq!(1, report.issues.len(), "{report:?}");
+    assert!(matches!(
+        report.issues.first(),
+        Some(VerifyIssue::MissingBlobFile { partition, .. }) if &**partition == "default"
+    ));
+
+    Ok(())
+}
+
+#[test]
+fn keyspace_recover_stray_file() -> fjall::Result<()> {
+    let folder = tempfile::tempdir()?;
+
+    {
+        let keyspace = Config::new(&folder).open()?;
+        let partition = keyspace.open_partition("default", PartitionCreateOptions::default())?;
+        partition.insert("a", "abc")?;
+    }
+
+    std::fs::write(folder.path().join("partitions").join("stray"), b"")?;
+
+    let keyspace = Config::new(&folder).open()?;
+    assert_eq!(1, keyspace.partition_count());
+
+    let report = keyspace.verify(VerifyOptions::default())?;
+    assert_eq!(1, report.issues.len(), "{report:?}");
+
+    Ok(())
+}
@@ -0,0 +1,128 @@
+use fjall::{Config, IoActivity, PartitionCreateOptions, RateLimit};
+use std::time::{Duration, Instant};
+
+const KIB: u64 = 1_024;
+
+#[test_log::test]
+fn rate_limiter_limits() -> fjall::Result<()> {
+    let folder = tempfile::tempdir()?;
+
+    let keyspace = Config::new(&folder)
+        .rate_limit(IoActivity::Compaction, RateLimit::new(100 * KIB))
+        .open()?;
+
+    let limiter = keyspace.rate_limiter();
+    assert_eq!(None, limiter.limit(IoActivity::Flush));
+    assert_eq!(None, limiter.limit(IoActivity::GarbageCollection));
+    assert_eq!(
+        Some(RateLimit {
+            bytes_per_second: 100 * KIB,
+            burst: 100 * KIB,
+        }),
+        limiter.limit(IoActivity::Compaction)
+    );
+
+    limiter.set_limit(IoActivity::Flush, Some(RateLimit::new(KIB).burst(2 * KIB)));
+    assert_eq!(
+        Some(RateLimit {
+            bytes_per_second: KIB,
+            burst: 2 * KIB,
+        }),
+        limiter.limit(IoActivity::Flush)
+    );
+
+    limiter.set_limit(IoActivity::Compaction, None);
+    assert_eq!(None, limiter.limit(IoActivity::Compaction));
+
+    Ok(())
+}
+
+#[test_log::test]
+fn rate_limiter_flush() -> fjall::Result<()> {
+    let folder = tempfile::tempdir()?;
+
+    let keyspace = Config::new(&folder)
+        .rate_limit(IoActivity::Flush, RateLimit::new(512 * KIB).burst(0))
+        .open()?;
+    let partition = keyspace.open_partition("default", PartitionCreateOptions::default())?;
+
+    for x in 0..256_u32 {
+        partition.insert(x.to_be_bytes(), vec![0; 1_024])?;
+    }
+
+    let start = Instant::now();
+    partition.rotate_memtable_and_wait()?;
+    assert!(start.elapsed() >= Duration::from_millis(400));
+
+    assert_eq!(256, partition.len()?);
+
+    Ok(())
+}
+
+#[test_log::test]
+fn rate_limiter_adjust_at_runtime() -> fjall::Result<()> {
+    let folder = tempfile::tempdir()?;
+
+    // NOTE: Flushing would take minutes with this limit
+    let keyspace = Config::new(&folder)
+        .rate_limit(IoActivity::Flush, RateLimit::new(KIB).burst(0))
+        .open()?;
+    let partition = keyspace.open_partition("default", PartitionCreateOptions::default())?;
+
+    for x in 0..256_u32 {
+        partition.insert(x.to_be_bytes(), vec![0; 1_024])?;
+    }
+
+    let limiter = keyspace.rate_limiter().clone();
+    let lift = std::thread::spawn(move || {
+        std::thread::sleep(Duration::from_millis(200));
+        limiter.set_limit(IoActivity::Flush, None);
+    });
+
+    let start = Instant::now();
+    partition.rotate_memtable_and_wait()?;
+    assert!(start.elapsed() < Duration::from_secs(10));
+
+    lift.join().expect("should join");
+
+    Ok(())
+}
+
+#[test_log::test]
+fn rate_limiter_flush_throttles_before_writing() -> fjall::Result<()> {
+    let folder = tempfile::tempdir()?;
+
+    let keyspace = Config::new(&folder)
+        .rate_limit(IoActivity::Flush, RateLimit::new(KIB).burst(0))
+        .open()?;
+    let partition = keyspace.open_partition("default", PartitionCreateOptions::default())?;
+
+    for x in 0..256_u32 {
+        partition.insert(x.to_be_bytes(), vec![0; 1_024])?;
+    }
+
+    let segments_folder = folder
+        .path()
+        .join("partitions")
+        .join("default")
+        .join("segments");
+
+    assert!(partition.rotate_memtable()?);
+    std::thread::sleep(Duration::from_millis(500));
+
+    // NOTE: The flush is throttled, but nothing has been written yet
+    assert_eq!(0, partition.segment_count());
+    assert_eq!(0, std::fs::read_dir(&segments_folder)?.count());
+
+    keyspace.rate_limiter().set_limit(IoActivity::Flush, None);
+
+    let start = Instant::now();
+    while partition.segment_count() == 0 {
+        assert!(start.elapsed() < Duration::from_secs(10));
+        std::thread::sleep(Duration::from_millis(10));
+    }
+
+    assert_eq!(256, partition.len()?);
+
+    Ok(())
+}
@@ -0,0 +1,181 @@
+use fjall::{
+    Config, PartitionCreateOptions, ReplicaApplier, ReplicationMessage, ReplicationSource,
+    StreamReceiver, StreamSender,
+};
+use std::net::{TcpListener, TcpStream};
+use test_log::test;
+
+#[test]
+fn replication_resume() -> fjall::Result<()> {
+    let folder = tempfile::tempdir()?;
+    let replica_folder = tempfile::tempdir()?;
+
+    let primary = Config::new(&folder).open()?;
+    let partition = primary.open_partition("default", PartitionCreateOptions::default())?;
+    let source = ReplicationSource::new(primary.clone());
+
+    for x in 0..10_u64 {
+        partition.insert(x.to_be_bytes(), "abc")?;
+    }
+
+    {
+        let replica = Config::new(&replica_folder).open()?;
+        let mut applier = ReplicaApplier::new(replica.clone());
+        assert_eq!(0, applier.next_seqno());
+
+        let (mut sender, mut receiver) = fjall::channel();
+        let next = source.ship(applier.next_seqno(), &mut sender)?;
+        drop(sender);
+
+        // NOTE: 1 partition + 10 batches
+        assert_eq!(11, applier.receive(&mut receiver)?);
+        assert_eq!(10, next);
+        assert_eq!(primary.instant(), applier.next_seqno());
+
+        let replica_partition =
+            replica.open_partition("default", PartitionCreateOptions::default())?;
+        assert_eq!(10, replica_partition.len()?);
+    }
+
+    let mut batch = primary.batch();
+    batch.insert(&partition, "a", "def");
+    batch.remove(&partition, 0_u64.to_be_bytes());
+    batch.commit()?;
+
+    let other = primary.open_partition("other", PartitionCreateOptions::default())?;
+    other.insert("b", "ghi")?;
+
+    // NOTE: Resume after restarting the replica
+    let replica = Config::new(&replica_folder).open()?;
+    let mut applier = ReplicaApplier::new(replica.clone());
+    assert_eq!(10, applier.next_seqno());
+
+    let (mut sender, mut receiver) = fjall::channel();
+    source.ship(applier.next_seqno(), &mut sender)?;
+
+    // NOTE: Nothing new, shipping again sends nothing
+    source.ship(primary.instant(), &mut sender)?;
+    drop(sender);
+
+    // NOTE: 2 partitions + 2 batches
+    assert_eq!(4, applier.receive(&mut receiver)?);
+    assert_eq!(primary.instant(), applier.next_seqno());
+
+    let replica_partition = replica.open_partition("default", PartitionCreateOptions::default())?;
+    let replica_other = replica.open_partition("other", PartitionCreateOptions::default())?;
+    assert_eq!(10, replica_partition.len()?);
+    assert!(!replica_partition.contains_key(0_u64.to_be_bytes())?);
+    assert_eq!(Some("def".as_bytes().into()), replica_partition.get("a")?);
+    assert_eq!(Some("ghi".as_bytes().into()), replica_other.get("b")?);
+
+    // NOTE: Batches that were already applied are skipped
+    applier.apply(ReplicationMessage::Batch {
+        seqno: 0,
+        items: vec![],
+    })?;
+    assert_eq!(primary.instant(), applier.next_seqno());
+
+    Ok(())
+}
+
+#[test]
+fn replication_snapshot_after_eviction() -> fjall::Result<()> {
+    let folder = tempfile::tempdir()?;
+    let replica_folder = tempfile::tempdir()?;
+
+    let primary = Config::new(&folder).open()?;
+    let partition = primary.open_partition("default", PartitionCreateOptions::default())?;
+    let source = ReplicationSource::new(primary.clone());
+
+    for batch in 0..3_u8 {
+        for idx in 0..10_u8 {
+            partition.insert([batch, idx], [idx; 10])?;
+        }
+        partition.remove([batch, 0])?;
+        partition.rotate_memtable_and_wait()?;
+    }
+    partition.insert("a", "abc")?;
+
+    // NOTE: Trigger journal maintenance
+    let _ = primary.journals()?;
+    partition.insert("b", "def")?;
+    assert_eq!(1, primary.journal_count());
+
+    let replica = Config::new(&replica_folder).open()?;
+    let mut applier = ReplicaApplier::new(replica.clone());
+
+    let (mut sender, mut receiver) = fjall::channel();
+    source.ship(applier.next_seqno(), &mut sender)?;
+    drop(sender);
+
+    applier.receive(&mut receiver)?;
+    assert_eq!(primary.instant(), applier.next_seqno());
+
+    let replica_partition = replica.open_partition("default", PartitionCreateOptions::default())?;
+    assert_eq!(partition.len()?, replica_partition.len()?);
+    assert_eq!(29, replica_partition.len()?);
+    assert!(!replica_partition.contains_key([1, 0])?);
+    assert_eq!(Some("abc".as_bytes().into()), replica_partition.get("a")?);
+
+    // NOTE: The replica is up to date, so no snapshot is needed anymore
+    partition.insert("c", "ghi")?;
+
+    let (mut sender, mut receiver) = fjall::channel();
+    source.ship(applier.next_seqno(), &mut sender)?;
+    drop(sender);
+
+    assert_eq!(2, applier.receive(&mut receiver)?);
+    assert_eq!(Some("ghi".as_bytes().into()), replica_partition.get("c")?);
+
+    // NOTE: A snapshot cannot be applied to a replica that is not empty
+    assert!(matches!(
+        applier.apply(ReplicationMessage::SnapshotStart),
+        Err(fjall::Error::Replication(fjall::ReplicationError::NotEmpty))
+    ));
+
+    Ok(())
+}
+
+#[test]
+fn replication_tcp() -> fjall::Result<()> {
+    let folder = tempfile::tempdir()?;
+    let replica_folder = tempfile::tempdir()?;
+
+    let primary = Config::new(&folder).open()?;
+    let partition = primary.open_partition("default", PartitionCreateOptions::default())?;
+
+    for x in 0..100_u64 {
+        partition.insert(x.to_be_bytes(), x.to_string())?;
+    }
+
+    let listener = TcpListener::bind("127.0.0.1:0")?;
+    let addr = listener.local_addr()?;
+
+    let replica = Config::new(&replica_folder).open()?;
+
+    let handle = std::thread::spawn(move || -> fjall::Result<u64> {
+        let (stream, _) = listener.accept()?;
+        let mut applier = ReplicaApplier::new(replica);
+        applier.receive(&mut StreamReceiver::new(stream))?;
+        Ok(applier.next_seqno())
+    });
+
+    {
+        let source = ReplicationSource::new(primary.clone());
+        let mut sender = StreamSender::new(TcpStream::connect(addr)?);
+        source.ship(0, &mut sender)?;
+    }
+
+    let next = handle.join().expect("should join")?;
+    assert_eq!(primary.instant(), next);
+
+    let replica = Config::new(&replica_folder).open()?;
+    let replica_partition = replica.open_partition("default", PartitionCreateOptions::default())?;
+    assert_eq!(100, replica_partition.len()?);
+    assert_eq!(
+        Some("42".as_bytes().into()),
+        replica_partition.get(42_u64.to_be_bytes())?
+    );
+
+    Ok(())
+}
@@ -0,0 +1,378 @@
+use fjall::{
+    Config, DefaultWriteStallPolicy, PartitionCreateOptions, UserKey, UserValue, WriteAdmission,
+    WriteLoad, WriteStallAction, WriteStallPolicy, WriteStallReason,
+};
+use std::sync::{
+    atomic::{AtomicUsize, Ordering},
+    Arc,
+};
+
+fn by_tags(_key: &[u8], value: &[u8]) -> Vec<UserKey> {
+    value
+        .split(|&b| b == b',')
+        .filter(|tag| !tag.is_empty())
+        .map(UserKey::from)
+        .collect()
+}
+
+fn lookup_keys(index: &fjall::SecondaryIndex, tag: &str) -> fjall::Result<Vec<String>> {
+    index
+        .lookup(tag..=tag)
+        .map(|kv| kv.map(|(k, _)| String::from_utf8(k.to_vec()).unwrap()))
+        .collect()
+}
+
+/// Returns the amount of index entries, without the definition and built markers.
+fn entry_count(index: &fjall::SecondaryIndex) -> fjall::Result<usize> {
+    Ok(index.partition().len()? - 2)
+}
+
+#[test_log::test]
+fn secondary_index_maintained() -> fjall::Result<()> {
+    let folder = tempfile::tempdir()?;
+
+    let keyspace = Config::new(&folder).open()?;
+    let items = keyspace.open_partition("items", PartitionCreateOptions::default())?;
+    let index = keyspace.create_index("items", "items_by_tag", by_tags)?;
+
+    items.insert("1", "red,blue")?;
+    items.insert("2", "blue")?;
+    items.insert("3", "green,red,red")?;
+
+    assert_eq!(vec!["1", "3"], lookup_keys(&index, "red")?);
+    assert_eq!(vec!["1", "2"], lookup_keys(&index, "blue")?);
+    assert_eq!(vec!["3"], lookup_keys(&index, "green")?);
+    assert_eq!(5, entry_count(&index)?);
+
+    // Overwrite removes stale entries
+    items.insert("1", "green")?;
+    assert_eq!(vec!["3"], lookup_keys(&index, "red")?);
+    assert_eq!(vec!["2"], lookup_keys(&index, "blue")?);
+    assert_eq!(vec!["1", "3"], lookup_keys(&index, "green")?);
+
+    // Remove removes all entries
+    items.remove("3")?;
+    assert!(lookup_keys(&index, "red")?.is_empty());
+    assert_eq!(vec!["1"], lookup_keys(&index, "green")?);
+
+    // Removing a non-existing key does nothing
+    items.remove("4")?;
+
+    assert_eq!(2, entry_count(&index)?);
+
+    // Range lookup is sorted by secondary key
+    let all = index
+        .lookup::<&str, _>(..)
+        .map(|kv| kv.map(|(_, v)| v))
+        .collect::<Result<Vec<_>, _>>()?;
+    assert_eq!(vec![UserValue::from("blue"), UserValue::from("green")], all);
+
+    Ok(())
+}
+
+#[test_log::test]
+fn secondary_index_batch() -> fjall::Result<()> {
+    let folder = tempfile::tempdir()?;
+
+    let keyspace = Config::new(&folder).open()?;
+    let items = keyspace.open_partition("items", PartitionCreateOptions::default())?;
+    let index = keyspace.create_index("items", "items_by_tag", by_tags)?;
+
+    items.insert("1", "red")?;
+
+    // Only the last write of a key inside the batch counts
+    let mut batch = keyspace.batch();
+    batch.insert(&items, "1", "blue");
+    batch.insert(&items, "1", "green");
+    batch.insert(&items, "2", "red");
+    batch.remove(&items, "2");
+    batch.insert(&items, "3", "red");
+    batch.commit()?;
+
+    assert_eq!(vec!["3"], lookup_keys(&index, "red")?);
+    assert!(lookup_keys(&index, "blue")?.is_empty());
+    assert_eq!(vec!["1"], lookup_keys(&index, "green")?);
+    assert_eq!(2, entry_count(&index)?);
+
+    // Failed preconditions do not touch the index
+    let mut batch = keyspace.batch();
+    batch.require_absent(&items, "1");
+    batch.insert(&items, "1", "blue");
+    assert!(batch.commit().is_err());
+
+    assert_eq!(vec!["1"], lookup_keys(&index, "green")?);
+
+    Ok(())
+}
+
+#[test_log::test]
+fn secondary_index_backfill_and_recover() -> fjall::Result<()> {
+    let folder = tempfile::tempdir()?;
+
+    {
+        let keyspace = Config::new(&folder).open()?;
+        let items = keyspace.open_partition("items", PartitionCreateOptions::default())?;
+
+        items.insert("1", "red")?;
+        items.insert("2", "red,blue")?;
+
+        // Existing items are indexed
+        let index = keyspace.create_index("items", "items_by_tag", by_tags)?;
+        assert_eq!(vec!["1", "2"], lookup_keys(&index, "red")?);
+        assert_eq!(vec!["2"], lookup_keys(&index, "blue")?);
+
+        items.remove("1")?;
+    }
+
+    {
+        let keyspace = Config::new(&folder).open()?;
+        let index = keyspace.create_index("items", "items_by_tag", by_tags)?;
+        let items = keyspace.open_partition("items", PartitionCreateOptions::default())?;
+
+        assert_eq!(vec!["2"], lookup_keys(&index, "red")?);
+        assert_eq!(vec!["2"], lookup_keys(&index, "blue")?);
+        assert_eq!(2, entry_count(&index)?);
+
+        items.insert("2", "blue")?;
+        assert!(lookup_keys(&index, "red")?.is_empty());
+    }
+
+    Ok(())
+}
+
+#[test_log::test]
+fn secondary_index_not_registered_after_reopen() -> fjall::Result<()> {
+    let folder = tempfile::tempdir()?;
+
+    {
+        let keyspace = Config::new(&folder).open()?;
+        let items = keyspace.open_partition("items", PartitionCreateOptions::default())?;
+        keyspace.create_index("items", "items_by_tag", by_tags)?;
+
+        items.insert("1", "red")?;
+    }
+
+    {
+        let keyspace = Config::new(&folder).open()?;
+
+        // Writes would be missing from the index
+        assert!(matches!(
+            keyspace.open_partition("items", PartitionCreateOptions::default()),
+            Err(fjall::Error::IndexNotRegistered(name)) if &*name == "items_by_tag"
+        ));
+
+        // Other partitions are not affected
+        let other = keyspace.open_partition("other", PartitionCreateOptions::default())?;
+        other.insert("a", "b")?;
+
+        let index = keyspace.create_index("items", "items_by_tag", by_tags)?;
+        let items = keyspace.open_partition("items", PartitionCreateOptions::default())?;
+        items.insert("2", "red")?;
+        assert_eq!(vec!["1", "2"], lookup_keys(&index, "red")?);
+    }
+
+    {
+        let keyspace = Config::new(&folder).read_only(true).open()?;
+
+        // Nothing can be written in a read-only keyspace
+        let items = keyspace.open_partition("items", PartitionCreateOptions::default())?;
+        assert_eq!(2, items.len()?);
+    }
+
+    {
+        let keyspace = Config::new(&folder).open()?;
+
+        // Deleting the index partition drops the index
+        let index = keyspace.open_partition("items_by_tag", PartitionCreateOptions::default())?;
+        keyspace.delete_partition(index)?;
+
+        let items = keyspace.open_partition("items", PartitionCreateOptions::default())?;
+        items.insert("3", "red")?;
+    }
+
+    let keyspace = Config::new(&folder).open()?;
+    let items = keyspace.open_partition("items", PartitionCreateOptions::default())?;
+    items.insert("4", "red")?;
+
+    Ok(())
+}
+
+#[test_log::test]
+fn secondary_index_binary_keys() -> fjall::Result<()> {
+    let folder = tempfile::tempdir()?;
+
+    let keyspace = Config::new(&folder).open()?;
+    let items = keyspace.open_partition("items", PartitionCreateOptions::default())?;
+
+    // Index by the full value, which may contain zero bytes
+    let index = keyspace.create_index("items", "items_by_value", |_, value| vec![value.into()])?;
+
+    items.insert("a", [0])?;
+    items.insert("b", [0, 0])?;
+    items.insert("c", [0, 1])?;
+    items.insert("d", [1])?;
+
+    let found = |range: (std::ops::Bound<Vec<u8>>, std::ops::Bound<Vec<u8>>)| {
+        index
+            .lookup(range)
+            .map(|kv| kv.map(|(k, _)| k))
+            .collect::<Result<Vec<_>, _>>()
+    };
+
+    use std::ops::Bound::{Excluded, Included, Unbounded};
+
+    assert_eq!(
+        vec![UserKey::from("a")],
+        found((Included(vec![0]), Included(vec![0])))?
+    );
+    assert_eq!(
+        vec![UserKey::from("b"), UserKey::from("c")],
+        found((Excluded(vec![0]), Excluded(vec![1])))?
+    );
+    assert_eq!(
+        vec![UserKey::from("a"), UserKey::from("b")],
+        found((Unbounded, Included(vec![0, 0])))?
+    );
+
+    Ok(())
+}
+
+#[test_log::test]
+#[cfg(feature = "single_writer_tx")]
+fn secondary_index_tx() -> fjall::Result<()> {
+    let folder = tempfile::tempdir()?;
+
+    let keyspace = Config::new(&folder).open_transactional()?;
+    let items = keyspace.open_partition("items", PartitionCreateOptions::default())?;
+    let index = keyspace
+        .inner
+        .create_index("items", "items_by_tag", by_tags)?;
+
+    items.insert("1", "red")?;
+
+    let mut tx = keyspace.write_tx();
+    tx.insert(&items, "1", "blue");
+    tx.insert(&items, "2", "red");
+    tx.commit()?;
+
+    assert_eq!(vec!["2"], lookup_keys(&index, "red")?);
+    assert_eq!(vec!["1"], lookup_keys(&index, "blue")?);
+
+    Ok(())
+}
+
+#[test_log::test]
+fn secondary_index_skips_stale_entries() -> fjall::Result<()> {
+    let folder = tempfile::tempdir()?;
+
+    let keyspace = Config::new(&folder).open()?;
+    let items = keyspace.open_partition("items", PartitionCreateOptions::default())?;
+    let index = keyspace.create_index("items", "items_by_tag", by_tags)?;
+
+    items.insert("1", "red")?;
+
+    // Entry "blue" -> "1", which does not match the item
+    index.partition().insert(b"blue\x00\x011", "")?;
+
+    assert_eq!(vec!["1"], lookup_keys(&index, "red")?);
+    assert!(lookup_keys(&index, "blue")?.is_empty());
+
+    Ok(())
+}
+
+#[test_log::test]
+fn secondary_index_backfill_concurrent_writes() -> fjall::Result<()> {
+    const ITEM_COUNT: u32 = 20_000;
+
+    let folder = tempfile::tempdir()?;
+
+    let keyspace = Config::new(&folder).open()?;
+    let items = keyspace.open_partition("items", PartitionCreateOptions::default())?;
+
+    for x in 0..ITEM_COUNT {
+        items.insert(x.to_be_bytes(), "red")?;
+    }
+
+    let writer = {
+        let items = items.clone();
+
+        std::thread::spawn(move || -> fjall::Result<()> {
+            for x in (0..ITEM_COUNT).rev() {
+                if x % 2 == 0 {
+                    items.insert(x.to_be_bytes(), "blue")?;
+                } else {
+                    items.remove(x.to_be_bytes())?;
+                }
+            }
+            Ok(())
+        })
+    };
+
+    let index = keyspace.create_index("items", "items_by_tag", by_tags)?;
+    writer.join().expect("should join")?;
+
+    // Every item has exactly one index entry, and no entry is stale
+    assert_eq!(items.len()?, entry_count(&index)?);
+    assert_eq!(
+        ITEM_COUNT as usize / 2,
+        index.lookup("blue"..="blue").count()
+    );
+    assert_eq!(0, index.lookup("red"..="red").count());
+
+    Ok(())
+}
+
+/// Rejects writes once the given amount of writes has been admitted
+struct FailAfter(Arc<AtomicUsize>);
+
+impl WriteStallPolicy for FailAfter {
+    fn check(&self, load: &dyn WriteLoad) -> WriteStallAction {
+        let admitted = self
+            .0
+            .fetch_update(Ordering::Relaxed, Ordering::Relaxed, |x| x.checked_sub(1));
+
+        if load.admission() && admitted.is_err() {
+            WriteStallAction::Halt(WriteStallReason::WriteBufferSize)
+        } else {
+            DefaultWriteStallPolicy.check(load)
+        }
+    }
+}
+
+#[test_log::test]
+fn secondary_index_backfill_interrupted() -> fjall::Result<()> {
+    const ITEM_COUNT: u32 = 25_000;
+
+    let folder = tempfile::tempdir()?;
+
+    {
+        let admissions = Arc::new(AtomicUsize::new(usize::MAX));
+
+        let keyspace = Config::new(&folder)
+            .write_admission(WriteAdmission::FailFast)
+            .write_stall_policy(FailAfter(admissions.clone()))
+            .open()?;
+        let items = keyspace.open_partition("items", PartitionCreateOptions::default())?;
+
+        for x in 0..ITEM_COUNT {
+            items.insert(x.to_be_bytes(), "red")?;
+        }
+
+        // Only the definition marker and the first backfill batch are written
+        admissions.store(2, Ordering::Relaxed);
+
+        assert!(matches!(
+            keyspace.create_index("items", "items_by_tag", by_tags),
+            Err(fjall::Error::WriteStalled { .. })
+        ));
+    }
+
+    let keyspace = Config::new(&folder).open()?;
+
+    // The index partition exists, but was not built completely, so it is built again
+    let index = keyspace.create_index("items", "items_by_tag", by_tags)?;
+    assert_eq!(ITEM_COUNT as usize, entry_count(&index)?);
+    assert_eq!(ITEM_COUNT as usize, index.lookup("red"..="red").count());
+
+    Ok(())
+}
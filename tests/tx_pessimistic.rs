@@ -0,0 +1,271 @@
+#[test_log::test]
+#[cfg(feature = "pessimistic_tx")]
+fn tx_pessimistic_lock_blocks_other_writer() -> fjall::Result<()> {
+    use fjall::{Config, PartitionCreateOptions};
+    use std::time::Duration;
+
+    let folder = tempfile::tempdir()?;
+
+    let keyspace = Config::new(&folder).open_transactional()?;
+    let tree = keyspace.open_partition("default", PartitionCreateOptions::default())?;
+
+    tree.insert("counter", 0_u64.to_be_bytes())?;
+
+    let threads = (0..4)
+        .map(|_| {
+            let keyspace = keyspace.clone();
+            let tree = tree.clone();
+
+            std::thread::spawn(move || -> fjall::Result<()> {
+                for _ in 0..25 {
+                    let mut tx = keyspace.write_tx();
+
+                    let value = tx.get_for_update(&tree, "counter")?.unwrap();
+                    let mut buf = [0; 8];
+                    buf.copy_from_slice(&value);
+                    let next = u64::from_be_bytes(buf) + 1;
+
+                    // Give other threads a chance to interleave
+                    std::thread::sleep(Duration::from_micros(100));
+
+                    tx.insert(&tree, "counter", next.to_be_bytes())?;
+                    tx.commit()?;
+                }
+
+                Ok(())
+            })
+        })
+        .collect::<Vec<_>>();
+
+    for thread in threads {
+        thread.join().unwrap()?;
+    }
+
+    assert_eq!(
+        &100_u64.to_be_bytes(),
+        &*tree.get("counter")?.unwrap(),
+        "no increment should be lost"
+    );
+
+    Ok(())
+}
+
+#[test_log::test]
+#[cfg(feature = "pessimistic_tx")]
+fn tx_pessimistic_deadlock() -> fjall::Result<()> {
+    use fjall::{Config, LockError, PartitionCreateOptions};
+    use std::{sync::Barrier, time::Duration};
+
+    let folder = tempfile::tempdir()?;
+
+    let keyspace = Config::new(&folder)
+        .tx_lock_timeout(Duration::from_secs(10))
+        .open_transactional()?;
+    let tree = keyspace.open_partition("default", PartitionCreateOptions::default())?;
+
+    let barrier = std::sync::Arc::new(Barrier::new(2));
+
+    let mut tx1 = keyspace.write_tx();
+    tx1.lock(&tree, "a")?;
+
+    let thread = {
+        let keyspace = keyspace.clone();
+        let tree = tree.clone();
+        let barrier = barrier.clone();
+
+        std::thread::spawn(move || -> fjall::Result<()> {
+            let mut tx2 = keyspace.write_tx();
+            tx2.lock(&tree, "b")?;
+            barrier.wait();
+
+            // Waits for tx1
+            tx2.lock(&tree, "a")?;
+            tx2.commit()
+        })
+    };
+
+    barrier.wait();
+
+    // Give tx2 time to start waiting for "a"
+    std::thread::sleep(Duration::from_millis(100));
+
+    // tx1 waits for tx2, which waits for tx1
+    assert!(matches!(
+        tx1.lock(&tree, "b"),
+        Err(fjall::Error::Lock(LockError::Deadlock))
+    ));
+    tx1.rollback();
+
+    // Rolling back tx1 unblocks tx2
+    thread.join().unwrap()?;
+
+    Ok(())
+}
+
+#[test_log::test]
+#[cfg(feature = "pessimistic_tx")]
+fn tx_pessimistic_deadlock_range() -> fjall::Result<()> {
+    use fjall::{Config, LockError, PartitionCreateOptions};
+    use std::time::Duration;
+
+    let folder = tempfile::tempdir()?;
+
+    let keyspace = Config::new(&folder)
+        .tx_lock_timeout(Duration::from_secs(10))
+        .open_transactional()?;
+    let tree = keyspace.open_partition("default", PartitionCreateOptions::default())?;
+
+    let mut tx1 = keyspace.write_tx();
+    tx1.lock(&tree, "a")?;
+
+    let mut tx2 = keyspace.write_tx();
+    tx2.lock(&tree, "b")?;
+
+    let mut tx3 = keyspace.write_tx();
+    tx3.lock(&tree, "c")?;
+
+    let mut tx4 = keyspace.write_tx();
+    tx4.lock(&tree, "d")?;
+
+    std::thread::scope(|scope| -> fjall::Result<()> {
+        // Waits for tx1 and tx2
+        let t3 = scope.spawn(|| -> fjall::Result<()> {
+            tx3.lock_range(&tree, "a"..="b")?;
+            tx3.commit()
+        });
+
+        std::thread::sleep(Duration::from_millis(100));
+
+        // Waits for tx3
+        let t4 = scope.spawn(|| -> fjall::Result<()> {
+            tx4.lock(&tree, "c")?;
+            tx4.commit()
+        });
+
+        std::thread::sleep(Duration::from_millis(100));
+
+        // tx2 waits for tx4, which waits for tx3, which waits for tx2 (and tx1)
+        assert!(matches!(
+            tx2.lock(&tree, "d"),
+            Err(fjall::Error::Lock(LockError::Deadlock))
+        ));
+        tx2.rollback();
+        tx1.rollback();
+
+        t3.join().unwrap()?;
+        t4.join().unwrap()?;
+
+        Ok(())
+    })?;
+
+    Ok(())
+}
+
+#[test_log::test]
+#[cfg(feature = "pessimistic_tx")]
+fn tx_pessimistic_timeout() -> fjall::Result<()> {
+    use fjall::{Config, LockError, PartitionCreateOptions};
+    use std::time::Duration;
+
+    let folder = tempfile::tempdir()?;
+
+    let keyspace = Config::new(&folder)
+        .tx_lock_timeout(Duration::from_millis(50))
+        .open_transactional()?;
+    let tree = keyspace.open_partition("default", PartitionCreateOptions::default())?;
+
+    let mut tx1 = keyspace.write_tx();
+    tx1.insert(&tree, "a", "tx1")?;
+
+    let mut tx2 = keyspace.write_tx();
+    assert!(matches!(
+        tx2.insert(&tree, "a", "tx2"),
+        Err(fjall::Error::Lock(LockError::Timeout))
+    ));
+    assert_eq!(None, tx2.get_for_update(&tree, "b")?);
+
+    tx1.commit()?;
+
+    // Lock was released on commit, and the snapshot is refreshed after locking
+    assert_eq!(
+        Some("tx1".as_bytes().into()),
+        tx2.get_for_update(&tree, "a")?
+    );
+
+    Ok(())
+}
+
+#[test_log::test]
+#[cfg(feature = "pessimistic_tx")]
+fn tx_pessimistic_autocommit_lock_timeout() -> fjall::Result<()> {
+    use fjall::{Config, LockError, PartitionCreateOptions};
+    use std::time::Duration;
+
+    let folder = tempfile::tempdir()?;
+
+    let keyspace = Config::new(&folder)
+        .tx_lock_timeout(Duration::from_millis(50))
+        .open_transactional()?;
+    let tree = keyspace.open_partition("default", PartitionCreateOptions::default())?;
+
+    let mut tx = keyspace.write_tx();
+    tx.insert(&tree, "a", "tx")?;
+
+    let is_timeout =
+        |result: fjall::Result<_>| matches!(result, Err(fjall::Error::Lock(LockError::Timeout)));
+
+    assert!(is_timeout(tree.insert("a", "other").map(|()| None)));
+    assert!(is_timeout(tree.remove("a").map(|()| None)));
+    assert!(is_timeout(tree.fetch_update("a", |_| None)));
+    assert!(is_timeout(tree.update_fetch("a", |_| None)));
+    assert!(is_timeout(tree.take("a")));
+
+    // Other keys are not locked
+    tree.insert("b", "other")?;
+
+    tx.commit()?;
+
+    tree.insert("a", "other")?;
+    assert_eq!(Some("other".as_bytes().into()), tree.get("a")?);
+
+    Ok(())
+}
+
+#[test_log::test]
+#[cfg(feature = "pessimistic_tx")]
+fn tx_pessimistic_range_lock() -> fjall::Result<()> {
+    use fjall::{Config, LockError, PartitionCreateOptions};
+    use std::time::Duration;
+
+    let folder = tempfile::tempdir()?;
+
+    let keyspace = Config::new(&folder)
+        .tx_lock_timeout(Duration::from_millis(50))
+        .open_transactional()?;
+    let tree = keyspace.open_partition("default", PartitionCreateOptions::default())?;
+    let other = keyspace.open_partition("other", PartitionCreateOptions::default())?;
+
+    let mut tx1 = keyspace.write_tx();
+    tx1.lock_range(&tree, "b".."d")?;
+
+    let is_timeout =
+        |result: fjall::Result<()>| matches!(result, Err(fjall::Error::Lock(LockError::Timeout)));
+
+    let mut tx2 = keyspace.write_tx();
+    assert!(is_timeout(tx2.insert(&tree, "c", "c")));
+    assert!(is_timeout(tx2.lock_prefix(&tree, "b")));
+    tx2.insert(&tree, "a", "a")?;
+    tx2.insert(&tree, "d", "d")?;
+    tx2.insert(&other, "c", "c")?;
+
+    // Locks are released on rollback
+    tx1.rollback();
+
+    tx2.insert(&tree, "c", "c")?;
+    tx2.commit()?;
+
+    assert_eq!(3, keyspace.read_tx().len(&tree)?);
+    assert_eq!(1, keyspace.read_tx().len(&other)?);
+
+    Ok(())
+}
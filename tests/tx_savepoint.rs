@@ -0,0 +1,175 @@
+#[test_log::test]
+#[cfg(feature = "single_writer_tx")]
+fn tx_savepoint() -> fjall::Result<()> {
+    use fjall::{Config, PartitionCreateOptions};
+
+    let folder = tempfile::tempdir()?;
+
+    let keyspace = Config::new(&folder).open_transactional()?;
+
+    let tree = keyspace.open_partition("default", PartitionCreateOptions::default())?;
+    let other = keyspace.open_partition("other", PartitionCreateOptions::default())?;
+
+    tree.insert("c", "c")?;
+
+    let mut tx = keyspace.write_tx();
+    tx.insert(&tree, "a", "a");
+
+    let sp1 = tx.savepoint();
+    tx.insert(&tree, "a", "a2");
+    tx.insert(&tree, "b", "b");
+
+    let sp2 = tx.savepoint();
+    tx.remove(&tree, "a");
+    tx.remove(&tree, "c");
+    tx.insert(&other, "x", "x");
+    assert!(!tx.contains_key(&tree, "a")?);
+    assert!(!tx.contains_key(&tree, "c")?);
+
+    tx.rollback_to(&sp2)?;
+    assert_eq!(b"a2", &*tx.get(&tree, "a")?.unwrap());
+    assert_eq!(b"c", &*tx.get(&tree, "c")?.unwrap());
+    assert!(!tx.contains_key(&other, "x")?);
+
+    tx.rollback_to(&sp1)?;
+    assert_eq!(b"a", &*tx.get(&tree, "a")?.unwrap());
+    assert!(!tx.contains_key(&tree, "b")?);
+
+    // Savepoint is still valid after rolling back to it
+    tx.insert(&tree, "d", "d");
+    tx.rollback_to(&sp1)?;
+    assert!(!tx.contains_key(&tree, "d")?);
+
+    tx.commit()?;
+
+    assert_eq!(b"a", &*tree.get("a")?.unwrap());
+    assert!(!tree.contains_key("b")?);
+    assert!(tree.contains_key("c")?);
+    assert!(!other.contains_key("x")?);
+
+    Ok(())
+}
+
+#[test_log::test]
+#[cfg(feature = "single_writer_tx")]
+fn tx_savepoint_released() -> fjall::Result<()> {
+    use fjall::{Config, PartitionCreateOptions};
+
+    let folder = tempfile::tempdir()?;
+
+    let keyspace = Config::new(&folder).open_transactional()?;
+
+    let tree = keyspace.open_partition("default", PartitionCreateOptions::default())?;
+
+    let mut tx = keyspace.write_tx();
+
+    let sp1 = tx.savepoint();
+    tx.insert(&tree, "a", "a");
+    let sp2 = tx.savepoint();
+    tx.insert(&tree, "b", "b");
+
+    tx.rollback_to(&sp1)?;
+    tx.insert(&tree, "c", "c");
+
+    assert!(matches!(
+        tx.rollback_to(&sp2),
+        Err(fjall::Error::InvalidSavepoint)
+    ));
+    assert!(tx.contains_key(&tree, "c")?);
+
+    Ok(())
+}
+
+#[test_log::test]
+#[cfg(feature = "ssi_tx")]
+fn tx_ssi_savepoint() -> fjall::Result<()> {
+    use fjall::{Config, PartitionCreateOptions};
+
+    let folder = tempfile::tempdir()?;
+
+    let keyspace = Config::new(&folder).open_transactional()?;
+
+    let tree = keyspace.open_partition("default", PartitionCreateOptions::default())?;
+
+    let mut tx1 = keyspace.write_tx()?;
+    let mut tx2 = keyspace.write_tx()?;
+
+    tx1.insert(&tree, "a", "a");
+
+    let sp = tx1.savepoint();
+    tx1.get(&tree, "b")?;
+    tx1.insert(&tree, "c", "c");
+    tx1.rollback_to(&sp)?;
+
+    tx2.insert(&tree, "b", "b");
+    tx2.get(&tree, "c")?;
+    tx2.commit()?.unwrap();
+
+    // The read of "b" was rolled back, so there is no conflict
+    tx1.commit()?.unwrap();
+
+    assert_eq!(b"a", &*tree.get("a")?.unwrap());
+    assert_eq!(b"b", &*tree.get("b")?.unwrap());
+    assert!(!tree.contains_key("c")?);
+
+    Ok(())
+}
+
+#[test_log::test]
+#[cfg(feature = "single_writer_tx")]
+fn tx_savepoint_other_tx() -> fjall::Result<()> {
+    use fjall::{Config, PartitionCreateOptions};
+
+    let folder = tempfile::tempdir()?;
+
+    let keyspace = Config::new(&folder).open_transactional()?;
+
+    let tree = keyspace.open_partition("default", PartitionCreateOptions::default())?;
+
+    let sp = {
+        let mut tx = keyspace.write_tx();
+        tx.savepoint()
+    };
+
+    let mut tx = keyspace.write_tx();
+    let _ = tx.savepoint();
+    tx.insert(&tree, "a", "a");
+
+    assert!(matches!(
+        tx.rollback_to(&sp),
+        Err(fjall::Error::InvalidSavepoint)
+    ));
+    assert!(tx.contains_key(&tree, "a")?);
+
+    Ok(())
+}
+
+#[test_log::test]
+#[cfg(feature = "single_writer_tx")]
+fn tx_savepoint_other_keyspace() -> fjall::Result<()> {
+    use fjall::{Config, PartitionCreateOptions};
+
+    let folder = tempfile::tempdir()?;
+    let other_folder = tempfile::tempdir()?;
+
+    let keyspace = Config::new(&folder).open_transactional()?;
+    let other_keyspace = Config::new(&other_folder).open_transactional()?;
+
+    let tree = keyspace.open_partition("default", PartitionCreateOptions::default())?;
+
+    // NOTE: Both are the first transaction of their keyspace
+    let mut other_tx = other_keyspace.write_tx();
+    let sp = other_tx.savepoint();
+
+    let mut tx = keyspace.write_tx();
+    let _ = tx.savepoint();
+    tx.insert(&tree, "a", "a");
+
+    assert!(matches!(
+        tx.rollback_to(&sp),
+        Err(fjall::Error::InvalidSavepoint)
+    ));
+    assert!(tx.contains_key(&tree, "a")?);
+
+    Ok(())
+}
@@ -0,0 +1,51 @@
+#[test_log::test]
+#[cfg(feature = "ssi_tx")]
+fn tx_ssi_conflict_info() -> fjall::Result<()> {
+    use fjall::{Config, ConflictingRead, PartitionCreateOptions};
+    use std::ops::Bound;
+
+    let folder = tempfile::tempdir()?;
+
+    let keyspace = Config::new(&folder).open_transactional()?;
+
+    let tree = keyspace.open_partition("default", PartitionCreateOptions::default())?;
+
+    let mut tx1 = keyspace.write_tx()?;
+    let mut tx2 = keyspace.write_tx()?;
+
+    tx1.get(&tree, "a")?;
+    tx1.insert(&tree, "b", "b");
+
+    tx2.insert(&tree, "a", "a");
+    tx2.commit()?.unwrap();
+    let seqno = keyspace.inner.instant() - 1;
+
+    let conflict = tx1.commit()?.unwrap_err();
+    assert_eq!(&*conflict.partition, "default");
+    assert_eq!(conflict.read, ConflictingRead::Key("a".into()));
+    assert_eq!(&*conflict.written_key, b"a");
+    assert_eq!(conflict.seqno, seqno);
+
+    let mut tx1 = keyspace.write_tx()?;
+    let mut tx2 = keyspace.write_tx()?;
+
+    for kv in tx1.range(&tree, "c".."e") {
+        kv?;
+    }
+    tx1.insert(&tree, "x", "x");
+
+    tx2.insert(&tree, "d", "d");
+    tx2.commit()?.unwrap();
+
+    let conflict = tx1.commit()?.unwrap_err();
+    assert_eq!(
+        conflict.read,
+        ConflictingRead::Range {
+            start: Bound::Included("c".into()),
+            end: Bound::Excluded("e".into()),
+        }
+    );
+    assert_eq!(&*conflict.written_key, b"d");
+
+    Ok(())
+}
@@ -0,0 +1,91 @@
+#[test_log::test]
+#[cfg(feature = "ssi_tx")]
+fn tx_ssi_transaction_retry() -> fjall::Result<()> {
+    use fjall::{Config, PartitionCreateOptions, RetryPolicy, TransactionError};
+
+    let folder = tempfile::tempdir()?;
+
+    let keyspace = Config::new(&folder).open_transactional()?;
+
+    let tree = keyspace.open_partition("default", PartitionCreateOptions::default())?;
+
+    // Conflicts on the first attempt only
+    let mut attempts = 0;
+    let value = keyspace
+        .transaction(|tx| {
+            attempts += 1;
+
+            let prev = tx.get(&tree, "a")?;
+
+            if attempts == 1 {
+                tree.insert("a", "other")?;
+            }
+
+            tx.insert(&tree, "a", "mine");
+            Ok::<_, fjall::Error>(prev)
+        })
+        .unwrap();
+
+    assert_eq!(2, attempts);
+    assert_eq!(b"other", &*value.unwrap());
+    assert_eq!(b"mine", &*tree.get("a")?.unwrap());
+
+    // Conflicts on every attempt
+    let result = keyspace.transaction_with(&RetryPolicy::default().max_attempts(3), |tx| {
+        tx.get(&tree, "a")?;
+        tree.insert("a", "other")?;
+        tx.insert(&tree, "b", "b");
+        Ok::<_, fjall::Error>(())
+    });
+
+    assert!(matches!(
+        result,
+        Err(TransactionError::RetriesExhausted { attempts: 3, .. })
+    ));
+    assert!(!tree.contains_key("b")?);
+
+    // User errors are not retried
+    let mut attempts = 0;
+    let result = keyspace.transaction(|tx| {
+        attempts += 1;
+        tx.insert(&tree, "c", "c");
+        Err::<(), _>("nope")
+    });
+
+    assert!(matches!(result, Err(TransactionError::User("nope"))));
+    assert_eq!(1, attempts);
+    assert!(!tree.contains_key("c")?);
+
+    Ok(())
+}
+
+#[test_log::test]
+#[cfg(feature = "ssi_tx")]
+fn tx_ssi_transaction_deadline() -> fjall::Result<()> {
+    use fjall::{Config, PartitionCreateOptions, RetryPolicy, TransactionError};
+    use std::time::Duration;
+
+    let folder = tempfile::tempdir()?;
+
+    let keyspace = Config::new(&folder).open_transactional()?;
+
+    let tree = keyspace.open_partition("default", PartitionCreateOptions::default())?;
+
+    let policy = RetryPolicy::default()
+        .max_attempts(u32::MAX)
+        .deadline(Some(Duration::from_millis(200)));
+
+    let result = keyspace.transaction_with(&policy, |tx| {
+        tx.get(&tree, "a")?;
+        tree.insert("a", "other")?;
+        tx.insert(&tree, "b", "b");
+        Ok::<_, fjall::Error>(())
+    });
+
+    assert!(matches!(
+        result,
+        Err(TransactionError::DeadlineExceeded { .. })
+    ));
+
+    Ok(())
+}
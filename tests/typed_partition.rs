@@ -0,0 +1,210 @@
+use fjall::{
+    codec::{Codec, CodecError, OrderedCodec, RawCodec},
+    Config, DecodeTarget, PartitionCreateOptions, TypedPartition,
+};
+
+#[test_log::test]
+fn typed_partition_order() -> fjall::Result<()> {
+    let folder = tempfile::tempdir()?;
+
+    let keyspace = Config::new(&folder).open()?;
+    let partition = keyspace.open_partition("default", PartitionCreateOptions::default())?;
+
+    let typed = TypedPartition::<(i64, String), u64>::new(partition);
+
+    let keys = [
+        (i64::MIN, String::new()),
+        (-1, "b".to_owned()),
+        (0, String::new()),
+        (0, "\0".to_owned()),
+        (0, "a".to_owned()),
+        (0, "a\0".to_owned()),
+        (0, "ab".to_owned()),
+        (1, String::new()),
+        (i64::MAX, "z".to_owned()),
+    ];
+
+    // Insert in reverse, so the order is not given by the write order
+    for (idx, key) in keys.iter().enumerate().rev() {
+        typed.insert(key, &(idx as u64))?;
+    }
+
+    let items = typed.iter().collect::<fjall::Result<Vec<_>>>()?;
+    assert_eq!(
+        keys.iter()
+            .cloned()
+            .enumerate()
+            .map(|(idx, key)| (key, idx as u64))
+            .collect::<Vec<_>>(),
+        items
+    );
+
+    let range = typed
+        .range((0, "a".to_owned())..(1, String::new()))
+        .map(|kv| kv.map(|(_, v)| v))
+        .collect::<fjall::Result<Vec<_>>>()?;
+    assert_eq!(vec![4, 5, 6], range);
+
+    let prefix = typed
+        .prefix(&(0_i64,))
+        .rev()
+        .map(|kv| kv.map(|(_, v)| v))
+        .collect::<fjall::Result<Vec<_>>>()?;
+    assert_eq!(vec![6, 5, 4, 3, 2], prefix);
+
+    // Prefix by full component, not by string prefix
+    let prefix = typed
+        .prefix(&(0_i64, "a".to_owned()))
+        .map(|kv| kv.map(|(_, v)| v))
+        .collect::<fjall::Result<Vec<_>>>()?;
+    assert_eq!(vec![4], prefix);
+
+    assert_eq!(
+        Some(((i64::MIN, String::new()), 0)),
+        typed.first_key_value()?
+    );
+    assert_eq!(
+        Some(((i64::MAX, "z".to_owned()), 8)),
+        typed.last_key_value()?
+    );
+
+    assert!(typed.contains_key(&(0, "\0".to_owned()))?);
+    typed.remove(&(0, "\0".to_owned()))?;
+    assert!(!typed.contains_key(&(0, "\0".to_owned()))?);
+    assert_eq!(None, typed.get(&(0, "\0".to_owned()))?);
+
+    Ok(())
+}
+
+#[test_log::test]
+fn typed_partition_raw_codec() -> fjall::Result<()> {
+    let folder = tempfile::tempdir()?;
+
+    let keyspace = Config::new(&folder).open()?;
+    let partition = keyspace.open_partition("default", PartitionCreateOptions::default())?;
+
+    let typed = TypedPartition::<String, String, RawCodec>::new(partition.clone());
+
+    typed.insert(&"user:1".to_owned(), &"alice".to_owned())?;
+    typed.insert(&"user:2".to_owned(), &"bob".to_owned())?;
+    typed.insert(&"session:1".to_owned(), &"xyz".to_owned())?;
+
+    // Items are stored as-is
+    assert_eq!(b"alice", &*partition.get("user:1")?.unwrap());
+
+    let users = typed
+        .prefix(&"user:".to_owned())
+        .map(|kv| kv.map(|(_, v)| v))
+        .collect::<fjall::Result<Vec<_>>>()?;
+    assert_eq!(vec!["alice".to_owned(), "bob".to_owned()], users);
+
+    Ok(())
+}
+
+#[test_log::test]
+fn typed_partition_decode_error() -> fjall::Result<()> {
+    let folder = tempfile::tempdir()?;
+
+    let keyspace = Config::new(&folder).open()?;
+    let partition = keyspace.open_partition("default", PartitionCreateOptions::default())?;
+
+    let typed = TypedPartition::<u32, String>::new(partition.clone());
+
+    partition.insert(1_u32.to_be_bytes(), "not terminated")?;
+    partition.insert("abc", OrderedCodec::encode(&"value".to_owned()))?;
+
+    match typed.get(&1) {
+        Err(fjall::Error::TypedDecode(e)) => {
+            assert_eq!(DecodeTarget::Value, e.target);
+            assert_eq!(&*e.partition, "default");
+            assert_eq!(&*e.key, &1_u32.to_be_bytes());
+            assert!(e.type_name.contains("String"));
+        }
+        other => panic!("expected decode error, got {other:?}"),
+    }
+
+    match typed.iter().next_back().unwrap() {
+        Err(fjall::Error::TypedDecode(e)) => {
+            assert_eq!(DecodeTarget::Key, e.target);
+            assert_eq!(&*e.key, b"abc");
+            assert!(e.type_name.contains("u32"));
+        }
+        other => panic!("expected decode error, got {other:?}"),
+    }
+
+    Ok(())
+}
+
+#[test_log::test]
+fn typed_partition_custom_codec() -> fjall::Result<()> {
+    /// Ordered keys, little-endian values
+    struct MyCodec;
+
+    impl Codec<u64> for MyCodec {
+        fn encode_into(item: &u64, buf: &mut Vec<u8>) {
+            buf.extend_from_slice(&item.to_be_bytes());
+        }
+
+        fn decode(bytes: &[u8]) -> Result<u64, CodecError> {
+            OrderedCodec::decode(bytes)
+        }
+    }
+
+    impl Codec<u32> for MyCodec {
+        fn encode_into(item: &u32, buf: &mut Vec<u8>) {
+            buf.extend_from_slice(&item.to_le_bytes());
+        }
+
+        fn decode(bytes: &[u8]) -> Result<u32, CodecError> {
+            let bytes = bytes
+                .try_into()
+                .map_err(|_| CodecError::new("expected 4 bytes"))?;
+            Ok(u32::from_le_bytes(bytes))
+        }
+    }
+
+    let folder = tempfile::tempdir()?;
+
+    let keyspace = Config::new(&folder).open()?;
+    let partition = keyspace.open_partition("default", PartitionCreateOptions::default())?;
+
+    let typed = TypedPartition::<u64, u32, MyCodec>::new(partition.clone());
+
+    typed.insert(&300, &1)?;
+    typed.insert(&2, &2)?;
+
+    assert_eq!(
+        &*partition.get(300_u64.to_be_bytes())?.unwrap(),
+        &[1, 0, 0, 0]
+    );
+    assert_eq!(Some((2, 2)), typed.first_key_value()?);
+
+    Ok(())
+}
+
+#[test_log::test]
+#[cfg(feature = "single_writer_tx")]
+fn typed_partition_tx() -> fjall::Result<()> {
+    use fjall::TxPartitionHandle;
+
+    let folder = tempfile::tempdir()?;
+
+    let keyspace = Config::new(&folder).open_transactional()?;
+    let partition = keyspace.open_partition("default", PartitionCreateOptions::default())?;
+
+    let typed = TypedPartition::<u32, String, OrderedCodec, TxPartitionHandle>::new(partition);
+
+    typed.insert(&2, &"b".to_owned())?;
+    typed.insert(&1, &"a".to_owned())?;
+
+    assert_eq!(Some("a".to_owned()), typed.get(&1)?);
+    assert_eq!(
+        vec![(1, "a".to_owned()), (2, "b".to_owned())],
+        typed.range(1..).collect::<fjall::Result<Vec<_>>>()?
+    );
+
+    typed.remove(&1)?;
+    assert_eq!(Some((2, "b".to_owned())), typed.first_key_value()?);
+
+    Ok(())
+}
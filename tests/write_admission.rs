@@ -0,0 +1,123 @@
+use fjall::{Config, PartitionCreateOptions, WriteAdmission, WriteStallReason};
+
+const LIMIT: u64 = 1_024 * 1_024;
+
+#[test_log::test]
+fn write_admission_fail_fast() -> fjall::Result<()> {
+    let folder = tempfile::tempdir()?;
+
+    let keyspace = Config::new(&folder)
+        .max_write_buffer_size(LIMIT)
+        .write_admission(WriteAdmission::FailFast)
+        .open()?;
+    let partition = keyspace.open_partition("default", PartitionCreateOptions::default())?;
+
+    // Fills up the write buffer
+    partition.insert("big", vec![0; LIMIT as usize + 1])?;
+
+    match partition.insert("a", "abc") {
+        Err(fjall::Error::WriteStalled {
+            reason,
+            retry_after,
+        }) => {
+            assert_eq!(WriteStallReason::WriteBufferSize, reason);
+            assert!(!retry_after.is_zero());
+        }
+        other => panic!("expected write stall, got {other:?}"),
+    }
+
+    assert!(matches!(
+        partition.remove("big"),
+        Err(fjall::Error::WriteStalled { .. })
+    ));
+
+    let mut batch = keyspace.batch();
+    batch.insert(&partition, "b", "def");
+    assert!(matches!(
+        batch.commit(),
+        Err(fjall::Error::WriteStalled { .. })
+    ));
+
+    // Rejected writes are not written
+    assert!(!partition.contains_key("a")?);
+    assert!(!partition.contains_key("b")?);
+    assert!(partition.contains_key("big")?);
+
+    // Flushing frees the write buffer
+    partition.rotate_memtable_and_wait()?;
+    assert!(keyspace.write_buffer_size() < LIMIT);
+
+    partition.insert("a", "abc")?;
+    assert!(partition.contains_key("a")?);
+
+    Ok(())
+}
+
+#[test_log::test]
+fn write_admission_fail_fast_journal_size() -> fjall::Result<()> {
+    const MAX_JOURNALING_SIZE: u64 = 24 * 1_024 * 1_024;
+
+    let folder = tempfile::tempdir()?;
+
+    // NOTE: Without flush workers, sealed journals cannot be evicted
+    let keyspace = Config::new(&folder)
+        .flush_workers(0)
+        .max_journaling_size(MAX_JOURNALING_SIZE)
+        .write_admission(WriteAdmission::FailFast)
+        .open()?;
+    let partition = keyspace.open_partition("default", PartitionCreateOptions::default())?;
+
+    let mut x = 0_u32;
+
+    while keyspace.journal_disk_space() <= MAX_JOURNALING_SIZE {
+        partition.insert(x.to_be_bytes(), vec![0; LIMIT as usize])?;
+        partition.rotate_memtable()?;
+        x += 1;
+    }
+
+    match partition.insert("a", "abc") {
+        Err(fjall::Error::WriteStalled { reason, .. }) => {
+            assert_eq!(WriteStallReason::JournalSize, reason);
+        }
+        other => panic!("expected write stall, got {other:?}"),
+    }
+    assert!(!partition.contains_key("a")?);
+
+    Ok(())
+}
+
+#[test_log::test]
+fn write_admission_fail_fast_l0_segments() -> fjall::Result<()> {
+    let folder = tempfile::tempdir()?;
+
+    // NOTE: Fail fast would already reject writes that are delayed
+    // because of 20+ L0 segments, so the segments are created beforehand
+    {
+        let keyspace = Config::new(&folder).compaction_workers(0).open()?;
+        let partition = keyspace.open_partition("default", PartitionCreateOptions::default())?;
+
+        // Overlapping segments
+        for _ in 0..32 {
+            partition.insert("a", "abc")?;
+            partition.insert("z", "abc")?;
+            partition.rotate_memtable_and_wait()?;
+        }
+    }
+
+    let keyspace = Config::new(&folder)
+        .compaction_workers(0)
+        .write_admission(WriteAdmission::FailFast)
+        .open()?;
+    let partition = keyspace.open_partition("default", PartitionCreateOptions::default())?;
+    assert_eq!(32, partition.segment_count());
+
+    match partition.insert("b", "abc") {
+        Err(fjall::Error::WriteStalled { reason, .. }) => {
+            assert_eq!(WriteStallReason::L0Segments, reason);
+        }
+        other => panic!("expected write stall, got {other:?}"),
+    }
+    assert!(!partition.contains_key("b")?);
+
+    Ok(())
+}
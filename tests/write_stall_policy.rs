@@ -0,0 +1,403 @@
+use fjall::{
+    Config, DefaultWriteStallPolicy, PartitionCreateOptions, WriteAdmission, WriteLoad,
+    WriteStallAction, WriteStallPolicy, WriteStallReason,
+};
+use std::{
+    sync::{Arc, Mutex},
+    time::{Duration, Instant},
+};
+
+const MIB: u64 = 1_024 * 1_024;
+
+#[derive(Clone)]
+struct Load {
+    l0_segments: usize,
+    l0_disjoint: bool,
+    journal_bytes: u64,
+    write_buffer_bytes: u64,
+    memtable_rotated: bool,
+    write_buffer_exceeded: bool,
+    admission: bool,
+}
+
+impl WriteLoad for Load {
+    fn l0_segments(&self) -> usize {
+        self.l0_segments
+    }
+
+    fn l0_disjoint(&self) -> bool {
+        self.l0_disjoint
+    }
+
+    fn pending_flush_bytes(&self) -> u64 {
+        0
+    }
+
+    fn journal_bytes(&self) -> u64 {
+        self.journal_bytes
+    }
+
+    fn max_journal_bytes(&self) -> u64 {
+        512 * MIB
+    }
+
+    fn write_buffer_bytes(&self) -> u64 {
+        self.write_buffer_bytes
+    }
+
+    fn max_write_buffer_bytes(&self) -> u64 {
+        64 * MIB
+    }
+
+    fn memtable_rotated(&self) -> bool {
+        self.memtable_rotated
+    }
+
+    fn write_buffer_exceeded(&self) -> bool {
+        self.write_buffer_exceeded
+    }
+
+    fn admission(&self) -> bool {
+        self.admission
+    }
+}
+
+fn load() -> Load {
+    Load {
+        l0_segments: 0,
+        l0_disjoint: false,
+        journal_bytes: 0,
+        write_buffer_bytes: 0,
+        memtable_rotated: false,
+        write_buffer_exceeded: false,
+        admission: false,
+    }
+}
+
+#[test_log::test]
+fn write_stall_policy_default() {
+    let policy = DefaultWriteStallPolicy;
+
+    assert_eq!(WriteStallAction::Proceed, policy.check(&load()));
+
+    let l0 = |l0_segments, l0_disjoint, memtable_rotated| {
+        policy.check(&Load {
+            l0_segments,
+            l0_disjoint,
+            memtable_rotated,
+            ..load()
+        })
+    };
+    assert_eq!(WriteStallAction::Proceed, l0(19, false, false));
+    assert_eq!(
+        WriteStallAction::Delay(WriteStallReason::L0Segments, Duration::from_micros(10)),
+        l0(20, false, false)
+    );
+    assert_eq!(
+        WriteStallAction::Delay(WriteStallReason::L0Segments, Duration::from_micros(60)),
+        l0(25, false, true)
+    );
+    assert_eq!(
+        WriteStallAction::Delay(WriteStallReason::L0Segments, Duration::from_millis(1)),
+        l0(31, false, false)
+    );
+    assert_eq!(WriteStallAction::Proceed, l0(32, false, false));
+    assert_eq!(
+        WriteStallAction::Halt(WriteStallReason::L0Segments),
+        l0(32, false, true)
+    );
+    assert_eq!(
+        WriteStallAction::Halt(WriteStallReason::L0Segments),
+        policy.check(&Load {
+            l0_segments: 32,
+            admission: true,
+            ..load()
+        })
+    );
+    assert_eq!(WriteStallAction::Proceed, l0(25, true, false));
+    assert_eq!(WriteStallAction::Proceed, l0(40, true, true));
+
+    let journal = |journal_bytes, memtable_rotated| {
+        policy.check(&Load {
+            journal_bytes,
+            memtable_rotated,
+            ..load()
+        })
+    };
+    assert_eq!(WriteStallAction::Proceed, journal(460 * MIB, true));
+    assert_eq!(WriteStallAction::Proceed, journal(500 * MIB, false));
+    assert_eq!(
+        WriteStallAction::Delay(WriteStallReason::JournalSize, Duration::from_millis(500)),
+        journal(500 * MIB, true)
+    );
+    assert_eq!(
+        WriteStallAction::Delay(WriteStallReason::JournalSize, Duration::from_millis(500)),
+        journal(512 * MIB, true)
+    );
+    assert_eq!(WriteStallAction::Proceed, journal(513 * MIB, false));
+    assert_eq!(
+        WriteStallAction::Halt(WriteStallReason::JournalSize),
+        journal(513 * MIB, true)
+    );
+
+    // Writes are not admitted while the journals are too large
+    assert_eq!(
+        WriteStallAction::Halt(WriteStallReason::JournalSize),
+        policy.check(&Load {
+            journal_bytes: 513 * MIB,
+            admission: true,
+            ..load()
+        })
+    );
+    assert_eq!(
+        WriteStallAction::Proceed,
+        policy.check(&Load {
+            journal_bytes: 500 * MIB,
+            admission: true,
+            ..load()
+        })
+    );
+
+    let write_buffer = |write_buffer_bytes, write_buffer_exceeded| {
+        policy.check(&Load {
+            write_buffer_bytes,
+            write_buffer_exceeded,
+            ..load()
+        })
+    };
+    assert_eq!(WriteStallAction::Proceed, write_buffer(64 * MIB, false));
+    assert_eq!(WriteStallAction::Proceed, write_buffer(57 * MIB, true));
+    assert_eq!(
+        WriteStallAction::Delay(
+            WriteStallReason::WriteBufferSize,
+            Duration::from_millis(100)
+        ),
+        write_buffer(60 * MIB, true)
+    );
+    assert_eq!(
+        WriteStallAction::Halt(WriteStallReason::WriteBufferSize),
+        write_buffer(64 * MIB, true)
+    );
+
+    // Halts are checked before delays
+    assert_eq!(
+        WriteStallAction::Halt(WriteStallReason::WriteBufferSize),
+        policy.check(&Load {
+            l0_segments: 25,
+            journal_bytes: 500 * MIB,
+            write_buffer_bytes: 64 * MIB,
+            memtable_rotated: true,
+            write_buffer_exceeded: true,
+            ..load()
+        })
+    );
+    assert_eq!(
+        WriteStallAction::Delay(WriteStallReason::JournalSize, Duration::from_millis(500)),
+        policy.check(&Load {
+            l0_segments: 25,
+            journal_bytes: 500 * MIB,
+            write_buffer_bytes: 60 * MIB,
+            memtable_rotated: true,
+            write_buffer_exceeded: true,
+            ..load()
+        })
+    );
+}
+
+/// Halts as soon as the write buffer holds more than the given amount of bytes
+struct WriteBufferLimit(u64);
+
+impl WriteStallPolicy for WriteBufferLimit {
+    fn check(&self, load: &dyn WriteLoad) -> WriteStallAction {
+        if load.write_buffer_bytes() > self.0 {
+            WriteStallAction::Halt(WriteStallReason::WriteBufferSize)
+        } else {
+            WriteStallAction::Proceed
+        }
+    }
+}
+
+struct Proceed;
+
+impl WriteStallPolicy for Proceed {
+    fn check(&self, _: &dyn WriteLoad) -> WriteStallAction {
+        WriteStallAction::Proceed
+    }
+}
+
+#[test_log::test]
+fn write_stall_policy_keyspace_and_partition() -> fjall::Result<()> {
+    let folder = tempfile::tempdir()?;
+
+    let keyspace = Config::new(&folder)
+        .write_admission(WriteAdmission::FailFast)
+        .write_stall_policy(WriteBufferLimit(1_000))
+        .open()?;
+    let a = keyspace.open_partition("a", PartitionCreateOptions::default())?;
+    let b = keyspace.open_partition("b", PartitionCreateOptions::default())?;
+
+    a.insert("big", vec![0; 2_000])?;
+
+    assert!(matches!(
+        a.insert("a", "abc"),
+        Err(fjall::Error::WriteStalled {
+            reason: WriteStallReason::WriteBufferSize,
+            ..
+        })
+    ));
+    assert!(matches!(
+        b.insert("a", "abc"),
+        Err(fjall::Error::WriteStalled { .. })
+    ));
+
+    // Partition policy overrides the keyspace policy
+    b.set_write_stall_policy(Some(Arc::new(Proceed)));
+    b.insert("a", "abc")?;
+    assert!(a.insert("a", "abc").is_err());
+
+    b.set_write_stall_policy(None);
+    assert!(b.insert("b", "abc").is_err());
+
+    Ok(())
+}
+
+/// Delays every write, recording the write buffer size and if the memtable was rotated
+#[derive(Default)]
+struct Recorder(Mutex<Vec<(u64, bool)>>);
+
+impl WriteStallPolicy for Recorder {
+    fn check(&self, load: &dyn WriteLoad) -> WriteStallAction {
+        self.0
+            .lock()
+            .unwrap()
+            .push((load.write_buffer_bytes(), load.memtable_rotated()));
+        WriteStallAction::Delay(WriteStallReason::L0Segments, Duration::from_millis(50))
+    }
+}
+
+#[test_log::test]
+fn write_stall_policy_delay() -> fjall::Result<()> {
+    let folder = tempfile::tempdir()?;
+
+    let keyspace = Config::new(&folder).open()?;
+    let partition = keyspace.open_partition("default", PartitionCreateOptions::default())?;
+
+    let recorder = Arc::new(Recorder::default());
+    partition.set_write_stall_policy(Some(recorder.clone()));
+
+    let start = Instant::now();
+    partition.insert("a", "abc")?;
+    assert!(start.elapsed() >= Duration::from_millis(50));
+
+    let loads = recorder.0.lock().unwrap();
+    assert_eq!(1, loads.len());
+    let (write_buffer_bytes, memtable_rotated) = loads[0];
+    assert!(write_buffer_bytes > 0);
+    assert!(!memtable_rotated);
+
+    Ok(())
+}
+
+/// Default policy, but the write buffer is only 95% full once it is not full anymore,
+/// recording all actions
+#[derive(Default)]
+struct AlmostDrained(Mutex<Vec<WriteStallAction>>);
+
+struct AlmostDrainedLoad<'a>(&'a dyn WriteLoad);
+
+impl WriteLoad for AlmostDrainedLoad<'_> {
+    fn l0_segments(&self) -> usize {
+        self.0.l0_segments()
+    }
+
+    fn l0_disjoint(&self) -> bool {
+        self.0.l0_disjoint()
+    }
+
+    fn pending_flush_bytes(&self) -> u64 {
+        self.0.pending_flush_bytes()
+    }
+
+    fn journal_bytes(&self) -> u64 {
+        self.0.journal_bytes()
+    }
+
+    fn max_journal_bytes(&self) -> u64 {
+        self.0.max_journal_bytes()
+    }
+
+    fn write_buffer_bytes(&self) -> u64 {
+        let bytes = self.0.write_buffer_bytes();
+        let max = self.0.max_write_buffer_bytes();
+
+        if bytes < max {
+            max / 100 * 95
+        } else {
+            bytes
+        }
+    }
+
+    fn max_write_buffer_bytes(&self) -> u64 {
+        self.0.max_write_buffer_bytes()
+    }
+
+    fn memtable_rotated(&self) -> bool {
+        self.0.memtable_rotated()
+    }
+
+    fn write_buffer_exceeded(&self) -> bool {
+        self.0.write_buffer_exceeded()
+    }
+
+    fn admission(&self) -> bool {
+        self.0.admission()
+    }
+}
+
+impl WriteStallPolicy for AlmostDrained {
+    fn check(&self, load: &dyn WriteLoad) -> WriteStallAction {
+        let action = DefaultWriteStallPolicy.check(&AlmostDrainedLoad(load));
+        self.0.lock().unwrap().push(action);
+        action
+    }
+}
+
+#[test_log::test]
+fn write_stall_policy_delay_after_halt() -> fjall::Result<()> {
+    let folder = tempfile::tempdir()?;
+
+    let keyspace = Config::new(&folder).max_write_buffer_size(MIB).open()?;
+    let partition = keyspace.open_partition("default", PartitionCreateOptions::default())?;
+
+    let policy = Arc::new(AlmostDrained::default());
+    partition.set_write_stall_policy(Some(policy.clone()));
+
+    let writer = std::thread::spawn({
+        let partition = partition.clone();
+        move || partition.insert("big", vec![0; MIB as usize + 1])
+    });
+
+    // Flushes the write buffer while the write is halted
+    while policy.0.lock().unwrap().is_empty() {
+        std::thread::sleep(Duration::from_millis(1));
+    }
+    partition.rotate_memtable_and_wait()?;
+
+    writer.join().unwrap()?;
+    assert!(keyspace.write_buffer_size() < MIB);
+
+    let actions = policy.0.lock().unwrap();
+    assert_eq!(
+        Some(&WriteStallAction::Halt(WriteStallReason::WriteBufferSize)),
+        actions.first()
+    );
+    assert_eq!(
+        Some(&WriteStallAction::Delay(
+            WriteStallReason::WriteBufferSize,
+            Duration::from_millis(100)
+        )),
+        actions.last()
+    );
+
+    Ok(())
+}